@@ -0,0 +1,39 @@
+//! Generates the client and the server of the service in `proto/noisedeck.proto` from the
+//! description below, so that building needs no `protoc`. Both have to be changed together, which
+//! the tests in `src/lib.rs` check.
+
+use std::env;
+use tonic_build::manual::{Builder, Method, MethodBuilder, Service};
+
+fn main() {
+    let method = |name: &str, route: &str, input: &str, output: &str| -> MethodBuilder {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("crate::{input}"))
+            .output_type(format!("crate::{output}"))
+            .codec_path("tonic_prost::ProstCodec")
+    };
+    let service = Service::builder()
+        .name("NoiseDeck")
+        .package("noisedeck.v1")
+        .comment("Controls a running noisedeck daemon.")
+        .method(method("play", "Play", "PlayRequest", "Done").build())
+        .method(method("stop", "Stop", "StopRequest", "Done").build())
+        .method(method("set_volume", "SetVolume", "SetVolumeRequest", "Done").build())
+        .method(
+            method("subscribe", "Subscribe", "SubscribeRequest", "Event")
+                .server_streaming()
+                .build(),
+        )
+        .method(method("history", "History", "HistoryRequest", "HistoryReply").build())
+        .build();
+
+    let client = env::var_os("CARGO_FEATURE_CLIENT").is_some();
+    Builder::new()
+        .build_client(client)
+        .build_server(env::var_os("CARGO_FEATURE_SERVER").is_some())
+        .build_transport(client)
+        .compile(&[service]);
+    println!("cargo::rerun-if-changed=build.rs");
+}
@@ -0,0 +1,322 @@
+//! The gRPC control API of the noisedeck daemon.
+//!
+//! The messages are written out by hand so that building needs no `protoc`; they have to match
+//! `proto/noisedeck.proto`, which is what clients in other languages are generated from, and a
+//! test compares the two. With the default `client` feature, `noise_deck_client::NoiseDeckClient`
+//! talks to a daemon that was started with `--grpc`:
+//!
+//! ```no_run
+//! # #[cfg(feature = "client")]
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use noisedeck_api::{PlayRequest, noise_deck_client::NoiseDeckClient};
+//!
+//! let mut deck = NoiseDeckClient::connect("http://127.0.0.1:50051").await?;
+//! deck.play(PlayRequest { path: "rain.mp3".into() }).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+#[derive(Clone, PartialEq, Eq, Hash, prost::Message)]
+pub struct PlayRequest {
+    /// As in the configuration: relative to the audio directory, or absolute. The sound has to be
+    /// on one of the pages.
+    #[prost(string, tag = "1")]
+    pub path: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, prost::Message)]
+pub struct StopRequest {
+    /// Like [`PlayRequest::path`].
+    #[prost(string, tag = "1")]
+    pub path: String,
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct SetVolumeRequest {
+    /// 0 is the volume that the deck starts out with.
+    #[prost(double, tag = "1")]
+    pub volume_db: f64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, prost::Message)]
+pub struct SubscribeRequest {}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, prost::Message)]
+pub struct Done {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Playback {
+    Stopped = 0,
+    Playing = 1,
+    Paused = 2,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, prost::Message)]
+pub struct Event {
+    #[prost(enumeration = "Playback", tag = "1")]
+    pub playback: i32,
+    /// Label and path of the sound that was started last, empty before the first one.
+    #[prost(string, tag = "2")]
+    pub latest_label: String,
+    #[prost(string, tag = "3")]
+    pub latest_path: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, prost::Message)]
+pub struct HistoryRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HistoryEntry {
+    /// RFC 3339, e.g. `2025-03-01T19:30:00Z`.
+    #[prost(string, tag = "1")]
+    pub at: String,
+    /// One of `pressed`, `held`, `played`, `stopped`, `paused`, `resumed` and `volume`.
+    #[prost(string, tag = "2")]
+    pub kind: String,
+    /// Label of the button or path of the sound, empty for the whole deck.
+    #[prost(string, tag = "3")]
+    pub subject: String,
+    /// For `volume`: the global volume, or by how much the volume of the sound was changed.
+    #[prost(double, tag = "4")]
+    pub volume_db: f64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HistoryReply {
+    #[prost(message, repeated, tag = "1")]
+    pub entries: Vec<HistoryEntry>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/noisedeck.v1.NoiseDeck.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+    use prost::bytes::Buf;
+    use std::collections::BTreeMap;
+
+    const PROTO: &str = include_str!("../proto/noisedeck.proto");
+
+    /// Name, type and tag of the fields of a message, and whether it is `repeated`.
+    type Fields = Vec<(String, String, u32, bool)>;
+
+    #[derive(Default)]
+    struct Proto {
+        messages: BTreeMap<String, Fields>,
+        enums: BTreeMap<String, Vec<(String, i32)>>,
+        /// Input, output and whether the output is a `stream`, by method.
+        rpcs: BTreeMap<String, (String, String, bool)>,
+    }
+
+    /// Reads just as much of the protobuf language as the file uses.
+    fn parse(proto: &str) -> Proto {
+        let mut parsed = Proto::default();
+        let (mut message, mut enumeration) = (None, None);
+        for line in proto.lines() {
+            let line = line.split("//").next().unwrap_or_default().trim();
+            let words = line
+                .split(|c: char| c.is_whitespace() || "(){};=".contains(c))
+                .filter(|w| !w.is_empty())
+                .collect::<Vec<_>>();
+            match words[..] {
+                ["message", name] => {
+                    parsed.messages.insert(name.to_string(), Vec::new());
+                    message = (!line.ends_with("{}")).then(|| name.to_string());
+                }
+                ["enum", name] => {
+                    parsed.enums.insert(name.to_string(), Vec::new());
+                    enumeration = Some(name.to_string());
+                }
+                ["rpc", name, input, "returns", output] => {
+                    let rpc = (input.to_string(), output.to_string(), false);
+                    parsed.rpcs.insert(name.to_string(), rpc);
+                }
+                ["rpc", name, input, "returns", "stream", output] => {
+                    let rpc = (input.to_string(), output.to_string(), true);
+                    parsed.rpcs.insert(name.to_string(), rpc);
+                }
+                [] if line == "}" => (message, enumeration) = (None, None),
+                _ => {
+                    let (repeated, field) = match words[..] {
+                        ["repeated", ref field @ ..] => (true, field),
+                        ref field => (false, field),
+                    };
+                    if let (Some(message), [ty, name, tag]) = (&message, field) {
+                        let field = (
+                            name.to_string(),
+                            ty.to_string(),
+                            tag.parse().unwrap(),
+                            repeated,
+                        );
+                        parsed.messages.get_mut(message).unwrap().push(field);
+                    } else if let (Some(enumeration), [name, value]) = (&enumeration, field) {
+                        let value = (name.to_string(), value.parse().unwrap());
+                        parsed.enums.get_mut(enumeration).unwrap().push(value);
+                    }
+                }
+            }
+        }
+        parsed
+    }
+
+    /// Every field is set, and repeated ones twice, so that they all show up in the encoding.
+    fn example<M: Message + std::fmt::Debug>(
+        name: &str,
+        message: M,
+    ) -> (String, (Vec<u8>, String)) {
+        (
+            name.to_string(),
+            (message.encode_to_vec(), format!("{message:#?}")),
+        )
+    }
+
+    /// Tag and wire type of every field in the encoding, in order.
+    fn wire_fields(mut bytes: &[u8]) -> Vec<(u32, u64)> {
+        let mut fields = Vec::new();
+        while bytes.has_remaining() {
+            let key = prost::encoding::decode_varint(&mut bytes).unwrap();
+            let (tag, wire_type) = ((key >> 3) as u32, key & 7);
+            match wire_type {
+                0 => drop(prost::encoding::decode_varint(&mut bytes)),
+                1 => bytes.advance(8),
+                2 => {
+                    let len = prost::encoding::decode_varint(&mut bytes).unwrap();
+                    bytes.advance(len as usize);
+                }
+                5 => bytes.advance(4),
+                other => panic!("Unexpected wire type {other}"),
+            }
+            fields.push((tag, wire_type));
+        }
+        fields
+    }
+
+    fn wire_type(ty: &str, enums: &BTreeMap<String, Vec<(String, i32)>>) -> u64 {
+        match ty {
+            "double" | "fixed64" | "sfixed64" => 1,
+            "float" | "fixed32" | "sfixed32" => 5,
+            "string" | "bytes" => 2,
+            _ if enums.contains_key(ty) => 0,
+            "int32" | "int64" | "uint32" | "uint64" | "sint32" | "sint64" | "bool" => 0,
+            _ => 2,
+        }
+    }
+
+    #[test]
+    fn test_messages_match_the_proto_file() {
+        let proto = parse(PROTO);
+        let entry = |at: &str| HistoryEntry {
+            at: at.to_string(),
+            kind: "volume".to_string(),
+            subject: "rain.mp3".to_string(),
+            volume_db: -3.0,
+        };
+        let examples = BTreeMap::from([
+            example(
+                "PlayRequest",
+                PlayRequest {
+                    path: "rain.mp3".into(),
+                },
+            ),
+            example(
+                "StopRequest",
+                StopRequest {
+                    path: "rain.mp3".into(),
+                },
+            ),
+            example("SetVolumeRequest", SetVolumeRequest { volume_db: -3.0 }),
+            example("SubscribeRequest", SubscribeRequest {}),
+            example("Done", Done {}),
+            example(
+                "Event",
+                Event {
+                    playback: Playback::Playing.into(),
+                    latest_label: "Rain".to_string(),
+                    latest_path: "rain.mp3".to_string(),
+                },
+            ),
+            example("HistoryRequest", HistoryRequest {}),
+            example("HistoryEntry", entry("2025-03-01T19:30:00Z")),
+            example(
+                "HistoryReply",
+                HistoryReply {
+                    entries: vec![entry("2025-03-01T19:30:00Z"), entry("2025-03-01T19:31:00Z")],
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            examples.keys().collect::<Vec<_>>(),
+            proto.messages.keys().collect::<Vec<_>>()
+        );
+        for (name, fields) in &proto.messages {
+            let (encoded, debug) = &examples[name];
+            let mut expected = Vec::new();
+            for (_, ty, tag, repeated) in fields {
+                let wire_type = wire_type(ty, &proto.enums);
+                expected.extend(std::iter::repeat_n(
+                    (*tag, wire_type),
+                    1 + *repeated as usize,
+                ));
+            }
+            assert_eq!(wire_fields(encoded), expected, "Fields of {name}");
+            // Only the top level of the pretty debug output is indented by four spaces
+            let rust_names = debug
+                .lines()
+                .filter_map(|line| line.strip_prefix("    "))
+                .filter(|line| !line.starts_with(' '))
+                .filter_map(|line| line.split_once(": ").map(|(name, _)| name))
+                .collect::<Vec<_>>();
+            let proto_names = fields
+                .iter()
+                .map(|(name, ..)| &name[..])
+                .collect::<Vec<_>>();
+            assert_eq!(rust_names, proto_names, "Field names of {name}");
+        }
+    }
+
+    #[test]
+    fn test_enums_match_the_proto_file() {
+        let proto = parse(PROTO);
+        assert_eq!(proto.enums.keys().collect::<Vec<_>>(), ["Playback"]);
+        for (name, value) in &proto.enums["Playback"] {
+            let playback = Playback::try_from(*value).map(|p| format!("PLAYBACK_{p:?}"));
+            assert_eq!(playback.map(|p| p.to_uppercase()).ok().as_ref(), Some(name));
+        }
+        assert!(Playback::try_from(proto.enums["Playback"].len() as i32).is_err());
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_the_service_matches_the_proto_file() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/noisedeck.v1.NoiseDeck.rs"));
+        let proto = parse(PROTO);
+        let routes = generated
+            .split("\"/noisedeck.v1.NoiseDeck/")
+            .skip(1)
+            .filter_map(|rest| rest.split_once('"').map(|(route, _)| route))
+            .collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(
+            routes.into_iter().collect::<Vec<_>>(),
+            proto.rpcs.keys().collect::<Vec<_>>()
+        );
+        for (name, (input, output, stream)) in &proto.rpcs {
+            let route = format!("\"/noisedeck.v1.NoiseDeck/{name}\"");
+            let method = generated
+                .split("pub async fn ")
+                .find(|method| method.contains(&route))
+                .unwrap_or_else(|| panic!("There is no method for {name}"));
+            assert!(
+                method.contains(&format!("IntoRequest<crate::{input}>")),
+                "Input of {name}"
+            );
+            let output = match stream {
+                true => format!("Streaming<crate::{output}>"),
+                false => format!("Response<crate::{output}>"),
+            };
+            assert!(method.contains(&output), "Output of {name}");
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Playback settings that a page hands down to its sounds, so that a hand-written page does not
+//! have to repeat them on every button, e.g. to loop everything on an ambience page.
+//!
+//! Reading a page fills them into every sound that leaves them out, so the rest of the deck sees
+//! complete settings, as if each button had its own. Writing the page out takes them back out of
+//! the sounds again.
+
+use super::{Button, Page, PlaybackMode};
+use serde::de::Error;
+use serde::{Deserialize, Serialize, Serializer, ser};
+use serde_json::{Map, Value};
+use std::time::Duration;
+
+/// The bus is handed down at playback instead, see [`Page::bus`].
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<PlaybackMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fade_in: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fade_out: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume: Option<Duration>,
+}
+
+impl PageDefaults {
+    pub fn is_empty(&self) -> bool {
+        *self == PageDefaults::default()
+    }
+
+    fn to_map(&self) -> serde_json::Result<Map<String, Value>> {
+        Ok(match serde_json::to_value(self)? {
+            Value::Object(defaults) => defaults,
+            _ => Map::new(),
+        })
+    }
+}
+
+/// A page as written, with its defaults not yet handed down.
+#[derive(Serialize, Deserialize)]
+pub(super) struct PageSource {
+    name: String,
+    buttons: Vec<Value>,
+    #[serde(default)]
+    bus: Option<String>,
+    #[serde(default, skip_serializing_if = "PageDefaults::is_empty")]
+    defaults: PageDefaults,
+}
+
+impl TryFrom<PageSource> for Page {
+    type Error = serde_json::Error;
+
+    fn try_from(source: PageSource) -> Result<Self, Self::Error> {
+        let defaults = source.defaults.to_map()?;
+        let buttons = source
+            .buttons
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut button)| {
+                // The buttons have been read already, so the error can only say where it is
+                // by naming the button
+                let at = match button.get("label").and_then(Value::as_str) {
+                    Some(label) => format!("button {i} ('{label}')"),
+                    None => format!("button {i}"),
+                };
+                if let Some(behavior) = button.get_mut("behavior") {
+                    each_sound(behavior, &mut |settings| {
+                        for (key, value) in &defaults {
+                            settings.entry(key).or_insert_with(|| value.clone());
+                        }
+                    });
+                }
+                serde_json::from_value::<Button>(button)
+                    .map_err(|e| Error::custom(format!("{at}: {e}")))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Page {
+            name: source.name,
+            buttons,
+            bus: source.bus,
+            defaults: source.defaults,
+        })
+    }
+}
+
+impl Serialize for Page {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let defaults = self.defaults.to_map().map_err(ser::Error::custom)?;
+        let buttons = self
+            .buttons
+            .iter()
+            .map(|button| {
+                let mut button = serde_json::to_value(button).map_err(ser::Error::custom)?;
+                if let Some(behavior) = button.get_mut("behavior") {
+                    // Settings that happen to be the same as the default were not necessarily
+                    // handed down, but reading them back gives the same sound either way
+                    each_sound(behavior, &mut |settings| {
+                        settings.retain(|key, value| defaults.get(key) != Some(value));
+                    });
+                }
+                Ok(button)
+            })
+            .collect::<Result<_, S::Error>>()?;
+        PageSource {
+            name: self.name.clone(),
+            buttons,
+            bus: self.bus.clone(),
+            defaults: self.defaults.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Steps of a sequence play their sounds as configured on the sounds' own buttons, but still
+/// need complete settings to be read at all.
+fn each_sound(behavior: &mut Value, f: &mut impl FnMut(&mut Map<String, Value>)) {
+    // Unit variants such as "StopAll" are plain strings and carry no settings
+    let Value::Object(variant) = behavior else {
+        return;
+    };
+    if let Some(Value::Array(play)) = variant.get_mut("PlaySound")
+        && let Some(Value::Object(settings)) = play.get_mut(1)
+    {
+        f(settings);
+    } else if let Some(Value::Array(steps)) = variant.get_mut("Sequence") {
+        for step in steps {
+            each_sound(step, f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{ButtonBehavior, Page, PlaybackMode};
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[test]
+    fn test_page_defaults_fill_in_what_buttons_leave_out() -> eyre::Result<()> {
+        let page: Page = serde_json::from_value(json!({
+            "name": "Ambience",
+            "defaults": { "mode": "LoopStop", "fade_in": { "secs": 2, "nanos": 0 } },
+            "buttons": [
+                { "label": "Rain", "behavior": { "PlaySound": ["rain.mp3", {}] } },
+                {
+                    "label": "Thunder",
+                    "behavior": { "PlaySound": ["thunder.mp3", { "mode": "PlayOverlap" }] }
+                },
+                { "label": "Stop", "behavior": "StopAll" }
+            ]
+        }))?;
+
+        let settings = |i: usize| match &page.buttons[i].behavior {
+            ButtonBehavior::PlaySound(_, settings) => Ok(settings),
+            other => Err(eyre::eyre!("Expected a sound, got {other:?}")),
+        };
+        assert_eq!(settings(0)?.mode, PlaybackMode::LoopStop);
+        assert_eq!(settings(0)?.fade_in, Some(Duration::from_secs(2)));
+        assert_eq!(settings(0)?.volume, 1.0);
+        assert_eq!(settings(1)?.mode, PlaybackMode::PlayOverlap);
+        assert_eq!(settings(1)?.fade_in, Some(Duration::from_secs(2)));
+        assert_eq!(settings(1)?.fade_out, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_written_pages_keep_their_defaults() -> eyre::Result<()> {
+        let written = json!({
+            "name": "Ambience",
+            "buttons": [
+                { "label": "Rain", "behavior": { "PlaySound": ["rain.mp3", {}] } },
+                {
+                    "label": "Thunder",
+                    "behavior": { "PlaySound": ["thunder.mp3", { "mode": "PlayOverlap" }] }
+                }
+            ],
+            "defaults": { "mode": "LoopStop" }
+        });
+        let page: Page = serde_json::from_value(written)?;
+
+        let json = serde_json::to_value(&page)?;
+        assert_eq!(json["defaults"], json!({ "mode": "LoopStop" }));
+        assert_eq!(
+            json["buttons"][0]["behavior"]["PlaySound"][1].get("mode"),
+            None
+        );
+        assert_eq!(
+            json["buttons"][1]["behavior"]["PlaySound"][1]["mode"],
+            "PlayOverlap"
+        );
+        let read: Page = serde_json::from_value(json)?;
+        assert_eq!(read.buttons.len(), 2);
+        assert_eq!(format!("{:?}", read.buttons), format!("{:?}", page.buttons));
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors_name_the_button() {
+        let page = serde_json::from_value::<Page>(json!({
+            "name": "Ambience",
+            "buttons": [
+                { "label": "Rain", "behavior": { "PlaySound": ["rain.mp3", {}] } }
+            ]
+        }));
+
+        let error = page.map(|_| ()).map_err(|e| e.to_string());
+        assert_eq!(
+            error,
+            Err("button 0 ('Rain'): missing field `mode`".to_string())
+        );
+    }
+}
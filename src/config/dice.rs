@@ -0,0 +1,135 @@
+//! Dice in the usual notation of tabletop games, e.g. `"2d6+3"` for two six-sided dice plus
+//! three, so that the table can roll on the deck.
+
+use crate::util::random_below;
+use serde::{Deserialize, Serialize};
+
+/// More would not fit on a button anyway.
+const MAX_DICE: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Dice {
+    count: u32,
+    sides: u32,
+    modifier: i32,
+}
+
+impl TryFrom<String> for Dice {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("'{s}' are not dice, e.g. 2d6+3");
+        let spec = s.trim().to_ascii_lowercase();
+        let (count, rest) = spec.split_once('d').ok_or_else(invalid)?;
+        let (sides, modifier) = match rest.find(['+', '-']) {
+            Some(at) => rest.split_at(at),
+            None => (rest, "0"),
+        };
+        let count = match count {
+            "" => 1,
+            count => count.parse::<u32>().map_err(|_| invalid())?,
+        };
+        let sides = sides.parse::<u32>().map_err(|_| invalid())?;
+        let modifier = modifier.parse::<i32>().map_err(|_| invalid())?;
+        if !(1..=MAX_DICE).contains(&count) {
+            return Err(format!(
+                "'{s}' rolls {count} dice, but at most {MAX_DICE} fit"
+            ));
+        }
+        if sides < 2 {
+            return Err(format!("'{s}' has dice with fewer than two sides"));
+        }
+        Ok(Dice {
+            count,
+            sides,
+            modifier,
+        })
+    }
+}
+
+impl From<Dice> for String {
+    fn from(dice: Dice) -> Self {
+        match dice.modifier {
+            0 => format!("{}d{}", dice.count, dice.sides),
+            modifier => format!("{}d{}{modifier:+}", dice.count, dice.sides),
+        }
+    }
+}
+
+impl Dice {
+    pub fn roll(&self) -> Result<i64, getrandom::Error> {
+        self.roll_with(roll_die)
+    }
+
+    fn roll_with<E>(&self, mut die: impl FnMut(u32) -> Result<u32, E>) -> Result<i64, E> {
+        let rolled = (0..self.count)
+            .map(|_| die(self.sides).map(i64::from))
+            .sum::<Result<i64, E>>()?;
+        Ok(rolled + i64::from(self.modifier))
+    }
+}
+
+/// From 1 to `sides`, each equally likely.
+fn roll_die(sides: u32) -> Result<u32, getrandom::Error> {
+    Ok(random_below(u64::from(sides))? as u32 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dice, roll_die};
+
+    fn dice(spec: &str) -> Result<Dice, String> {
+        Dice::try_from(spec.to_string())
+    }
+
+    #[test]
+    fn test_dice_are_parsed_from_their_notation() -> Result<(), String> {
+        let parsed = |count, sides, modifier| Dice {
+            count,
+            sides,
+            modifier,
+        };
+        assert_eq!(dice("2d6+3")?, parsed(2, 6, 3));
+        assert_eq!(dice("d20")?, parsed(1, 20, 0));
+        assert_eq!(dice(" 3D8-2 ")?, parsed(3, 8, -2));
+        assert_eq!(String::from(dice("d20")?), "1d20");
+        assert_eq!(String::from(dice("3d8-2")?), "3d8-2");
+
+        let invalid = [
+            "", "6", "2d", "2d6+", "xd6", "2d6+3+1", "2d6+-3", "-1d6", "0d6", "101d6", "2d1",
+        ];
+        for invalid in invalid {
+            assert!(dice(invalid).is_err(), "'{invalid}' should not parse");
+        }
+        let invalid = serde_json::from_value::<Dice>(serde_json::json!("2w6"));
+        assert!(invalid.is_err_and(|e| e.to_string().contains("are not dice")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rolls_add_up_the_dice_and_the_modifier() -> Result<(), String> {
+        let mut rolled = [4, 2].into_iter();
+        let next = |_| rolled.next().ok_or("Rolled too often".to_string());
+        assert_eq!(dice("2d6+3")?.roll_with(next)?, 9);
+        assert_eq!(dice("1d4-5")?.roll_with(Ok::<_, String>)?, -1);
+        for _ in 0..100 {
+            let rolled = dice("2d6-2")?.roll().map_err(|e| e.to_string())?;
+            assert!((0..=10).contains(&rolled));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_every_side_is_equally_likely() -> Result<(), getrandom::Error> {
+        let mut counts = [0u32; 6];
+        for _ in 0..60_000 {
+            counts[roll_die(6)? as usize - 1] += 1;
+        }
+        // About ten thousand each; more than six standard deviations away would hardly be chance
+        for count in counts {
+            assert!((9_400..=10_600).contains(&count), "{counts:?}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,388 @@
+//! Small edits of a configuration from the command line, e.g. for scripts that set up a session,
+//! which would otherwise have to edit the JSON by hand and risk breaking it.
+//!
+//! Edits are made to the file as written, so templates and page defaults stay as they are, and
+//! the buttons that a page gets from its template are edited in the template. Every edit is
+//! validated before the file is replaced.
+
+use super::{Config, PlaybackMode};
+use clap::{Args, Subcommand};
+use eyre::{Context, OptionExt, bail};
+use serde_json::{Map, Value, json};
+use std::path::{Path, PathBuf};
+use tracing::info;
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, Args, Clone)]
+pub struct EditArgs {
+    /// The configuration to edit, as JSON.
+    #[arg(required = true, env = "config_path")]
+    pub path: PathBuf,
+
+    #[command(subcommand)]
+    pub edit: Edit,
+}
+
+/// Pages are named by their name or id, buttons by their label.
+#[derive(Debug, PartialEq, Subcommand, Clone)]
+pub enum Edit {
+    /// Adds a button at the end of a page, or at `--index`.
+    AddButton {
+        #[arg(long)]
+        page: String,
+        #[arg(long)]
+        label: String,
+        /// Plays this sound, relative to the audio directory.
+        #[arg(
+            long,
+            required_unless_present = "behavior",
+            conflicts_with = "behavior"
+        )]
+        sound: Option<String>,
+        /// Without it, the page's default mode, or else PlayStop.
+        #[arg(long, value_enum, requires = "sound")]
+        mode: Option<PlaybackMode>,
+        /// Any behavior as JSON, e.g. `{"PushPage": "<id>"}` or `"StopAll"`.
+        #[arg(long)]
+        behavior: Option<String>,
+        #[arg(long)]
+        index: Option<usize>,
+    },
+    /// Removes a button from a page.
+    RemoveButton {
+        #[arg(long)]
+        page: String,
+        #[arg(long)]
+        label: String,
+    },
+    /// Moves a button to `--index` on its page or to the end of `--to-page`, or both.
+    Move {
+        #[arg(long)]
+        page: String,
+        #[arg(long)]
+        label: String,
+        #[arg(long, required_unless_present = "index")]
+        to_page: Option<String>,
+        #[arg(long)]
+        index: Option<usize>,
+    },
+    /// Sets the volume of a sound, from 0.0 (silent) over 1.0 (as recorded) upwards.
+    SetVolume {
+        #[arg(long)]
+        page: String,
+        #[arg(long)]
+        label: String,
+        volume: f64,
+    },
+}
+
+pub async fn run(args: EditArgs) -> eyre::Result<()> {
+    let json = tokio::fs::read(&args.path)
+        .await
+        .with_context(|| format!("Failed to read {}", args.path.display()))?;
+    let mut source: Value = serde_json::from_slice(&json)
+        .with_context(|| format!("Failed to parse {}", args.path.display()))?;
+    apply(&mut source, &args.edit)?;
+    let mut config: Config = serde_json::from_value(source.clone())
+        .context("The edited configuration cannot be read")?;
+    let path = args.path.clone();
+    tokio::task::spawn_blocking(move || {
+        super::read_scripts(&mut config, path.parent().unwrap_or(Path::new("")))?;
+        let issues = super::validate(&config);
+        for issue in &issues {
+            println!("{issue}");
+        }
+        super::ensure_no_errors(&issues)?;
+        super::write(&source, &path)
+    })
+    .await??;
+    info!("Saved {}", args.path.display());
+    Ok(())
+}
+
+fn apply(config: &mut Value, edit: &Edit) -> eyre::Result<()> {
+    match edit {
+        Edit::AddButton {
+            page,
+            label,
+            sound,
+            mode,
+            behavior,
+            index,
+        } => {
+            let behavior = match (sound, behavior) {
+                (Some(sound), _) => {
+                    let mode = match mode {
+                        Some(mode) => Some(*mode),
+                        None if inherits_mode(config, page)? => None,
+                        None => Some(PlaybackMode::PlayStop),
+                    };
+                    let settings = match mode {
+                        Some(mode) => json!({ "mode": mode }),
+                        None => json!({}),
+                    };
+                    json!({ "PlaySound": [sound, settings] })
+                }
+                (None, Some(json)) => serde_json::from_str(json)
+                    .with_context(|| format!("'{json}' is not a behavior"))?,
+                (None, None) => bail!("A button needs a --sound or a --behavior"),
+            };
+            let button = json!({ "label": label, "behavior": behavior });
+            insert(buttons_mut(page_mut(config, page)?)?, button, *index);
+        }
+        Edit::RemoveButton { page, label } => {
+            let page = page_mut(config, page)?;
+            let index = button_index(page, label)?;
+            buttons_mut(page)?.remove(index);
+        }
+        Edit::Move {
+            page,
+            label,
+            to_page,
+            index,
+        } => {
+            let from = page_mut(config, page)?;
+            let at = button_index(from, label)?;
+            let mut button = buttons_mut(from)?.remove(at);
+            let to = match to_page {
+                Some(to_page) => {
+                    // Its key on the other page may well be taken
+                    if let Value::Object(button) = &mut button {
+                        button.remove("position");
+                    }
+                    page_mut(config, to_page)?
+                }
+                None => page_mut(config, page)?,
+            };
+            insert(buttons_mut(to)?, button, *index);
+        }
+        Edit::SetVolume {
+            page,
+            label,
+            volume,
+        } => {
+            let page = page_mut(config, page)?;
+            let index = button_index(page, label)?;
+            let settings = buttons_mut(page)?[index]
+                .pointer_mut("/behavior/PlaySound/1")
+                .and_then(Value::as_object_mut)
+                .ok_or_else(|| eyre::eyre!("Button '{label}' does not play a sound"))?;
+            settings.insert("volume".to_string(), json!(volume));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the page or its template has a default mode for the sound to pick up.
+fn inherits_mode(config: &mut Value, page: &str) -> eyre::Result<bool> {
+    let page = page_mut(config, page)?;
+    if page.get("defaults").and_then(|d| d.get("mode")).is_some() {
+        return Ok(true);
+    }
+    let Some(template) = page
+        .get("template")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+    else {
+        return Ok(false);
+    };
+    let templates = config.get("templates");
+    let defaults = templates
+        .and_then(|t| t.get(&template))
+        .and_then(|t| t.get("defaults"));
+    Ok(defaults.and_then(|d| d.get("mode")).is_some())
+}
+
+/// Past the end is the end, so that scripts need not know how many buttons a page has.
+fn insert(buttons: &mut Vec<Value>, button: Value, index: Option<usize>) {
+    let index = index.map_or(buttons.len(), |i| i.min(buttons.len()));
+    buttons.insert(index, button);
+}
+
+fn page_mut<'a>(config: &'a mut Value, page: &str) -> eyre::Result<&'a mut Map<String, Value>> {
+    let pages = config
+        .get_mut("pages")
+        .and_then(Value::as_object_mut)
+        .ok_or_eyre("The configuration has no pages")?;
+    let id = Uuid::parse_str(page).ok();
+    let name = |p: &Value| p.get("name").and_then(Value::as_str) == Some(page);
+    let key = pages
+        .keys()
+        .find(|key| id.is_some() && Uuid::parse_str(key).ok() == id)
+        .or_else(|| pages.iter().find(|(_, p)| name(p)).map(|(key, _)| key))
+        .cloned()
+        .ok_or_else(|| eyre::eyre!("There is no page '{page}'"))?;
+    pages
+        .get_mut(&key)
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| eyre::eyre!("Page '{page}' is not an object"))
+}
+
+/// A page made from a template may leave out buttons of its own.
+fn buttons_mut(page: &mut Map<String, Value>) -> eyre::Result<&mut Vec<Value>> {
+    page.entry("buttons")
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .ok_or_eyre("The page's buttons are not a list")
+}
+
+/// Labels are not unique, so a label that is on the page more than once is refused rather than
+/// guessed.
+fn button_index(page: &Map<String, Value>, label: &str) -> eyre::Result<usize> {
+    let name = page.get("name").and_then(Value::as_str).unwrap_or_default();
+    let mut matching = page
+        .get("buttons")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .filter(|(_, b)| b.get("label").and_then(Value::as_str) == Some(label))
+        .map(|(i, _)| i);
+    match (matching.next(), matching.next()) {
+        (Some(index), None) => Ok(index),
+        (None, _) if page.contains_key("template") => bail!(
+            "There is no button '{label}' on page {name}, those of its template are edited there"
+        ),
+        (None, _) => bail!("There is no button '{label}' on page {name}"),
+        (Some(_), Some(_)) => bail!("Several buttons on page {name} are labeled '{label}'"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Edit, apply};
+    use crate::config::{ButtonBehavior, Config, PlaybackMode};
+    use serde_json::{Value, json};
+
+    #[test]
+    fn test_buttons_are_added_moved_and_removed_by_label() -> eyre::Result<()> {
+        let mut source = json!({
+            "pages": {
+                "00000000-0000-0000-0000-000000000001": { "name": "Tavern", "buttons": [{
+                    "label": "Fire",
+                    "behavior": { "PlaySound": ["fire.mp3", { "mode": "LoopStop" }] }
+                }]},
+                "00000000-0000-0000-0000-000000000002": { "name": "Forest", "buttons": [] }
+            },
+            "start_page": "00000000-0000-0000-0000-000000000001"
+        });
+        let name = |name: &str| name.to_string();
+        let labels = |source: &Value, page: &str| -> eyre::Result<Option<Vec<String>>> {
+            let config: Config = serde_json::from_value(source.clone())?;
+            Ok(config
+                .pages
+                .values()
+                .find(|p| p.name == page)
+                .map(|p| p.buttons.iter().map(|b| b.label.to_string()).collect()))
+        };
+
+        apply(
+            &mut source,
+            &Edit::AddButton {
+                page: name("Tavern"),
+                label: name("Crowd"),
+                sound: Some("crowd.mp3".to_string()),
+                mode: Some(PlaybackMode::LoopStop),
+                behavior: None,
+                index: Some(0),
+            },
+        )?;
+        assert_eq!(
+            labels(&source, "Tavern")?,
+            Some(vec![name("Crowd"), name("Fire")])
+        );
+
+        let move_fire = Edit::Move {
+            page: name("Tavern"),
+            label: name("Fire"),
+            to_page: Some(name("Forest")),
+            index: None,
+        };
+        apply(&mut source, &move_fire)?;
+        assert_eq!(labels(&source, "Forest")?, Some(vec![name("Fire")]));
+
+        let set_volume = |volume| Edit::SetVolume {
+            page: name("Forest"),
+            label: name("Fire"),
+            volume,
+        };
+        apply(&mut source, &set_volume(0.5))?;
+        let config: Config = serde_json::from_value(source.clone())?;
+        let forest = config.pages.values().find(|p| p.name == "Forest");
+        assert!(forest.is_some_and(|p| matches!(
+            &p.buttons[0].behavior,
+            ButtonBehavior::PlaySound(_, settings) if settings.volume == 0.5
+        )));
+
+        let remove = |label: &str| Edit::RemoveButton {
+            page: name("Tavern"),
+            label: label.to_string(),
+        };
+        apply(&mut source, &remove("Crowd"))?;
+        assert_eq!(labels(&source, "Tavern")?, Some(vec![]));
+        assert!(apply(&mut source, &remove("Crowd")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_edits_keep_templates_and_page_defaults_as_written() -> eyre::Result<()> {
+        let templates = json!({ "combat": {
+            "defaults": { "mode": "PlayOverlap" },
+            "buttons": [
+                { "label": "Stop", "behavior": "StopAll" },
+                { "slot": "music", "label": "Music", "settings": { "mode": "LoopStop" } }
+            ]
+        }});
+        let defaults = json!({ "volume": 0.8, "fade_in": { "secs": 2, "nanos": 0 } });
+        let mut source = json!({
+            "templates": templates,
+            "pages": {
+                "00000000-0000-0000-0000-000000000001": {
+                    "name": "Ambush",
+                    "template": "combat",
+                    "slots": { "music": "combat/drums.mp3" },
+                    "defaults": defaults
+                }
+            },
+            "start_page": "00000000-0000-0000-0000-000000000001"
+        });
+        let written = |value: &Value| serde_json::to_vec_pretty(value);
+        let page = "/pages/00000000-0000-0000-0000-000000000001";
+
+        apply(
+            &mut source,
+            &Edit::AddButton {
+                page: "Ambush".to_string(),
+                label: "Arrow".to_string(),
+                sound: Some("sfx/arrow.mp3".to_string()),
+                mode: None,
+                behavior: None,
+                index: None,
+            },
+        )?;
+        assert_eq!(written(&source["templates"])?, written(&templates)?);
+        let page_defaults = source.pointer(&format!("{page}/defaults"));
+        assert_eq!(
+            page_defaults.map(written).transpose()?,
+            Some(written(&defaults)?)
+        );
+        // The new sound picks up the mode of the template's defaults
+        let arrow = source.pointer(&format!("{page}/buttons/0/behavior/PlaySound/1"));
+        assert_eq!(arrow, Some(&json!({})));
+
+        let config: Config = serde_json::from_value(source.clone())?;
+        let labels = config
+            .pages
+            .values()
+            .flat_map(|p| &p.buttons)
+            .map(|b| b.label.as_str());
+        assert_eq!(labels.collect::<Vec<_>>(), ["Stop", "Music", "Arrow"]);
+        // Buttons from the template are not the page's to remove
+        let remove_stop = Edit::RemoveButton {
+            page: "Ambush".to_string(),
+            label: "Stop".to_string(),
+        };
+        assert!(apply(&mut source, &remove_stop).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+//! Start pages for parts of the week, e.g. the "Friday night D&D" page from 19:00 on Fridays and
+//! the "Streaming" page during the day, so that the right one is up without looking for it:
+//!
+//! ```json
+//! "schedule": [
+//!     { "days": ["Fri"], "from": "19:00", "to": "02:00", "start": { "Page": "<id>" } },
+//!     { "from": "08:00", "to": "18:00", "start": { "Profile": "Streaming" } }
+//! ]
+//! ```
+//!
+//! The first entry that covers the local time wins; outside of all of them, the deck starts at
+//! the `start_page`. The deck switches when an entry begins or ends, not in between, so that
+//! whoever navigated away from the scheduled page is left alone.
+//!
+//! Stream Deck profiles have nothing like it, so a schedule needs the daemon's `--config`.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    /// Every day if there are none. An entry that runs past midnight belongs to the day that it
+    /// starts on.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+    pub from: TimeOfDay,
+    /// Before `from` for entries that end on the next day; the same as `from` for whole days.
+    pub to: TimeOfDay,
+    pub start: ScheduledStart,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScheduledStart {
+    /// A page of this configuration.
+    Page(Uuid),
+    /// Another campaign, like [`super::ButtonBehavior::SwitchProfile`].
+    Profile(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Weekday {
+    fn previous(self) -> Weekday {
+        match self {
+            Weekday::Mon => Weekday::Sun,
+            Weekday::Tue => Weekday::Mon,
+            Weekday::Wed => Weekday::Tue,
+            Weekday::Thu => Weekday::Wed,
+            Weekday::Fri => Weekday::Thu,
+            Weekday::Sat => Weekday::Fri,
+            Weekday::Sun => Weekday::Sat,
+        }
+    }
+}
+
+/// Minutes since midnight, written like `"19:00"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(u16);
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let parsed = s
+            .split_once(':')
+            .and_then(|(h, m)| Some((h.parse::<u16>().ok()?, m.parse::<u16>().ok()?)));
+        match parsed {
+            Some((h, m)) if h < 24 && m < 60 => Ok(TimeOfDay(h * 60 + m)),
+            _ => Err(format!("'{s}' is not a time of day, e.g. 19:00")),
+        }
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        format!("{:02}:{:02}", time.0 / 60, time.0 % 60)
+    }
+}
+
+impl Schedule {
+    pub fn covers(&self, (day, time): (Weekday, TimeOfDay)) -> bool {
+        let on = |day| self.days.is_empty() || self.days.contains(&day);
+        if self.from < self.to {
+            on(day) && self.from <= time && time < self.to
+        } else {
+            (on(day) && self.from <= time) || (on(day.previous()) && time < self.to)
+        }
+    }
+}
+
+/// Index of the entry that applies at the time, if any.
+pub fn current(schedule: &[Schedule], now: (Weekday, TimeOfDay)) -> Option<usize> {
+    schedule.iter().position(|entry| entry.covers(now))
+}
+
+/// Where the deck gets the day and time of day from, so that tests can let entries begin and end
+/// without waiting for them. The default is the local time, see [`now`].
+#[derive(Clone)]
+pub struct Clock(Arc<dyn Fn() -> (Weekday, TimeOfDay) + Send + Sync>);
+
+impl Clock {
+    pub fn new(now: impl Fn() -> (Weekday, TimeOfDay) + Send + Sync + 'static) -> Self {
+        Clock(Arc::new(now))
+    }
+
+    pub fn now(&self) -> (Weekday, TimeOfDay) {
+        (self.0)()
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new(now)
+    }
+}
+
+impl std::fmt::Debug for Clock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Clock").field(&self.now()).finish()
+    }
+}
+
+/// The local day and time of day.
+pub fn now() -> (Weekday, TimeOfDay) {
+    #[cfg(not(feature = "local-time"))]
+    {
+        static WARNED: std::sync::Once = std::sync::Once::new();
+        WARNED.call_once(|| {
+            tracing::warn!(
+                "Following the schedule in UTC, noisedeck was built without the `local-time` \
+                 feature"
+            );
+        });
+    }
+    let now = jiff::Zoned::now();
+    let day = match now.weekday() {
+        jiff::civil::Weekday::Monday => Weekday::Mon,
+        jiff::civil::Weekday::Tuesday => Weekday::Tue,
+        jiff::civil::Weekday::Wednesday => Weekday::Wed,
+        jiff::civil::Weekday::Thursday => Weekday::Thu,
+        jiff::civil::Weekday::Friday => Weekday::Fri,
+        jiff::civil::Weekday::Saturday => Weekday::Sat,
+        jiff::civil::Weekday::Sunday => Weekday::Sun,
+    };
+    (day, TimeOfDay(now.hour() as u16 * 60 + now.minute() as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Schedule, TimeOfDay, Weekday, current};
+    use serde_json::json;
+
+    #[test]
+    fn test_entries_cover_their_days_past_midnight() -> eyre::Result<()> {
+        let schedule: Vec<Schedule> = serde_json::from_value(json!([
+            { "days": ["Fri"], "from": "19:00", "to": "02:00", "start": { "Profile": "D&D" } },
+            { "from": "08:00", "to": "18:00", "start": { "Profile": "Streaming" } }
+        ]))?;
+        let at = |day, time: &str| -> eyre::Result<Option<usize>> {
+            let time = TimeOfDay::try_from(time.to_string()).map_err(eyre::Report::msg)?;
+            Ok(current(&schedule, (day, time)))
+        };
+        assert_eq!(at(Weekday::Fri, "12:00")?, Some(1));
+        assert_eq!(at(Weekday::Fri, "18:30")?, None);
+        assert_eq!(at(Weekday::Fri, "19:00")?, Some(0));
+        assert_eq!(at(Weekday::Sat, "01:59")?, Some(0));
+        assert_eq!(at(Weekday::Sat, "02:00")?, None);
+        assert_eq!(at(Weekday::Thu, "20:00")?, None);
+
+        let invalid = serde_json::from_value::<TimeOfDay>(json!("24:00"));
+        assert!(invalid.is_err_and(|e| e.to_string().contains("not a time of day")));
+        Ok(())
+    }
+}
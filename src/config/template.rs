@@ -0,0 +1,233 @@
+//! Templates for pages that share a layout, e.g. every combat page with Stop All, a music track
+//! and a few sound effects, so that each of them only names its own files.
+//!
+//! A template is laid out like a page. Among its buttons, `{ "slot": "music" }` marks where the
+//! page's own buttons go; a page names the template and fills its slots:
+//!
+//! ```json
+//! "templates": {
+//!     "combat": {
+//!         "defaults": { "mode": "PlayOverlap" },
+//!         "buttons": [
+//!             { "label": "Stop", "behavior": "StopAll" },
+//!             { "slot": "music", "label": "Music", "settings": { "mode": "LoopStop" } },
+//!             { "slot": "sfx" }
+//!         ]
+//!     }
+//! }
+//! ...
+//! { "name": "Ambush", "template": "combat", "slots": {
+//!     "music": "combat/drums.mp3",
+//!     "sfx": ["sfx/arrow.mp3", { "label": "Horn", "behavior": "StopAll" }]
+//! } }
+//! ```
+//!
+//! A slot takes a file, which plays with the slot's `settings` under the slot's label or else
+//! the file's name, a whole button, or a list of either. Slots that a page leaves empty are
+//! left out. The page's own `buttons` follow those of the template, and its `defaults` and
+//! `bus` take precedence.
+//!
+//! Like page defaults, templates only exist in the file: reading the configuration turns every
+//! page into a plain one.
+
+use super::{Bus, Config, Page, Schedule};
+use serde::Deserialize;
+use serde::de::Error;
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A configuration as written, before its pages are made from their templates.
+#[derive(Deserialize)]
+pub(super) struct ConfigSource {
+    pages: HashMap<Uuid, Value>,
+    start_page: Uuid,
+    #[serde(default)]
+    buses: Vec<Bus>,
+    #[serde(default)]
+    templates: HashMap<String, Template>,
+    #[serde(default)]
+    schedule: Vec<Schedule>,
+}
+
+#[derive(Deserialize)]
+struct Template {
+    buttons: Vec<Value>,
+    #[serde(default)]
+    bus: Option<String>,
+    #[serde(default)]
+    defaults: Map<String, Value>,
+}
+
+impl TryFrom<ConfigSource> for Config {
+    type Error = serde_json::Error;
+
+    fn try_from(source: ConfigSource) -> Result<Self, Self::Error> {
+        let pages = source
+            .pages
+            .into_iter()
+            .map(|(id, page)| {
+                let page = instantiate(&source.templates, page)
+                    .and_then(serde_json::from_value::<Page>)
+                    .map_err(|e| serde_json::Error::custom(format!("page {id}: {e}")))?;
+                Ok((id, Arc::new(page)))
+            })
+            .collect::<Result<_, serde_json::Error>>()?;
+        Ok(Config {
+            pages,
+            start_page: source.start_page,
+            buses: source.buses,
+            schedule: source.schedule,
+        })
+    }
+}
+
+/// Turns a page that names a template into a plain page; other pages are left as they are.
+fn instantiate(
+    templates: &HashMap<String, Template>,
+    mut page: Value,
+) -> Result<Value, serde_json::Error> {
+    let Value::Object(fields) = &mut page else {
+        return Ok(page);
+    };
+    let Some(name) = fields.remove("template") else {
+        return Ok(page);
+    };
+    let name = name
+        .as_str()
+        .ok_or_else(|| Error::custom("a template is named by a string"))?;
+    let template = templates
+        .get(name)
+        .ok_or_else(|| Error::custom(format!("there is no template '{name}'")))?;
+    let mut slots = match fields.remove("slots") {
+        Some(Value::Object(slots)) => slots,
+        Some(_) => return Err(Error::custom("slots are filled by name")),
+        None => Map::new(),
+    };
+
+    let mut buttons = Vec::new();
+    for button in &template.buttons {
+        match button.get("slot").and_then(Value::as_str) {
+            Some(slot) => {
+                if let Some(filling) = slots.remove(slot) {
+                    fill(button, filling, &mut buttons)?;
+                }
+            }
+            None => buttons.push(button.clone()),
+        }
+    }
+    // A misspelled slot would otherwise silently leave a button out
+    if let Some(slot) = slots.keys().next() {
+        return Err(Error::custom(format!(
+            "template '{name}' has no slot '{slot}'"
+        )));
+    }
+    if let Some(Value::Array(own)) = fields.remove("buttons") {
+        buttons.extend(own);
+    }
+    fields.insert("buttons".to_string(), Value::Array(buttons));
+
+    let mut defaults = template.defaults.clone();
+    if let Some(Value::Object(own)) = fields.remove("defaults") {
+        defaults.extend(own);
+    }
+    fields.insert("defaults".to_string(), Value::Object(defaults));
+    if !fields.contains_key("bus")
+        && let Some(bus) = &template.bus
+    {
+        fields.insert("bus".to_string(), json!(bus));
+    }
+    Ok(page)
+}
+
+fn fill(slot: &Value, filling: Value, buttons: &mut Vec<Value>) -> Result<(), serde_json::Error> {
+    match filling {
+        Value::Array(fillings) => {
+            for filling in fillings {
+                fill(slot, filling, buttons)?;
+            }
+        }
+        Value::String(path) => {
+            let label = match slot.get("label").and_then(Value::as_str) {
+                Some(label) => label.to_string(),
+                None => Path::new(&path)
+                    .file_stem()
+                    .map_or_else(|| path.clone(), |stem| stem.to_string_lossy().into_owned()),
+            };
+            let settings = slot.get("settings").cloned().unwrap_or_else(|| json!({}));
+            buttons.push(json!({ "label": label, "behavior": { "PlaySound": [path, settings] } }));
+        }
+        button @ Value::Object(_) => buttons.push(button),
+        _ => {
+            return Err(Error::custom(
+                "a slot takes a file, a button or a list of them",
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{ButtonBehavior, Config, PlaybackMode};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_pages_fill_the_slots_of_their_template() -> eyre::Result<()> {
+        let ambush = Uuid::from_u128(1);
+        let source = |slots: serde_json::Value| {
+            json!({
+                "templates": { "combat": {
+                    "defaults": { "mode": "PlayOverlap" },
+                    "buttons": [
+                        { "label": "Stop", "behavior": "StopAll" },
+                        { "slot": "music", "label": "Music", "settings": { "mode": "LoopStop" } },
+                        { "slot": "sfx" }
+                    ]
+                }},
+                "pages": { ambush.to_string(): {
+                    "name": "Ambush",
+                    "template": "combat",
+                    "slots": slots,
+                    "buttons": [{ "label": "Back", "behavior": "Pop" }]
+                }},
+                "start_page": ambush
+            })
+        };
+
+        let config: Config = serde_json::from_value(source(json!({
+            "music": "combat/drums.mp3",
+            "sfx": ["sfx/arrow.mp3", "sfx/horn.mp3"]
+        })))?;
+        let page = &config.pages[&ambush];
+        let buttons = page
+            .buttons
+            .iter()
+            .map(|b| {
+                let mode = match &b.behavior {
+                    ButtonBehavior::PlaySound(_, settings) => Some(settings.mode),
+                    _ => None,
+                };
+                (b.label.to_string(), mode)
+            })
+            .collect::<Vec<_>>();
+        let button = |label: &str, mode| (label.to_string(), mode);
+        assert_eq!(
+            buttons,
+            [
+                button("Stop", None),
+                button("Music", Some(PlaybackMode::LoopStop)),
+                button("arrow", Some(PlaybackMode::PlayOverlap)),
+                button("horn", Some(PlaybackMode::PlayOverlap)),
+                button("Back", None),
+            ]
+        );
+
+        let misspelled = serde_json::from_value::<Config>(source(json!({ "musik": "drums.mp3" })));
+        assert!(misspelled.is_err_and(|e| e.to_string().contains("no slot 'musik'")));
+        Ok(())
+    }
+}
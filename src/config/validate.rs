@@ -0,0 +1,544 @@
+//! Checks a configuration as a whole, so that everything wrong with it can be fixed in one go
+//! instead of one restart of the daemon per problem.
+
+use super::{ButtonBehavior, Config, Effect, PlaySoundSettings, ScheduledStart};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The deck cannot work as configured, e.g. a button leads to a page that does not exist.
+    Error,
+    /// The deck works, but probably not as intended, e.g. a sound plays through the main track
+    /// because its bus does not exist.
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    /// `None` for problems with the configuration as a whole.
+    pub page: Option<Uuid>,
+    /// Index into [`super::Page::buttons`].
+    pub button: Option<usize>,
+    /// Path to the offending value, e.g. `behavior[2].pan` for a step of a sequence.
+    pub field: String,
+    pub message: String,
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "error: ")?,
+            Severity::Warning => write!(f, "warning: ")?,
+        }
+        if let Some(page) = self.page {
+            write!(f, "page {page}, ")?;
+        }
+        if let Some(button) = self.button {
+            write!(f, "button {button}, ")?;
+        }
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Reports every problem rather than just the first one. Pages come in the order of their ids,
+/// so that the report stays the same between runs.
+pub fn validate(config: &Config) -> Vec<ValidationIssue> {
+    let mut v = Validator {
+        config,
+        played: config
+            .pages
+            .values()
+            .flat_map(|page| &page.buttons)
+            .filter_map(|b| match &b.behavior {
+                ButtonBehavior::PlaySound(path, _) => Some(path.as_str()),
+                _ => None,
+            })
+            .collect(),
+        tags: config
+            .pages
+            .values()
+            .flat_map(|page| &page.buttons)
+            .filter_map(|b| match &b.behavior {
+                ButtonBehavior::PlaySound(_, settings) => Some(&settings.tags),
+                _ => None,
+            })
+            .flatten()
+            .map(String::as_str)
+            .collect(),
+        page: None,
+        button: None,
+        issues: Vec::new(),
+    };
+
+    if !config.pages.contains_key(&config.start_page) {
+        v.error(
+            "start_page",
+            format!("there is no page {}", config.start_page),
+        );
+    }
+    let mut bus_names = HashSet::new();
+    for (i, bus) in config.buses.iter().enumerate() {
+        if !bus_names.insert(bus.name.as_str()) {
+            v.warning(
+                format!("buses[{i}].name"),
+                format!(
+                    "another bus is also called '{}', only the first one is used",
+                    bus.name
+                ),
+            );
+        }
+        for (j, effect) in bus.effects.iter().enumerate() {
+            v.check_effect(&format!("buses[{i}].effects[{j}]"), effect);
+        }
+        if bus
+            .output
+            .as_ref()
+            .is_some_and(|device| device.trim().is_empty())
+        {
+            v.error(format!("buses[{i}].output"), "no device");
+        }
+    }
+
+    for (i, entry) in config.schedule.iter().enumerate() {
+        let field = format!("schedule[{i}].start");
+        match &entry.start {
+            ScheduledStart::Page(id) if !config.pages.contains_key(id) => {
+                v.error(field, format!("there is no page {id}"));
+            }
+            ScheduledStart::Profile(name) if name.trim().is_empty() => {
+                v.error(field, "no profile to switch to");
+            }
+            ScheduledStart::Page(_) | ScheduledStart::Profile(_) => (),
+        }
+    }
+
+    let mut page_ids: Vec<_> = config.pages.keys().copied().collect();
+    page_ids.sort();
+    let mut button_ids = HashSet::new();
+    for id in page_ids {
+        let page = &config.pages[&id];
+        v.page = Some(id);
+        v.button = None;
+        if let Some(bus) = &page.bus {
+            v.check_bus("bus", bus);
+        }
+        for (i, button) in page.buttons.iter().enumerate() {
+            v.button = Some(i);
+            if let Some(id) = button.id
+                && !button_ids.insert(id)
+            {
+                v.error("id", format!("another button also has the id {id}"));
+            }
+            v.check_behavior("behavior", &button.behavior, Nesting::Button);
+        }
+    }
+    v.issues
+}
+
+/// Fails with all errors at once, if there are any. Warnings are left to the caller.
+pub fn ensure_no_errors(issues: &[ValidationIssue]) -> eyre::Result<()> {
+    let errors: Vec<_> = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .map(ToString::to_string)
+        .collect();
+    if !errors.is_empty() {
+        eyre::bail!(
+            "The configuration has {} error(s):\n{}",
+            errors.len(),
+            errors.join("\n")
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Nesting {
+    Button,
+    SequenceStep,
+}
+
+struct Validator<'a> {
+    config: &'a Config,
+    /// Sounds that are on some button, which is where sequences and stop buttons find them.
+    played: HashSet<&'a str>,
+    /// Tags of the sounds that are on some button.
+    tags: HashSet<&'a str>,
+    page: Option<Uuid>,
+    button: Option<usize>,
+    issues: Vec<ValidationIssue>,
+}
+
+impl Validator<'_> {
+    fn error(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Error, field.into(), message.into());
+    }
+
+    fn warning(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Warning, field.into(), message.into());
+    }
+
+    fn push(&mut self, severity: Severity, field: String, message: String) {
+        self.issues.push(ValidationIssue {
+            severity,
+            page: self.page,
+            button: self.button,
+            field,
+            message,
+        });
+    }
+
+    fn check_behavior(&mut self, field: &str, behavior: &ButtonBehavior, nesting: Nesting) {
+        match behavior {
+            ButtonBehavior::PushPage(id) | ButtonBehavior::GotoPage(id) => {
+                if !self.config.pages.contains_key(id) {
+                    self.error(field, format!("there is no page {id}"));
+                }
+            }
+            ButtonBehavior::PopN(0) => self.warning(field, "goes back by zero pages"),
+            ButtonBehavior::Pop | ButtonBehavior::PopToRoot | ButtonBehavior::PopN(_) => (),
+            ButtonBehavior::PlaySound(path, settings) => {
+                if path.is_empty() {
+                    self.error(field, "the sound has no path");
+                } else if nesting == Nesting::SequenceStep && !self.played.contains(path.as_str()) {
+                    self.warning(field, format!("'{path}' is not on any button"));
+                }
+                self.check_settings(field, settings);
+            }
+            ButtonBehavior::StopSound(path) | ButtonBehavior::StopInstances(path, _) => {
+                if !self.played.contains(path.as_str()) {
+                    self.warning(field, format!("'{path}' is not on any button"));
+                }
+            }
+            ButtonBehavior::StopAll
+            | ButtonBehavior::PauseAll
+            | ButtonBehavior::ResumeAll
+            | ButtonBehavior::LibraryStatus
+            | ButtonBehavior::Mixer
+            | ButtonBehavior::Stopwatch
+            | ButtonBehavior::RollDice { .. } => (),
+            ButtonBehavior::StopTag(tag) | ButtonBehavior::PlayTag(tag) => {
+                if tag.trim().is_empty() {
+                    self.error(field, "no tag");
+                } else if !self.tags.contains(tag.as_str()) {
+                    self.warning(field, format!("no sound on any button has the tag '{tag}'"));
+                }
+            }
+            ButtonBehavior::Sequence(steps) => {
+                if steps.is_empty() {
+                    self.warning(field, "the sequence has no steps");
+                }
+                for (i, step) in steps.iter().enumerate() {
+                    self.check_behavior(&format!("{field}[{i}]"), step, Nesting::SequenceStep);
+                }
+            }
+            ButtonBehavior::RunCommand { program, .. } => {
+                if program.trim().is_empty() {
+                    self.error(format!("{field}.program"), "no program to run");
+                }
+            }
+            ButtonBehavior::SendKeys(chord) => {
+                if chord.trim().is_empty() {
+                    self.error(field, "no keys to send");
+                }
+            }
+            ButtonBehavior::Script { path, source } => {
+                if path.is_empty() {
+                    self.error(format!("{field}.path"), "no script to run");
+                } else if let Err(e) = crate::daemon::check_script(source) {
+                    self.error(format!("{field}.path"), format!("'{path}': {e:#}"));
+                }
+            }
+            // Which kinds exist is only known to the deck
+            ButtonBehavior::Custom { kind, .. } => {
+                if kind.trim().is_empty() {
+                    self.error(format!("{field}.kind"), "no kind of behavior");
+                }
+            }
+            ButtonBehavior::Placeholder(name) => {
+                self.warning(
+                    field,
+                    format!("the {name} action of the profile is not supported"),
+                );
+            }
+            ButtonBehavior::Countdown { duration, .. } => {
+                if duration.is_zero() {
+                    self.error(format!("{field}.duration"), "the countdown has no duration");
+                }
+            }
+            // Which profiles the archive has is only known to the import
+            ButtonBehavior::SwitchProfile(name) => {
+                if name.trim().is_empty() {
+                    self.error(field, "no profile to switch to");
+                }
+            }
+        }
+    }
+
+    fn check_settings(&mut self, field: &str, settings: &PlaySoundSettings) {
+        if !(settings.volume.is_finite() && settings.volume >= 0.0) {
+            self.error(
+                format!("{field}.volume"),
+                format!("{} is not a volume of 0.0 or more", settings.volume),
+            );
+        }
+        if !settings.gain_db.is_finite() {
+            self.error(
+                format!("{field}.gain_db"),
+                format!("{} is not a number of dB", settings.gain_db),
+            );
+        }
+        if !(-1.0..=1.0).contains(&settings.pan) {
+            self.error(
+                format!("{field}.pan"),
+                format!("{} is outside of -1.0 to 1.0", settings.pan),
+            );
+        }
+        if let Some(rate) = settings.playback_rate
+            && !(rate.is_finite() && rate > 0.0)
+        {
+            self.error(
+                format!("{field}.playback_rate"),
+                format!("{rate} is not a speed above 0.0"),
+            );
+        }
+        if let Some(bus) = &settings.bus {
+            self.check_bus(&format!("{field}.bus"), bus);
+        }
+        if settings.max_instances == Some(0) {
+            self.error(
+                format!("{field}.max_instances"),
+                "the sound could never play",
+            );
+        }
+        if settings
+            .fallback
+            .as_ref()
+            .is_some_and(|f| f.trim().is_empty())
+        {
+            self.error(format!("{field}.fallback"), "no file to fall back to");
+        }
+    }
+
+    fn check_bus(&mut self, field: &str, name: &str) {
+        if !self.config.buses.iter().any(|b| b.name == name) {
+            self.warning(
+                field,
+                format!("there is no bus '{name}', the sound plays without effects"),
+            );
+        }
+    }
+
+    fn check_effect(&mut self, field: &str, effect: &Effect) {
+        let mix = match effect {
+            Effect::Reverb {
+                feedback,
+                damping,
+                mix,
+            } => {
+                self.check_unit(&format!("{field}.feedback"), *feedback);
+                self.check_unit(&format!("{field}.damping"), *damping);
+                mix
+            }
+            Effect::Filter {
+                cutoff_hz,
+                resonance,
+                mix,
+                ..
+            } => {
+                if !(cutoff_hz.is_finite() && *cutoff_hz > 0.0) {
+                    self.error(
+                        format!("{field}.cutoff_hz"),
+                        format!("{cutoff_hz} is not a frequency above 0 Hz"),
+                    );
+                }
+                self.check_unit(&format!("{field}.resonance"), *resonance);
+                mix
+            }
+            Effect::Delay {
+                feedback_db, mix, ..
+            } => {
+                if !feedback_db.is_finite() {
+                    self.error(
+                        format!("{field}.feedback_db"),
+                        format!("{feedback_db} is not a number of dB"),
+                    );
+                } else if *feedback_db > 0.0 {
+                    self.warning(
+                        format!("{field}.feedback_db"),
+                        format!("{feedback_db} dB makes every echo louder than the one before"),
+                    );
+                }
+                mix
+            }
+        };
+        self.check_unit(&format!("{field}.mix"), f64::from(*mix));
+    }
+
+    fn check_unit(&mut self, field: &str, value: f64) {
+        if !(0.0..=1.0).contains(&value) {
+            self.error(field, format!("{value} is outside of 0.0 to 1.0"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Severity, ValidationIssue, validate};
+    use crate::config::{Button, ButtonBehavior, Config, Page, PlaySoundSettings, PlaybackMode};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    fn button(behavior: ButtonBehavior) -> Button {
+        Button::new("Button", behavior)
+    }
+
+    fn sound(path: &str, pan: f32) -> ButtonBehavior {
+        ButtonBehavior::PlaySound(
+            Arc::new(path.to_string()),
+            PlaySoundSettings {
+                pan,
+                ..PlaySoundSettings::new(PlaybackMode::PlayStop)
+            },
+        )
+    }
+
+    #[test]
+    fn test_validate_reports_every_issue_with_its_location() {
+        let page_id = Uuid::from_u128(1);
+        let missing_page = Uuid::from_u128(2);
+        let page = Page {
+            name: "Start".to_string(),
+            buttons: vec![
+                button(sound("rain.mp3", 0.0)),
+                button(ButtonBehavior::PushPage(missing_page)),
+                button(ButtonBehavior::Sequence(vec![
+                    ButtonBehavior::StopAll,
+                    sound("rain.mp3", 1.5),
+                ])),
+                button(ButtonBehavior::StopSound(Arc::new("wind.mp3".to_string()))),
+            ],
+            bus: Some("hall".to_string()),
+            defaults: Default::default(),
+        };
+        let config = Config {
+            pages: HashMap::from([(page_id, Arc::new(page))]),
+            start_page: page_id,
+            buses: Vec::new(),
+            schedule: Vec::new(),
+        };
+
+        let issue = |severity, button, field: &str, message: &str| ValidationIssue {
+            severity,
+            page: Some(page_id),
+            button,
+            field: field.to_string(),
+            message: message.to_string(),
+        };
+        assert_eq!(
+            validate(&config),
+            vec![
+                issue(
+                    Severity::Warning,
+                    None,
+                    "bus",
+                    "there is no bus 'hall', the sound plays without effects"
+                ),
+                issue(
+                    Severity::Error,
+                    Some(1),
+                    "behavior",
+                    &format!("there is no page {missing_page}")
+                ),
+                issue(
+                    Severity::Error,
+                    Some(2),
+                    "behavior[1].pan",
+                    "1.5 is outside of -1.0 to 1.0"
+                ),
+                issue(
+                    Severity::Warning,
+                    Some(3),
+                    "behavior",
+                    "'wind.mp3' is not on any button"
+                ),
+            ]
+        );
+        assert_eq!(
+            validate(&config)[2].to_string(),
+            format!(
+                "error: page {page_id}, button 2, behavior[1].pan: 1.5 is outside of -1.0 to 1.0"
+            )
+        );
+    }
+
+    #[test]
+    fn test_button_ids_are_unique_across_pages() {
+        let (first, second) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let id = Uuid::from_u128(3);
+        let with_id = |behavior| Button {
+            id: Some(id),
+            ..button(behavior)
+        };
+        let page = |behavior| Page {
+            name: "Page".to_string(),
+            buttons: vec![with_id(behavior)],
+            bus: None,
+            defaults: Default::default(),
+        };
+        let config = Config {
+            pages: HashMap::from([
+                (first, Arc::new(page(ButtonBehavior::PushPage(second)))),
+                (second, Arc::new(page(ButtonBehavior::Pop))),
+            ]),
+            start_page: first,
+            buses: Vec::new(),
+            schedule: Vec::new(),
+        };
+
+        assert_eq!(
+            validate(&config),
+            vec![ValidationIssue {
+                severity: Severity::Error,
+                page: Some(second),
+                button: Some(0),
+                field: "id".to_string(),
+                message: format!("another button also has the id {id}"),
+            }]
+        );
+    }
+
+    #[cfg(feature = "scripts")]
+    #[test]
+    fn test_scripts_are_compiled_like_the_deck_does() {
+        let page_id = Uuid::from_u128(1);
+        let script = |source: &str| {
+            button(ButtonBehavior::Script {
+                path: Arc::new("scene.rhai".to_string()),
+                source: Arc::new(source.to_string()),
+            })
+        };
+        let page = Page {
+            name: "Start".to_string(),
+            // The deck's engine has no eval, which would run code that was never checked
+            buttons: vec![script(r#"play("rain.mp3")"#), script(r#"eval("back()")"#)],
+            bus: None,
+            defaults: Default::default(),
+        };
+        let config = Config {
+            pages: HashMap::from([(page_id, Arc::new(page))]),
+            start_page: page_id,
+            buses: Vec::new(),
+            schedule: Vec::new(),
+        };
+
+        let issues = validate(&config);
+        assert_eq!(issues.len(), 1, "{issues:?}");
+        assert_eq!(issues[0].button, Some(1));
+        assert_eq!(issues[0].field, "behavior.path");
+    }
+}
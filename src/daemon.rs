@@ -1,162 +1,1371 @@
-use crate::config::{ButtonBehavior, Config, Page};
-use crate::daemon::ui::{ButtonData, ButtonRef, UiCommand};
+use crate::config::{self, ButtonBehavior, Config, Page};
+use crate::daemon::backend::{DeckBackend, KeyEvent, KeyReader, Mirrored, StreamDeck};
+use crate::daemon::history::History;
+use crate::daemon::remote_deck::RemoteDeck;
+use crate::daemon::render::{DisplayMode, RenderJob, RenderRequest, RenderResult, Rendered};
+use crate::daemon::satellite::{CompanionSurface, Satellite};
+use crate::daemon::ui::{ButtonData, ButtonRef, ButtonStyle, StripSegment, Swipe, UiCommand};
 use crate::import::ImportArgs;
+use crate::util::{
+    Switch, canonical_path, is_stream_url, parse_duration_secs, parse_interval_secs,
+};
 use clap::Args;
-use cosmic_text::{Attrs, Buffer, Color, FontSystem, Metrics, Shaping, SwashCache, Weight};
+use cosmic_text::FontSystem;
 use elgato_streamdeck::asynchronous::list_devices_async;
 use elgato_streamdeck::info::Kind;
-use elgato_streamdeck::{AsyncStreamDeck, DeviceStateUpdate, new_hidapi};
-use eyre::{Context, ContextCompat, OptionExt, Report};
-use image::{DynamicImage, ImageBuffer, Rgb};
-use imageproc::image::RgbImage;
-use std::path::PathBuf;
+use elgato_streamdeck::new_hidapi;
+use eyre::{Context, ContextCompat, Report};
+use hidapi::HidApi;
+use image::DynamicImage;
+use std::collections::{BTreeMap, HashSet};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::mpsc::{Receiver, Sender, UnboundedSender};
+use tokio::task::JoinHandle;
 use tokio::time::{Instant, sleep_until};
 use tracing::{debug, error, info, instrument, trace, warn};
 
 mod audio;
+mod backend;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod history;
+mod keys;
+#[cfg(target_os = "linux")]
+mod mpris;
+mod mqtt;
+mod notify;
+#[cfg(feature = "plugins")]
+mod plugin;
+/// Stands in for the plugins of a build without them. Buttons of their behaviors are disabled
+/// like those of any other unknown behavior.
+#[cfg(not(feature = "plugins"))]
+mod plugin {
+    use crate::daemon::ui::{BehaviorRegistry, MediaStatus};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use tokio::sync::watch;
+    use tracing::warn;
+
+    pub enum Plugin {}
+
+    pub fn load(manifests: &[PathBuf]) -> eyre::Result<Vec<Arc<Plugin>>> {
+        if !manifests.is_empty() {
+            warn!(
+                "Not loading {} plugins, noisedeck was built without the `plugins` feature",
+                manifests.len()
+            );
+        }
+        Ok(Vec::new())
+    }
+
+    pub fn register_behaviors(_plugins: &[Arc<Plugin>], _registry: &mut BehaviorRegistry) {}
+
+    pub fn subscribe(_plugins: &[Arc<Plugin>], _status: watch::Receiver<MediaStatus>) {}
+}
+mod remote_deck;
+mod render;
+mod satellite;
+mod state;
+mod systemd;
 mod ui;
+mod watch;
+
+pub use history::{HistoryArgs, run as history};
+pub(crate) use ui::check_script;
+
+/// Where the configuration comes from, for the daemon as well as for `validate`.
+#[derive(Debug, PartialEq, Args, Clone)]
+pub struct ConfigSource {
+    /// Configuration in noisedeck's own JSON format, e.g. written by `import --output` and
+    /// changed with `config`, instead of importing a Stream Deck profile on every start. Only
+    /// this format can hold buses, schedules and the buttons that the Stream Deck lacks.
+    #[arg(
+        long = "config",
+        env = "config_path",
+        required_unless_present = "path",
+        conflicts_with = "ImportArgs"
+    )]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    import: Option<ImportArgs>,
+}
+
+impl ConfigSource {
+    /// Reads the configuration along with the scripts that it refers to. Everything after
+    /// that, such as finding the sound files, is up to the daemon.
+    pub(crate) fn read(
+        &self,
+        progress: crate::import::Progress,
+        send_keys: Switch,
+        run_commands: Switch,
+    ) -> eyre::Result<Config> {
+        let mut config = match (&self.config, &self.import) {
+            (Some(path), _) => config::read(path)?,
+            (None, Some(import)) => {
+                let import = ImportArgs {
+                    send_keys,
+                    run_commands,
+                    ..import.clone()
+                };
+                crate::import::run_sync(import, progress)?
+            }
+            (None, None) => eyre::bail!("Neither a configuration nor a profile to import"),
+        };
+        config::read_scripts(&mut config, self.dir())?;
+        Ok(config)
+    }
+
+    /// Scripts and the state file are found next to the configuration or the profile.
+    fn dir(&self) -> &Path {
+        self.config
+            .as_deref()
+            .or(self.import.as_ref().map(|import| import.path.as_path()))
+            .and_then(Path::parent)
+            .unwrap_or(Path::new(""))
+    }
+}
+
+/// Reads the configuration like the daemon does and reports every problem with it, for the
+/// `validate` command.
+pub(crate) async fn validate(
+    source: ConfigSource,
+    progress: crate::import::Progress,
+) -> eyre::Result<Vec<config::ValidationIssue>> {
+    tokio::task::spawn_blocking(move || {
+        let config = source.read(progress, Switch::Off, Switch::Off)?;
+        Ok(config::validate(&config))
+    })
+    .await?
+}
 
-#[derive(Debug, Eq, PartialEq, Args, Clone)]
+#[derive(Debug, PartialEq, Args, Clone)]
 pub struct DaemonArgs {
     #[command(flatten)]
-    import: ImportArgs,
+    source: ConfigSource,
 
     #[arg(long, env = "audio_path")]
     audio_path: PathBuf,
 
     #[arg(long, env = "check_paths")]
     check_paths: bool,
+
+    /// Put sound files that are added to --audio-path while the daemon runs on an "Unsorted"
+    /// page, e.g. when downloading sounds while preparing a session
+    #[arg(long, env = "watch_audio_path")]
+    watch_audio_path: bool,
+
+    /// Seconds over which playing tracks are faded out when the daemon shuts down
+    #[arg(long, env = "shutdown_fade", default_value = "1.5", value_parser = parse_duration_secs)]
+    shutdown_fade: Duration,
+
+    /// Limiter on the main output that keeps overlapping loud tracks from clipping
+    #[arg(long, env = "limiter", value_enum, default_value_t = Switch::On)]
+    limiter: Switch,
+
+    /// Level in dB above which the limiter reduces the output volume
+    #[arg(long, env = "limiter_threshold", default_value_t = -3.0, allow_negative_numbers = true)]
+    limiter_threshold: f64,
+
+    /// How strongly the limiter compresses levels above the threshold, e.g. 8 means 8:1
+    #[arg(long, env = "limiter_ratio", default_value_t = 8.0)]
+    limiter_ratio: f64,
+
+    /// Confirm presses with a click, presses that change the page with a short chime and presses
+    /// that fail with a falling tone, so that the deck can be used without looking. They play on
+    /// the default output but outside the mix, so the limiter, the global volume and recordings
+    /// leave them alone.
+    #[arg(long, env = "ui_feedback", value_enum, default_value_t = Switch::Off)]
+    ui_feedback: Switch,
+
+    /// Volume in dB of `--ui-feedback`, quiet enough not to be heard over the scene. Values
+    /// above 0 are limited to 0, where the tones are as loud as they get without clipping.
+    #[arg(long, env = "ui_feedback_volume", default_value_t = -24.0, allow_negative_numbers = true)]
+    ui_feedback_volume: f64,
+
+    /// Output device (as named by the OS) on which holding a stopped track previews it,
+    /// e.g. the game master's headphones
+    #[arg(long, env = "cue_device")]
+    cue_device: Option<String>,
+
+    /// Input device (as named by the OS, or `default`) that is played through the mixer, e.g. a
+    /// microphone for the game master's voice. It has its own volume and mute on the volume page
+    /// and starts out muted.
+    #[arg(long, env = "input_device")]
+    input_device: Option<String>,
+
+    /// Directory that recordings of the output mix started from the deck are saved to
+    #[arg(long, env = "recording_dir", default_value = ".")]
+    recording_dir: PathBuf,
+
+    /// Record the output mix into this WAV file from startup until shutdown or until the
+    /// recording is stopped from the deck
+    #[arg(long, env = "record")]
+    record: Option<PathBuf>,
+
+    /// How the configured sound files are checked in the background after startup, so that
+    /// broken ones are marked on the deck before they are first played
+    #[arg(long, env = "preload", value_enum, default_value_t = audio::PreloadMode::Verify)]
+    preload: audio::PreloadMode,
+
+    /// Play nothing and only simulate how long tracks take, for machines without a sound card
+    #[arg(long, env = "null_audio")]
+    null_audio: bool,
+
+    /// Most sounds that may play at once, counting every instance of overlapping tracks, so
+    /// that a runaway pile of overlaps cannot use up the CPU mid-session
+    #[arg(long, env = "max_sounds", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_sounds: Option<usize>,
+
+    /// What happens to a sound that would go over --max-sounds or its track's max_instances
+    #[arg(long, env = "over_limit", value_enum, default_value_t = audio::OverLimit::Refuse)]
+    over_limit: audio::OverLimit,
+
+    /// Seconds between updates of playing tracks on the deck, e.g. of their remaining time
+    #[arg(long, env = "update_interval", default_value = "0.5", value_parser = parse_interval_secs)]
+    update_interval: Duration,
+
+    /// Seconds between updates while a track is near its end, see --near-end, so that its
+    /// countdown runs smoothly
+    #[arg(long, env = "fast_update_interval", default_value = "0.1", value_parser = parse_interval_secs)]
+    fast_update_interval: Duration,
+
+    /// Seconds before the end of a track from which its button warns that it is about to end;
+    /// 0 turns the warning off. Looping tracks start over instead of ending, so they never warn.
+    #[arg(long, env = "near_end", default_value = "10", value_parser = parse_duration_secs)]
+    near_end: Duration,
+
+    /// Most times per second that changed key images are sent to the StreamDeck. Changes in
+    /// between are sent together, which keeps countdowns on many keys of an XL from flooding USB.
+    #[arg(
+        long,
+        env = "max_fps",
+        default_value_t = 25,
+        value_parser = clap::builder::RangedU64ValueParser::<u32>::new().range(1..)
+    )]
+    max_fps: u32,
+
+    /// How the keys look. `high-contrast` has bigger, bolder text in black and white only, for
+    /// reading the deck at arm's length.
+    #[arg(long, env = "display_mode", value_enum, default_value_t = DisplayMode::Standard)]
+    display_mode: DisplayMode,
+
+    /// Show the current page's name and how deep it is in the navigation stack on a key of the
+    /// bottom row. Holding that key lists the pages that lead to it.
+    #[arg(long, env = "page_title")]
+    page_title: bool,
+
+    /// What holding a stopped track does. Defaults to cueing it when there is a --cue-device
+    /// and to pinning it to the favorites page otherwise.
+    #[arg(long, env = "hold_stopped_track", value_enum)]
+    hold_stopped_track: Option<ui::HoldStoppedTrack>,
+
+    /// What holding Back does
+    #[arg(long, env = "back_hold", value_enum, default_value_t = ui::BackHold::Home)]
+    back_hold: ui::BackHold,
+
+    /// What holding Next does
+    #[arg(long, env = "next_hold", value_enum, default_value_t = ui::NextHold::PreviousPage)]
+    next_hold: ui::NextHold,
+
+    /// How the playing tracks are ordered on the deck. Can be changed on the volume page.
+    #[arg(long, env = "playing_order", value_enum, default_value_t = ui::PlayingOrder::Started)]
+    playing_order: ui::PlayingOrder,
+
+    /// How volumes are shown on the deck. Can be changed by holding a global volume button.
+    #[arg(long, env = "volume_unit", value_enum, default_value_t = ui::VolumeUnit::Decibel)]
+    volume_unit: ui::VolumeUnit,
+
+    /// Which time the buttons of playing tracks show
+    #[arg(long, env = "track_time", value_enum, default_value_t = ui::TrackTime::Remaining)]
+    track_time: ui::TrackTime,
+
+    /// Whether the time of playing tracks also shows tenths of a second
+    #[arg(
+        long,
+        env = "track_time_precision",
+        value_enum,
+        default_value_t = ui::TimePrecision::Seconds
+    )]
+    track_time_precision: ui::TimePrecision,
+
+    /// File that keeps what is changed on the deck across restarts and re-imports, such as pinned
+    /// favorites, track edits, labels and the volume. Defaults to `noisedeck-state.json` in the
+    /// state directory that systemd gives the service, or else next to the configuration.
+    #[arg(long, env = "state_file")]
+    state_file: Option<PathBuf>,
+
+    /// File to append what is pressed and played to, for `noisedeck history`. Without one, only
+    /// the latest entries are kept, and only until the daemon stops.
+    #[arg(long, env = "history_file")]
+    history_file: Option<PathBuf>,
+
+    /// JSON file that renames the deck's own buttons, e.g. `{"back": "Zurück"}`, to translate
+    /// or shorten them
+    #[arg(long, env = "labels")]
+    labels: Option<PathBuf>,
+
+    /// Let buttons run the programs named in the configuration. Off by default, because an
+    /// imported profile could otherwise run anything on this machine.
+    #[arg(long, env = "allow_commands")]
+    allow_commands: bool,
+
+    /// Let buttons send the key chords named in the configuration to the focused application
+    #[arg(long, env = "allow_keystrokes")]
+    allow_keystrokes: bool,
+
+    /// Show desktop notifications when playback fails, the deck disconnects or a configuration
+    /// reload fails
+    #[arg(long, env = "desktop_notifications")]
+    desktop_notifications: bool,
+
+    /// Start without a StreamDeck and attach to one once it is plugged in, e.g. when the daemon
+    /// is started at boot. A StreamDeck that is unplugged is waited for in the same way, while
+    /// the sounds keep playing.
+    #[arg(long, env = "wait_for_device")]
+    wait_for_device: bool,
+
+    /// The StreamDeck that the pages are laid out for while none is plugged in, see
+    /// `--wait-for-device`. StreamDecks with other keys are not attached to until the daemon is
+    /// restarted with them.
+    #[arg(long, env = "device_model", value_enum, default_value_t = DeckModel::Original)]
+    device_model: DeckModel,
+
+    /// Manifest of a WebAssembly plugin to load; can be given several times
+    #[arg(long = "plugin", env = "plugins", value_delimiter = ',')]
+    plugins: Vec<PathBuf>,
+
+    /// Another profile of the imported archive, e.g. of a second campaign, that the deck can
+    /// switch to from its "Campaigns" page; can be given several times. With `--config`, the
+    /// configuration `<campaign>.json` next to it.
+    #[arg(long = "campaign", env = "campaigns", value_delimiter = ',')]
+    campaigns: Vec<String>,
+
+    /// What happens to the playing sounds when the deck switches to another campaign
+    #[arg(long, env = "campaign_switch", value_enum, default_value_t = ui::CampaignSwitch::Stop)]
+    campaign_switch: ui::CampaignSwitch,
+
+    /// What a second StreamDeck shows, if one is plugged in when the daemon starts
+    #[arg(long, env = "second_deck", value_enum)]
+    second_deck: Option<SecondDeck>,
+
+    /// Address of Bitfocus Companion's Satellite port, for `--second-deck companion`
+    #[arg(long, env = "companion", default_value = "127.0.0.1:16622")]
+    companion: String,
+
+    /// Address to serve Bitfocus Companion Satellite on in place of Companion, e.g.
+    /// `0.0.0.0:16622`. A surface with the same keys as the StreamDeck then shows them as well,
+    /// and presses on it count.
+    #[arg(long, env = "satellite")]
+    satellite: Option<String>,
+
+    /// Machine other than this one whose Satellite surfaces `--satellite` serves; can be given
+    /// several times. The protocol has no password, so anyone else is turned away.
+    #[arg(
+        long = "satellite-peer",
+        env = "satellite_peers",
+        value_delimiter = ','
+    )]
+    satellite_peers: Vec<IpAddr>,
+
+    /// MQTT broker to announce the deck to Home Assistant on, e.g. `localhost:1883`. Stopping
+    /// everything, pausing, the volume of each bus and `Sequence` buttons (as scenes) then show
+    /// up as a device there. The connection, including the password, is plain TCP.
+    #[arg(long, env = "mqtt")]
+    mqtt: Option<String>,
+
+    /// User name for the MQTT broker
+    #[arg(long, env = "mqtt_user", requires = "mqtt")]
+    mqtt_user: Option<String>,
+
+    /// Password for the MQTT broker
+    #[arg(long, env = "mqtt_password", requires = "mqtt", hide_env_values = true)]
+    mqtt_password: Option<String>,
+
+    /// Name of the device in Home Assistant and start of its MQTT topics; differs between
+    /// daemons that share a broker
+    #[arg(long, env = "mqtt_name", default_value = "noisedeck")]
+    mqtt_name: String,
+
+    /// Topic prefix that Home Assistant discovers devices under
+    #[arg(long, env = "mqtt_discovery_prefix", default_value = "homeassistant")]
+    mqtt_discovery_prefix: String,
+
+    /// Address to wait for `noisedeck deck` on, e.g. `0.0.0.0:16700`, in place of a StreamDeck
+    /// that is plugged in here. The StreamDeck can then sit at the table, on another machine,
+    /// while the sound comes from this one.
+    #[arg(long, env = "remote_deck", requires = "remote_deck_token")]
+    remote_deck: Option<String>,
+
+    /// Secret that `noisedeck deck` has to know, given to it with `--token`. Whoever can reach
+    /// `--remote-deck` could otherwise take over the deck, and with it the commands that
+    /// `--allow-commands` lets buttons run. The token and everything after it go over plain TCP,
+    /// so anyone on the network in between can read them; keep both machines on a network you
+    /// trust.
+    #[arg(long, env = "remote_deck_token", hide_env_values = true)]
+    remote_deck_token: Option<String>,
+
+    /// The StreamDeck that `noisedeck deck` runs on, which the pages are laid out for
+    #[arg(long, env = "remote_deck_model", value_enum, default_value_t = DeckModel::Original)]
+    remote_deck_model: DeckModel,
+
+    /// Address to serve the gRPC control API on, e.g. `127.0.0.1:50051`. Anyone who can reach it
+    /// can play and stop sounds.
+    #[cfg(feature = "grpc")]
+    #[arg(long, env = "grpc")]
+    grpc: Option<String>,
 }
 
-#[tracing::instrument(skip(args))]
-pub async fn run(args: DaemonArgs) -> Result<(), eyre::Error> {
-    let hid = new_hidapi().context("Failed to create HIDAPI")?;
-    let devices = list_devices_async(&hid);
-    info!("Found {} devices", devices.len());
-    let (kind, serial) = devices
-        .iter()
-        .find(|(kind, _)| *kind == Kind::Original || *kind == Kind::OriginalV2)
-        .ok_or_eyre("No supported StreamDeck found")?;
-
-    let device = AsyncStreamDeck::connect(&hid, *kind, serial)
-        .with_context(|| format!("Failed to connect to device {:?} {}", kind, &serial))?;
-    debug!(
-        "Connected to '{}' with version '{}'. Key count {}",
-        device.serial_number().await?,
-        device.firmware_version().await?,
-        kind.key_count()
-    );
-
-    device.set_brightness(60).await?;
-    device.clear_all_button_images().await?;
-
-    let config = Arc::new(
-        tokio::task::spawn_blocking(move || match crate::import::run_sync(args.import.clone()) {
-            Ok(mut config) => {
-                rebase_paths(&args, &mut config)?;
-                Ok(config)
+impl DaemonArgs {
+    /// The profile that is imported, or the name of the configuration file without `.json`.
+    fn campaign(&self) -> String {
+        match (&self.source.config, &self.source.import) {
+            (Some(path), _) => path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            (None, Some(import)) => import.profile_name.clone(),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// Another campaign is another profile of the same archive, or another configuration in the
+    /// same directory.
+    fn with_campaign(&self, name: &str) -> eyre::Result<DaemonArgs> {
+        let mut args = self.clone();
+        if let Some(path) = &mut args.source.config {
+            // Otherwise a button could load any configuration file on the machine
+            if name.contains(std::path::is_separator) {
+                eyre::bail!("The campaign {name:?} is not a file name");
             }
-            e => e,
-        })
-        .await??,
-    );
+            path.set_file_name(format!("{name}.json"));
+        } else if let Some(import) = &mut args.source.import {
+            import.profile_name = name.to_string();
+        }
+        Ok(args)
+    }
 
-    let (mut deck, ui_event_tx, mut ui_command_rx, audio_event_tx, audio_command_rx) =
-        ui::NoiseDeck::new(device.kind(), config.clone());
-    deck.init().await?;
-    let deck_finished = tokio::spawn(deck.run());
-    let audio_player_finished = tokio::spawn(audio::run(audio_event_tx, audio_command_rx));
+    fn state_file(&self) -> PathBuf {
+        if let Some(path) = &self.state_file {
+            return path.clone();
+        }
+        // Wherever the daemon happens to be started from would lose the state between restarts
+        let dir = std::env::var_os("STATE_DIRECTORY")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.config_dir().to_path_buf());
+        dir.join("noisedeck-state.json")
+    }
 
-    let font_system = load_fonts().await?;
-    let swash_cache = SwashCache::new();
-    let mut state = DeckState {
-        page: vec![],
-        render_cache: vec![],
-        font_system,
-        swash_cache,
-        device,
-        event_tx: ui_event_tx,
-        buttons_held: vec![],
+    fn config_dir(&self) -> &Path {
+        self.source.dir()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SecondDeck {
+    /// The same keys as the first deck
+    Mirror,
+    /// The playing tracks, whichever page the first deck is on
+    NowPlaying,
+    /// The pages of Bitfocus Companion, as a Satellite surface of Companion's, see `--companion`
+    Companion,
+}
+
+/// The StreamDecks that the daemon supports, for `--remote-deck-model` and `--device-model`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DeckModel {
+    /// 15 keys, like both versions of the original
+    Original,
+    /// 8 keys and a touch strip
+    Plus,
+}
+
+impl DeckModel {
+    fn kind(self) -> Kind {
+        match self {
+            DeckModel::Original => Kind::Original,
+            DeckModel::Plus => Kind::Plus,
+        }
+    }
+}
+
+/// Runs only the device loop, for a daemon on another machine that has `--remote-deck`.
+#[derive(Debug, PartialEq, Args, Clone)]
+pub struct DeckArgs {
+    /// Address of the daemon, e.g. `192.168.1.20:16700`
+    #[arg(long, env = "deck_host")]
+    host: String,
+
+    /// The daemon's `--remote-deck-token`
+    #[arg(long, env = "deck_token", hide_env_values = true)]
+    token: String,
+
+    /// See `noisedeck daemon --max-fps`
+    #[arg(
+        long,
+        env = "max_fps",
+        default_value_t = 25,
+        value_parser = clap::builder::RangedU64ValueParser::<u32>::new().range(1..)
+    )]
+    max_fps: u32,
+
+    /// See `noisedeck daemon --display-mode`
+    #[arg(long, env = "display_mode", value_enum, default_value_t = DisplayMode::Standard)]
+    display_mode: DisplayMode,
+}
+
+#[tracing::instrument(skip(args))]
+pub async fn run(args: DaemonArgs) -> Result<(), eyre::Error> {
+    if let Some(address) = args.remote_deck.clone() {
+        let token = args
+            .remote_deck_token
+            .clone()
+            .context("--remote-deck needs --remote-deck-token")?;
+        return run_remote_host(args, &address, token).await;
+    }
+    let mut hid = new_hidapi().context("Failed to create HIDAPI")?;
+    let mut found = supported_devices(list_devices_async(&hid)).into_iter();
+    let (found, second) = (found.next(), found.next());
+    if found.is_none() && !args.wait_for_device {
+        eyre::bail!("No supported StreamDeck found");
+    }
+    let second = match (args.second_deck, second) {
+        (Some(role), Some(second)) => Some((role, second)),
+        (Some(_), None) => {
+            warn!("No second StreamDeck found, the first one is used on its own");
+            None
+        }
+        (None, _) => None,
+    };
+    let kind = found
+        .as_ref()
+        .map_or(args.device_model.kind(), |(kind, _)| *kind);
+    let layout = kind.key_layout();
+
+    let now_playing_deck = match &second {
+        Some((SecondDeck::NowPlaying, (kind, _))) => Some(kind.key_count().into()),
+        _ => None,
     };
+    let Running {
+        ui_event_tx,
+        mut ui_command_rx,
+        deck_finished,
+        audio_player_finished,
+    } = start(&args, kind, now_playing_deck).await?;
 
-    let reader = state.device.get_reader();
+    let font_system = load_fonts().await?;
+    let (rendered_tx, mut rendered_rx) = tokio::sync::mpsc::channel(16);
+    let render_tx = render::spawn(font_system, args.display_mode, rendered_tx)?;
     let sigint = tokio::signal::ctrl_c();
     tokio::pin!(sigint);
+    let mut reload = reload_signal().context("Failed to register reload signal handler")?;
+    let mut watchdog = systemd::watchdog_interval().map(tokio::time::interval);
+    let mut ready = false;
+    let loader = Loader::spawn(args.clone(), &ui_event_tx);
 
-    'infinite: loop {
-        let active_timeout = state
-            .buttons_held
-            .iter()
-            .map(|(_, at)| at)
-            .min()
-            .map(|earliest| sleep_until(*earliest + HOLD_TIME));
-        tokio::select! {
-            _ = async { active_timeout.unwrap().await }, if active_timeout.is_some() => {
-                debug!("Hold timeout reached");
-                let now = Instant::now();
-                while let Some(i) = state.buttons_held.iter().position(|(_, pressed_at)| now.duration_since(*pressed_at) >= HOLD_TIME){
-                    let (b, _) = state.buttons_held.swap_remove(i);
-                    if state.page.iter().any(|ob| ob.as_ref().is_some_and(|page_b| *page_b == b)) {
-                        state.event_tx.send(ui::UiEvent::ButtonHold(b)).await?;
-                    } else {
-                        warn!("held button {:?} no longer found in page", b);
+    let satellite = match &args.satellite {
+        Some(address) => {
+            let peers = args.satellite_peers.clone();
+            Some(Satellite::listen(address, layout, peers).await?)
+        }
+        None => None,
+    };
+    // Whichever order the devices are listed in, the second deck stays the second one
+    let second_serial = second.as_ref().map(|(_, (_, serial))| serial.clone());
+    let mut found = found;
+    let mut waiting_page = None;
+    // StreamDecks that were plugged in but do not fit the layout, so that each is reported once
+    let mut unfit = HashSet::new();
+    let mut retry = false;
+    // Only goes round again with --wait-for-device, once the StreamDeck has been unplugged or could
+    // not be opened
+    'device: loop {
+        let (kind, serial) = match found.take() {
+            Some(found) => found,
+            None => {
+                info!("No supported StreamDeck found, waiting for one to be plugged in");
+                // Waiting for the device is what the daemon was started to do, so a service manager
+                // must neither time out its start nor kill it as hung in the meantime
+                if !ready {
+                    systemd::ready();
+                    ready = true;
+                }
+                let mut poll = tokio::time::interval(DEVICE_POLL_INTERVAL);
+                // Opening the same StreamDeck again right away would most likely fail again
+                if std::mem::take(&mut retry) {
+                    poll.reset();
+                }
+                loop {
+                    tokio::select! {
+                        _ = poll.tick() => {
+                            if let Err(e) = hid.refresh_devices() {
+                                warn!(error = %e, "Error looking for devices");
+                            }
+                            let mut devices = supported_devices(list_devices_async(&hid))
+                                .into_iter()
+                                .filter(|(_, serial)| Some(serial) != second_serial.as_ref());
+                            if let Some(found) = devices.find(|(kind, serial)| {
+                                // The pages are laid out already, and only fit the same keys
+                                let fits = kind.key_layout() == layout;
+                                if !fits && unfit.insert(serial.clone()) {
+                                    warn!(
+                                        "The {kind:?} that was plugged in has other keys than the \
+                                        pages are laid out for, see --device-model"
+                                    );
+                                }
+                                fits
+                            }) {
+                                break found;
+                            }
+                        },
+                        _ = async { watchdog.as_mut().unwrap().tick().await },
+                            if watchdog.is_some() =>
+                        {
+                            systemd::pet_watchdog();
+                        },
+                        // The deck and the audio engine are already running; the page that is
+                        // current once the device appears is the one to show.
+                        command = ui_command_rx.recv() => match command {
+                            Some(UiCommand::Flip(page)) => waiting_page = Some(page),
+                            Some(UiCommand::LoadCampaign(name)) => {
+                                loader.load_campaign(name);
+                            }
+                            Some(_) => {}
+                            None => eyre::bail!("Deck stopped before a StreamDeck was found"),
+                        },
+                        Some(()) = reload.recv() => {
+                            loader.reload();
+                        },
+                        _ = &mut sigint => {
+                            info!("Received SIGINT while waiting for a StreamDeck, shutting down");
+                            drop(ui_event_tx);
+                            if let Err(e) = deck_finished.await? {
+                                error!("Deck task failed: {}", e);
+                            }
+                            if let Err(e) = audio_player_finished.await? {
+                                error!("Audio player task failed: {}", e);
+                            }
+                            return Ok(());
+                        }
                     }
                 }
-            },
-            updates_result = reader.read(100.0) => {
-                let updates = updates_result.context("Failed to read updates")?;
-                match state.handle_updates(updates).await {
-                    Ok(_) => {}
-                    Err(e) => {
-                        warn!(error = %e, "Error handling updates");
+            }
+        };
+
+        let device = match StreamDeck::connect(&hid, kind, &serial).await {
+            Ok(device) => device,
+            // A StreamDeck that is still being set up, or that may not be opened, can be
+            // plugged in again
+            Err(e) if args.wait_for_device => {
+                warn!(
+                    error = %e,
+                    "Failed to open the StreamDeck, waiting for it to be plugged in again"
+                );
+                found = None;
+                retry = true;
+                continue 'device;
+            }
+            Err(e) => return Err(e),
+        };
+        let mut now_playing = None;
+        let mut companion = None;
+        let device = match &second {
+            Some((SecondDeck::Mirror, (kind, serial))) => {
+                match connect_second_deck(&hid, *kind, serial).await {
+                    Some(second) => Mirrored::with_copy(device, second)?,
+                    None => Mirrored::new(device),
+                }
+            }
+            Some((SecondDeck::NowPlaying, (kind, serial))) => {
+                if let Some(second) = connect_second_deck(&hid, *kind, serial).await {
+                    let event_tx = ui_event_tx.clone();
+                    let deck =
+                        NowPlayingDeck::spawn(second, event_tx, args.max_fps, args.display_mode);
+                    now_playing = Some(deck.await?);
+                }
+                Mirrored::new(device)
+            }
+            Some((SecondDeck::Companion, (kind, serial))) => {
+                if let Some(second) = connect_second_deck(&hid, *kind, serial).await {
+                    let device_id = format!("noisedeck-{serial}");
+                    companion = Some(CompanionSurface::spawn(
+                        second,
+                        args.companion.clone(),
+                        device_id,
+                    ));
+                }
+                Mirrored::new(device)
+            }
+            None => Mirrored::new(device),
+        };
+        let device = match &satellite {
+            Some(satellite) => Mirrored::with_copy(device, satellite.clone())?,
+            None => Mirrored::new(device),
+        };
+        device.set_brightness(60).await?;
+        device.clear_all_keys().await?;
+
+        let mut state = DeckState {
+            page: vec![],
+            render_cache: vec![],
+            render_tx: render_tx.clone(),
+            device,
+            event_tx: ui_event_tx.clone(),
+            buttons_held: vec![],
+            overlays: vec![],
+            strip: None,
+            frame: Frame::new(args.max_fps),
+        };
+
+        if let Some(page) = waiting_page.take() {
+            state.handle_command(UiCommand::Flip(page)).await?;
+        }
+
+        let reader = state.device.reader();
+
+        'infinite: loop {
+            let active_timeout = state.hold_deadline().map(sleep_until);
+            let overlay_timeout = state.overlay_deadline().map(sleep_until);
+            let frame_timeout = state.frame.deadline().map(sleep_until);
+            tokio::select! {
+                _ = async { overlay_timeout.unwrap().await }, if overlay_timeout.is_some() => {
+                    if let Err(e) = state.end_overlays().await {
+                        warn!(error = %e, "Error restoring keys after an overlay");
                         break 'infinite;
                     }
-                }
-            },
-            command = ui_command_rx.recv() => {
-                if let Some(command) = command {
-                    match state.handle_command(command).await {
+                },
+                _ = async { frame_timeout.unwrap().await }, if frame_timeout.is_some() => {
+                    if let Err(e) = state.send_frame().await {
+                        warn!(error = %e, "Error showing rendered buttons");
+                        break 'infinite;
+                    }
+                },
+                _ = async { active_timeout.unwrap().await }, if active_timeout.is_some() => {
+                    if let Err(e) = state.send_holds().await {
+                        warn!(error = %e, "Error handling held buttons");
+                        break 'infinite;
+                    }
+                },
+                updates_result = reader.read() => {
+                    let updates = match updates_result {
+                        Ok(updates) => updates,
+                        Err(e) => {
+                            if args.desktop_notifications {
+                                notify::error("Stream Deck disconnected", &e);
+                            }
+                            if !args.wait_for_device {
+                                return Err(e.wrap_err("Failed to read updates"));
+                            }
+                            warn!(
+                                error = %e,
+                                "StreamDeck disconnected, waiting for it to be plugged in again"
+                            );
+                            waiting_page = Some(std::mem::take(&mut state.page));
+                            drop(reader);
+                            drop(state);
+                            stop_second_decks(now_playing, companion).await;
+                            continue 'device;
+                        }
+                    };
+                    match state.handle_updates(updates).await {
                         Ok(_) => {}
                         Err(e) => {
-                            warn!(error = %e, "Error handling command");
+                            warn!(error = %e, "Error handling updates");
                             break 'infinite;
                         }
                     }
-                } else {
-                    info!("Command channel closed");
-                    break 'infinite
-                }
-            },
-            sigint_result = &mut sigint => {
-                match sigint_result {
-                    Ok(_) => {
-                        info!("Received SIGINT, shutting down gracefully");
+                },
+                Some(rendered) = rendered_rx.recv() => {
+                    if let Err(e) = state.show_rendered(rendered).await {
+                        warn!(error = %e, "Error showing rendered buttons");
                         break 'infinite;
                     }
-                    Err(e) => {
-                        warn!(error = %e, "Error waiting for SIGINT");
-                        break 'infinite;
+                    // Only now is the deck usable, which is what a service manager waits for
+                    if !ready {
+                        systemd::ready();
+                        ready = true;
+                    }
+                },
+                _ = async { watchdog.as_mut().unwrap().tick().await }, if watchdog.is_some() => {
+                    systemd::pet_watchdog();
+                },
+                command = ui_command_rx.recv() => {
+                    if let Some(command) = command {
+                        for command in UiCommand::coalesce(command, &mut ui_command_rx) {
+                            let result = match command {
+                                UiCommand::FlipNowPlaying(page) => {
+                                    let flip = UiCommand::Flip(page);
+                                    NowPlayingDeck::forward(&mut now_playing, flip).await;
+                                    Ok(())
+                                }
+                                UiCommand::Refresh => {
+                                    let refresh = UiCommand::Refresh;
+                                    NowPlayingDeck::forward(&mut now_playing, refresh).await;
+                                    state.handle_command(UiCommand::Refresh).await
+                                }
+                                UiCommand::LoadCampaign(name) => {
+                                    loader.load_campaign(name);
+                                    Ok(())
+                                }
+                                command => state.handle_command(command).await,
+                            };
+                            match result {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    warn!(error = %e, "Error handling command");
+                                    break 'infinite;
+                                }
+                            }
+                        }
+                    } else {
+                        info!("Command channel closed");
+                        break 'infinite
+                    }
+                },
+                Some(()) = reload.recv() => {
+                    loader.reload();
+                },
+                sigint_result = &mut sigint => {
+                    match sigint_result {
+                        Ok(_) => {
+                            info!("Received SIGINT, shutting down gracefully");
+                            break 'infinite;
+                        }
+                        Err(e) => {
+                            warn!(error = %e, "Error waiting for SIGINT");
+                            break 'infinite;
+                        }
                     }
                 }
             }
         }
+        systemd::stopping();
+        drop(reader);
+        let device = state.shutdown();
+        stop_second_decks(now_playing, companion).await;
+        drop(ui_event_tx);
+        if let Err(e) = deck_finished.await? {
+            error!("Deck task failed: {}", e);
+        }
+        if let Err(e) = audio_player_finished.await? {
+            error!("Audio player task failed: {}", e);
+        }
+
+        return device.shut_down().await;
     }
-    drop(reader);
-    let device = state.shutdown();
+}
+
+/// The second deck is an extra: when it cannot be opened, the first deck goes on without it.
+async fn connect_second_deck(hid: &HidApi, kind: Kind, serial: &str) -> Option<StreamDeck> {
+    match StreamDeck::connect(hid, kind, serial).await {
+        Ok(second) => Some(second),
+        Err(e) => {
+            warn!(error = %e, "Failed to open the second StreamDeck, going on without it");
+            None
+        }
+    }
+}
+
+/// The second deck and the Companion surface stop along with the first deck.
+async fn stop_second_decks(
+    now_playing: Option<NowPlayingDeck>,
+    companion: Option<CompanionSurface>,
+) {
+    if let Some(now_playing) = now_playing
+        && let Err(e) = now_playing.stop().await
+    {
+        error!("Now playing deck failed: {:?}", e);
+    }
+    if let Some(companion) = companion
+        && let Err(e) = companion.stop().await
+    {
+        error!("Companion surface failed: {:?}", e);
+    }
+}
+
+/// The deck and the audio engine, which keep running until the deck's events stop.
+struct Running {
+    ui_event_tx: Sender<ui::UiEvent>,
+    ui_command_rx: Receiver<UiCommand>,
+    deck_finished: JoinHandle<eyre::Result<()>>,
+    audio_player_finished: JoinHandle<eyre::Result<()>>,
+}
+
+/// Loads the configuration and starts everything but the device loop, which is up to the caller.
+async fn start(
+    args: &DaemonArgs,
+    kind: Kind,
+    now_playing_deck: Option<usize>,
+) -> eyre::Result<Running> {
+    let config = Arc::new(load_config(args.clone()).await?);
+    let audio_settings = audio::AudioSettings {
+        shutdown_fade: args.shutdown_fade,
+        limiter: match args.limiter {
+            Switch::On => Some(audio::LimiterSettings {
+                threshold_db: args.limiter_threshold,
+                ratio: args.limiter_ratio,
+            }),
+            Switch::Off => None,
+        },
+        buses: config.buses.clone(),
+        cue_device: args.cue_device.clone(),
+        input_device: args.input_device.clone(),
+        recording_dir: args.recording_dir.clone(),
+        record_on_start: args.record.clone(),
+        preload: args.preload,
+        output: if args.null_audio {
+            audio::AudioOutput::Null
+        } else {
+            audio::AudioOutput::Device
+        },
+        updates: audio::UpdateIntervals {
+            normal: args.update_interval,
+            fast: args.fast_update_interval,
+            near_end: args.near_end,
+        },
+        limits: audio::VoiceLimits {
+            max_sounds: args.max_sounds,
+            over_limit: args.over_limit,
+        },
+        ui_feedback: match args.ui_feedback {
+            Switch::On => Some(args.ui_feedback_volume),
+            Switch::Off => None,
+        },
+    };
+
+    let manifests = args.plugins.clone();
+    let plugins = tokio::task::spawn_blocking(move || plugin::load(&manifests)).await??;
+    let history = Arc::new(match &args.history_file {
+        Some(path) => History::with_file(path).await?,
+        None => History::default(),
+    });
+    let labels = match &args.labels {
+        Some(path) => ui::Labels::load(path).await?,
+        None => ui::Labels::default(),
+    };
+    let mut behaviors = ui::BehaviorRegistry::default();
+    plugin::register_behaviors(&plugins, &mut behaviors);
+    let ui_settings = ui::UiSettings {
+        page_title: if args.page_title {
+            Switch::On
+        } else {
+            Switch::Off
+        },
+        hold_stopped_track: args
+            .hold_stopped_track
+            .unwrap_or(if args.cue_device.is_some() {
+                ui::HoldStoppedTrack::Cue
+            } else {
+                ui::HoldStoppedTrack::Pin
+            }),
+        back_hold: args.back_hold,
+        next_hold: args.next_hold,
+        playing_order: args.playing_order,
+        volume_unit: args.volume_unit,
+        track_time: args.track_time,
+        track_time_precision: args.track_time_precision,
+        state_file: Some(args.state_file()),
+        run_commands: if args.allow_commands {
+            Switch::On
+        } else {
+            Switch::Off
+        },
+        send_keys: if args.allow_keystrokes {
+            Switch::On
+        } else {
+            Switch::Off
+        },
+        desktop_notifications: if args.desktop_notifications {
+            Switch::On
+        } else {
+            Switch::Off
+        },
+        behaviors,
+        now_playing_deck,
+        touch_strip: if kind.lcd_strip_size().is_some() {
+            Switch::On
+        } else {
+            Switch::Off
+        },
+        campaigns: std::iter::once(args.campaign())
+            .chain(
+                args.campaigns
+                    .iter()
+                    .filter(|c| **c != args.campaign())
+                    .cloned(),
+            )
+            .collect(),
+        campaign_switch: args.campaign_switch,
+        history: history.clone(),
+        labels: Arc::new(labels),
+        ui_feedback: args.ui_feedback,
+        clock: config::schedule::Clock::default(),
+        near_end: args.near_end,
+    };
+    let (mut deck, ui_event_tx, ui_command_rx, audio_event_tx, audio_command_rx) =
+        ui::NoiseDeck::new(kind, config.clone(), ui_settings);
+    deck.init().await?;
+    #[cfg(target_os = "linux")]
+    if let Err(e) = mpris::serve(ui_event_tx.clone(), deck.media_status()).await {
+        warn!("Media keys cannot control the deck: {e:#}");
+    }
+    plugin::subscribe(&plugins, deck.media_status());
+    if let Some(address) = &args.mqtt {
+        let settings = mqtt::MqttSettings {
+            address: address.clone(),
+            user: args.mqtt_user.clone(),
+            password: args.mqtt_password.clone(),
+            name: args.mqtt_name.clone(),
+            discovery_prefix: args.mqtt_discovery_prefix.clone(),
+        };
+        mqtt::spawn(
+            settings,
+            ui_event_tx.clone(),
+            deck.media_status(),
+            deck.config(),
+        );
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(address) = &args.grpc {
+        let audio_path = args.audio_path.clone();
+        let status = deck.media_status();
+        grpc::serve(
+            address,
+            audio_path,
+            ui_event_tx.clone(),
+            status,
+            history.clone(),
+        )
+        .await?;
+    }
+    if args.watch_audio_path {
+        watch::spawn(args.audio_path.clone(), ui_event_tx.clone());
+    }
+    let deck_finished = tokio::spawn(deck.run());
+    let audio_player_finished = tokio::spawn(audio::run(
+        audio_event_tx,
+        history.tap(audio_command_rx),
+        audio_settings,
+    ));
+    Ok(Running {
+        ui_event_tx,
+        ui_command_rx,
+        deck_finished,
+        audio_player_finished,
+    })
+}
+
+/// Serves the deck to `noisedeck deck` on another machine rather than to a StreamDeck here.
+async fn run_remote_host(args: DaemonArgs, address: &str, token: String) -> eyre::Result<()> {
+    let kind = args.remote_deck_model.kind();
+    let Running {
+        ui_event_tx,
+        mut ui_command_rx,
+        deck_finished,
+        audio_player_finished,
+    } = start(&args, kind, None).await?;
+    let remote = RemoteDeck::listen(address, kind.key_layout(), token, ui_event_tx.clone()).await?;
+    let mut reload = reload_signal().context("Failed to register reload signal handler")?;
+    let mut watchdog = systemd::watchdog_interval().map(tokio::time::interval);
+    let loader = Loader::spawn(args.clone(), &ui_event_tx);
+    // The StreamDeck comes and goes with the machine at the table, so it is not waited for
+    systemd::ready();
+    let sigint = tokio::signal::ctrl_c();
+    tokio::pin!(sigint);
+
+    loop {
+        tokio::select! {
+            command = ui_command_rx.recv() => match command {
+                Some(command) => {
+                    let mut sent = Ok(());
+                    for command in UiCommand::coalesce(command, &mut ui_command_rx) {
+                        match command {
+                            UiCommand::LoadCampaign(name) => {
+                                loader.load_campaign(name);
+                            }
+                            command => {
+                                sent = remote.send(command).await;
+                                if sent.is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    if let Err(e) = sent {
+                        warn!("{e:#}");
+                        break;
+                    }
+                }
+                None => {
+                    info!("Command channel closed");
+                    break;
+                }
+            },
+            Some(()) = reload.recv() => {
+                loader.reload();
+            },
+            _ = async { watchdog.as_mut().unwrap().tick().await }, if watchdog.is_some() => {
+                systemd::pet_watchdog();
+            },
+            _ = &mut sigint => {
+                info!("Received SIGINT, shutting down gracefully");
+                break;
+            }
+        }
+    }
+    systemd::stopping();
+    drop(remote);
+    drop(ui_event_tx);
     if let Err(e) = deck_finished.await? {
         error!("Deck task failed: {}", e);
     }
     if let Err(e) = audio_player_finished.await? {
         error!("Audio player task failed: {}", e);
     }
+    Ok(())
+}
+
+/// Shows the deck of a daemon on another machine on the StreamDeck that is plugged in here.
+#[tracing::instrument(skip(args))]
+pub async fn run_remote(args: DeckArgs) -> eyre::Result<()> {
+    let hid = new_hidapi().context("Failed to create HIDAPI")?;
+    let (kind, serial) = supported_devices(list_devices_async(&hid))
+        .into_iter()
+        .next()
+        .context("No supported StreamDeck found")?;
+    let device = StreamDeck::connect(&hid, kind, &serial).await?;
+    let (event_tx, event_rx) = tokio::sync::mpsc::channel(16);
+    let (state, rendered_rx) =
+        DeckState::start(device, event_tx, args.max_fps, args.display_mode).await?;
+    let (command_tx, command_rx) = tokio::sync::mpsc::channel(16);
+    let follow = tokio::spawn(remote_deck::follow(
+        args.host,
+        args.token,
+        kind.key_layout(),
+        command_tx,
+        event_rx,
+    ));
 
-    if device.shutdown().await.is_err() && device.sleep().await.is_err() {
-        device.set_brightness(15).await?;
+    let deck = run_deck(state, command_rx, rendered_rx);
+    tokio::pin!(deck);
+    let result = tokio::select! {
+        result = &mut deck => result,
+        _ = tokio::signal::ctrl_c() => {
+            info!("Received SIGINT, shutting down gracefully");
+            // Without its commands, the device loop shuts the StreamDeck down and ends
+            follow.abort();
+            deck.await
+        }
+    };
+    match follow.await {
+        Ok(followed) => followed?,
+        Err(e) if e.is_cancelled() => {}
+        Err(e) => return Err(e.into()),
     }
+    result
+}
 
-    Ok(())
+fn supported_devices(devices: Vec<(Kind, String)>) -> Vec<(Kind, String)> {
+    debug!("Found {} devices", devices.len());
+    devices
+        .into_iter()
+        .filter(|(kind, _)| matches!(kind, Kind::Original | Kind::OriginalV2 | Kind::Plus))
+        .collect()
+}
+
+/// A second device with its own keys, which only ever shows the playing tracks. Its presses go to
+/// the same deck as the first device's, so a tap stops the track wherever it was started.
+struct NowPlayingDeck {
+    command_tx: Sender<UiCommand>,
+    finished: JoinHandle<eyre::Result<()>>,
+}
+
+impl NowPlayingDeck {
+    async fn spawn(
+        device: StreamDeck,
+        event_tx: Sender<ui::UiEvent>,
+        max_fps: u32,
+        display_mode: DisplayMode,
+    ) -> eyre::Result<NowPlayingDeck> {
+        let (state, rendered_rx) =
+            DeckState::start(device, event_tx, max_fps, display_mode).await?;
+        let (command_tx, command_rx) = tokio::sync::mpsc::channel(16);
+        Ok(NowPlayingDeck {
+            command_tx,
+            finished: tokio::spawn(run_deck(state, command_rx, rendered_rx)),
+        })
+    }
+
+    /// A deck that failed is left dark rather than taking the first device down with it.
+    async fn forward(deck: &mut Option<NowPlayingDeck>, command: UiCommand) {
+        if let Some(d) = deck
+            && d.command_tx.send(command).await.is_err()
+            && let Some(d) = deck.take()
+            && let Err(e) = d.stop().await
+        {
+            warn!("Now playing deck stopped: {:?}", e);
+        }
+    }
+
+    async fn stop(self) -> eyre::Result<()> {
+        drop(self.command_tx);
+        self.finished.await?
+    }
+}
+
+/// The device loop of a deck that only shows what it is told, such as the now playing deck.
+async fn run_deck<B: DeckBackend>(
+    mut state: DeckState<B>,
+    mut command_rx: Receiver<UiCommand>,
+    mut rendered_rx: Receiver<RenderResult>,
+) -> eyre::Result<()> {
+    let reader = state.device.reader();
+    loop {
+        let hold_timeout = state.hold_deadline().map(sleep_until);
+        let overlay_timeout = state.overlay_deadline().map(sleep_until);
+        let frame_timeout = state.frame.deadline().map(sleep_until);
+        tokio::select! {
+            _ = async { overlay_timeout.unwrap().await }, if overlay_timeout.is_some() => {
+                state.end_overlays().await?;
+            },
+            _ = async { frame_timeout.unwrap().await }, if frame_timeout.is_some() => {
+                state.send_frame().await?;
+            },
+            _ = async { hold_timeout.unwrap().await }, if hold_timeout.is_some() => {
+                state.send_holds().await?;
+            },
+            updates = reader.read() => {
+                state.handle_updates(updates.context("Failed to read updates")?).await?;
+            },
+            Some(rendered) = rendered_rx.recv() => state.show_rendered(rendered).await?,
+            command = command_rx.recv() => match command {
+                Some(command) => {
+                    for command in UiCommand::coalesce(command, &mut command_rx) {
+                        state.handle_command(command).await?;
+                    }
+                }
+                None => break,
+            },
+        }
+    }
+    drop(reader);
+    state.shutdown().shut_down().await
+}
+
+/// Imports configurations in the background, since an import can take a while and button
+/// handling goes on in the meantime. Imports run one after the other, so that a reload re-imports
+/// the campaign that the deck switched to last, even when that switch was still importing.
+struct Loader(UnboundedSender<Load>);
+
+enum Load {
+    /// Re-imports the current campaign.
+    Reload,
+    Campaign(String),
+}
+
+impl Loader {
+    /// Only holds on to the deck's event channel weakly, so that the deck still stops once
+    /// everything else has let go of it.
+    fn spawn(args: DaemonArgs, event_tx: &Sender<ui::UiEvent>) -> Self {
+        let (load_tx, mut load_rx) = tokio::sync::mpsc::unbounded_channel();
+        let event_tx = event_tx.downgrade();
+        tokio::spawn(async move {
+            let mut campaign = args.campaign();
+            while let Some(load) = load_rx.recv().await {
+                let (name, failure) = match &load {
+                    Load::Reload => {
+                        info!("Reload requested, re-importing configuration");
+                        (campaign.clone(), "Configuration reload failed")
+                    }
+                    Load::Campaign(name) => {
+                        info!("Switching to campaign {name}");
+                        (name.clone(), "Campaign switch failed")
+                    }
+                };
+                let loaded = match args.with_campaign(&name) {
+                    Ok(campaign_args) => load_config(campaign_args).await,
+                    Err(e) => Err(e),
+                };
+                let config = match loaded {
+                    Ok(config) => Arc::new(config),
+                    Err(e) => {
+                        error!(
+                            "{failure} for {name}, keeping the current configuration: {:?}",
+                            e
+                        );
+                        if args.desktop_notifications {
+                            notify::error(failure, format!("{e:#}"));
+                        }
+                        continue;
+                    }
+                };
+                let event = match load {
+                    Load::Reload => ui::UiEvent::ConfigReloaded(config),
+                    Load::Campaign(_) => {
+                        campaign = name.clone();
+                        ui::UiEvent::CampaignLoaded(name, config)
+                    }
+                };
+                let Some(event_tx) = event_tx.upgrade() else {
+                    break;
+                };
+                if let Err(e) = event_tx.send(event).await {
+                    warn!(error = %e, "Failed to hand the loaded configuration to the UI");
+                }
+            }
+        });
+        Loader(load_tx)
+    }
+
+    fn reload(&self) {
+        self.send(Load::Reload);
+    }
+
+    fn load_campaign(&self, name: String) {
+        self.send(Load::Campaign(name));
+    }
+
+    fn send(&self, load: Load) {
+        if self.0.send(load).is_err() {
+            warn!("The loader stopped, ignoring the request");
+        }
+    }
+}
+
+async fn load_config(args: DaemonArgs) -> eyre::Result<Config> {
+    tokio::task::spawn_blocking(move || {
+        let mut config = args.source.read(
+            crate::import::Progress::Log,
+            Switch::from(args.allow_keystrokes),
+            Switch::from(args.allow_commands),
+        )?;
+        rebase_paths(&args, &mut config)?;
+        crate::import::dedup::dedup_files(&mut config);
+        let issues = config::validate(&config);
+        for issue in issues
+            .iter()
+            .filter(|issue| issue.severity == config::Severity::Warning)
+        {
+            warn!("{issue}");
+        }
+        config::ensure_no_errors(&issues)?;
+        Ok(config)
+    })
+    .await?
+}
+
+/// Windows has no SIGHUP, so Ctrl+Break in the daemon's console is the equivalent there.
+#[cfg(unix)]
+fn reload_signal() -> std::io::Result<tokio::signal::unix::Signal> {
+    tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+}
+
+#[cfg(windows)]
+fn reload_signal() -> std::io::Result<tokio::signal::windows::CtrlBreak> {
+    tokio::signal::windows::ctrl_break()
 }
 
 #[instrument(skip_all, level = "DEBUG")]
@@ -165,126 +1374,162 @@ fn rebase_paths(args: &DaemonArgs, config: &mut Config) -> eyre::Result<()> {
     for (_, page) in config.pages.iter_mut() {
         let mut new_page: Page = (**page).clone();
         for b in new_page.buttons.iter_mut() {
-            if let ButtonBehavior::PlaySound(path, _) = &mut b.behavior {
-                buf.clear();
-                buf.push(&args.audio_path);
-                buf.push(&**path);
-                if args.check_paths {
-                    match std::fs::metadata(&buf) {
-                        Ok(m) if m.is_file() => (),
-                        Ok(m) => warn!("Path {} is not a file: {:?}", buf.display(), m.file_type()),
-                        Err(e) => warn!("Error checking path {}: {}", buf.display(), e),
-                    }
-                }
-                *path = buf
-                    .to_str()
-                    .with_context(|| {
-                        format!("Rebased path is not valid UTF-8: '{:?}'", buf.display())
-                    })?
-                    .to_string()
-                    .into();
-            }
+            rebase_behavior(args, &mut buf, &mut b.behavior)?;
         }
         *page = Arc::new(new_page);
     }
     Ok(())
 }
 
+/// Steps of a sequence name their sounds by path too, and these must match the paths of the
+/// buttons that play them.
+fn rebase_behavior(
+    args: &DaemonArgs,
+    buf: &mut PathBuf,
+    behavior: &mut ButtonBehavior,
+) -> eyre::Result<()> {
+    match behavior {
+        ButtonBehavior::PlaySound(path, settings) => {
+            if let Some(fallback) = &mut settings.fallback {
+                rebase_path(args, buf, fallback)?;
+            }
+            rebase_path(args, buf, path)
+        }
+        ButtonBehavior::StopSound(path)
+        | ButtonBehavior::StopInstances(path, _)
+        | ButtonBehavior::Countdown {
+            chime: Some(path), ..
+        }
+        | ButtonBehavior::RollDice {
+            sound: Some(path), ..
+        } => rebase_path(args, buf, path),
+        ButtonBehavior::Sequence(steps) => steps
+            .iter_mut()
+            .try_for_each(|step| rebase_behavior(args, buf, step)),
+        ButtonBehavior::PushPage(_)
+        | ButtonBehavior::GotoPage(_)
+        | ButtonBehavior::Pop
+        | ButtonBehavior::PopToRoot
+        | ButtonBehavior::PopN(_)
+        | ButtonBehavior::StopAll
+        | ButtonBehavior::PauseAll
+        | ButtonBehavior::ResumeAll
+        | ButtonBehavior::LibraryStatus
+        | ButtonBehavior::Mixer
+        | ButtonBehavior::StopTag(_)
+        | ButtonBehavior::PlayTag(_)
+        | ButtonBehavior::RunCommand { .. }
+        | ButtonBehavior::SendKeys(_)
+        | ButtonBehavior::Custom { .. }
+        | ButtonBehavior::Placeholder(_)
+        | ButtonBehavior::SwitchProfile(_)
+        | ButtonBehavior::Script { .. }
+        | ButtonBehavior::Stopwatch
+        | ButtonBehavior::Countdown { chime: None, .. }
+        | ButtonBehavior::RollDice { sound: None, .. } => Ok(()),
+    }
+}
+
+fn rebase_path(args: &DaemonArgs, buf: &mut PathBuf, path: &mut Arc<String>) -> eyre::Result<()> {
+    if is_stream_url(path) {
+        return Ok(());
+    }
+    buf.clear();
+    buf.push(&args.audio_path);
+    buf.push(&**path);
+    // The deck tells tracks apart by path, which must not depend on how the config spells it
+    *buf = canonical_path(buf);
+    if args.check_paths {
+        match std::fs::metadata(&buf) {
+            Ok(m) if m.is_file() => (),
+            Ok(m) => warn!("Path {} is not a file: {:?}", buf.display(), m.file_type()),
+            Err(e) => warn!("Error checking path {}: {}", buf.display(), e),
+        }
+    }
+    *path = buf
+        .to_str()
+        .with_context(|| format!("Rebased path is not valid UTF-8: '{:?}'", buf.display()))?
+        .to_string()
+        .into();
+    Ok(())
+}
+
 struct RenderCacheEntry {
     button: Option<ButtonData>,
 }
 
-struct DeckState {
+struct DeckState<B> {
     page: Vec<Option<ButtonRef>>,
     render_cache: Vec<Option<RenderCacheEntry>>,
-    font_system: FontSystem,
-    swash_cache: SwashCache,
-    device: AsyncStreamDeck,
+    render_tx: Sender<RenderRequest>,
+    device: B,
     event_tx: tokio::sync::mpsc::Sender<ui::UiEvent>,
     buttons_held: Vec<(ButtonRef, Instant)>,
+    /// Keys that show something other than their button, such as the pressed look or a toast,
+    /// and until when.
+    overlays: Vec<(usize, Instant)>,
+    /// What the touch strip shows or is about to show.
+    strip: Option<Vec<StripSegment>>,
+    frame: Frame,
 }
 
-impl DeckState {
-    fn shutdown(self) -> AsyncStreamDeck {
-        self.device
+/// Key images that wait to be sent to the device together, see `--max-fps`.
+struct Frame {
+    /// By key, where a later image replaces an earlier one that was not sent yet.
+    images: BTreeMap<u8, DynamicImage>,
+    interval: Duration,
+    sent_at: Option<Instant>,
+}
+
+impl Frame {
+    fn new(max_fps: u32) -> Frame {
+        Frame {
+            images: BTreeMap::new(),
+            interval: Duration::from_secs(1) / max_fps.max(1),
+            sent_at: None,
+        }
     }
 
-    #[instrument(skip(self), level = "TRACE")]
-    async fn render_button_image(&mut self, button: &mut ButtonData) -> DynamicImage {
-        let mut bg_color = Rgb([0u8, 0u8, 0u8]);
-        let mut text_color = Rgb([0xFFu8, 0xFFu8, 0xFFu8]);
-        if button.notification.is_some() {
-            std::mem::swap(&mut bg_color, &mut text_color);
-        };
-        let mut image = RgbImage::from_pixel(72, 72, bg_color);
-        let metrics = Metrics::new(16.0, 24.0);
-        let text_color = Color::rgb(text_color.0[0], text_color.0[1], text_color.0[2]);
-
-        self.render_text(
-            &mut image,
-            &button.label,
-            metrics,
-            bg_color,
-            text_color,
-            if button.notification.is_some() {
-                Weight::NORMAL
-            } else {
-                Weight::EXTRA_BOLD
-            },
-            72,
-        );
-        if let Some(notification) = &button.notification {
-            self.render_text(
-                &mut image,
-                notification,
-                metrics,
-                bg_color,
-                text_color,
-                Weight::EXTRA_BOLD,
-                32,
-            );
+    /// When the waiting images may be sent, which is right away on a deck that was idle.
+    fn deadline(&self) -> Option<Instant> {
+        if self.images.is_empty() {
+            return None;
         }
+        Some(
+            self.sent_at
+                .map_or_else(Instant::now, |at| at + self.interval),
+        )
+    }
+}
 
-        image.into()
-    }
-
-    #[allow(clippy::too_many_arguments)]
-    fn render_text(
-        &mut self,
-        image: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
-        text: &str,
-        metrics: Metrics,
-        bg_color: Rgb<u8>,
-        text_color: Color,
-        weight: Weight,
-        height: i32,
-    ) {
-        let mut buffer = Buffer::new(&mut self.font_system, metrics);
-        let mut buffer = buffer.borrow_with(&mut self.font_system);
-        buffer.set_size(Some(70.0), Some((height - 2) as f32));
-        let mut attrs = Attrs::new();
-        attrs.weight = weight;
-        buffer.set_text(text, &attrs, Shaping::Advanced);
-
-        buffer.shape_until_scroll(true);
-        let swash_cache = &mut self.swash_cache;
-        buffer.draw(swash_cache, text_color, |x, y, _w, _h, color| {
-            let x = x + 1;
-            let y = y + 1 + (72 - height);
-            if x < 0 || y < 0 || x > 71 || y > 71 {
-                if x < -1 || y < -1 || x > 72 || y > 72 {
-                    warn!("Out of bounds: x: {}, y: {}", x, y);
-                }
-                return;
-            }
-            let alpha_f = color.a() as f32 / 255.0;
-            let image_color_multiplied_alpha = Rgb([
-                (color.r() as f32 * alpha_f + bg_color.0[0] as f32 * (1.0 - alpha_f)) as u8,
-                (color.g() as f32 * alpha_f + bg_color.0[1] as f32 * (1.0 - alpha_f)) as u8,
-                (color.b() as f32 * alpha_f + bg_color.0[2] as f32 * (1.0 - alpha_f)) as u8,
-            ]);
-            image.put_pixel(x as u32, y as u32, image_color_multiplied_alpha)
-        });
+impl<B: DeckBackend> DeckState<B> {
+    /// Sets up a device with a render thread of its own, for the decks besides the main one.
+    async fn start(
+        device: B,
+        event_tx: Sender<ui::UiEvent>,
+        max_fps: u32,
+        display_mode: DisplayMode,
+    ) -> eyre::Result<(DeckState<B>, Receiver<RenderResult>)> {
+        device.set_brightness(60).await?;
+        device.clear_all_keys().await?;
+        let (rendered_tx, rendered_rx) = tokio::sync::mpsc::channel(16);
+        let render_tx = render::spawn(load_fonts().await?, display_mode, rendered_tx)?;
+        let state = DeckState {
+            page: vec![],
+            render_cache: vec![],
+            render_tx,
+            device,
+            event_tx,
+            buttons_held: vec![],
+            overlays: vec![],
+            strip: None,
+            frame: Frame::new(max_fps),
+        };
+        Ok((state, rendered_rx))
+    }
+
+    fn shutdown(self) -> B {
+        self.device
     }
 
     #[instrument(skip(self), level = "TRACE")]
@@ -292,6 +1537,7 @@ impl DeckState {
         match command {
             UiCommand::Refresh => {
                 let mut flush_required = false;
+                let mut jobs = Vec::new();
                 for (i, button) in self
                     .page
                     .clone()
@@ -299,8 +1545,12 @@ impl DeckState {
                     .take(u8::MAX as usize)
                     .enumerate()
                 {
-                    let image = if let Some(r) = button.as_ref() {
-                        let mut data = r.read().await;
+                    if self.overlays.iter().any(|(key, _)| *key == i) {
+                        // Redrawn once the overlay ends, so that it stays visible long enough
+                        continue;
+                    }
+                    if let Some(r) = button.as_ref() {
+                        let data = r.read().await;
                         if self
                             .render_cache
                             .get(i)
@@ -309,12 +1559,14 @@ impl DeckState {
                             .unwrap_or(false)
                         {
                             continue;
-                        } else {
-                            self.render_cache[i] = Some(RenderCacheEntry {
-                                button: Some(data.clone()),
-                            });
-                            self.render_button_image(&mut data).await
                         }
+                        self.render_cache[i] = Some(RenderCacheEntry {
+                            button: Some(data.clone()),
+                        });
+                        jobs.push(RenderJob {
+                            key: i,
+                            button: data,
+                        });
                     } else if self
                         .render_cache
                         .get(i)
@@ -325,46 +1577,213 @@ impl DeckState {
                         continue;
                     } else {
                         self.render_cache[i] = Some(RenderCacheEntry { button: None });
-                        ImageBuffer::from_pixel(71, 71, Rgb([0u8, 0u8, 0u8])).into()
-                    };
-                    self.device.set_button_image(i as u8, image).await?;
-                    flush_required = true;
+                        self.frame.images.remove(&(i as u8));
+                        self.device.clear_key(i as u8).await?;
+                        flush_required = true;
+                    }
                 }
 
+                if !jobs.is_empty() {
+                    self.render_tx.send(RenderRequest::Buttons(jobs)).await?;
+                }
                 if flush_required {
                     trace!("Flushing stream deck");
                     self.device.flush().await?;
                 }
             }
             UiCommand::Flip(new_page) => {
+                // Most flips keep some keys in place (e.g. navigation), and re-uploading those
+                // images is what makes flips slow on large decks
+                for (i, entry) in self.render_cache.iter_mut().enumerate() {
+                    if self.page.get(i) != new_page.get(i) {
+                        *entry = None;
+                        self.frame.images.remove(&(i as u8));
+                    }
+                }
+                self.render_cache.resize_with(new_page.len(), || None);
                 self.page = new_page;
                 self.buttons_held.clear();
-                // TODO: Some flips are partial; be smarter about clearing cache entries
-                self.render_cache.clear();
-                self.render_cache.extend((0..self.page.len()).map(|_| None));
                 Box::pin(self.handle_command(UiCommand::Refresh)).await?;
             }
+            UiCommand::Toast(text, duration) => self.toast(text, duration).await?,
+            // Only meant for a NowPlayingDeck, which gets it as a Flip
+            UiCommand::FlipNowPlaying(_) => {}
+            // Only meant for the daemon, which has the import arguments
+            UiCommand::LoadCampaign(_) => {}
+            UiCommand::Strip(segments) => {
+                // Most updates are for remaining times of tracks that are not on the strip
+                if let Some(size) = self.device.strip_size()
+                    && self.strip.as_ref() != Some(&segments)
+                {
+                    self.strip = Some(segments.clone());
+                    self.render_tx
+                        .send(RenderRequest::Strip { segments, size })
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Uploads images that the render thread finished, unless their key changed again meanwhile.
+    async fn show_rendered(&mut self, rendered: RenderResult) -> eyre::Result<()> {
+        match rendered {
+            RenderResult::Buttons(rendered) => {
+                for Rendered { key, button, image } in rendered {
+                    let up_to_date = self
+                        .render_cache
+                        .get(key)
+                        .and_then(|e| e.as_ref())
+                        .is_some_and(|e| e.button.as_ref() == Some(&button));
+                    if !up_to_date {
+                        trace!("Dropping outdated image for key {}", key);
+                        continue;
+                    }
+                    self.frame.images.insert(key as u8, image);
+                }
+            }
+            RenderResult::Toast(images) => {
+                for (key, image) in images.into_iter().enumerate() {
+                    if self.overlays.iter().any(|(k, _)| *k == key) {
+                        self.frame.images.insert(key as u8, image);
+                    }
+                }
+            }
+            RenderResult::Strip { segments, image } => {
+                if self.strip.as_ref() == Some(&segments) {
+                    self.device.set_strip_image(image).await?;
+                } else {
+                    trace!("Dropping outdated touch strip image");
+                }
+            }
+        }
+        if self.frame.deadline().is_some_and(|at| at <= Instant::now()) {
+            self.send_frame().await?;
+        }
+        Ok(())
+    }
+
+    /// Uploads the key images that waited for the next frame.
+    async fn send_frame(&mut self) -> eyre::Result<()> {
+        let images = std::mem::take(&mut self.frame.images);
+        if images.is_empty() {
+            return Ok(());
+        }
+        for (key, image) in images {
+            self.device.set_key_image(key, image).await?;
+        }
+        trace!("Flushing stream deck");
+        self.device.flush().await?;
+        self.frame.sent_at = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Confirms the press right away, since the tap's own refresh may take a while to arrive.
+    async fn flash(&mut self, key: usize, button: &ButtonRef) -> eyre::Result<()> {
+        let mut data = button.read().await;
+        data.style = ButtonStyle::Pressed;
+        if let Some(entry) = self.render_cache.get_mut(key) {
+            *entry = Some(RenderCacheEntry {
+                button: Some(data.clone()),
+            });
+        }
+        self.render_tx
+            .send(RenderRequest::Buttons(vec![RenderJob {
+                key,
+                button: data,
+            }]))
+            .await?;
+        self.overlay(key, Instant::now() + FLASH_TIME);
+        Ok(())
+    }
+
+    async fn toast(&mut self, text: String, duration: Duration) -> eyre::Result<()> {
+        let (_, cols) = self.device.key_layout();
+        let keys = usize::from(cols).min(self.page.len());
+        let until = Instant::now() + duration;
+        for key in 0..keys {
+            self.overlay(key, until);
+            // Images of the buttons that are still being rendered must not cover the toast
+            if let Some(entry) = self.render_cache.get_mut(key) {
+                *entry = None;
+            }
         }
+        self.render_tx
+            .send(RenderRequest::Toast { text, keys })
+            .await?;
         Ok(())
     }
 
+    fn hold_deadline(&self) -> Option<Instant> {
+        self.buttons_held
+            .iter()
+            .map(|(_, at)| *at + HOLD_TIME)
+            .min()
+    }
+
+    fn overlay_deadline(&self) -> Option<Instant> {
+        self.overlays.iter().map(|(_, until)| *until).min()
+    }
+
+    /// Turns presses that have lasted long enough into holds.
+    async fn send_holds(&mut self) -> eyre::Result<()> {
+        debug!("Hold timeout reached");
+        let now = Instant::now();
+        while let Some(i) = self
+            .buttons_held
+            .iter()
+            .position(|(_, pressed_at)| now.duration_since(*pressed_at) >= HOLD_TIME)
+        {
+            let (b, _) = self.buttons_held.swap_remove(i);
+            if self
+                .page
+                .iter()
+                .any(|ob| ob.as_ref().is_some_and(|page_b| *page_b == b))
+            {
+                self.event_tx.send(ui::UiEvent::ButtonHold(b)).await?;
+            } else {
+                warn!("held button {:?} no longer found in page", b);
+            }
+        }
+        Ok(())
+    }
+
+    fn overlay(&mut self, key: usize, until: Instant) {
+        self.overlays.retain(|(k, _)| *k != key);
+        self.overlays.push((key, until));
+    }
+
+    async fn end_overlays(&mut self) -> eyre::Result<()> {
+        let now = Instant::now();
+        let render_cache = &mut self.render_cache;
+        self.overlays.retain(|(key, until)| {
+            let done = *until <= now;
+            if done && let Some(entry) = render_cache.get_mut(*key) {
+                *entry = None;
+            }
+            !done
+        });
+        self.handle_command(UiCommand::Refresh).await
+    }
+
     fn button_by_key(&mut self, key: u8) -> eyre::Result<Option<ButtonRef>> {
         Ok(self.page.get::<usize>(key.into()).and_then(|b| b.clone()))
     }
 
     #[tracing::instrument(level = "TRACE", skip_all)]
-    async fn handle_updates(&mut self, updates: Vec<DeviceStateUpdate>) -> Result<(), Report> {
+    async fn handle_updates(&mut self, updates: Vec<KeyEvent>) -> Result<(), Report> {
         for update in updates {
             match update {
-                DeviceStateUpdate::ButtonDown(key) => {
+                KeyEvent::Down(key) => {
                     info!("Button {} down", key);
                     if let Some(button) = self.button_by_key(key)? {
+                        self.flash(key.into(), &button).await?;
                         self.buttons_held.push((button, Instant::now()));
                     } else {
                         warn!("Button {} not found", key);
                     }
                 }
-                DeviceStateUpdate::ButtonUp(key) => {
+                KeyEvent::Up(key) => {
                     info!("Button {} up", key);
                     if let Some(button) = self.button_by_key(key)? {
                         let now = Instant::now();
@@ -388,8 +1807,19 @@ impl DeckState {
                         warn!("Button {} not found", key);
                     }
                 }
-                unknown => {
-                    info!("Ignoring device update: {:?}", unknown);
+                KeyEvent::StripTap(x) => {
+                    if let Some((width, _)) = self.device.strip_size() {
+                        let segment = usize::from(x) * ui::STRIP_SEGMENTS / width.max(1) as usize;
+                        self.event_tx.send(ui::UiEvent::StripTap(segment)).await?;
+                    }
+                }
+                KeyEvent::StripSwipe { from, to } => {
+                    let swipe = match to.cmp(&from) {
+                        std::cmp::Ordering::Greater => Swipe::Right,
+                        std::cmp::Ordering::Less => Swipe::Left,
+                        std::cmp::Ordering::Equal => continue,
+                    };
+                    self.event_tx.send(ui::UiEvent::StripSwipe(swipe)).await?;
                 }
             };
         }
@@ -398,6 +1828,9 @@ impl DeckState {
 }
 
 const HOLD_TIME: Duration = Duration::from_millis(250);
+const FLASH_TIME: Duration = Duration::from_millis(150);
+/// How often to look for a StreamDeck with `--wait-for-device`.
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
 #[tracing::instrument(level = tracing::Level::DEBUG)]
 async fn load_fonts() -> eyre::Result<FontSystem> {
@@ -424,3 +1857,266 @@ async fn load_fonts() -> eyre::Result<FontSystem> {
     .await
     .context("Failed to load fonts")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{DeckState, Frame, run_deck};
+    use crate::daemon::backend::fake::FakeDeck;
+    use crate::daemon::render::{RenderRequest, RenderResult, Rendered};
+    use crate::daemon::ui::{ButtonData, ButtonId, ButtonRef, UiCommand};
+    use image::{DynamicImage, GrayImage, Luma};
+    use std::time::Duration;
+    use tokio::sync::mpsc::Receiver;
+    use tokio::time::Instant;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_key_images_wait_for_the_next_frame() -> eyre::Result<()> {
+        let (device, _presses) = FakeDeck::new((1, 2));
+        let (render_tx, mut render_rx) = tokio::sync::mpsc::channel(16);
+        let (rendered_tx, rendered_rx) = tokio::sync::mpsc::channel(16);
+        let (command_tx, command_rx) = tokio::sync::mpsc::channel(16);
+        let (event_tx, _event_rx) = tokio::sync::mpsc::channel(16);
+        let state = DeckState {
+            page: vec![],
+            render_cache: vec![],
+            render_tx,
+            device: device.clone(),
+            event_tx,
+            buttons_held: vec![],
+            overlays: vec![],
+            strip: None,
+            frame: Frame::new(10),
+        };
+        let deck = tokio::spawn(run_deck(state, command_rx, rendered_rx));
+        let buttons = [0, 1].map(|_| ButtonRef::detached(ButtonId::default(), Default::default()));
+        let image = |n: u8| DynamicImage::ImageLuma8(GrayImage::from_pixel(1, 1, Luma([n])));
+        let start = Instant::now();
+
+        // Renders like the render thread would, with the label's number in the image
+        let render = async |render_rx: &mut Receiver<RenderRequest>| -> eyre::Result<()> {
+            let Some(RenderRequest::Buttons(jobs)) = render_rx.recv().await else {
+                eyre::bail!("No buttons to render");
+            };
+            let rendered = jobs
+                .into_iter()
+                .map(|job| Rendered {
+                    key: job.key,
+                    image: image(job.button.label.parse().unwrap_or(0)),
+                    button: job.button,
+                })
+                .collect();
+            rendered_tx.send(RenderResult::Buttons(rendered)).await?;
+            Ok(())
+        };
+
+        command_tx
+            .send(UiCommand::Flip(buttons.iter().cloned().map(Some).collect()))
+            .await?;
+        render(&mut render_rx).await?;
+        for n in 1..=50u8 {
+            let data = ButtonData {
+                label: n.to_string().into(),
+                ..ButtonData::default()
+            };
+            buttons[usize::from(n % 2)].show(data).await;
+            command_tx.send(UiCommand::Refresh).await?;
+            render(&mut render_rx).await?;
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        drop(command_tx);
+        deck.await??;
+
+        let frames = device.sent(|sent| {
+            sent.flushes
+                .iter()
+                .filter(|flush| flush.iter().any(|(_, image)| image.is_some()))
+                .count()
+        });
+        let interval = Duration::from_millis(100);
+        assert!(frames > 1 && frames < 50, "{frames} frames");
+        let elapsed = start.elapsed().div_duration_f64(interval);
+        assert!(
+            frames as f64 <= elapsed + 1.0,
+            "{frames} frames in {elapsed} intervals"
+        );
+        let shown = |key: u8| {
+            device.sent(|sent| {
+                sent.flushes
+                    .iter()
+                    .flatten()
+                    .filter(|(k, _)| *k == key)
+                    .filter_map(|(_, image)| image.clone())
+                    .next_back()
+            })
+        };
+        assert_eq!(shown(0), Some(image(50)));
+        assert_eq!(shown(1), Some(image(49)));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_flips_only_redraw_the_keys_that_changed() -> eyre::Result<()> {
+        let (device, _presses) = FakeDeck::new((1, 3));
+        let (render_tx, mut render_rx) = tokio::sync::mpsc::channel(16);
+        let (event_tx, _event_rx) = tokio::sync::mpsc::channel(16);
+        let mut state = DeckState {
+            page: vec![],
+            render_cache: vec![],
+            render_tx,
+            device: device.clone(),
+            event_tx,
+            buttons_held: vec![],
+            overlays: vec![],
+            strip: None,
+            frame: Frame::new(10),
+        };
+        let button = |label: &str| {
+            let data = ButtonData {
+                label: label.to_string().into(),
+                ..ButtonData::default()
+            };
+            ButtonRef::detached(ButtonId::default(), data)
+        };
+        let (back, next) = (button("Back"), button("Next"));
+        let page = |middle: Option<ButtonRef>| vec![Some(back.clone()), middle, Some(next.clone())];
+
+        // Renders what the flip asked for and sends it, returning the keys that were rendered
+        let render = async |state: &mut DeckState<_>,
+                            render_rx: &mut Receiver<RenderRequest>|
+               -> eyre::Result<Vec<usize>> {
+            let Ok(RenderRequest::Buttons(jobs)) = render_rx.try_recv() else {
+                return Ok(vec![]);
+            };
+            let keys = jobs.iter().map(|job| job.key).collect();
+            let rendered = jobs
+                .into_iter()
+                .map(|job| Rendered {
+                    key: job.key,
+                    image: DynamicImage::ImageLuma8(GrayImage::new(1, 1)),
+                    button: job.button,
+                })
+                .collect();
+            state.show_rendered(RenderResult::Buttons(rendered)).await?;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            state.send_frame().await?;
+            Ok(keys)
+        };
+        let last_flush = || {
+            device.sent(|sent| {
+                let flush = sent.flushes.last().cloned().unwrap_or_default();
+                flush
+                    .into_iter()
+                    .map(|(key, image)| (key, image.is_some()))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        state
+            .handle_command(UiCommand::Flip(page(Some(button("Rain")))))
+            .await?;
+        assert_eq!(render(&mut state, &mut render_rx).await?, [0, 1, 2]);
+        assert_eq!(last_flush(), [(0, true), (1, true), (2, true)]);
+
+        state
+            .handle_command(UiCommand::Flip(page(Some(button("Thunder")))))
+            .await?;
+        assert_eq!(render(&mut state, &mut render_rx).await?, [1]);
+        assert_eq!(last_flush(), [(1, true)]);
+
+        state.handle_command(UiCommand::Flip(page(None))).await?;
+        assert!(render(&mut state, &mut render_rx).await?.is_empty());
+        assert_eq!(last_flush(), [(1, false)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validate_reports_every_issue_of_a_configuration_file() -> eyre::Result<()> {
+        use super::{ConfigSource, validate};
+        use crate::config::{self, Button, ButtonBehavior, Config, Page, Severity};
+        use std::collections::HashMap;
+        use std::sync::Arc;
+        use uuid::Uuid;
+
+        let (start, missing) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let page = Page {
+            name: "Start".to_string(),
+            buttons: vec![
+                Button::new(
+                    "Rain",
+                    ButtonBehavior::PlaySound(
+                        Arc::new("rain.mp3".to_string()),
+                        config::PlaySoundSettings {
+                            pan: 1.5,
+                            ..config::PlaySoundSettings::new(config::PlaybackMode::PlayStop)
+                        },
+                    ),
+                ),
+                Button::new("Away", ButtonBehavior::PushPage(missing)),
+            ],
+            bus: Some("hall".to_string()),
+            defaults: Default::default(),
+        };
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("campaign.json");
+        config::write(
+            &Config {
+                pages: HashMap::from([(start, Arc::new(page))]),
+                start_page: start,
+                buses: Vec::new(),
+                schedule: Vec::new(),
+            },
+            &path,
+        )?;
+
+        let source = ConfigSource {
+            config: Some(path),
+            import: None,
+        };
+        let issues = validate(source, crate::import::Progress::Log).await?;
+
+        let found: Vec<_> = issues
+            .iter()
+            .map(|issue| (issue.severity, issue.button, issue.field.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Severity::Warning, None, "bus"),
+                (Severity::Error, Some(0), "behavior.pan"),
+                (Severity::Error, Some(1), "behavior"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_campaigns_stay_next_to_the_configuration() -> eyre::Result<()> {
+        use super::DaemonArgs;
+        use clap::Parser;
+        use std::path::Path;
+
+        #[derive(Parser)]
+        struct Cli {
+            #[command(flatten)]
+            args: DaemonArgs,
+        }
+        let cli = [
+            "noisedeck",
+            "--config",
+            "/srv/decks/curse.json",
+            "--audio-path",
+            "/srv/audio",
+        ];
+        let args = Cli::try_parse_from(cli)?.args;
+
+        let crypt = args.with_campaign("Crypt")?;
+        assert_eq!(
+            crypt.source.config.as_deref(),
+            Some(Path::new("/srv/decks/Crypt.json"))
+        );
+        assert!(args.with_campaign("../secrets/other").is_err());
+        assert!(args.with_campaign("sub/x").is_err());
+        Ok(())
+    }
+}
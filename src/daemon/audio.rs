@@ -1,23 +1,65 @@
-use crate::config::PlaySoundSettings;
+use crate::config::{self, PlaySoundSettings, PlaybackMode};
 use crate::daemon::audio::BlockingAudioCommand::AsyncCommand;
-use eyre::Context;
+use crate::daemon::state::TrackEdits;
+use crate::util::{Switch, is_stream_url};
+use cpal::traits::{DeviceTrait, HostTrait};
+use eyre::{Context, ContextCompat};
+use feedback::FeedbackOutput;
+use input::Input;
+use kira::backend::cpal::CpalBackendSettings;
+use kira::effect::compressor::CompressorBuilder;
+use kira::effect::delay::DelayBuilder;
+use kira::effect::filter::{FilterBuilder, FilterMode};
+use kira::effect::reverb::ReverbBuilder;
 use kira::effect::volume_control::VolumeControlHandle;
 use kira::sound::streaming::{StreamingSoundData, StreamingSoundHandle};
 use kira::sound::{FromFileError, PlaybackState};
-use kira::{AudioManager, AudioManagerSettings, DefaultBackend, Decibels, Easing, StartTime, Tween};
+use kira::track::{TrackBuilder, TrackHandle};
+use kira::{
+    AudioManager, AudioManagerSettings, Decibels, DefaultBackend, Easing, Mix, Panning,
+    PlaybackRate, StartTime, Tween,
+};
+use recorder::{Recorder, RecorderBuilder};
+use serde::{Deserialize, Serialize};
 use std::any::Any;
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::time::Duration;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, mpsc};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use stream::{StreamDecoder, StreamStatus};
 use tokio::sync::Mutex;
-use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::time::MissedTickBehavior;
-use tracing::{error, info, instrument, trace};
+use tokio::sync::mpsc::{Receiver, Sender, UnboundedSender};
+use tokio::sync::watch;
+use tokio::time::{Interval, MissedTickBehavior};
+use tracing::{debug, error, info, instrument, trace, warn};
+
+mod feedback;
+mod input;
+#[cfg(test)]
+pub mod mock;
+mod null;
+mod preload;
+mod recorder;
+mod stinger;
+mod stream;
+mod voices;
+
+pub use feedback::Feedback;
+use stinger::Stingers;
+use voices::{Admission, Voices};
+pub use voices::{OverLimit, VoiceLimits};
 
 pub struct Track {
     pub path: Arc<PathBuf>,
     pub settings: PlaySoundSettings,
     state: Mutex<Box<dyn TrackState>>,
+    /// Made on the deck after the track was created. Only the mode and fades are taken from
+    /// here, the volume offset goes through [`AudioCommand::AdjustTrackVolume`].
+    edits: std::sync::Mutex<TrackEdits>,
+    /// Where in its file the track was last stopped, for [`PlaySoundSettings::resume`]. The
+    /// engine updates it, and the deck keeps it in the state file.
+    resume_position: std::sync::Mutex<Option<Duration>>,
 }
 
 impl std::fmt::Debug for Track {
@@ -40,21 +82,85 @@ impl Track {
             path,
             settings,
             state: Mutex::new(state),
+            edits: std::sync::Mutex::default(),
+            resume_position: std::sync::Mutex::default(),
+        }
+    }
+
+    /// Takes effect the next time the track starts or stops.
+    pub fn set_edits(&self, edits: TrackEdits) {
+        *self.edits.lock().unwrap_or_else(PoisonError::into_inner) = edits;
+    }
+
+    fn edits(&self) -> TrackEdits {
+        *self.edits.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn mode(&self) -> PlaybackMode {
+        self.edits().mode.unwrap_or(self.settings.mode)
+    }
+
+    /// `None` if the track starts at full volume.
+    pub fn fade_in(&self) -> Option<Duration> {
+        self.edits()
+            .fade_in
+            .or(self.settings.fade_in)
+            .filter(|d| !d.is_zero())
+    }
+
+    /// How long the track takes to fade out when stopped, which is a zero duration for a cut.
+    pub fn fade_out(&self) -> Duration {
+        self.edits()
+            .fade_out
+            .or(self.settings.fade_out)
+            .unwrap_or(DEFAULT_FADE_OUT)
+    }
+
+    pub fn resume_position(&self) -> Option<Duration> {
+        *self
+            .resume_position
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Only kept for tracks that resume, the others always start from the beginning.
+    pub fn set_resume_position(&self, position: Option<Duration>) {
+        if self.settings.resume.is_some() {
+            *self
+                .resume_position
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) = position;
+        }
+    }
+
+    /// Where the track starts when played, rewound from where it was stopped.
+    fn start_position(&self) -> Option<Duration> {
+        let rewind = self.settings.resume?;
+        Some(self.resume_position()?.saturating_sub(rewind))
+    }
+
+    /// Opens the track's file with `open`, or its [`PlaySoundSettings::fallback`] if that fails.
+    fn open_with_fallback<T>(&self, open: impl Fn(&Path) -> eyre::Result<T>) -> eyre::Result<T> {
+        match (open(&self.path), &self.settings.fallback) {
+            (Err(e), Some(fallback)) => {
+                let path = self.path.display();
+                warn!("{path} cannot be played, trying {fallback} instead: {e:#}");
+                open(Path::new(fallback.as_str()))
+                    .wrap_err_with(|| format!("Neither {path} nor {fallback} can be played"))
+            }
+            (result, _) => result,
         }
     }
 
     pub async fn read(&self) -> TrackStateData {
         let guard = self.state.lock().await;
-        TrackStateData {
-            rem_duration: guard.rem_duration(),
-            playback: guard.playback_state(),
-        }
+        TrackStateData::from(&**guard)
     }
 
     #[cfg(test)]
     pub async fn update_mock_state(&self, playback: PlaybackState) -> eyre::Result<()> {
         use crate::daemon::ui::tests::harness::MockTrackState;
-        
+
         let mut guard = self.state.lock().await;
         let mock_state = guard
             .as_any_mut()
@@ -63,34 +169,112 @@ impl Track {
         mock_state.playback = playback;
         Ok(())
     }
+
+    #[cfg(test)]
+    pub async fn update_mock(
+        &self,
+        f: impl FnOnce(&mut crate::daemon::ui::tests::harness::MockTrackState),
+    ) -> eyre::Result<()> {
+        use crate::daemon::ui::tests::harness::MockTrackState;
+
+        let mut guard = self.state.lock().await;
+        let mock_state = guard
+            .as_any_mut()
+            .downcast_mut::<MockTrackState>()
+            .ok_or_else(|| eyre::eyre!("Expected MockTrackState in test"))?;
+        f(mock_state);
+        Ok(())
+    }
 }
 
 pub trait TrackState: Send {
     fn rem_duration(&self) -> Option<Duration>;
+    /// How long the track takes at the rate it plays at, so that it adds up with
+    /// [`TrackState::rem_duration`].
+    fn duration(&self) -> Option<Duration>;
     fn playback_state(&self) -> PlaybackState;
+    /// Runtime adjustment on top of the configured track volume.
+    fn volume_offset_db(&self) -> f64;
+    /// Runtime stereo position that replaces the configured one.
+    fn pan_override(&self) -> Option<f32>;
+    /// Runtime playback rate that replaces the configured one.
+    fn playback_rate_override(&self) -> Option<f64>;
+    /// Whether a playing stream is waiting for data from the network.
+    fn is_buffering(&self) -> bool;
+    /// Why the track's file cannot be played, as far as preloading could tell.
+    fn load_error(&self) -> Option<Arc<String>>;
+    /// How many overlapping instances of the track are playing at once.
+    fn instances(&self) -> usize;
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
 }
 
 #[derive(Default)]
 pub struct RealTrackState {
+    /// The newest instance of the track.
     pub sink: Option<StreamingSoundHandle<FromFileError>>,
+    /// Instances of an overlapping track that were started before `sink`, oldest first. They
+    /// are kept so that they can still be stopped once a newer instance has taken their place.
+    pub earlier: Vec<StreamingSoundHandle<FromFileError>>,
+    /// Known from preloading even before the track is first played.
     pub duration: Option<Duration>,
+    /// Outlives individual playbacks so that a track keeps its adjusted level when restarted.
+    pub volume_offset_db: f64,
+    pub pan_override: Option<f32>,
+    pub playback_rate_override: Option<f64>,
+    /// Needed to convert the remaining file duration into wall-clock time.
+    pub current_rate: Option<PlaybackRate>,
+    /// Set for URL tracks from the moment they are requested, before there is a sink.
+    pub stream: Option<Arc<StreamStatus>>,
+    pub load_error: Option<Arc<String>>,
 }
 
 impl TrackState for RealTrackState {
     fn rem_duration(&self) -> Option<Duration> {
+        let rate = self.current_rate.unwrap_or_default().0;
         self.duration.zip(self.sink.as_ref()).map(|(d, h)| {
             let played = Duration::from_secs_f64(h.position());
-            d.checked_sub(played).unwrap_or_default()
+            d.checked_sub(played).unwrap_or_default().div_f64(rate)
         })
     }
 
+    fn duration(&self) -> Option<Duration> {
+        let rate = self.current_rate.unwrap_or_default().0;
+        self.duration.map(|d| d.div_f64(rate))
+    }
+
     fn playback_state(&self) -> PlaybackState {
-        self.sink
-            .as_ref()
-            .map(|s| s.state())
-            .unwrap_or(PlaybackState::Stopped)
+        match (&self.sink, &self.stream) {
+            (Some(sink), _) => sink.state(),
+            // Still connecting, but the deck should already treat it as playing so it can be stopped
+            (None, Some(_)) => PlaybackState::Playing,
+            (None, None) => PlaybackState::Stopped,
+        }
+    }
+
+    fn volume_offset_db(&self) -> f64 {
+        self.volume_offset_db
+    }
+
+    fn pan_override(&self) -> Option<f32> {
+        self.pan_override
+    }
+
+    fn playback_rate_override(&self) -> Option<f64> {
+        self.playback_rate_override
+    }
+
+    fn is_buffering(&self) -> bool {
+        self.stream.as_ref().is_some_and(|s| s.is_buffering())
+            && self.playback_state().is_advancing()
+    }
+
+    fn load_error(&self) -> Option<Arc<String>> {
+        self.load_error.clone()
+    }
+
+    fn instances(&self) -> usize {
+        self.handles().filter(|h| is_audible(h)).count()
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -102,29 +286,172 @@ impl TrackState for RealTrackState {
     }
 }
 
+impl RealTrackState {
+    /// Every instance of the track, oldest first.
+    fn handles(&self) -> impl Iterator<Item = &StreamingSoundHandle<FromFileError>> {
+        self.earlier.iter().chain(&self.sink)
+    }
+
+    fn handles_mut(&mut self) -> impl Iterator<Item = &mut StreamingSoundHandle<FromFileError>> {
+        self.earlier.iter_mut().chain(&mut self.sink)
+    }
+}
+
+/// Instances that are already fading out no longer count as playing.
+fn is_audible(handle: &StreamingSoundHandle<FromFileError>) -> bool {
+    !matches!(
+        handle.state(),
+        PlaybackState::Stopping | PlaybackState::Stopped
+    )
+}
+
 pub struct TrackStateData {
     pub rem_duration: Option<Duration>,
+    pub duration: Option<Duration>,
     pub playback: PlaybackState,
+    pub volume_offset_db: f64,
+    pub pan_override: Option<f32>,
+    pub playback_rate_override: Option<f64>,
+    pub buffering: bool,
+    pub load_error: Option<Arc<String>>,
+    pub instances: usize,
+}
+
+impl TrackStateData {
+    /// Paused tracks still count, since resuming picks them up where they were.
+    pub fn is_playing(&self) -> bool {
+        self.playback.is_advancing() || self.playback == PlaybackState::Paused
+    }
 }
 
 impl<T: TrackState + ?Sized> From<&T> for TrackStateData {
     fn from(state: &T) -> Self {
         TrackStateData {
             rem_duration: state.rem_duration(),
+            duration: state.duration(),
             playback: state.playback_state(),
+            volume_offset_db: state.volume_offset_db(),
+            pan_override: state.pan_override(),
+            playback_rate_override: state.playback_rate_override(),
+            buffering: state.is_buffering(),
+            load_error: state.load_error(),
+            instances: state.instances(),
         }
     }
 }
 
+#[allow(clippy::enum_variant_names)]
 pub enum AudioEvent {
     TrackStateChanged(Arc<Track>),
+    /// Sent whenever a recording of the output mix starts or stops, including on failure.
+    RecordingChanged(Switch),
+    /// The engine's global volume in dB, in reply to [`AudioCommand::GetGlobalVolume`] and
+    /// after every change.
+    GlobalVolumeChanged(f64),
+    /// Sent at startup if there is an input, and after every change to it.
+    InputChanged(InputStatus),
+    /// A command from the deck could not be carried out, e.g. because a file is missing. `track`
+    /// is the track the command was about, if any.
+    Error {
+        track: Option<Arc<Track>>,
+        message: String,
+    },
 }
 
 #[derive(Debug)]
 pub enum AudioCommand {
     Play(Arc<Track>),
+    /// Stops every instance of the track.
     Stop(Arc<Track>),
+    StopInstances(Arc<Track>, config::Instances),
+    /// Freezes every playing track where it is, for [`AudioCommand::ResumeAll`].
+    PauseAll,
+    ResumeAll,
     SetGlobalVolume(f64),
+    /// Asks for a [`AudioEvent::GlobalVolumeChanged`] with the current global volume.
+    GetGlobalVolume,
+    /// Changes the track's volume offset by the given number of decibels.
+    AdjustTrackVolume(Arc<Track>, f64),
+    /// Moves the track to the given stereo position, overriding its configured pan.
+    SetTrackPan(Arc<Track>, f32),
+    /// Changes the track's speed (and pitch), overriding its configured playback rate.
+    SetPlaybackRate(Arc<Track>, f64),
+    /// Replaces the set of buses, e.g. after the configuration was reloaded.
+    ConfigureBuses(Vec<config::Bus>),
+    /// Sets the volume of the named bus as a share of its full volume, from 0.0 to 1.0.
+    SetBusVolume(String, f64),
+    /// Previews the track on the cue output, or stops the preview if it is already running.
+    Cue(Arc<Track>),
+    ToggleRecording,
+    /// Sets the volume of the input in dB, see [`AudioSettings::input_device`].
+    SetInputVolume(f64),
+    ToggleInputMute,
+    /// Checks the tracks' files in the background, see [`PreloadMode`].
+    Preload(Vec<Arc<Track>>),
+    /// Confirms a press at the deck, see [`AudioSettings::ui_feedback`].
+    Feedback(Feedback),
+}
+
+/// [`AudioCommand`] with the tracks named by path, for other processes. The deck turns it back
+/// into tracks, see [`crate::daemon::ui::IpcEvent::Audio`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum IpcAudioCommand {
+    Play(PathBuf),
+    Stop(PathBuf),
+    StopInstances(PathBuf, config::Instances),
+    PauseAll,
+    ResumeAll,
+    SetGlobalVolume(f64),
+    GetGlobalVolume,
+    AdjustTrackVolume(PathBuf, f64),
+    SetTrackPan(PathBuf, f32),
+    SetPlaybackRate(PathBuf, f64),
+    ConfigureBuses(Vec<config::Bus>),
+    SetBusVolume(String, f64),
+    Cue(PathBuf),
+    ToggleRecording,
+    SetInputVolume(f64),
+    ToggleInputMute,
+    Preload(Vec<PathBuf>),
+    Feedback(Feedback),
+}
+
+impl From<&AudioCommand> for IpcAudioCommand {
+    fn from(command: &AudioCommand) -> Self {
+        let path = |track: &Track| PathBuf::clone(&track.path);
+        match command {
+            AudioCommand::Play(track) => IpcAudioCommand::Play(path(track)),
+            AudioCommand::Stop(track) => IpcAudioCommand::Stop(path(track)),
+            AudioCommand::StopInstances(track, instances) => {
+                IpcAudioCommand::StopInstances(path(track), *instances)
+            }
+            AudioCommand::PauseAll => IpcAudioCommand::PauseAll,
+            AudioCommand::ResumeAll => IpcAudioCommand::ResumeAll,
+            AudioCommand::SetGlobalVolume(db) => IpcAudioCommand::SetGlobalVolume(*db),
+            AudioCommand::GetGlobalVolume => IpcAudioCommand::GetGlobalVolume,
+            AudioCommand::AdjustTrackVolume(track, db) => {
+                IpcAudioCommand::AdjustTrackVolume(path(track), *db)
+            }
+            AudioCommand::SetTrackPan(track, pan) => {
+                IpcAudioCommand::SetTrackPan(path(track), *pan)
+            }
+            AudioCommand::SetPlaybackRate(track, rate) => {
+                IpcAudioCommand::SetPlaybackRate(path(track), *rate)
+            }
+            AudioCommand::ConfigureBuses(buses) => IpcAudioCommand::ConfigureBuses(buses.clone()),
+            AudioCommand::SetBusVolume(bus, volume) => {
+                IpcAudioCommand::SetBusVolume(bus.clone(), *volume)
+            }
+            AudioCommand::Cue(track) => IpcAudioCommand::Cue(path(track)),
+            AudioCommand::ToggleRecording => IpcAudioCommand::ToggleRecording,
+            AudioCommand::SetInputVolume(db) => IpcAudioCommand::SetInputVolume(*db),
+            AudioCommand::ToggleInputMute => IpcAudioCommand::ToggleInputMute,
+            AudioCommand::Preload(tracks) => {
+                IpcAudioCommand::Preload(tracks.iter().map(|t| path(t)).collect())
+            }
+            AudioCommand::Feedback(feedback) => IpcAudioCommand::Feedback(*feedback),
+        }
+    }
 }
 
 pub enum BlockingAudioCommand {
@@ -132,115 +459,883 @@ pub enum BlockingAudioCommand {
     UpdateState,
 }
 
-struct AudioState {
+/// A URL track that finished connecting (or failed to), picked up by
+/// [`AudioEngine::update_state`].
+type OpenedStream = (Arc<Track>, Arc<StreamStatus>, eyre::Result<StreamDecoder>);
+
+/// Daemon-wide audio settings that are not tied to any particular track.
+#[derive(Debug, Clone)]
+pub struct AudioSettings {
+    /// A zero duration cuts all tracks off immediately on shutdown.
+    pub shutdown_fade: Duration,
+    /// `None` sends the mix to the output unprocessed.
+    pub limiter: Option<LimiterSettings>,
+    /// Initial buses, later changes arrive via [`AudioCommand::ConfigureBuses`].
+    pub buses: Vec<config::Bus>,
+    /// Name of the output device that cued tracks are previewed on, if any.
+    pub cue_device: Option<String>,
+    /// Name of the input device that is played through the mixer, if any, or `default` for
+    /// the system's default input.
+    pub input_device: Option<String>,
+    /// Where recordings started from the deck are saved.
+    pub recording_dir: PathBuf,
+    /// Recording that starts together with the daemon.
+    pub record_on_start: Option<PathBuf>,
+    pub preload: PreloadMode,
+    pub output: AudioOutput,
+    pub updates: UpdateIntervals,
+    pub limits: VoiceLimits,
+    /// Volume in dB of the sounds that confirm presses, `None` to leave presses silent.
+    pub ui_feedback: Option<f64>,
+}
+
+/// The mixer's setting for the input, see [`AudioSettings::input_device`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputStatus {
+    pub volume_db: f64,
+    pub mute: Mute,
+}
+
+/// Whether the input is heard, see [`AudioCommand::ToggleInputMute`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mute {
+    Muted,
+    Live,
+}
+
+impl Mute {
+    pub fn toggled(self) -> Mute {
+        match self {
+            Mute::Muted => Mute::Live,
+            Mute::Live => Mute::Muted,
+        }
+    }
+}
+
+/// How often the state of playing tracks is sent to the deck, e.g. for their remaining time.
+#[derive(Debug, Clone, Copy)]
+pub struct UpdateIntervals {
+    pub normal: Duration,
+    /// Used while any track is about to end, so that its countdown is smooth.
+    pub fast: Duration,
+    /// Tracks with less time than this left are about to end, unless they loop and start over.
+    pub near_end: Duration,
+}
+
+/// For tracks without a configured fade-out, so that stopping one is not jarring.
+const DEFAULT_FADE_OUT: Duration = Duration::from_millis(2000);
+
+/// Quick enough to feel like freezing the scene, but without the click of cutting it off.
+const PAUSE_FADE: Duration = Duration::from_millis(300);
+
+/// Default for [`UpdateIntervals::near_end`] and [`crate::daemon::ui::UiSettings::near_end`].
+pub const NEAR_END: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioOutput {
+    /// The default output device
+    Device,
+    /// Simulates playback without producing any sound, for machines without a sound card
+    Null,
+}
+
+/// How thoroughly the configured files are checked at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PreloadMode {
+    /// Files are only opened when they are played
+    Off,
+    /// Make sure every file can be decoded
+    Verify,
+    /// Also read every file completely, so that the OS has it cached for the first playback
+    Cache,
+}
+
+#[derive(Debug, Clone)]
+pub struct LimiterSettings {
+    pub threshold_db: f64,
+    pub ratio: f64,
+}
+
+/// The playback operations that the audio loop drives. [`KiraEngine`] plays through an actual
+/// output device, while the other engines only keep track of state.
+pub trait AudioEngine {
+    fn play(&mut self, track: Arc<Track>) -> eyre::Result<()>;
+    fn stop(&mut self, track: &Arc<Track>);
+    /// Like [`AudioEngine::stop`], but leaves the other instances of an overlapping track
+    /// playing. A track with a single instance is stopped either way.
+    fn stop_instances(&mut self, track: &Arc<Track>, instances: config::Instances);
+    /// Freezes every instance of the track where it is, for [`AudioEngine::resume`].
+    fn pause(&mut self, track: &Arc<Track>);
+    fn resume(&mut self, track: &Arc<Track>);
+    fn set_global_volume(&mut self, volume_db: f64) -> eyre::Result<()>;
+    fn global_volume_db(&self) -> f64;
+    /// Changes the track's volume offset by the given number of decibels, up to
+    /// [`MAX_VOLUME_OFFSET_DB`].
+    fn adjust_track_volume(&mut self, track: &Track, delta_db: f64);
+    fn set_track_pan(&mut self, track: &Track, pan: f32);
+    fn set_playback_rate(&mut self, track: &Track, playback_rate: f64);
+    fn configure_buses(&mut self, buses: Vec<config::Bus>);
+    /// Outlasts changes to the bus, see [`AudioCommand::SetBusVolume`].
+    fn set_bus_volume(&mut self, bus: &str, volume: f64);
+    fn cue(&mut self, track: Arc<Track>) -> eyre::Result<()>;
+    fn toggle_recording(&mut self) -> eyre::Result<()>;
+    fn is_recording(&self) -> bool;
+    /// `None` if no input is played, e.g. because its device could not be opened.
+    fn input(&self) -> Option<InputStatus>;
+    fn set_input_volume(&mut self, volume_db: f64);
+    fn toggle_input_mute(&mut self);
+    fn preload(&mut self, tracks: Vec<Arc<Track>>);
+    fn feedback(&mut self, feedback: Feedback);
+    /// Returns the tracks whose state the deck should refresh, and forgets those that finished.
+    fn update_state(&mut self) -> Vec<Arc<Track>>;
+    /// The tracks that are playing, in the order they were started. Overlapping tracks may be
+    /// listed once per instance.
+    fn playing(&self) -> &[Arc<Track>];
+    /// Called once after the last command, before the audio loop exits. Records where the
+    /// playing tracks are, like [`AudioEngine::stop`], so that they can resume there.
+    fn shutdown(&mut self);
+}
+
+/// Plays on the default output device through kira.
+pub struct KiraEngine {
     manager: AudioManager,
     tracks: Vec<Arc<Track>>,
     event_tx: Sender<AudioEvent>,
+    /// Wakes the audio loop once background work, such as connecting to streams, has finished.
+    internal_tx: UnboundedSender<BlockingAudioCommand>,
+    opened_tx: mpsc::Sender<OpenedStream>,
+    opened_rx: mpsc::Receiver<OpenedStream>,
     global_volume: VolumeControlHandle,
     current_volume_db: f64,
+    buses: HashMap<String, BusTrack>,
+    /// Set from outside the deck, e.g. from Home Assistant. Buses that are not in here play at
+    /// full volume.
+    bus_volumes: HashMap<String, f64>,
+    /// The devices that buses play on instead of the main output, by name.
+    outputs: HashMap<String, Output>,
+    cue: Option<CueOutput>,
+    input: Option<Input>,
+    feedback: Option<FeedbackOutput>,
+    recorder: Recorder,
+    settings: AudioSettings,
+}
+
+/// A second audio manager on a separate device, so that previews stay out of the main mix.
+struct CueOutput {
+    manager: AudioManager,
+    playing: Option<(Arc<Track>, StreamingSoundHandle<FromFileError>)>,
+}
+
+impl CueOutput {
+    fn open(device_name: &str) -> eyre::Result<Self> {
+        let manager = AudioManager::<DefaultBackend>::new(AudioManagerSettings {
+            backend_settings: CpalBackendSettings {
+                device: Some(find_output_device(device_name)?),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with_context(|| format!("Unable to open audio output device '{device_name}'"))?;
+        Ok(CueOutput {
+            manager,
+            playing: None,
+        })
+    }
 }
-impl AudioState {
-    pub fn new(event_tx: Sender<AudioEvent>) -> eyre::Result<Self> {
-        let mut settings = AudioManagerSettings::default();
-        let global_volume = settings
+
+/// Another audio manager for the buses that play on a device of their own, e.g. a virtual cable
+/// that a streaming program captures. Unlike the cue output, it is part of the mix: the global
+/// volume and the limiter apply to it as well, only the recording leaves it out.
+struct Output {
+    manager: AudioManager,
+    volume: VolumeControlHandle,
+}
+
+impl Output {
+    fn open(
+        device_name: &str,
+        limiter: Option<&LimiterSettings>,
+        volume_db: f64,
+    ) -> eyre::Result<Self> {
+        let (mut manager_settings, mut volume) = output_settings(limiter);
+        manager_settings.backend_settings.device = Some(find_output_device(device_name)?);
+        let manager = AudioManager::<DefaultBackend>::new(manager_settings)
+            .with_context(|| format!("Unable to open audio output device '{device_name}'"))?;
+        volume.set_volume(Decibels(volume_db as f32), Tween::default());
+        info!("Opened audio output '{device_name}' for buses");
+        Ok(Output { manager, volume })
+    }
+}
+
+fn find_output_device(device_name: &str) -> eyre::Result<cpal::Device> {
+    cpal::default_host()
+        .output_devices()
+        .context("Unable to list audio output devices")?
+        .find(|d| d.name().is_ok_and(|name| name == device_name))
+        .with_context(|| format!("Audio output device '{device_name}' not found"))
+}
+
+/// The start of the main track of every output that is part of the mix.
+fn output_settings(
+    limiter: Option<&LimiterSettings>,
+) -> (AudioManagerSettings<DefaultBackend>, VolumeControlHandle) {
+    let mut manager_settings = AudioManagerSettings::default();
+    let global_volume = manager_settings
+        .main_track_builder
+        .add_effect(kira::effect::volume_control::VolumeControlBuilder::default());
+    // After the global volume so that turning the deck up cannot push the output into clipping
+    if let Some(limiter) = limiter {
+        manager_settings.main_track_builder.add_effect(
+            CompressorBuilder::new()
+                .threshold(limiter.threshold_db)
+                .ratio(limiter.ratio)
+                .attack_duration(Duration::from_millis(5))
+                .release_duration(Duration::from_millis(100)),
+        );
+    }
+    (manager_settings, global_volume)
+}
+
+struct BusTrack {
+    config: config::Bus,
+    handle: TrackHandle,
+}
+
+impl KiraEngine {
+    pub fn new(
+        event_tx: Sender<AudioEvent>,
+        internal_tx: UnboundedSender<BlockingAudioCommand>,
+        settings: AudioSettings,
+    ) -> eyre::Result<Self> {
+        let (mut manager_settings, global_volume) = output_settings(settings.limiter.as_ref());
+        // Last in the chain, so that the recording sounds exactly like the output
+        let recorder = manager_settings
             .main_track_builder
-            .add_effect(kira::effect::volume_control::VolumeControlBuilder::default());
-        let manager = AudioManager::<DefaultBackend>::new(settings)
+            .add_effect(RecorderBuilder);
+        let manager = AudioManager::<DefaultBackend>::new(manager_settings)
             .context("Unable to create audio device")?;
-        Ok(AudioState {
+        let (opened_tx, opened_rx) = mpsc::channel();
+        let mut state = KiraEngine {
             manager,
             global_volume,
             tracks: Vec::new(),
             event_tx,
+            internal_tx,
+            opened_tx,
+            opened_rx,
             current_volume_db: 0.0, // Start at 0 dB (no change)
-        })
+            buses: HashMap::new(),
+            bus_volumes: HashMap::new(),
+            outputs: HashMap::new(),
+            cue: None,
+            input: None,
+            feedback: None,
+            recorder,
+            settings,
+        };
+        // A missing monitor (e.g. unplugged headphones) must not keep the main output from working
+        if let Some(device_name) = &state.settings.cue_device {
+            match CueOutput::open(device_name) {
+                Ok(cue) => state.cue = Some(cue),
+                Err(e) => error!("Cue output disabled: {:?}", e),
+            }
+        }
+        // Likewise for a microphone
+        if let Some(device_name) = &state.settings.input_device {
+            match Input::open(&mut state.manager, device_name) {
+                Ok(input) => state.input = Some(input),
+                Err(e) => error!("Audio input disabled: {:?}", e),
+            }
+        }
+        // Likewise for the sounds that confirm presses
+        if let Some(volume_db) = state.settings.ui_feedback {
+            match FeedbackOutput::open(volume_db) {
+                Ok(feedback) => state.feedback = Some(feedback),
+                Err(e) => error!("UI feedback disabled: {:?}", e),
+            }
+        }
+        let buses = state.settings.buses.clone();
+        state.configure_buses(buses);
+        if let Some(path) = state.settings.record_on_start.clone()
+            && let Err(e) = state.recorder.start(&path)
+        {
+            error!("Error starting recording: {:?}", e);
+        }
+        Ok(state)
     }
 
-    #[instrument(skip_all, level = "debug", fields(volume_db))]
-    fn set_global_volume(&mut self, volume_db: f64) -> eyre::Result<()> {
-        self.global_volume.set_volume(
-            Decibels(volume_db as f32),
-            Tween {
-                duration: Duration::from_secs(1),
-                easing: Easing::OutPowi(1),
-                start_time: StartTime::Immediate,
-            },
-        );
-        self.current_volume_db = volume_db;
+    /// Connecting may take a while, so it happens on a separate thread. Playback starts with the
+    /// update that it wakes the audio loop for.
+    #[instrument(skip(self, track), level = "debug")]
+    fn open_stream(&mut self, track: Arc<Track>, url: String) -> eyre::Result<()> {
+        let status = StreamStatus::new();
+        {
+            let mut track_state_guard = track.state.blocking_lock();
+            let state = track_state_guard
+                .as_any_mut()
+                .downcast_mut::<RealTrackState>()
+                .expect("invalid track state type");
+            state.stream = Some(status.clone());
+        }
+        let internal_tx = self.internal_tx.clone();
+        let opened_tx = self.opened_tx.clone();
+        let opened_track = track.clone();
+        std::thread::Builder::new()
+            .name("stream open".to_string())
+            .spawn(move || {
+                let result = stream::open(&url, status.clone());
+                if opened_tx.send((opened_track, status, result)).is_err()
+                    || internal_tx.send(BlockingAudioCommand::UpdateState).is_err()
+                {
+                    debug!("Audio loop shut down while connecting to {url}");
+                }
+            })
+            .context("Failed to start stream thread")?;
+
+        self.tracks.push(track.clone());
+        update_track_state(track, &self.event_tx)
+    }
+
+    /// Continues [`AudioEngine::play`] for URL tracks once connecting has finished.
+    #[instrument(skip_all, level = "debug")]
+    fn stream_opened(
+        &mut self,
+        track: &Arc<Track>,
+        status: &Arc<StreamStatus>,
+        decoder: eyre::Result<StreamDecoder>,
+    ) -> eyre::Result<()> {
+        let mut track_state_guard = track.state.blocking_lock();
+        let state = track_state_guard
+            .as_any_mut()
+            .downcast_mut::<RealTrackState>()
+            .expect("invalid track state type");
+        if !state
+            .stream
+            .as_ref()
+            .is_some_and(|s| Arc::ptr_eq(s, status))
+        {
+            debug!("Track {:?} was stopped while connecting", &track);
+            return Ok(());
+        }
+        let decoder = match decoder {
+            Ok(decoder) => decoder,
+            Err(e) => {
+                state.stream = None;
+                drop(track_state_guard);
+                self.tracks.retain(|t| !Arc::ptr_eq(track, t));
+                return Err(e);
+            }
+        };
+        let total_duration = decoder.duration();
+        if total_duration.is_none() && track.mode().loops() {
+            info!(
+                "Track {:?} is an endless stream, playing it without looping",
+                &track
+            );
+        }
+        let sound_data =
+            apply_track_settings(StreamingSoundData::from_decoder(decoder), track, state);
+        self.play_sound_data(track, state, sound_data, total_duration)
+    }
+
+    /// `total_duration` is `None` for endless streams.
+    fn play_sound_data(
+        &mut self,
+        track: &Track,
+        state: &mut RealTrackState,
+        sound_data: StreamingSoundData<FromFileError>,
+        total_duration: Option<Duration>,
+    ) -> eyre::Result<()> {
+        let rate = track_playback_rate(&track.settings, state.playback_rate_override);
+        let bus = track.settings.bus.as_ref().and_then(|name| {
+            let bus = self.buses.get_mut(name);
+            if bus.is_none() {
+                warn!(
+                    "Bus {name} not found, playing {:?} on the main track",
+                    &track.path
+                );
+            }
+            bus
+        });
+        let mut track_handle = match bus {
+            Some(bus) => bus.handle.play(sound_data),
+            None => self.manager.play(sound_data),
+        }
+        .with_context(|| format!("Failed to play {:?}", &track.path))?;
+        // Endless streams have no end to loop back from
+        if track.mode().loops() && total_duration.is_some() {
+            track_handle.set_loop_region(..);
+        }
+
+        state.earlier.retain(is_audible);
+        if let Some(previous) = state.sink.replace(track_handle)
+            && is_audible(&previous)
+        {
+            state.earlier.push(previous);
+        }
+        state.duration = total_duration;
+        state.current_rate = Some(rate);
+        state.load_error = None;
         Ok(())
     }
+}
 
+impl AudioEngine for KiraEngine {
     #[instrument(skip_all, level = "debug")]
     fn play(&mut self, track: Arc<Track>) -> eyre::Result<()> {
-        if !track.settings.mode.overlaps() && self.tracks.iter().any(|t| Arc::ptr_eq(&track, t)) {
+        if !track.mode().overlaps() && self.tracks.iter().any(|t| Arc::ptr_eq(&track, t)) {
             info!("Track {:?} already playing, not changing anything", &track);
             return Ok(());
         }
 
+        if let Some(url) = track.path.to_str().filter(|p| is_stream_url(p)) {
+            let url = url.to_string();
+            return self.open_stream(track, url);
+        }
+
         let mut track_state_guard = track.state.blocking_lock();
-        let mut sound_data =
-            StreamingSoundData::from_file(track.path.as_path()).with_context(|| {
-                format!(
-                    "Failed to load sound data from path {}",
-                    &track.path.display()
-                )
-            })?;
+        let state = track_state_guard
+            .as_any_mut()
+            .downcast_mut::<RealTrackState>()
+            .expect("invalid track state type");
+        let sound_data = load_sound_data(&track, state)?;
         let total_duration = sound_data.duration();
-        if let Some(fade_in) = track.settings.fade_in {
-            sound_data = sound_data.fade_in_tween(Tween {
-                duration: fade_in,
-                easing: Easing::OutPowi(2),
-                ..Default::default()
-            });
+        self.play_sound_data(&track, state, sound_data, Some(total_duration))?;
+        drop(track_state_guard);
+
+        self.tracks.push(track);
+        Ok(())
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    fn stop(&mut self, track: &Arc<Track>) {
+        let mut track_state_guard = track.state.blocking_lock();
+        let track_state = track_state_guard
+            .as_any_mut()
+            .downcast_mut::<RealTrackState>()
+            .expect("invalid track state type");
+        if let Some(sink) = &track_state.sink {
+            track.set_resume_position(Some(Duration::from_secs_f64(sink.position())));
         }
-        let mut track_handle = self
-            .manager
-            .play(sound_data)
-            .with_context(|| format!("Failed to play {:?}", &track.path))?;
-        if track.settings.mode.loops() {
-            track_handle.set_loop_region(..);
+        let fade_out = fade_out_tween(track);
+        for handle in track_state.handles_mut() {
+            handle.stop(fade_out);
         }
+        track_state.sink = None;
+        track_state.earlier.clear();
+        track_state.stream = None;
+        drop(track_state_guard);
+
+        self.tracks.retain(|t| !Arc::ptr_eq(track, t));
+    }
 
+    #[instrument(skip(self, track), level = "debug")]
+    fn stop_instances(&mut self, track: &Arc<Track>, instances: config::Instances) {
+        let mut track_state_guard = track.state.blocking_lock();
+        let track_state = track_state_guard
+            .as_any_mut()
+            .downcast_mut::<RealTrackState>()
+            .expect("invalid track state type");
+        track_state.earlier.retain(is_audible);
+        if track_state.earlier.is_empty() || instances == config::Instances::All {
+            drop(track_state_guard);
+            return self.stop(track);
+        }
+        let stopped = match instances {
+            config::Instances::Oldest => Some(track_state.earlier.remove(0)),
+            config::Instances::Newest | config::Instances::All => {
+                std::mem::replace(&mut track_state.sink, track_state.earlier.pop())
+            }
+        };
+        if let Some(mut handle) = stopped {
+            handle.stop(fade_out_tween(track));
+        }
+        drop(track_state_guard);
+
+        // Overlapping tracks are listed once per instance, in the order they were started
+        let listed = match instances {
+            config::Instances::Oldest => self.tracks.iter().position(|t| Arc::ptr_eq(track, t)),
+            config::Instances::Newest | config::Instances::All => {
+                self.tracks.iter().rposition(|t| Arc::ptr_eq(track, t))
+            }
+        };
+        if let Some(i) = listed {
+            self.tracks.remove(i);
+        }
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    fn pause(&mut self, track: &Arc<Track>) {
+        let fade = Tween {
+            duration: PAUSE_FADE,
+            ..Default::default()
+        };
+        with_handles(track, |handle| handle.pause(fade));
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    fn resume(&mut self, track: &Arc<Track>) {
+        let fade = Tween {
+            duration: PAUSE_FADE,
+            ..Default::default()
+        };
+        with_handles(track, |handle| handle.resume(fade));
+    }
+
+    #[instrument(skip_all, level = "debug", fields(volume_db))]
+    fn set_global_volume(&mut self, volume_db: f64) -> eyre::Result<()> {
+        let tween = Tween {
+            duration: Duration::from_secs(1),
+            easing: Easing::OutPowi(1),
+            start_time: StartTime::Immediate,
+        };
+        self.global_volume
+            .set_volume(Decibels(volume_db as f32), tween);
+        for output in self.outputs.values_mut() {
+            output.volume.set_volume(Decibels(volume_db as f32), tween);
+        }
+        self.current_volume_db = volume_db;
+        Ok(())
+    }
+
+    fn global_volume_db(&self) -> f64 {
+        self.current_volume_db
+    }
+
+    #[instrument(skip(self, track), level = "debug")]
+    fn adjust_track_volume(&mut self, track: &Track, delta_db: f64) {
+        let mut track_state_guard = track.state.blocking_lock();
         let state = track_state_guard
             .as_any_mut()
             .downcast_mut::<RealTrackState>()
             .expect("invalid track state type");
-        state.sink = Some(track_handle);
-        state.duration = Some(total_duration);
+        state.volume_offset_db = (state.volume_offset_db + delta_db).min(MAX_VOLUME_OFFSET_DB);
+        let volume = track_volume(&track.settings, state.volume_offset_db);
+        for sink in state.handles_mut() {
+            sink.set_volume(
+                volume,
+                Tween {
+                    duration: Duration::from_millis(250),
+                    ..Default::default()
+                },
+            );
+        }
+    }
 
-        self.tracks.push(track.clone());
+    #[instrument(skip(self, track), level = "debug")]
+    fn set_track_pan(&mut self, track: &Track, pan: f32) {
+        let mut track_state_guard = track.state.blocking_lock();
+        let state = track_state_guard
+            .as_any_mut()
+            .downcast_mut::<RealTrackState>()
+            .expect("invalid track state type");
+        state.pan_override = Some(pan);
+        let panning = track_pan(&track.settings, state.pan_override);
+        for sink in state.handles_mut() {
+            sink.set_panning(
+                panning,
+                Tween {
+                    duration: Duration::from_millis(250),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    #[instrument(skip(self, track), level = "debug")]
+    fn set_playback_rate(&mut self, track: &Track, playback_rate: f64) {
+        let mut track_state_guard = track.state.blocking_lock();
+        let state = track_state_guard
+            .as_any_mut()
+            .downcast_mut::<RealTrackState>()
+            .expect("invalid track state type");
+        state.playback_rate_override = Some(playback_rate);
+        let rate = track_playback_rate(&track.settings, state.playback_rate_override);
+        // Not tweened: the remaining time shown on the deck assumes a constant rate
+        for sink in state.handles_mut() {
+            sink.set_playback_rate(rate, Tween::default());
+        }
+        if state.sink.is_some() {
+            state.current_rate = Some(rate);
+        }
+    }
+
+    /// Buses with an unchanged definition are kept so that sounds playing through them go on.
+    #[instrument(skip_all, level = "debug")]
+    fn configure_buses(&mut self, buses: Vec<config::Bus>) {
+        self.buses
+            .retain(|name, bus| buses.iter().any(|b| &b.name == name && *b == bus.config));
+        for config in buses {
+            if self.buses.contains_key(&config.name) {
+                continue;
+            }
+            let volume = self.bus_volumes.get(&config.name).copied().unwrap_or(1.0);
+            let mut builder = TrackBuilder::new().volume(amplitude_to_decibels(volume));
+            for effect in &config.effects {
+                add_bus_effect(&mut builder, effect);
+            }
+            let manager = match &config.output {
+                Some(device_name) => {
+                    let output = match self.outputs.entry(device_name.clone()) {
+                        Entry::Occupied(output) => Ok(output.into_mut()),
+                        Entry::Vacant(entry) => Output::open(
+                            device_name,
+                            self.settings.limiter.as_ref(),
+                            self.current_volume_db,
+                        )
+                        .map(|output| entry.insert(output)),
+                    };
+                    // Like a missing cue output, a missing device must not silence the bus
+                    match output {
+                        Ok(output) => &mut output.manager,
+                        Err(e) => {
+                            error!("Bus {} plays on the main output: {:?}", config.name, e);
+                            &mut self.manager
+                        }
+                    }
+                }
+                None => &mut self.manager,
+            };
+            match manager.add_sub_track(builder) {
+                Ok(handle) => {
+                    self.buses
+                        .insert(config.name.clone(), BusTrack { config, handle });
+                }
+                Err(e) => error!("Unable to create bus {}: {:?}", config.name, e),
+            }
+        }
+        let buses = &self.buses;
+        self.outputs.retain(|device_name, _| {
+            buses
+                .values()
+                .any(|bus| bus.config.output.as_ref() == Some(device_name))
+        });
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn set_bus_volume(&mut self, bus: &str, volume: f64) {
+        let volume = volume.clamp(0.0, 1.0);
+        self.bus_volumes.insert(bus.to_string(), volume);
+        match self.buses.get_mut(bus) {
+            Some(track) => track
+                .handle
+                .set_volume(amplitude_to_decibels(volume), Tween::default()),
+            None => debug!("No bus {bus} yet, its volume applies once it is configured"),
+        }
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    fn cue(&mut self, track: Arc<Track>) -> eyre::Result<()> {
+        let Some(cue) = &mut self.cue else {
+            debug!("No cue output configured, ignoring cue of {:?}", &track);
+            return Ok(());
+        };
+        if track.path.to_str().is_some_and(is_stream_url) {
+            // Connecting would block the audio loop, and a preview of a live stream is of little use
+            info!("Streams cannot be cued, ignoring cue of {:?}", &track);
+            return Ok(());
+        }
+        if let Some((cued, mut handle)) = cue.playing.take() {
+            let was_advancing = handle.state().is_advancing();
+            handle.stop(Tween::default());
+            if was_advancing && Arc::ptr_eq(&cued, &track) {
+                return Ok(());
+            }
+        }
+
+        let sound_data = {
+            let track_state_guard = track.state.blocking_lock();
+            let state = track_state_guard
+                .as_any()
+                .downcast_ref::<RealTrackState>()
+                .expect("invalid track state type");
+            load_sound_data(&track, state)?
+        };
+        let handle = cue
+            .manager
+            .play(sound_data)
+            .with_context(|| format!("Failed to cue {:?}", &track.path))?;
+        cue.playing = Some((track, handle));
         Ok(())
     }
 
     #[instrument(skip_all, level = "debug")]
-    pub fn shutdown(self) {
-        for track in self.tracks {
+    fn toggle_recording(&mut self) -> eyre::Result<()> {
+        if self.recorder.is_recording() {
+            return self.recorder.stop();
+        }
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = self
+            .settings
+            .recording_dir
+            .join(format!("noisedeck-{secs}.wav"));
+        self.recorder.start(&path)
+    }
+
+    fn is_recording(&self) -> bool {
+        self.recorder.is_recording()
+    }
+
+    fn input(&self) -> Option<InputStatus> {
+        self.input.as_ref().map(Input::status)
+    }
+
+    fn set_input_volume(&mut self, volume_db: f64) {
+        if let Some(input) = &mut self.input {
+            input.set_volume(volume_db);
+        }
+    }
+
+    fn toggle_input_mute(&mut self) {
+        if let Some(input) = &mut self.input {
+            input.toggle_mute();
+        }
+    }
+
+    fn preload(&mut self, tracks: Vec<Arc<Track>>) {
+        start_preload(tracks, self.settings.preload, &self.event_tx);
+    }
+
+    fn feedback(&mut self, feedback: Feedback) {
+        if let Some(output) = &mut self.feedback {
+            output.play(feedback);
+        }
+    }
+
+    fn update_state(&mut self) -> Vec<Arc<Track>> {
+        let mut tracks = Vec::new();
+        while let Ok((track, status, decoder)) = self.opened_rx.try_recv() {
+            if let Err(e) = self.stream_opened(&track, &status, decoder) {
+                if report_error(&self.event_tx, Some(&track), "playing stream", e).is_err() {
+                    debug!("Audio events closed while connecting to {:?}", &track);
+                }
+                // No longer among the playing tracks, but the deck has yet to show that it stopped
+                tracks.push(track);
+            }
+        }
+        tracks.extend(self.tracks.iter().cloned());
+        self.tracks.retain(|track| {
+            let state_guard = track.state.blocking_lock();
+            let track_state = state_guard
+                .as_any()
+                .downcast_ref::<RealTrackState>()
+                .expect("invalid track state type");
+            let playing = track_state.sink.is_none()
+                || track_state
+                    .handles()
+                    .any(|sink| sink.state() != PlaybackState::Stopped);
+            // Played to the end, so the next time starts over
+            if !playing {
+                track.set_resume_position(None);
+            }
+            playing
+        });
+        tracks
+    }
+
+    fn playing(&self) -> &[Arc<Track>] {
+        &self.tracks
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    fn shutdown(&mut self) {
+        let fade = self.settings.shutdown_fade;
+        let mut any_audible = false;
+        for track in self.tracks.drain(..) {
             let mut track_state_guard = track.state.blocking_lock();
             let state = track_state_guard
                 .as_any_mut()
                 .downcast_mut::<RealTrackState>()
                 .expect("invalid track state type");
-            if let Some(sink) = &mut state.sink {
+            if let Some(sink) = &state.sink {
+                track.set_resume_position(Some(Duration::from_secs_f64(sink.position())));
+            }
+            for sink in state.handles_mut() {
+                any_audible |= sink.state().is_advancing();
                 sink.stop(Tween {
-                    duration: Duration::default(),
+                    duration: fade,
+                    easing: Easing::InPowi(2),
                     ..Default::default()
                 })
             }
             state.sink = None;
+            state.earlier.clear();
+            state.stream = None;
+        }
+
+        // The manager owns the output stream; dropping it before the fade has finished would
+        // cut the tracks off just as abruptly as not fading at all.
+        if any_audible && !fade.is_zero() {
+            info!("Fading out playing tracks over {:?}", fade);
+            std::thread::sleep(fade);
+        }
+        if let Err(e) = self.recorder.stop() {
+            error!("Error finishing recording: {:?}", e);
         }
     }
 }
 
+fn start_preload(tracks: Vec<Arc<Track>>, mode: PreloadMode, event_tx: &Sender<AudioEvent>) {
+    if mode == PreloadMode::Off {
+        return;
+    }
+    let event_tx = event_tx.clone();
+    if let Err(e) = std::thread::Builder::new()
+        .name("preload".to_string())
+        .spawn(move || preload::preload_tracks(tracks, mode, event_tx))
+    {
+        error!("Error starting preload: {:?}", e);
+    }
+}
+
 pub async fn run(
+    event_tx: Sender<AudioEvent>,
+    command_rx: Receiver<AudioCommand>,
+    settings: AudioSettings,
+) -> eyre::Result<()> {
+    let engine_event_tx = event_tx.clone();
+    let updates = settings.updates;
+    let limits = settings.limits;
+    match settings.output {
+        AudioOutput::Device => {
+            run_with_engine(event_tx, command_rx, updates, limits, move |internal_tx| {
+                KiraEngine::new(engine_event_tx, internal_tx, settings)
+            })
+            .await
+        }
+        AudioOutput::Null => {
+            run_with_engine(event_tx, command_rx, updates, limits, move |_| {
+                Ok(null::NullEngine::new(engine_event_tx, settings))
+            })
+            .await
+        }
+    }
+}
+
+/// Drives the engine from the deck's commands until the command channel closes. The engine is
+/// created on the blocking audio thread and stays there for its whole life.
+pub async fn run_with_engine<E: AudioEngine>(
     event_tx: Sender<AudioEvent>,
     mut command_rx: Receiver<AudioCommand>,
+    updates: UpdateIntervals,
+    limits: VoiceLimits,
+    new_engine: impl FnOnce(UnboundedSender<BlockingAudioCommand>) -> eyre::Result<E> + Send + 'static,
 ) -> eyre::Result<()> {
     let (blocking_cmd_tx, blocking_cmd_rx) = std::sync::mpsc::channel::<BlockingAudioCommand>();
+    let (internal_tx, mut internal_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (near_end_tx, mut near_end_rx) = watch::channel(false);
     let interrupt_task = tokio::task::spawn(async move {
-        let mut timeout = tokio::time::interval(Duration::from_millis(500));
-        timeout.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut timeout = update_interval(updates.normal);
         'task: loop {
             tokio::select! {
+                Ok(()) = near_end_rx.changed() => {
+                    let near_end = *near_end_rx.borrow_and_update();
+                    trace!(near_end, "switching audio state update interval");
+                    timeout = update_interval(if near_end { updates.fast } else { updates.normal });
+                },
                 command = command_rx.recv() => {
                     let Some(command) = command else {
                         trace!("Audio command channel closed, shutting down translation loop");
@@ -251,6 +1346,12 @@ pub async fn run(
                         break 'task;
                     }
                 },
+                Some(command) = internal_rx.recv() => {
+                    if blocking_cmd_tx.send(command).is_err() {
+                        trace!("Blocking audio command channel closed, shutting down translation loop (s)");
+                        break 'task;
+                    }
+                },
                 _ = timeout.tick() => {
                     trace!("ask for audio state update");
                     if blocking_cmd_tx.send(BlockingAudioCommand::UpdateState).is_err() {
@@ -262,8 +1363,17 @@ pub async fn run(
         }
     });
 
-    let sync_thread_finished =
-        tokio::task::spawn_blocking(move || run_sync(event_tx, blocking_cmd_rx));
+    let sync_thread_finished = tokio::task::spawn_blocking(move || {
+        let engine = new_engine(internal_tx)?;
+        run_sync(
+            engine,
+            Voices::new(limits),
+            event_tx,
+            blocking_cmd_rx,
+            near_end_tx,
+            updates.near_end,
+        )
+    });
 
     sync_thread_finished.await??;
     interrupt_task.await?;
@@ -271,69 +1381,348 @@ pub async fn run(
 }
 
 #[instrument(skip_all)]
-fn run_sync(
+fn run_sync<E: AudioEngine>(
+    mut engine: E,
+    mut voices: Voices,
     event_tx: Sender<AudioEvent>,
     command_rx: std::sync::mpsc::Receiver<BlockingAudioCommand>,
+    near_end_tx: watch::Sender<bool>,
+    near_end: Duration,
 ) -> eyre::Result<()> {
-    let mut state = AudioState::new(event_tx)?;
+    let set_near_end = |near_end: bool| {
+        near_end_tx.send_if_modified(|current| std::mem::replace(current, near_end) != near_end);
+    };
+    let play = |engine: &mut E, stingers: &mut Stingers, track: Arc<Track>| -> eyre::Result<()> {
+        if track.mode() == PlaybackMode::Stinger {
+            for paused in stingers.interrupt(engine, &track) {
+                update_track_state(paused, &event_tx)?;
+            }
+        }
+        if let Err(e) = engine.play(track.clone()) {
+            report_error(&event_tx, Some(&track), "playing track", e)?;
+        }
+        // Short one-shots are near their end right away, so waiting for the next update
+        // would skip most of their countdown
+        if is_near_end(&track, near_end) {
+            set_near_end(true);
+        }
+        Ok(())
+    };
+    // Stopped sounds make room for those waiting in the queue, and a finished stinger gives the
+    // scene back
+    let play_queued =
+        |engine: &mut E, voices: &mut Voices, stingers: &mut Stingers| -> eyre::Result<()> {
+            for resumed in stingers.resume_after(engine) {
+                update_track_state(resumed, &event_tx)?;
+            }
+            while let Some(track) = voices.next_ready(engine.playing()) {
+                play(engine, stingers, track.clone())?;
+                update_track_state(track, &event_tx)?;
+            }
+            Ok(())
+        };
+    let mut stingers = Stingers::default();
+    // A recording requested on the command line is already running
+    if engine.is_recording() {
+        event_tx.blocking_send(AudioEvent::RecordingChanged(Switch::On))?;
+    }
+    if let Some(input) = engine.input() {
+        event_tx.blocking_send(AudioEvent::InputChanged(input))?;
+    }
     while let Ok(command) = command_rx.recv() {
         match command {
             AsyncCommand(AudioCommand::Play(track)) => {
-                if let Err(e) = state.play(track) {
-                    error!("Error playing track: {:?}", e);
+                match voices.admit(engine.playing(), &track) {
+                    Admission::Play => play(&mut engine, &mut stingers, track)?,
+                    Admission::Steal(oldest) => {
+                        engine.stop_instances(&oldest, config::Instances::Oldest);
+                        update_track_state(oldest, &event_tx)?;
+                        play(&mut engine, &mut stingers, track)?;
+                    }
+                    Admission::Queue => voices.queue(track),
+                    Admission::Refuse => {
+                        let e = eyre::eyre!("Too many sounds playing to start {:?}", track.path);
+                        report_error(&event_tx, Some(&track), "playing track", e)?;
+                    }
                 }
             }
             AsyncCommand(AudioCommand::Stop(track)) => {
-                let mut track_state_guard = track.state.blocking_lock();
-                let track_state = track_state_guard
-                    .as_any_mut()
-                    .downcast_mut::<RealTrackState>()
-                    .expect("invalid track state type");
-                if let Some(sink) = &mut track_state.sink {
-                    sink.stop(Tween {
-                        duration: Duration::from_millis(2000),
-                        easing: Easing::InPowi(2),
-                        ..Default::default()
-                    });
+                voices.dequeue(&track);
+                engine.stop(&track);
+                update_track_state(track, &event_tx)?;
+                play_queued(&mut engine, &mut voices, &mut stingers)?;
+            }
+            AsyncCommand(AudioCommand::StopInstances(track, instances)) => {
+                engine.stop_instances(&track, instances);
+                update_track_state(track, &event_tx)?;
+                play_queued(&mut engine, &mut voices, &mut stingers)?;
+            }
+            AsyncCommand(AudioCommand::PauseAll) => {
+                for track in distinct(engine.playing()) {
+                    engine.pause(&track);
+                    update_track_state(track, &event_tx)?;
                 }
-                track_state.sink = None;
-                drop(track_state_guard);
-
-                state.tracks.retain(|t| !Arc::ptr_eq(&track, t));
-                update_track_state(track, &state.event_tx)?
+            }
+            AsyncCommand(AudioCommand::ResumeAll) => {
+                for track in distinct(engine.playing()) {
+                    engine.resume(&track);
+                    update_track_state(track, &event_tx)?;
+                }
+            }
+            AsyncCommand(AudioCommand::AdjustTrackVolume(track, delta_db)) => {
+                engine.adjust_track_volume(&track, delta_db);
+                update_track_state(track, &event_tx)?
+            }
+            AsyncCommand(AudioCommand::SetTrackPan(track, pan)) => {
+                engine.set_track_pan(&track, pan);
+                update_track_state(track, &event_tx)?
+            }
+            AsyncCommand(AudioCommand::SetPlaybackRate(track, playback_rate)) => {
+                engine.set_playback_rate(&track, playback_rate);
+                update_track_state(track, &event_tx)?
+            }
+            AsyncCommand(AudioCommand::ToggleRecording) => {
+                if let Err(e) = engine.toggle_recording() {
+                    report_error(&event_tx, None, "toggling recording", e)?;
+                }
+                let recording = if engine.is_recording() {
+                    Switch::On
+                } else {
+                    Switch::Off
+                };
+                event_tx.blocking_send(AudioEvent::RecordingChanged(recording))?;
+            }
+            AsyncCommand(AudioCommand::SetInputVolume(volume_db)) => {
+                engine.set_input_volume(volume_db);
+                if let Some(input) = engine.input() {
+                    event_tx.blocking_send(AudioEvent::InputChanged(input))?;
+                }
+            }
+            AsyncCommand(AudioCommand::ToggleInputMute) => {
+                engine.toggle_input_mute();
+                if let Some(input) = engine.input() {
+                    event_tx.blocking_send(AudioEvent::InputChanged(input))?;
+                }
+            }
+            AsyncCommand(AudioCommand::Cue(track)) => {
+                if let Err(e) = engine.cue(track.clone()) {
+                    report_error(&event_tx, Some(&track), "cueing track", e)?;
+                }
+            }
+            AsyncCommand(AudioCommand::Preload(tracks)) => {
+                engine.preload(tracks);
+            }
+            AsyncCommand(AudioCommand::Feedback(feedback)) => {
+                engine.feedback(feedback);
+            }
+            AsyncCommand(AudioCommand::ConfigureBuses(buses)) => {
+                engine.configure_buses(buses);
+            }
+            AsyncCommand(AudioCommand::SetBusVolume(bus, volume)) => {
+                engine.set_bus_volume(&bus, volume);
             }
             AsyncCommand(AudioCommand::SetGlobalVolume(volume_db)) => {
-                if let Err(e) = state.set_global_volume(volume_db) {
-                    error!("Error setting global volume: {:?}", e);
+                if let Err(e) = engine.set_global_volume(volume_db) {
+                    report_error(&event_tx, None, "setting global volume", e)?;
                 }
+                event_tx
+                    .blocking_send(AudioEvent::GlobalVolumeChanged(engine.global_volume_db()))?;
+            }
+            AsyncCommand(AudioCommand::GetGlobalVolume) => {
+                event_tx
+                    .blocking_send(AudioEvent::GlobalVolumeChanged(engine.global_volume_db()))?;
             }
             BlockingAudioCommand::UpdateState => {
-                let mut idx_to_remove = Vec::new();
-                for (idx, track) in state.tracks.iter().enumerate() {
-                    let state_guard = track.state.blocking_lock();
-                    let track_state = state_guard
-                        .as_any()
-                        .downcast_ref::<RealTrackState>()
-                        .expect("invalid track state type");
-                    if let Some(sink) = &track_state.sink {
-                        if sink.state() == PlaybackState::Stopped {
-                            idx_to_remove.push(idx);
-                        }
-                    }
-                    drop(state_guard);
-                    update_track_state(track.clone(), &state.event_tx)?;
-                }
-
-                // swap remove is only safe in reverse order (idx_to_remove is sorted asc)
-                for idx in idx_to_remove.into_iter().rev() {
-                    state.tracks.swap_remove(idx);
+                let tracks = engine.update_state();
+                set_near_end(tracks.iter().any(|track| is_near_end(track, near_end)));
+                for track in tracks {
+                    update_track_state(track, &event_tx)?;
                 }
+                play_queued(&mut engine, &mut voices, &mut stingers)?;
             }
         }
     }
 
     info!("Audio command channel closed, shutting down");
-    state.shutdown();
+    engine.shutdown();
+    Ok(())
+}
+
+/// The config expresses track volume as a linear amplitude factor, kira expects decibels.
+fn amplitude_to_decibels(amplitude: f64) -> Decibels {
+    if amplitude <= 0.0 {
+        return Decibels::SILENCE;
+    }
+    let db = 20.0 * amplitude.log10();
+    Decibels(db.max(Decibels::SILENCE.0 as f64) as f32)
+}
+
+fn with_handles(track: &Track, f: impl FnMut(&mut StreamingSoundHandle<FromFileError>)) {
+    let mut track_state_guard = track.state.blocking_lock();
+    let state = track_state_guard
+        .as_any_mut()
+        .downcast_mut::<RealTrackState>()
+        .expect("invalid track state type");
+    state.handles_mut().for_each(f);
+}
+
+/// Overlapping tracks are listed once per instance, see [`AudioEngine::playing`].
+fn distinct(tracks: &[Arc<Track>]) -> Vec<Arc<Track>> {
+    let mut seen = HashSet::new();
+    tracks
+        .iter()
+        .filter(|t| seen.insert(Arc::as_ptr(t)))
+        .cloned()
+        .collect()
+}
+
+fn fade_out_tween(track: &Track) -> Tween {
+    Tween {
+        duration: track.fade_out(),
+        easing: Easing::InPowi(2),
+        ..Default::default()
+    }
+}
+
+/// How far a track can be turned up from the deck. A sound that is still too quiet then needs a
+/// louder file, since a few taps more would clip the mix.
+pub const MAX_VOLUME_OFFSET_DB: f64 = 12.0;
+
+fn track_volume(settings: &PlaySoundSettings, volume_offset_db: f64) -> Decibels {
+    let db = amplitude_to_decibels(settings.volume).0 as f64 + settings.gain_db + volume_offset_db;
+    Decibels(db.max(Decibels::SILENCE.0 as f64) as f32)
+}
+
+fn track_pan(settings: &PlaySoundSettings, pan_override: Option<f32>) -> Panning {
+    Panning(pan_override.unwrap_or(settings.pan).clamp(-1.0, 1.0))
+}
+
+/// Applies the track's mix settings so that every output hears it the same way.
+fn load_sound_data(
+    track: &Track,
+    state: &RealTrackState,
+) -> eyre::Result<StreamingSoundData<FromFileError>> {
+    let sound_data = track.open_with_fallback(|path| {
+        StreamingSoundData::from_file(path)
+            .with_context(|| format!("Failed to load sound data from path {}", path.display()))
+    })?;
+    let sound_data = match track.start_position() {
+        Some(position) => sound_data.start_position(position.as_secs_f64()),
+        None => sound_data,
+    };
+    Ok(apply_track_settings(sound_data, track, state))
+}
+
+fn apply_track_settings<E: Send + 'static>(
+    mut sound_data: StreamingSoundData<E>,
+    track: &Track,
+    state: &RealTrackState,
+) -> StreamingSoundData<E> {
+    sound_data = sound_data
+        .volume(track_volume(&track.settings, state.volume_offset_db))
+        .panning(track_pan(&track.settings, state.pan_override))
+        .playback_rate(track_playback_rate(
+            &track.settings,
+            state.playback_rate_override,
+        ));
+    if let Some(fade_in) = track.fade_in() {
+        sound_data = sound_data.fade_in_tween(Tween {
+            duration: fade_in,
+            easing: Easing::OutPowi(2),
+            ..Default::default()
+        });
+    }
+    sound_data
+}
+
+fn add_bus_effect(builder: &mut TrackBuilder, effect: &config::Effect) {
+    match effect {
+        config::Effect::Reverb {
+            feedback,
+            damping,
+            mix,
+        } => {
+            builder.add_effect(
+                ReverbBuilder::new()
+                    .feedback(*feedback)
+                    .damping(*damping)
+                    .mix(Mix(*mix)),
+            );
+        }
+        config::Effect::Filter {
+            mode,
+            cutoff_hz,
+            resonance,
+            mix,
+        } => {
+            builder.add_effect(
+                FilterBuilder::new()
+                    .mode(match mode {
+                        config::FilterMode::LowPass => FilterMode::LowPass,
+                        config::FilterMode::BandPass => FilterMode::BandPass,
+                        config::FilterMode::HighPass => FilterMode::HighPass,
+                        config::FilterMode::Notch => FilterMode::Notch,
+                    })
+                    .cutoff(*cutoff_hz)
+                    .resonance(*resonance)
+                    .mix(Mix(*mix)),
+            );
+        }
+        config::Effect::Delay {
+            delay,
+            feedback_db,
+            mix,
+        } => {
+            builder.add_effect(
+                DelayBuilder::new()
+                    .delay_time(*delay)
+                    .feedback(Decibels(*feedback_db as f32))
+                    .mix(Mix(*mix)),
+            );
+        }
+    }
+}
+
+pub const MIN_PLAYBACK_RATE: f64 = 0.25;
+pub const MAX_PLAYBACK_RATE: f64 = 4.0;
+
+/// Keeps the rate positive and within a range where the sound is still recognizable.
+fn track_playback_rate(
+    settings: &PlaySoundSettings,
+    playback_rate_override: Option<f64>,
+) -> PlaybackRate {
+    let rate = playback_rate_override
+        .or(settings.playback_rate)
+        .unwrap_or(1.0);
+    PlaybackRate(rate.clamp(MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE))
+}
+
+fn update_interval(period: Duration) -> Interval {
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
+}
+
+/// Looping tracks start over rather than end, so they count down no more smoothly than others.
+fn is_near_end(track: &Track, near_end: Duration) -> bool {
+    let state = track.state.blocking_lock();
+    !track.mode().loops()
+        && state.playback_state().is_advancing()
+        && state.rem_duration().is_some_and(|d| d < near_end)
+}
+
+/// Besides logging, tells the deck, which shows the failure to the game master.
+fn report_error(
+    event_tx: &Sender<AudioEvent>,
+    track: Option<&Arc<Track>>,
+    action: &str,
+    e: eyre::Report,
+) -> eyre::Result<()> {
+    error!("Error {action}: {:?}", e);
+    event_tx.blocking_send(AudioEvent::Error {
+        track: track.cloned(),
+        message: format!("{e}"),
+    })?;
     Ok(())
 }
 
@@ -341,3 +1730,277 @@ fn update_track_state(track: Arc<Track>, event_tx: &Sender<AudioEvent>) -> eyre:
     event_tx.blocking_send(AudioEvent::TrackStateChanged(track.clone()))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::mock::MockAudioEngine;
+    use super::{
+        AudioCommand, AudioEvent, InputStatus, Mute, NEAR_END, Track, UpdateIntervals, VoiceLimits,
+        amplitude_to_decibels, run_with_engine, track_pan, track_playback_rate,
+    };
+    use crate::config::{PlaySoundSettings, PlaybackMode};
+    use crate::daemon::ui::tests::harness::MockTrackState;
+    use crate::util::Switch;
+    use kira::sound::PlaybackState;
+    use kira::{Decibels, Panning, PlaybackRate};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc::channel;
+    use tokio::time::timeout;
+
+    const UPDATES: UpdateIntervals = UpdateIntervals {
+        normal: Duration::from_millis(500),
+        fast: Duration::from_millis(100),
+        near_end: NEAR_END,
+    };
+
+    fn mock_settings() -> PlaySoundSettings {
+        PlaySoundSettings::new(PlaybackMode::PlayStop)
+    }
+
+    fn mock_track() -> Arc<Track> {
+        Arc::new(Track::with_state(
+            Arc::new(PathBuf::from("rain.mp3")),
+            mock_settings(),
+            Box::<MockTrackState>::default(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_engine_loop_reports_track_states() -> eyre::Result<()> {
+        let (event_tx, mut event_rx) = channel(16);
+        let (command_tx, command_rx) = channel(16);
+        let limits = VoiceLimits::default();
+        let audio = tokio::spawn(run_with_engine(
+            event_tx,
+            command_rx,
+            UPDATES,
+            limits,
+            |_| Ok(MockAudioEngine::default()),
+        ));
+        let track = mock_track();
+
+        command_tx.send(AudioCommand::Play(track.clone())).await?;
+        // Playing tracks are reported on the next state update tick
+        assert!(matches!(
+            timeout(Duration::from_secs(2), event_rx.recv()).await?,
+            Some(AudioEvent::TrackStateChanged(t)) if Arc::ptr_eq(&t, &track)
+        ));
+        assert_eq!(track.read().await.playback, PlaybackState::Playing);
+
+        command_tx.send(AudioCommand::Stop(track.clone())).await?;
+        while track.read().await.playback != PlaybackState::Stopped {
+            assert!(matches!(
+                timeout(Duration::from_secs(2), event_rx.recv()).await?,
+                Some(AudioEvent::TrackStateChanged(_))
+            ));
+        }
+
+        drop(command_tx);
+        audio.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_engine_loop_updates_tracks_near_their_end_quickly() -> eyre::Result<()> {
+        let (event_tx, mut event_rx) = channel(16);
+        let (command_tx, command_rx) = channel(16);
+        let updates = UpdateIntervals {
+            normal: Duration::from_secs(3600),
+            fast: Duration::from_millis(20),
+            near_end: NEAR_END,
+        };
+        let limits = VoiceLimits::default();
+        let audio = tokio::spawn(run_with_engine(
+            event_tx,
+            command_rx,
+            updates,
+            limits,
+            |_| Ok(MockAudioEngine::default()),
+        ));
+        let track = Arc::new(Track::with_state(
+            Arc::new(PathBuf::from("gong.mp3")),
+            mock_settings(),
+            Box::new(MockTrackState {
+                rem_duration: Some(Duration::from_secs(5)),
+                ..Default::default()
+            }),
+        ));
+
+        command_tx.send(AudioCommand::Play(track.clone())).await?;
+        for _ in 0..3 {
+            assert!(matches!(
+                timeout(Duration::from_secs(1), event_rx.recv()).await?,
+                Some(AudioEvent::TrackStateChanged(t)) if Arc::ptr_eq(&t, &track)
+            ));
+        }
+
+        drop(command_tx);
+        audio.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_engine_loop_reports_global_volume() -> eyre::Result<()> {
+        let (event_tx, mut event_rx) = channel(16);
+        let (command_tx, command_rx) = channel(16);
+        let limits = VoiceLimits::default();
+        let audio = tokio::spawn(run_with_engine(
+            event_tx,
+            command_rx,
+            UPDATES,
+            limits,
+            |_| Ok(MockAudioEngine::default()),
+        ));
+
+        command_tx.send(AudioCommand::GetGlobalVolume).await?;
+        assert!(matches!(
+            timeout(Duration::from_secs(2), event_rx.recv()).await?,
+            Some(AudioEvent::GlobalVolumeChanged(0.0))
+        ));
+        command_tx.send(AudioCommand::SetGlobalVolume(-6.0)).await?;
+        assert!(matches!(
+            timeout(Duration::from_secs(2), event_rx.recv()).await?,
+            Some(AudioEvent::GlobalVolumeChanged(-6.0))
+        ));
+
+        drop(command_tx);
+        audio.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_engine_loop_reports_recording() -> eyre::Result<()> {
+        let (event_tx, mut event_rx) = channel(16);
+        let (command_tx, command_rx) = channel(16);
+        let limits = VoiceLimits::default();
+        let audio = tokio::spawn(run_with_engine(
+            event_tx,
+            command_rx,
+            UPDATES,
+            limits,
+            |_| Ok(MockAudioEngine::default()),
+        ));
+
+        command_tx.send(AudioCommand::ToggleRecording).await?;
+        assert!(matches!(
+            timeout(Duration::from_secs(2), event_rx.recv()).await?,
+            Some(AudioEvent::RecordingChanged(Switch::On))
+        ));
+
+        drop(command_tx);
+        audio.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_engine_loop_reports_input() -> eyre::Result<()> {
+        let (event_tx, mut event_rx) = channel(16);
+        let (command_tx, command_rx) = channel(16);
+        let limits = VoiceLimits::default();
+        let audio = tokio::spawn(run_with_engine(
+            event_tx,
+            command_rx,
+            UPDATES,
+            limits,
+            |_| Ok(MockAudioEngine::with_input()),
+        ));
+
+        let status = |volume_db, mute| InputStatus { volume_db, mute };
+        assert!(matches!(
+            timeout(Duration::from_secs(2), event_rx.recv()).await?,
+            Some(AudioEvent::InputChanged(s)) if s == status(0.0, Mute::Muted)
+        ));
+        command_tx.send(AudioCommand::ToggleInputMute).await?;
+        assert!(matches!(
+            timeout(Duration::from_secs(2), event_rx.recv()).await?,
+            Some(AudioEvent::InputChanged(s)) if s == status(0.0, Mute::Live)
+        ));
+        command_tx.send(AudioCommand::SetInputVolume(-6.0)).await?;
+        assert!(matches!(
+            timeout(Duration::from_secs(2), event_rx.recv()).await?,
+            Some(AudioEvent::InputChanged(s)) if s == status(-6.0, Mute::Live)
+        ));
+
+        drop(command_tx);
+        audio.await??;
+        Ok(())
+    }
+
+    #[test]
+    fn test_fallback_plays_when_the_file_cannot_be_opened() {
+        let settings = PlaySoundSettings {
+            fallback: Some(Arc::new("local/rain.mp3".to_string())),
+            ..mock_settings()
+        };
+        let track = Track::with_state(
+            Arc::new(PathBuf::from("nas/rain.mp3")),
+            settings,
+            Box::<MockTrackState>::default(),
+        );
+        let open = |only: &'static str| {
+            move |path: &Path| {
+                if path == Path::new(only) {
+                    Ok(path.to_path_buf())
+                } else {
+                    Err(eyre::eyre!("{} is unreachable", path.display()))
+                }
+            }
+        };
+
+        assert_eq!(
+            track.open_with_fallback(open("nas/rain.mp3")).ok(),
+            Some("nas/rain.mp3".into())
+        );
+        assert_eq!(
+            track.open_with_fallback(open("local/rain.mp3")).ok(),
+            Some("local/rain.mp3".into())
+        );
+        let both_failed = track.open_with_fallback(open("elsewhere.mp3"));
+        assert!(both_failed.is_err_and(|e| e.to_string().contains("Neither nas/rain.mp3")));
+    }
+
+    #[test]
+    fn test_unity_volume_is_unchanged() {
+        assert_eq!(amplitude_to_decibels(1.0), Decibels::IDENTITY);
+    }
+
+    #[test]
+    fn test_volume_is_applied_as_decibels() {
+        assert!((amplitude_to_decibels(0.5).0 - -6.0206).abs() < 0.001);
+        assert!((amplitude_to_decibels(2.0).0 - 6.0206).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_zero_volume_is_silent() {
+        assert_eq!(amplitude_to_decibels(0.0), Decibels::SILENCE);
+        assert_eq!(amplitude_to_decibels(-1.0), Decibels::SILENCE);
+        assert_eq!(amplitude_to_decibels(1e-9), Decibels::SILENCE);
+    }
+
+    #[test]
+    fn test_pan_override_replaces_configured_pan() {
+        let settings = PlaySoundSettings {
+            pan: -0.5,
+            ..PlaySoundSettings::new(PlaybackMode::PlayStop)
+        };
+        assert_eq!(track_pan(&settings, None), Panning(-0.5));
+        assert_eq!(track_pan(&settings, Some(0.25)), Panning(0.25));
+        assert_eq!(track_pan(&settings, Some(3.0)), Panning::RIGHT);
+    }
+
+    #[test]
+    fn test_playback_rate_override_is_clamped() {
+        let settings = PlaySoundSettings {
+            playback_rate: Some(0.8),
+            ..PlaySoundSettings::new(PlaybackMode::PlayStop)
+        };
+        assert_eq!(track_playback_rate(&settings, None), PlaybackRate(0.8));
+        assert_eq!(track_playback_rate(&settings, Some(1.5)), PlaybackRate(1.5));
+        assert_eq!(
+            track_playback_rate(&settings, Some(0.0)),
+            PlaybackRate(0.25)
+        );
+    }
+}
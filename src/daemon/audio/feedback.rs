@@ -0,0 +1,97 @@
+//! Short sounds that confirm presses at the deck, so that nobody has to look down to know that a
+//! press registered, see `--ui-feedback`.
+//!
+//! They play on an audio manager of their own on the default output, like the cue output, so
+//! that they stay out of the mix: the limiter does not react to them, the global volume does not
+//! change them, recordings leave them out and stingers do not pause them.
+
+use eyre::Context;
+use kira::sound::static_sound::{StaticSoundData, StaticSoundSettings};
+use kira::{AudioManager, AudioManagerSettings, DefaultBackend, Frame};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::time::Duration;
+use tracing::{info, warn};
+
+const SAMPLE_RATE: u32 = 48_000;
+
+/// Sent for every press, with the more noticeable one for presses that change the page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Feedback {
+    Press,
+    Navigate,
+    /// The press failed, e.g. a command that is not allowed. Descends where navigating rises, so
+    /// that it is not mistaken for a press that worked.
+    Error,
+}
+
+pub struct FeedbackOutput {
+    manager: AudioManager,
+    press: StaticSoundData,
+    navigate: StaticSoundData,
+    error: StaticSoundData,
+}
+
+impl FeedbackOutput {
+    pub fn open(volume_db: f64) -> eyre::Result<Self> {
+        // Above 0 dB the tones would clip rather than get louder
+        if volume_db > 0.0 {
+            warn!("Limiting the UI feedback volume of {volume_db} dB to 0 dB");
+        }
+        let volume_db = volume_db.min(0.0);
+        let manager = AudioManager::<DefaultBackend>::new(AudioManagerSettings::default())
+            .context("Unable to open the audio output for UI feedback")?;
+        let amplitude = 10f32.powf(volume_db as f32 / 20.0);
+        info!("Confirming presses with sounds at {volume_db} dB");
+        Ok(FeedbackOutput {
+            manager,
+            press: tones(&[(2000.0, Duration::from_millis(15))], amplitude),
+            navigate: tones(
+                &[
+                    (880.0, Duration::from_millis(40)),
+                    (1320.0, Duration::from_millis(60)),
+                ],
+                amplitude,
+            ),
+            error: tones(
+                &[
+                    (660.0, Duration::from_millis(80)),
+                    (440.0, Duration::from_millis(120)),
+                ],
+                amplitude,
+            ),
+        })
+    }
+
+    pub fn play(&mut self, feedback: Feedback) {
+        let sound = match feedback {
+            Feedback::Press => &self.press,
+            Feedback::Navigate => &self.navigate,
+            Feedback::Error => &self.error,
+        };
+        if let Err(e) = self.manager.play(sound.clone()) {
+            warn!("Unable to play UI feedback: {:?}", e);
+        }
+    }
+}
+
+/// Sine tones one after the other, each fading out so that it ends without a click.
+fn tones(tones: &[(f32, Duration)], amplitude: f32) -> StaticSoundData {
+    let frames = tones
+        .iter()
+        .flat_map(|&(frequency, duration)| {
+            let n = (duration.as_secs_f32() * SAMPLE_RATE as f32) as usize;
+            (0..n).map(move |i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                let fade = 1.0 - i as f32 / n as f32;
+                Frame::from_mono((TAU * frequency * t).sin() * amplitude * fade)
+            })
+        })
+        .collect();
+    StaticSoundData {
+        sample_rate: SAMPLE_RATE,
+        frames,
+        settings: StaticSoundSettings::default(),
+        slice: None,
+    }
+}
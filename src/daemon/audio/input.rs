@@ -0,0 +1,289 @@
+//! Plays an input device, e.g. the game master's microphone, through the mixer on a track of its
+//! own, so that voices can be balanced against the ambience on the same output.
+//!
+//! cpal captures on a thread of its own and only pushes frames into a lock-free ring buffer. The
+//! sound that kira plays drains the buffer on the audio thread and resamples it to the output
+//! rate.
+
+use super::{InputStatus, Mute};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, InputCallbackInfo, Sample, SampleFormat, SizedSample, StreamConfig};
+use eyre::{Context, ContextCompat, bail};
+use kira::info::Info;
+use kira::sound::{Sound, SoundData};
+use kira::track::{TrackBuilder, TrackHandle};
+use kira::{AudioManager, Decibels, Easing, Frame, StartTime, Tween};
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::convert::Infallible;
+use std::sync::mpsc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Names the system's default input rather than a particular device.
+pub const DEFAULT_DEVICE: &str = "default";
+
+/// A voice that lags behind its speaker is hard to listen to. The input and output clocks drift
+/// apart, so frames that pile up beyond this are dropped.
+const MAX_LATENCY: Duration = Duration::from_millis(100);
+
+/// Far more than [`MAX_LATENCY`] at any sample rate.
+const BUFFER_FRAMES: usize = 1 << 15;
+
+pub struct Input {
+    track: TrackHandle,
+    status: InputStatus,
+    /// Dropping it ends the thread that owns the capture stream.
+    _stop: mpsc::Sender<()>,
+}
+
+impl Input {
+    /// Starts muted, since an open microphone next to the speakers may well feed back.
+    pub fn open(manager: &mut AudioManager, device_name: &str) -> eyre::Result<Self> {
+        let (producer, consumer) = RingBuffer::new(BUFFER_FRAMES);
+        let (sample_rate, stop) = capture(device_name, producer)?;
+        let mut track = manager
+            .add_sub_track(TrackBuilder::new().volume(Decibels::SILENCE))
+            .context("Unable to create a track for the audio input")?;
+        track
+            .play(InputSoundData {
+                consumer,
+                sample_rate,
+            })
+            .context("Unable to play the audio input")?;
+        info!("Playing audio input '{device_name}' at {sample_rate} Hz, muted for now");
+        Ok(Input {
+            track,
+            status: InputStatus {
+                volume_db: 0.0,
+                mute: Mute::Muted,
+            },
+            _stop: stop,
+        })
+    }
+
+    pub fn status(&self) -> InputStatus {
+        self.status
+    }
+
+    pub fn set_volume(&mut self, volume_db: f64) {
+        self.status.volume_db = volume_db;
+        self.apply();
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.status.mute = self.status.mute.toggled();
+        self.apply();
+    }
+
+    fn apply(&mut self) {
+        let volume = match self.status.mute {
+            Mute::Muted => Decibels::SILENCE,
+            Mute::Live => Decibels(self.status.volume_db as f32),
+        };
+        self.track.set_volume(
+            volume,
+            Tween {
+                duration: Duration::from_millis(50),
+                easing: Easing::Linear,
+                start_time: StartTime::Immediate,
+            },
+        );
+    }
+}
+
+/// cpal streams cannot move between threads on every platform, so the stream lives on a thread
+/// that waits for `stop` to be dropped. Returns the sample rate of the input.
+fn capture(device_name: &str, producer: Producer<Frame>) -> eyre::Result<(u32, mpsc::Sender<()>)> {
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let (stop, stopped) = mpsc::channel::<()>();
+    let device_name = device_name.to_string();
+    std::thread::Builder::new()
+        .name("audio input".to_string())
+        .spawn(move || {
+            let _stream = match open_stream(&device_name, producer) {
+                Ok((stream, sample_rate)) => {
+                    if ready_tx.send(Ok(sample_rate)).is_err() {
+                        return;
+                    }
+                    stream
+                }
+                Err(e) => {
+                    if ready_tx.send(Err(e)).is_err() {
+                        debug!("Audio output shut down while opening the input");
+                    }
+                    return;
+                }
+            };
+            // Only ever ends by the sender being dropped
+            while stopped.recv().is_ok() {}
+            debug!("Stopping audio input '{device_name}'");
+        })
+        .context("Failed to start audio input thread")?;
+    let sample_rate = ready_rx
+        .recv()
+        .context("Audio input thread ended before opening the input")??;
+    Ok((sample_rate, stop))
+}
+
+fn open_stream(device_name: &str, producer: Producer<Frame>) -> eyre::Result<(cpal::Stream, u32)> {
+    let host = cpal::default_host();
+    let device = if device_name == DEFAULT_DEVICE {
+        host.default_input_device()
+            .context("There is no default audio input device")?
+    } else {
+        host.input_devices()
+            .context("Unable to list audio input devices")?
+            .find(|d| d.name().is_ok_and(|name| name == device_name))
+            .with_context(|| format!("Audio input device '{device_name}' not found"))?
+    };
+    let supported = device
+        .default_input_config()
+        .with_context(|| format!("Audio input device '{device_name}' has no usable format"))?;
+    let config = supported.config();
+    let stream = match supported.sample_format() {
+        SampleFormat::F32 => build_stream::<f32>(&device, &config, producer),
+        SampleFormat::I16 => build_stream::<i16>(&device, &config, producer),
+        SampleFormat::U16 => build_stream::<u16>(&device, &config, producer),
+        SampleFormat::I32 => build_stream::<i32>(&device, &config, producer),
+        other => bail!("Audio input device '{device_name}' delivers unsupported {other:?} samples"),
+    }
+    .with_context(|| format!("Unable to open audio input device '{device_name}'"))?;
+    stream
+        .play()
+        .with_context(|| format!("Unable to start audio input device '{device_name}'"))?;
+    Ok((stream, supported.sample_rate().0))
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut producer: Producer<Frame>,
+) -> eyre::Result<cpal::Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = usize::from(config.channels.max(1));
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _: &InputCallbackInfo| {
+            for samples in data.chunks_exact(channels) {
+                let frame = match samples {
+                    [mono] => Frame::from_mono(f32::from_sample(*mono)),
+                    [left, right, ..] => {
+                        Frame::new(f32::from_sample(*left), f32::from_sample(*right))
+                    }
+                    [] => Frame::ZERO,
+                };
+                // Full while kira is not playing the input yet; those frames are stale anyway
+                if producer.push(frame).is_err() {
+                    break;
+                }
+            }
+        },
+        |e| warn!("Audio input failed: {e}"),
+        None,
+    )?;
+    Ok(stream)
+}
+
+struct InputSoundData {
+    consumer: Consumer<Frame>,
+    sample_rate: u32,
+}
+
+impl SoundData for InputSoundData {
+    type Error = Infallible;
+    type Handle = ();
+
+    fn into_sound(self) -> Result<(Box<dyn Sound>, Self::Handle), Self::Error> {
+        let sound = InputSound {
+            consumer: self.consumer,
+            sample_rate: self.sample_rate,
+            previous: Frame::ZERO,
+            next: Frame::ZERO,
+            position: 0.0,
+        };
+        Ok((Box::new(sound), ()))
+    }
+}
+
+/// Resamples by interpolating between the two input frames around each output frame.
+struct InputSound {
+    consumer: Consumer<Frame>,
+    sample_rate: u32,
+    previous: Frame,
+    next: Frame,
+    /// Between `previous` (0.0) and `next` (1.0).
+    position: f64,
+}
+
+impl Sound for InputSound {
+    fn process(&mut self, out: &mut [Frame], dt: f64, _info: &Info) {
+        self.fill(out, dt);
+    }
+
+    /// Plays until its track is dropped.
+    fn finished(&self) -> bool {
+        false
+    }
+}
+
+impl InputSound {
+    fn fill(&mut self, out: &mut [Frame], dt: f64) {
+        let max_frames = (MAX_LATENCY.as_secs_f64() * f64::from(self.sample_rate)) as usize;
+        let excess = self.consumer.slots().saturating_sub(max_frames);
+        if excess > 0
+            && let Ok(chunk) = self.consumer.read_chunk(excess)
+        {
+            chunk.commit_all();
+        }
+        let step = f64::from(self.sample_rate) * dt;
+        for frame in out {
+            self.position += step;
+            while self.position >= 1.0 {
+                self.position -= 1.0;
+                self.previous = self.next;
+                // Silence while the input falls behind
+                self.next = self.consumer.pop().unwrap_or(Frame::ZERO);
+            }
+            *frame = interpolate(self.previous, self.next, self.position as f32);
+        }
+    }
+}
+
+fn interpolate(from: Frame, to: Frame, position: f32) -> Frame {
+    Frame::new(
+        from.left + (to.left - from.left) * position,
+        from.right + (to.right - from.right) * position,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InputSound;
+    use kira::Frame;
+    use rtrb::RingBuffer;
+
+    #[test]
+    fn test_input_is_resampled_to_the_output_rate() -> eyre::Result<()> {
+        let (mut producer, consumer) = RingBuffer::new(16);
+        for sample in [1.0, 2.0, 3.0, 4.0] {
+            producer
+                .push(Frame::from_mono(sample))
+                .map_err(|_| eyre::eyre!("ring buffer full"))?;
+        }
+        let mut sound = InputSound {
+            consumer,
+            sample_rate: 24_000,
+            previous: Frame::ZERO,
+            next: Frame::ZERO,
+            position: 0.0,
+        };
+        let mut out = [Frame::ZERO; 6];
+        sound.fill(&mut out, 1.0 / 48_000.0);
+        let left = out.iter().map(|f| f.left).collect::<Vec<_>>();
+        assert_eq!(left, [0.0, 0.0, 0.5, 1.0, 1.5, 2.0]);
+        Ok(())
+    }
+}
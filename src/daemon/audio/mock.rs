@@ -0,0 +1,153 @@
+//! An [`AudioEngine`] for tests that needs no audio device. It only updates the
+//! [`MockTrackState`] of the tracks it is asked to play.
+
+use super::{AudioEngine, Feedback, InputStatus, MAX_VOLUME_OFFSET_DB, Mute, Track};
+use crate::config;
+use crate::daemon::ui::tests::harness::MockTrackState;
+use kira::sound::PlaybackState;
+use std::sync::Arc;
+
+#[derive(Default)]
+pub struct MockAudioEngine {
+    playing: Vec<Arc<Track>>,
+    global_volume_db: f64,
+    recording: bool,
+    input: Option<InputStatus>,
+}
+
+impl MockAudioEngine {
+    /// Like an engine that plays an input, which starts out muted.
+    pub fn with_input() -> Self {
+        MockAudioEngine {
+            input: Some(InputStatus {
+                volume_db: 0.0,
+                mute: Mute::Muted,
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+fn with_mock_state<R>(track: &Track, f: impl FnOnce(&mut MockTrackState) -> R) -> R {
+    let mut guard = track.state.blocking_lock();
+    let state = guard
+        .as_any_mut()
+        .downcast_mut::<MockTrackState>()
+        .expect("MockAudioEngine needs tracks with MockTrackState");
+    f(state)
+}
+
+impl AudioEngine for MockAudioEngine {
+    fn play(&mut self, track: Arc<Track>) -> eyre::Result<()> {
+        with_mock_state(&track, |state| {
+            state.playback = PlaybackState::Playing;
+            state.instances += 1;
+        });
+        self.playing.push(track);
+        Ok(())
+    }
+
+    fn stop(&mut self, track: &Arc<Track>) {
+        with_mock_state(track, |state| {
+            state.playback = PlaybackState::Stopped;
+            state.instances = 0;
+        });
+        self.playing.retain(|t| !Arc::ptr_eq(track, t));
+    }
+
+    fn stop_instances(&mut self, track: &Arc<Track>, instances: config::Instances) {
+        let remaining = with_mock_state(track, |state| state.instances.saturating_sub(1));
+        if instances == config::Instances::All || remaining == 0 {
+            return self.stop(track);
+        }
+        with_mock_state(track, |state| state.instances = remaining);
+        if let Some(i) = self.playing.iter().position(|t| Arc::ptr_eq(track, t)) {
+            self.playing.remove(i);
+        }
+    }
+
+    fn pause(&mut self, track: &Arc<Track>) {
+        with_mock_state(track, |state| state.playback = PlaybackState::Paused);
+    }
+
+    fn resume(&mut self, track: &Arc<Track>) {
+        with_mock_state(track, |state| state.playback = PlaybackState::Playing);
+    }
+
+    fn set_global_volume(&mut self, volume_db: f64) -> eyre::Result<()> {
+        self.global_volume_db = volume_db;
+        Ok(())
+    }
+
+    fn global_volume_db(&self) -> f64 {
+        self.global_volume_db
+    }
+
+    fn adjust_track_volume(&mut self, track: &Track, delta_db: f64) {
+        with_mock_state(track, |state| {
+            state.volume_offset_db = (state.volume_offset_db + delta_db).min(MAX_VOLUME_OFFSET_DB);
+        });
+    }
+
+    fn set_track_pan(&mut self, track: &Track, pan: f32) {
+        with_mock_state(track, |state| state.pan_override = Some(pan));
+    }
+
+    fn set_playback_rate(&mut self, track: &Track, playback_rate: f64) {
+        with_mock_state(track, |state| {
+            state.playback_rate_override = Some(playback_rate)
+        });
+    }
+
+    fn configure_buses(&mut self, _buses: Vec<config::Bus>) {}
+
+    fn set_bus_volume(&mut self, _bus: &str, _volume: f64) {}
+
+    fn cue(&mut self, _track: Arc<Track>) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    fn toggle_recording(&mut self) -> eyre::Result<()> {
+        self.recording = !self.recording;
+        Ok(())
+    }
+
+    fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    fn input(&self) -> Option<InputStatus> {
+        self.input
+    }
+
+    fn set_input_volume(&mut self, volume_db: f64) {
+        if let Some(input) = &mut self.input {
+            input.volume_db = volume_db;
+        }
+    }
+
+    fn toggle_input_mute(&mut self) {
+        if let Some(input) = &mut self.input {
+            input.mute = input.mute.toggled();
+        }
+    }
+
+    fn preload(&mut self, _tracks: Vec<Arc<Track>>) {}
+
+    fn feedback(&mut self, _feedback: Feedback) {}
+
+    fn update_state(&mut self) -> Vec<Arc<Track>> {
+        let tracks = self.playing.clone();
+        self.playing
+            .retain(|t| with_mock_state(t, |state| state.playback != PlaybackState::Stopped));
+        tracks
+    }
+
+    fn playing(&self) -> &[Arc<Track>] {
+        &self.playing
+    }
+
+    fn shutdown(&mut self) {
+        self.playing.clear();
+    }
+}
@@ -0,0 +1,521 @@
+//! An [`AudioEngine`] that plays nothing, so that the daemon also runs on machines without a
+//! sound card, such as CI runners. Tracks still take as long as their files to finish, which
+//! keeps the deck's timers and state updates behaving as they do with real playback.
+
+use super::{
+    AudioEngine, AudioEvent, AudioSettings, Feedback, InputStatus, MAX_VOLUME_OFFSET_DB, Mute,
+    PreloadMode, Track, TrackState, preload, start_preload, track_playback_rate,
+};
+use crate::config;
+use crate::util::is_stream_url;
+use kira::sound::PlaybackState;
+use std::any::Any;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, info, instrument};
+
+pub struct NullEngine {
+    tracks: Vec<Arc<Track>>,
+    event_tx: Sender<AudioEvent>,
+    settings: AudioSettings,
+    recording: bool,
+    global_volume_db: f64,
+    input: Option<InputStatus>,
+}
+
+impl NullEngine {
+    pub fn new(event_tx: Sender<AudioEvent>, settings: AudioSettings) -> Self {
+        info!("Using the null audio output, nothing will be audible");
+        NullEngine {
+            tracks: Vec::new(),
+            event_tx,
+            recording: settings.record_on_start.is_some(),
+            global_volume_db: 0.0,
+            // Muted like a real input, so that the deck shows the same
+            input: settings.input_device.as_ref().map(|_| InputStatus {
+                volume_db: 0.0,
+                mute: Mute::Muted,
+            }),
+            settings,
+        }
+    }
+}
+
+/// Replaces whatever state the track had before, keeping its runtime adjustments.
+#[derive(Default)]
+struct NullTrackState {
+    /// When the newest instance of the track started.
+    started: Option<Instant>,
+    /// Starts of the earlier instances of an overlapping track, oldest first.
+    earlier: Vec<Instant>,
+    /// Time stands still for a paused track; resuming moves its starts forward by the pause.
+    paused_at: Option<Instant>,
+    /// `None` for looping tracks and streams, which play until they are stopped.
+    duration: Option<Duration>,
+    playback_rate: f64,
+    volume_offset_db: f64,
+    pan_override: Option<f32>,
+    playback_rate_override: Option<f64>,
+    load_error: Option<Arc<String>>,
+}
+
+impl NullTrackState {
+    fn played(&self) -> Option<Duration> {
+        self.started.map(|started| self.played_since(started))
+    }
+
+    fn played_since(&self, started: Instant) -> Duration {
+        self.paused_at
+            .unwrap_or_else(Instant::now)
+            .saturating_duration_since(started)
+            .mul_f64(self.playback_rate)
+    }
+
+    fn is_over(&self, started: Instant) -> bool {
+        self.duration
+            .is_some_and(|duration| self.played_since(started) >= duration)
+    }
+}
+
+impl TrackState for NullTrackState {
+    fn rem_duration(&self) -> Option<Duration> {
+        let played = self.played()?;
+        let duration = self.duration?;
+        Some(
+            duration
+                .checked_sub(played)
+                .unwrap_or_default()
+                .div_f64(self.playback_rate),
+        )
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.duration.map(|d| d.div_f64(self.playback_rate))
+    }
+
+    fn playback_state(&self) -> PlaybackState {
+        match (self.played(), self.duration) {
+            (None, _) => PlaybackState::Stopped,
+            (Some(played), Some(duration)) if played >= duration => PlaybackState::Stopped,
+            (Some(_), _) if self.paused_at.is_some() => PlaybackState::Paused,
+            (Some(_), _) => PlaybackState::Playing,
+        }
+    }
+
+    fn volume_offset_db(&self) -> f64 {
+        self.volume_offset_db
+    }
+
+    fn pan_override(&self) -> Option<f32> {
+        self.pan_override
+    }
+
+    fn playback_rate_override(&self) -> Option<f64> {
+        self.playback_rate_override
+    }
+
+    fn is_buffering(&self) -> bool {
+        false
+    }
+
+    fn load_error(&self) -> Option<Arc<String>> {
+        self.load_error.clone()
+    }
+
+    fn instances(&self) -> usize {
+        self.earlier
+            .iter()
+            .chain(&self.started)
+            .filter(|started| !self.is_over(**started))
+            .count()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn with_null_state<R>(track: &Track, f: impl FnOnce(&mut NullTrackState) -> R) -> R {
+    let mut guard = track.state.blocking_lock();
+    if let Some(state) = guard.as_any_mut().downcast_mut::<NullTrackState>() {
+        return f(state);
+    }
+    let mut state = NullTrackState {
+        volume_offset_db: guard.volume_offset_db(),
+        pan_override: guard.pan_override(),
+        playback_rate_override: guard.playback_rate_override(),
+        load_error: guard.load_error(),
+        ..Default::default()
+    };
+    let result = f(&mut state);
+    *guard = Box::new(state);
+    result
+}
+
+impl AudioEngine for NullEngine {
+    #[instrument(skip_all, level = "debug")]
+    fn play(&mut self, track: Arc<Track>) -> eyre::Result<()> {
+        if !track.mode().overlaps() && self.tracks.iter().any(|t| Arc::ptr_eq(&track, t)) {
+            info!("Track {:?} already playing, not changing anything", &track);
+            return Ok(());
+        }
+
+        let is_stream = track.path.to_str().is_some_and(is_stream_url);
+        // Reading the file makes missing or broken files fail just like they would on a device
+        let duration = if is_stream {
+            None
+        } else {
+            let check = |path: &std::path::Path| preload::check_file(path, PreloadMode::Verify);
+            Some(track.open_with_fallback(check)?)
+        };
+        with_null_state(&track, |state| {
+            state.playback_rate =
+                track_playback_rate(&track.settings, state.playback_rate_override).0;
+            // Resuming is as if the track had started that much earlier
+            let skipped = track.start_position().unwrap_or_default();
+            let started = Instant::now()
+                .checked_sub(skipped.div_f64(state.playback_rate))
+                .unwrap_or_else(Instant::now);
+            if let Some(previous) = state.started.replace(started)
+                && track.mode().overlaps()
+            {
+                state.earlier.push(previous);
+            }
+            state.duration = duration.filter(|_| !track.mode().loops());
+            state.load_error = None;
+        });
+        if !self.tracks.iter().any(|t| Arc::ptr_eq(&track, t)) {
+            self.tracks.push(track);
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self, track: &Arc<Track>) {
+        with_null_state(track, |state| {
+            if state.playback_state() != PlaybackState::Stopped {
+                track.set_resume_position(state.played());
+            }
+            state.started = None;
+            state.earlier.clear();
+            state.paused_at = None;
+        });
+        self.tracks.retain(|t| !Arc::ptr_eq(track, t));
+    }
+
+    fn stop_instances(&mut self, track: &Arc<Track>, instances: config::Instances) {
+        let stopped_all = with_null_state(track, |state| {
+            let earlier = std::mem::take(&mut state.earlier);
+            state.earlier = earlier.into_iter().filter(|s| !state.is_over(*s)).collect();
+            if state.earlier.is_empty() {
+                return true;
+            }
+            match instances {
+                config::Instances::All => true,
+                config::Instances::Oldest => {
+                    state.earlier.remove(0);
+                    false
+                }
+                config::Instances::Newest => {
+                    state.started = state.earlier.pop();
+                    false
+                }
+            }
+        });
+        if stopped_all {
+            self.stop(track);
+        }
+    }
+
+    fn pause(&mut self, track: &Arc<Track>) {
+        with_null_state(track, |state| {
+            state.paused_at.get_or_insert_with(Instant::now);
+        });
+    }
+
+    fn resume(&mut self, track: &Arc<Track>) {
+        with_null_state(track, |state| {
+            if let Some(paused_at) = state.paused_at.take() {
+                let pause = paused_at.elapsed();
+                for started in state.earlier.iter_mut().chain(&mut state.started) {
+                    *started += pause;
+                }
+            }
+        });
+    }
+
+    fn set_global_volume(&mut self, volume_db: f64) -> eyre::Result<()> {
+        self.global_volume_db = volume_db;
+        Ok(())
+    }
+
+    fn global_volume_db(&self) -> f64 {
+        self.global_volume_db
+    }
+
+    fn adjust_track_volume(&mut self, track: &Track, delta_db: f64) {
+        with_null_state(track, |state| {
+            state.volume_offset_db = (state.volume_offset_db + delta_db).min(MAX_VOLUME_OFFSET_DB);
+        });
+    }
+
+    fn set_track_pan(&mut self, track: &Track, pan: f32) {
+        with_null_state(track, |state| state.pan_override = Some(pan));
+    }
+
+    fn set_playback_rate(&mut self, track: &Track, playback_rate: f64) {
+        with_null_state(track, |state| {
+            let starts = state.earlier.iter().chain(&state.started);
+            let played: Vec<_> = starts.map(|started| state.played_since(*started)).collect();
+            state.playback_rate_override = Some(playback_rate);
+            state.playback_rate =
+                track_playback_rate(&track.settings, state.playback_rate_override).0;
+            // Keeps the positions, so that only the rest of the track plays at the new rate. A
+            // paused track played up to the pause, and resuming moves its starts on from there.
+            let now = state.paused_at.unwrap_or_else(Instant::now);
+            let starts = state.earlier.iter_mut().chain(&mut state.started);
+            for (started, played) in starts.zip(played) {
+                *started = now
+                    .checked_sub(played.div_f64(state.playback_rate))
+                    .unwrap_or(now);
+            }
+        });
+    }
+
+    fn configure_buses(&mut self, _buses: Vec<config::Bus>) {}
+
+    fn set_bus_volume(&mut self, _bus: &str, _volume: f64) {}
+
+    fn cue(&mut self, track: Arc<Track>) -> eyre::Result<()> {
+        debug!("No cue output without audio, ignoring cue of {:?}", &track);
+        Ok(())
+    }
+
+    fn toggle_recording(&mut self) -> eyre::Result<()> {
+        self.recording = !self.recording;
+        info!("The null audio output records nothing, no file is written");
+        Ok(())
+    }
+
+    fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    fn input(&self) -> Option<InputStatus> {
+        self.input
+    }
+
+    fn set_input_volume(&mut self, volume_db: f64) {
+        if let Some(input) = &mut self.input {
+            input.volume_db = volume_db;
+        }
+    }
+
+    fn toggle_input_mute(&mut self) {
+        if let Some(input) = &mut self.input {
+            input.mute = input.mute.toggled();
+        }
+    }
+
+    fn preload(&mut self, tracks: Vec<Arc<Track>>) {
+        start_preload(tracks, self.settings.preload, &self.event_tx);
+    }
+
+    fn feedback(&mut self, _feedback: Feedback) {}
+
+    fn update_state(&mut self) -> Vec<Arc<Track>> {
+        let tracks = self.tracks.clone();
+        self.tracks.retain(|track| {
+            let playing = with_null_state(track, |state| {
+                state.playback_state() != PlaybackState::Stopped
+            });
+            // The next play after the end starts over
+            if !playing {
+                track.set_resume_position(None);
+            }
+            playing
+        });
+        tracks
+    }
+
+    fn playing(&self) -> &[Arc<Track>] {
+        &self.tracks
+    }
+
+    fn shutdown(&mut self) {
+        for track in self.tracks.drain(..) {
+            with_null_state(&track, |state| {
+                if state.playback_state() != PlaybackState::Stopped {
+                    track.set_resume_position(state.played());
+                }
+                state.started = None;
+                state.earlier.clear();
+                state.paused_at = None;
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_VOLUME_OFFSET_DB, NullEngine, NullTrackState};
+    use crate::config::{PlaySoundSettings, PlaybackMode};
+    use crate::daemon::audio::{
+        AudioEngine, AudioOutput, AudioSettings, NEAR_END, PreloadMode, Track, TrackState,
+        UpdateIntervals, VoiceLimits,
+    };
+    use kira::sound::PlaybackState;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    fn settings() -> AudioSettings {
+        AudioSettings {
+            shutdown_fade: Duration::ZERO,
+            limiter: None,
+            buses: Vec::new(),
+            cue_device: None,
+            input_device: None,
+            recording_dir: PathBuf::new(),
+            record_on_start: None,
+            preload: PreloadMode::Off,
+            output: AudioOutput::Null,
+            updates: UpdateIntervals {
+                normal: Duration::from_millis(500),
+                fast: Duration::from_millis(100),
+                near_end: NEAR_END,
+            },
+            limits: VoiceLimits::default(),
+            ui_feedback: None,
+        }
+    }
+
+    fn started_ago(ago: Duration, duration: Option<Duration>) -> NullTrackState {
+        NullTrackState {
+            started: Instant::now().checked_sub(ago),
+            duration,
+            playback_rate: 1.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_track_stops_after_its_duration() {
+        let state = started_ago(Duration::from_secs(1), Some(Duration::from_secs(60)));
+        assert_eq!(state.playback_state(), PlaybackState::Playing);
+        assert!(
+            state
+                .rem_duration()
+                .is_some_and(|d| d <= Duration::from_secs(59))
+        );
+
+        let state = started_ago(Duration::from_secs(61), Some(Duration::from_secs(60)));
+        assert_eq!(state.playback_state(), PlaybackState::Stopped);
+        assert_eq!(state.rem_duration(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_looping_track_plays_until_stopped() {
+        let state = started_ago(Duration::from_secs(3600), None);
+        assert_eq!(state.playback_state(), PlaybackState::Playing);
+        assert_eq!(state.rem_duration(), None);
+
+        assert_eq!(
+            NullTrackState::default().playback_state(),
+            PlaybackState::Stopped
+        );
+    }
+
+    #[test]
+    fn test_faster_playback_finishes_sooner() {
+        let mut state = started_ago(Duration::from_secs(31), Some(Duration::from_secs(60)));
+        state.playback_rate = 2.0;
+        assert_eq!(state.playback_state(), PlaybackState::Stopped);
+    }
+
+    #[test]
+    fn test_paused_track_does_not_run_out() {
+        let mut state = started_ago(Duration::from_secs(50), Some(Duration::from_secs(60)));
+        state.paused_at = Instant::now().checked_sub(Duration::from_secs(40));
+        assert_eq!(state.playback_state(), PlaybackState::Paused);
+        assert!(
+            state
+                .rem_duration()
+                .is_some_and(|d| d >= Duration::from_secs(49))
+        );
+    }
+
+    #[test]
+    fn test_rate_change_while_paused_keeps_the_position() {
+        let (event_tx, _event_rx) = tokio::sync::mpsc::channel(1);
+        let mut engine = NullEngine::new(event_tx, settings());
+        let track = Arc::new(Track::new(
+            Arc::new(PathBuf::from("rain.mp3")),
+            PlaySoundSettings::new(PlaybackMode::PlayStop),
+        ));
+        // Played for 5s of 60s, then paused for another 5s
+        let mut state = started_ago(Duration::from_secs(10), Some(Duration::from_secs(60)));
+        state.paused_at = Instant::now().checked_sub(Duration::from_secs(5));
+        *track.state.blocking_lock() = Box::new(state);
+
+        engine.set_playback_rate(&track, 2.0);
+        engine.resume(&track);
+        let rem = track.state.blocking_lock().rem_duration();
+        assert!(
+            rem.is_some_and(
+                |d| d <= Duration::from_millis(27_500) && d >= Duration::from_millis(27_000)
+            ),
+            "{rem:?}"
+        );
+    }
+
+    #[test]
+    fn test_tracks_are_turned_up_no_further_than_the_limit() {
+        let (event_tx, _event_rx) = tokio::sync::mpsc::channel(1);
+        let mut engine = NullEngine::new(event_tx, settings());
+        let track = Track::new(
+            Arc::new(PathBuf::from("rain.mp3")),
+            PlaySoundSettings::new(PlaybackMode::LoopStop),
+        );
+
+        engine.adjust_track_volume(&track, 9.0);
+        engine.adjust_track_volume(&track, 9.0);
+        assert_eq!(
+            track.state.blocking_lock().volume_offset_db(),
+            MAX_VOLUME_OFFSET_DB
+        );
+        engine.adjust_track_volume(&track, -3.0);
+        assert_eq!(
+            track.state.blocking_lock().volume_offset_db(),
+            MAX_VOLUME_OFFSET_DB - 3.0
+        );
+    }
+
+    #[test]
+    fn test_shutdown_records_where_tracks_were() -> eyre::Result<()> {
+        let (event_tx, _event_rx) = tokio::sync::mpsc::channel(1);
+        let mut engine = NullEngine::new(event_tx, settings());
+        // A stream, which needs no file to play
+        let track = Arc::new(Track::new(
+            Arc::new(PathBuf::from("https://radio.example/ambience")),
+            PlaySoundSettings {
+                resume: Some(Duration::ZERO),
+                ..PlaySoundSettings::new(PlaybackMode::LoopStop)
+            },
+        ));
+
+        engine.play(track.clone())?;
+        std::thread::sleep(Duration::from_millis(20));
+        engine.shutdown();
+        let position = track.resume_position();
+        assert!(
+            position.is_some_and(|p| p >= Duration::from_millis(20)),
+            "{position:?}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+//! Checks every configured sound file in the background right after startup, so that broken
+//! files show up on the deck before anyone taps them.
+
+use super::{AudioEvent, PreloadMode, RealTrackState, Track};
+use crate::util::is_stream_url;
+use eyre::{Context, OptionExt};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use symphonia::core::codecs::{CODEC_TYPE_NULL, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, info, warn};
+
+/// Runs on its own thread; the audio loop must stay responsive to taps in the meantime.
+pub fn preload_tracks(tracks: Vec<Arc<Track>>, mode: PreloadMode, event_tx: Sender<AudioEvent>) {
+    let mut checked: HashMap<Arc<PathBuf>, Result<Duration, Arc<String>>> = HashMap::new();
+    let mut failures = 0usize;
+    for track in tracks {
+        if track.path.to_str().is_some_and(is_stream_url) {
+            continue;
+        }
+        let result = checked
+            .entry(track.path.clone())
+            .or_insert_with(|| {
+                track
+                    .open_with_fallback(|path| check_file(path, mode))
+                    .map_err(|e| {
+                        warn!("{} cannot be played: {e:?}", track.path.display());
+                        failures += 1;
+                        Arc::new(format!("{e:#}"))
+                    })
+            })
+            .clone();
+
+        let changed = {
+            let mut track_state_guard = track.state.blocking_lock();
+            let Some(state) = track_state_guard
+                .as_any_mut()
+                .downcast_mut::<RealTrackState>()
+            else {
+                continue;
+            };
+            let load_error = result.as_ref().err().cloned();
+            let mut changed = state.load_error != load_error;
+            state.load_error = load_error;
+            if let Ok(duration) = result
+                && state.sink.is_none()
+            {
+                // Labels can show the length
+                changed |= state.duration != Some(duration);
+                state.duration = Some(duration);
+            }
+            changed
+        };
+        if changed
+            && event_tx
+                .blocking_send(AudioEvent::TrackStateChanged(track))
+                .is_err()
+        {
+            debug!("Audio event channel closed, stopping preload");
+            return;
+        }
+    }
+    info!(
+        "Preloaded {} files, {failures} of them cannot be played",
+        checked.len()
+    );
+}
+
+/// Decodes the first packet to make sure that the codec is supported, not just the container.
+pub(super) fn check_file(path: &Path, mode: PreloadMode) -> eyre::Result<Duration> {
+    if mode == PreloadMode::Cache {
+        // Reading the whole file once leaves it in the page cache for the first playback
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        std::io::copy(&mut file, &mut std::io::sink())
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+    }
+
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .context("Unsupported audio format")?
+        .format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_eyre("No audio track found")?;
+    let track_id = track.id;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_eyre("Unknown sample rate")?;
+    // kira refuses to stream files without a known length
+    let n_frames = track.codec_params.n_frames.ok_or_eyre("Unknown length")?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .context("Unsupported codec")?;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                eyre::bail!("No decodable audio found")
+            }
+            Err(e) => return Err(e).context("Failed to read audio packet"),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode(&packet) {
+            Ok(_) => break,
+            // Players skip corrupt frames, so only a file without any good frame is broken
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(e).context("Failed to decode audio"),
+        }
+    }
+    Ok(Duration::from_secs_f64(
+        n_frames as f64 / sample_rate as f64,
+    ))
+}
@@ -0,0 +1,276 @@
+//! Captures the main mix into a WAV file.
+//!
+//! The effect runs on kira's realtime audio thread, so it only copies frames into a lock-free
+//! ring buffer. A separate writer thread drains the buffer into the file.
+
+use eyre::{Context, bail, eyre};
+use kira::Frame;
+use kira::effect::{Effect, EffectBuilder};
+use kira::info::Info;
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// About five seconds at 48kHz, enough to ride out a stalled disk without dropping audio.
+const BUFFER_FRAMES: usize = 1 << 18;
+
+pub struct RecorderBuilder;
+
+impl EffectBuilder for RecorderBuilder {
+    type Handle = Recorder;
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        let (producer, consumer) = RingBuffer::new(BUFFER_FRAMES);
+        let shared = Arc::new(Shared {
+            recording: AtomicBool::new(false),
+            sample_rate: AtomicU32::new(0),
+            dropped_frames: AtomicU64::new(0),
+        });
+        let effect = RecorderEffect {
+            shared: shared.clone(),
+            producer,
+        };
+        let handle = Recorder {
+            shared,
+            consumer: Some(consumer),
+            writer: None,
+        };
+        (Box::new(effect), handle)
+    }
+}
+
+struct Shared {
+    recording: AtomicBool,
+    sample_rate: AtomicU32,
+    dropped_frames: AtomicU64,
+}
+
+struct RecorderEffect {
+    shared: Arc<Shared>,
+    producer: Producer<Frame>,
+}
+
+impl Effect for RecorderEffect {
+    fn init(&mut self, sample_rate: u32, _internal_buffer_size: usize) {
+        self.shared
+            .sample_rate
+            .store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.shared
+            .sample_rate
+            .store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn process(&mut self, input: &mut [Frame], _dt: f64, _info: &Info) {
+        if !self.shared.recording.load(Ordering::Acquire) {
+            return;
+        }
+        for frame in input.iter() {
+            if self.producer.push(*frame).is_err() {
+                self.shared.dropped_frames.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Starts and stops recordings; the effect itself keeps passing audio through unchanged.
+pub struct Recorder {
+    shared: Arc<Shared>,
+    /// Owned by the writer thread while a recording is running.
+    consumer: Option<Consumer<Frame>>,
+    writer: Option<Writer>,
+}
+
+struct Writer {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<(Consumer<Frame>, eyre::Result<()>)>,
+}
+
+impl Recorder {
+    pub fn is_recording(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    pub fn start(&mut self, path: &Path) -> eyre::Result<()> {
+        if self.writer.is_some() {
+            bail!("Already recording");
+        }
+        let mut consumer = self
+            .consumer
+            .take()
+            .ok_or_else(|| eyre!("Recorder is unavailable after an earlier failure"))?;
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create recording {}", path.display()))?;
+        // Leftovers from the end of the previous recording
+        while consumer.pop().is_ok() {}
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let (shared, stop) = (self.shared.clone(), stop.clone());
+            std::thread::Builder::new()
+                .name("recorder".to_string())
+                .spawn(move || {
+                    let result = wait_for_sample_rate(&shared, &stop)
+                        .and_then(|sample_rate| write_wav(file, &mut consumer, sample_rate, &stop));
+                    (consumer, result)
+                })
+                .context("Failed to start recorder thread")?
+        };
+        self.shared.dropped_frames.store(0, Ordering::Relaxed);
+        self.shared.recording.store(true, Ordering::Release);
+        info!("Recording output to {}", path.display());
+        self.writer = Some(Writer {
+            path: path.to_path_buf(),
+            stop,
+            thread,
+        });
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> eyre::Result<()> {
+        let Some(writer) = self.writer.take() else {
+            return Ok(());
+        };
+        self.shared.recording.store(false, Ordering::Release);
+        writer.stop.store(true, Ordering::Release);
+        let (consumer, result) = writer
+            .thread
+            .join()
+            .map_err(|_| eyre!("Recorder thread panicked"))?;
+        self.consumer = Some(consumer);
+        let dropped = self.shared.dropped_frames.load(Ordering::Relaxed);
+        if dropped > 0 {
+            warn!("Recording dropped {dropped} frames because the disk could not keep up");
+        }
+        result.with_context(|| format!("Failed to write recording {}", writer.path.display()))?;
+        info!("Finished recording {}", writer.path.display());
+        Ok(())
+    }
+}
+
+/// The effect learns the sample rate from the audio thread, which may not have processed anything
+/// yet when recording right at startup. Waiting for it here keeps the caller, the audio engine's
+/// thread, from blocking.
+fn wait_for_sample_rate(shared: &Shared, stop: &AtomicBool) -> eyre::Result<u32> {
+    loop {
+        let sample_rate = shared.sample_rate.load(Ordering::Relaxed);
+        if sample_rate != 0 {
+            return Ok(sample_rate);
+        }
+        if stop.load(Ordering::Acquire) {
+            bail!("Audio output never started");
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Writes 32-bit float stereo. The size fields are patched once the length is known.
+fn write_wav(
+    file: File,
+    consumer: &mut Consumer<Frame>,
+    sample_rate: u32,
+    stop: &AtomicBool,
+) -> eyre::Result<()> {
+    const CHANNELS: u16 = 2;
+    const BYTES_PER_SAMPLE: u16 = 4;
+    const HEADER_LEN: u32 = 44;
+    const FORMAT_IEEE_FLOAT: u16 = 3;
+
+    let mut out = BufWriter::new(file);
+    out.write_all(b"RIFF")?;
+    out.write_all(&0u32.to_le_bytes())?;
+    out.write_all(b"WAVEfmt ")?;
+    out.write_all(&16u32.to_le_bytes())?;
+    out.write_all(&FORMAT_IEEE_FLOAT.to_le_bytes())?;
+    out.write_all(&CHANNELS.to_le_bytes())?;
+    out.write_all(&sample_rate.to_le_bytes())?;
+    let block_align = CHANNELS * BYTES_PER_SAMPLE;
+    out.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    out.write_all(&block_align.to_le_bytes())?;
+    out.write_all(&(BYTES_PER_SAMPLE * 8).to_le_bytes())?;
+    out.write_all(b"data")?;
+    out.write_all(&0u32.to_le_bytes())?;
+
+    let max_data_len = (u32::MAX - HEADER_LEN) as u64;
+    let mut data_len: u64 = 0;
+    let mut truncated = false;
+    loop {
+        // The stop flag is checked before draining so that frames pushed right before the
+        // recording flag was cleared still make it into the file.
+        let stopping = stop.load(Ordering::Acquire);
+        let mut wrote_any = false;
+        while let Ok(frame) = consumer.pop() {
+            if data_len + block_align as u64 > max_data_len {
+                if !truncated {
+                    warn!("Recording reached the WAV size limit of 4 GiB, the rest is discarded");
+                    truncated = true;
+                }
+                continue;
+            }
+            out.write_all(&frame.left.to_le_bytes())?;
+            out.write_all(&frame.right.to_le_bytes())?;
+            data_len += block_align as u64;
+            wrote_any = true;
+        }
+        if stopping {
+            break;
+        }
+        if !wrote_any {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    let data_len = data_len as u32;
+    let mut file = out.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&(HEADER_LEN - 8 + data_len).to_le_bytes())?;
+    file.seek(SeekFrom::Start(40))?;
+    file.write_all(&data_len.to_le_bytes())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_wav;
+    use kira::Frame;
+    use rtrb::RingBuffer;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn test_wav_header_matches_written_frames() -> eyre::Result<()> {
+        let path = std::env::temp_dir().join(format!("noisedeck-test-{}.wav", std::process::id()));
+        let (mut producer, mut consumer) = RingBuffer::new(16);
+        for i in 0..10 {
+            producer
+                .push(Frame::new(i as f32, -(i as f32)))
+                .map_err(|_| eyre::eyre!("ring buffer full"))?;
+        }
+        write_wav(
+            std::fs::File::create(&path)?,
+            &mut consumer,
+            48_000,
+            &AtomicBool::new(true),
+        )?;
+
+        let bytes = std::fs::read(&path)?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(bytes.len(), 44 + 10 * 8);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into()?), 36 + 80);
+        assert_eq!(u32::from_le_bytes(bytes[24..28].try_into()?), 48_000);
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into()?), 80);
+        assert_eq!(f32::from_le_bytes(bytes[52..56].try_into()?), 1.0);
+        assert_eq!(f32::from_le_bytes(bytes[56..60].try_into()?), -1.0);
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+//! Stingers cut into the scene, e.g. for a dramatic reveal: whatever else is playing pauses while
+//! the stinger plays, and picks up where it was once the stinger is over, see
+//! [`crate::config::PlaybackMode::Stinger`].
+//!
+//! Only tracks that were audible are paused, so that the end of a stinger does not resume a scene
+//! that someone had paused on purpose.
+
+use super::{AudioEngine, Track, distinct};
+use kira::sound::PlaybackState;
+use std::sync::Arc;
+use tracing::info;
+
+struct Interruption {
+    stinger: Arc<Track>,
+    paused: Vec<Arc<Track>>,
+}
+
+#[derive(Default)]
+pub(super) struct Stingers {
+    current: Option<Interruption>,
+}
+
+impl Stingers {
+    /// Pauses the audible tracks before the stinger starts, and returns them.
+    pub fn interrupt(
+        &mut self,
+        engine: &mut impl AudioEngine,
+        stinger: &Arc<Track>,
+    ) -> Vec<Arc<Track>> {
+        let audible = distinct(engine.playing())
+            .into_iter()
+            .filter(|t| !Arc::ptr_eq(t, stinger) && is_audible(t))
+            .collect::<Vec<_>>();
+        for track in &audible {
+            engine.pause(track);
+        }
+        // A stinger on top of another one pauses the first, and resumes everything once it is over
+        let mut paused = self.current.take().map(|i| i.paused).unwrap_or_default();
+        paused.extend(audible.iter().cloned());
+        self.current = Some(Interruption {
+            stinger: stinger.clone(),
+            paused,
+        });
+        audible
+    }
+
+    /// Resumes the paused tracks once the stinger has stopped, whether it ran out or was stopped,
+    /// and returns them.
+    pub fn resume_after(&mut self, engine: &mut impl AudioEngine) -> Vec<Arc<Track>> {
+        let Some(current) = &self.current else {
+            return Vec::new();
+        };
+        if engine
+            .playing()
+            .iter()
+            .any(|t| Arc::ptr_eq(t, &current.stinger))
+        {
+            return Vec::new();
+        }
+        let Some(Interruption { stinger, paused }) = self.current.take() else {
+            return Vec::new();
+        };
+        info!("Stinger {:?} is over, resuming the scene", stinger.path);
+        // Tracks stopped while the stinger played stay stopped
+        let resumed = paused
+            .into_iter()
+            .filter(|t| engine.playing().iter().any(|p| Arc::ptr_eq(p, t)))
+            .collect::<Vec<_>>();
+        for track in &resumed {
+            engine.resume(track);
+        }
+        resumed
+    }
+}
+
+fn is_audible(track: &Track) -> bool {
+    matches!(
+        track.state.blocking_lock().playback_state(),
+        PlaybackState::Playing | PlaybackState::Resuming
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Stingers;
+    use crate::config::{PlaySoundSettings, PlaybackMode};
+    use crate::daemon::audio::mock::MockAudioEngine;
+    use crate::daemon::audio::{AudioEngine, Track};
+    use crate::daemon::ui::tests::harness::MockTrackState;
+    use kira::sound::PlaybackState;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    fn track(path: &str, mode: PlaybackMode) -> Arc<Track> {
+        let settings = PlaySoundSettings::new(mode);
+        Arc::new(Track::with_state(
+            Arc::new(PathBuf::from(path)),
+            settings,
+            Box::<MockTrackState>::default(),
+        ))
+    }
+
+    fn playback(track: &Track) -> PlaybackState {
+        track.state.blocking_lock().playback_state()
+    }
+
+    #[test]
+    fn test_stinger_pauses_the_scene_until_it_is_over() -> eyre::Result<()> {
+        let rain = track("rain.mp3", PlaybackMode::LoopStop);
+        let wind = track("wind.mp3", PlaybackMode::LoopStop);
+        let reveal = track("reveal.mp3", PlaybackMode::Stinger);
+        let mut engine = MockAudioEngine::default();
+        let mut stingers = Stingers::default();
+        engine.play(rain.clone())?;
+        engine.play(wind.clone())?;
+        engine.pause(&wind);
+
+        let paused = stingers.interrupt(&mut engine, &reveal);
+        engine.play(reveal.clone())?;
+        assert_eq!(paused.len(), 1);
+        assert_eq!(playback(&rain), PlaybackState::Paused);
+        assert!(stingers.resume_after(&mut engine).is_empty());
+
+        engine.stop(&reveal);
+        let resumed = stingers.resume_after(&mut engine);
+        assert!(resumed.len() == 1 && Arc::ptr_eq(&resumed[0], &rain));
+        assert_eq!(playback(&rain), PlaybackState::Playing);
+        assert_eq!(playback(&wind), PlaybackState::Paused);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tracks_stopped_during_a_stinger_stay_stopped() -> eyre::Result<()> {
+        let rain = track("rain.mp3", PlaybackMode::LoopStop);
+        let reveal = track("reveal.mp3", PlaybackMode::Stinger);
+        let mut engine = MockAudioEngine::default();
+        let mut stingers = Stingers::default();
+        engine.play(rain.clone())?;
+
+        stingers.interrupt(&mut engine, &reveal);
+        engine.play(reveal.clone())?;
+        engine.stop(&rain);
+        engine.stop(&reveal);
+        assert!(stingers.resume_after(&mut engine).is_empty());
+        assert_eq!(playback(&rain), PlaybackState::Stopped);
+        Ok(())
+    }
+}
@@ -0,0 +1,420 @@
+//! Plays sounds from `http(s)://` URLs, such as internet radio or hosted ambience.
+//!
+//! A download thread buffers the response body in memory while a symphonia-based decoder feeds
+//! kira from that buffer. Whenever playback catches up with the download, the decoder waits for
+//! more data and the stream reports that it is buffering. A server that stops sending ends the
+//! stream after a while, rather than leaving it buffering for good.
+
+use eyre::{Context, OptionExt};
+use kira::Frame;
+use kira::sound::FromFileError;
+use kira::sound::streaming::Decoder;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CODEC_TYPE_NULL, Decoder as CodecDecoder, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tracing::{debug, warn};
+
+#[cfg(feature = "streams")]
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long playback waits for more data before giving up on the stream. ureq only limits how
+/// long a whole body may take, which endless streams always exceed, so the reader keeps track.
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+const CHUNK_LEN: usize = 16 * 1024;
+/// Endless streams stop downloading this far ahead of playback, so that memory stays bounded.
+const MAX_READ_AHEAD: usize = 8 * 1024 * 1024;
+/// Already played data of endless streams is dropped in batches to avoid shifting the buffer on
+/// every read.
+const DISCARD_BATCH: usize = 1024 * 1024;
+/// Stands in for the unknown length of endless streams. kira converts the frame count to `i64`.
+const ENDLESS_FRAMES: usize = i64::MAX as usize;
+
+/// Shared between the decoder and the track state, so that the deck can show when a stream stalls.
+pub struct StreamStatus {
+    buffering: AtomicBool,
+}
+
+impl StreamStatus {
+    /// Streams start out buffering until the first audio arrives.
+    pub fn new() -> Arc<Self> {
+        Arc::new(StreamStatus {
+            buffering: AtomicBool::new(true),
+        })
+    }
+
+    pub fn is_buffering(&self) -> bool {
+        self.buffering.load(Ordering::Relaxed)
+    }
+}
+
+/// Connects to `url` and reads enough of the stream to set up decoding. This blocks on the
+/// network, so it must not run on the audio thread.
+pub fn open(url: &str, status: Arc<StreamStatus>) -> eyre::Result<StreamDecoder> {
+    let (len, body) = request(url)?;
+    let download = Arc::new(Download::default());
+    {
+        let download = download.clone();
+        std::thread::Builder::new()
+            .name("stream download".to_string())
+            .spawn(move || download_body(body, &download, len.is_none()))
+            .context("Failed to start stream download thread")?;
+    }
+    let source = HttpSource {
+        download,
+        status,
+        position: 0,
+        len,
+        stall_timeout: STALL_TIMEOUT,
+    };
+    StreamDecoder::new(source, url)
+}
+
+/// The length of the body, if the server says, and the body.
+#[cfg(feature = "streams")]
+fn request(url: &str) -> eyre::Result<(Option<u64>, impl Read + Send + 'static)> {
+    let agent = ureq::Agent::config_builder()
+        .timeout_connect(Some(CONNECT_TIMEOUT))
+        .timeout_recv_response(Some(CONNECT_TIMEOUT))
+        .build()
+        .new_agent();
+    let response = agent
+        .get(url)
+        .call()
+        .with_context(|| format!("Failed to request {url}"))?;
+    let len = response.body().content_length();
+    Ok((len, response.into_body().into_reader()))
+}
+
+#[cfg(not(feature = "streams"))]
+fn request(url: &str) -> eyre::Result<(Option<u64>, std::io::Empty)> {
+    eyre::bail!("Cannot play {url}, noisedeck was built without the `streams` feature")
+}
+
+#[derive(Default)]
+struct Download {
+    state: Mutex<DownloadState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct DownloadState {
+    data: Vec<u8>,
+    /// Stream offset of `data[0]`, which only advances for endless streams.
+    start: u64,
+    /// `Some` once the body has been read completely or the connection failed.
+    end: Option<Result<(), String>>,
+    /// Set when the decoder is gone, so that the download stops.
+    cancelled: bool,
+}
+
+impl Download {
+    fn lock(&self) -> MutexGuard<'_, DownloadState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait<'a>(&self, guard: MutexGuard<'a, DownloadState>) -> MutexGuard<'a, DownloadState> {
+        self.changed
+            .wait(guard)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait_timeout<'a>(
+        &self,
+        guard: MutexGuard<'a, DownloadState>,
+        timeout: Duration,
+    ) -> MutexGuard<'a, DownloadState> {
+        self.changed
+            .wait_timeout(guard, timeout)
+            .unwrap_or_else(PoisonError::into_inner)
+            .0
+    }
+}
+
+fn download_body(mut body: impl Read, download: &Download, endless: bool) {
+    let mut chunk = vec![0; CHUNK_LEN];
+    let end = loop {
+        {
+            let mut state = download.lock();
+            while endless && !state.cancelled && state.data.len() >= MAX_READ_AHEAD {
+                state = download.wait(state);
+            }
+            if state.cancelled {
+                return;
+            }
+        }
+        match body.read(&mut chunk) {
+            Ok(0) => break Ok(()),
+            Ok(n) => {
+                download.lock().data.extend_from_slice(&chunk[..n]);
+                download.changed.notify_all();
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => {
+                warn!("Stream download failed: {e}");
+                break Err(e.to_string());
+            }
+        }
+    };
+    download.lock().end = Some(end);
+    download.changed.notify_all();
+}
+
+/// Reads from the download buffer, waiting for the download where necessary.
+struct HttpSource {
+    download: Arc<Download>,
+    status: Arc<StreamStatus>,
+    position: u64,
+    /// Known for responses with a `Content-Length`. Those are kept in full, so that they can loop.
+    len: Option<u64>,
+    stall_timeout: Duration,
+}
+
+impl Read for HttpSource {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut state = self.download.lock();
+        let stalled_at = Instant::now() + self.stall_timeout;
+        loop {
+            let offset = self
+                .position
+                .checked_sub(state.start)
+                .and_then(|offset| usize::try_from(offset).ok())
+                .ok_or_else(|| {
+                    std::io::Error::new(ErrorKind::Unsupported, "stream data was already discarded")
+                })?;
+            if offset < state.data.len() {
+                let n = buf.len().min(state.data.len() - offset);
+                buf[..n].copy_from_slice(&state.data[offset..offset + n]);
+                self.position += n as u64;
+                if self.len.is_none() && offset + n >= DISCARD_BATCH {
+                    state.data.drain(..offset + n);
+                    state.start = self.position;
+                    self.download.changed.notify_all();
+                }
+                self.status.buffering.store(false, Ordering::Relaxed);
+                return Ok(n);
+            }
+            match &state.end {
+                Some(Ok(())) => return Ok(0),
+                Some(Err(e)) => return Err(std::io::Error::other(e.clone())),
+                None => {}
+            }
+            self.status.buffering.store(true, Ordering::Relaxed);
+            let Some(timeout) = stalled_at.checked_duration_since(Instant::now()) else {
+                // The download thread stops once its read returns, if ever
+                state.cancelled = true;
+                self.download.changed.notify_all();
+                return Err(std::io::Error::new(
+                    ErrorKind::TimedOut,
+                    "the stream stalled",
+                ));
+            };
+            state = self.download.wait_timeout(state, timeout);
+        }
+    }
+}
+
+impl Seek for HttpSource {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.len.and_then(|len| len.checked_add_signed(delta)),
+        }
+        .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "invalid stream position"))?;
+        if self.len.is_none() && target != self.position {
+            return Err(std::io::Error::new(
+                ErrorKind::Unsupported,
+                "endless streams cannot seek",
+            ));
+        }
+        self.position = target;
+        Ok(target)
+    }
+}
+
+impl MediaSource for HttpSource {
+    fn is_seekable(&self) -> bool {
+        self.len.is_some()
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.len
+    }
+}
+
+impl Drop for HttpSource {
+    fn drop(&mut self) {
+        self.download.lock().cancelled = true;
+        self.download.changed.notify_all();
+    }
+}
+
+/// Unlike kira's own decoder, this one also accepts streams without a known length.
+pub struct StreamDecoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn CodecDecoder>,
+    track_id: u32,
+    sample_rate: u32,
+    num_frames: Option<usize>,
+}
+
+impl StreamDecoder {
+    fn new(source: HttpSource, url: &str) -> eyre::Result<Self> {
+        let mut hint = Hint::new();
+        if let Some(ext) = url_extension(url) {
+            hint.with_extension(ext);
+        }
+        let stream = MediaSourceStream::new(Box::new(source), Default::default());
+        let format = symphonia::default::get_probe()
+            .format(
+                &hint,
+                stream,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .with_context(|| format!("Unsupported stream format at {url}"))?
+            .format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_eyre("No audio track found in stream")?;
+        let sample_rate = track
+            .codec_params
+            .sample_rate
+            .ok_or_eyre("Stream has an unknown sample rate")?;
+        let num_frames = track
+            .codec_params
+            .n_frames
+            .and_then(|n| usize::try_from(n).ok());
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .context("Unsupported stream codec")?;
+        Ok(StreamDecoder {
+            track_id: track.id,
+            format,
+            decoder,
+            sample_rate,
+            num_frames,
+        })
+    }
+
+    /// `None` for endless streams, such as internet radio, which cannot loop either.
+    pub fn duration(&self) -> Option<Duration> {
+        self.num_frames
+            .map(|n| Duration::from_secs_f64(n as f64 / self.sample_rate as f64))
+    }
+}
+
+impl Decoder for StreamDecoder {
+    type Error = FromFileError;
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn num_frames(&self) -> usize {
+        self.num_frames.unwrap_or(ENDLESS_FRAMES)
+    }
+
+    fn decode(&mut self) -> Result<Vec<Frame>, Self::Error> {
+        loop {
+            let packet = self.format.next_packet()?;
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(SymphoniaError::DecodeError(e)) => {
+                    // Radio streams may start mid-frame, the decoder resynchronizes on the next one
+                    debug!("Skipping undecodable stream packet: {e}");
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let spec = *decoded.spec();
+            let channels = spec.channels.count();
+            if channels == 0 {
+                return Err(FromFileError::UnsupportedChannelConfiguration);
+            }
+            let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            samples.copy_interleaved_ref(decoded);
+            return Ok(samples
+                .samples()
+                .chunks_exact(channels)
+                .map(|frame| match frame {
+                    [mono] => Frame::from_mono(*mono),
+                    [left, right, ..] => Frame::new(*left, *right),
+                    [] => Frame::ZERO,
+                })
+                .collect());
+        }
+    }
+
+    fn seek(&mut self, index: usize) -> Result<usize, Self::Error> {
+        let seeked = self.format.seek(
+            SeekMode::Accurate,
+            SeekTo::TimeStamp {
+                ts: index as u64,
+                track_id: self.track_id,
+            },
+        )?;
+        self.decoder.reset();
+        Ok(usize::try_from(seeked.actual_ts).unwrap_or(index))
+    }
+}
+
+/// The format hint ignores query strings, which hosted files often carry for authentication.
+fn url_extension(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next()?;
+    let file_name = path.rsplit('/').next()?;
+    Path::new(file_name).extension()?.to_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Download, HttpSource, StreamStatus, url_extension};
+    use std::io::{ErrorKind, Read};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_url_extension_ignores_query() {
+        assert_eq!(url_extension("https://example.com/rain.mp3"), Some("mp3"));
+        assert_eq!(
+            url_extension("https://example.com/a.b/rain.mp3?token=x.y#t=1"),
+            Some("mp3")
+        );
+        assert_eq!(url_extension("http://radio.example.com:8000/live"), None);
+        assert_eq!(url_extension("https://example.com/"), None);
+    }
+
+    #[test]
+    fn test_a_stalled_stream_ends() {
+        let download = Arc::new(Download::default());
+        download.lock().data.extend_from_slice(b"ID3");
+        let mut source = HttpSource {
+            download: download.clone(),
+            status: StreamStatus::new(),
+            position: 0,
+            len: None,
+            stall_timeout: Duration::from_millis(50),
+        };
+        let mut buf = [0; 16];
+
+        assert_eq!(source.read(&mut buf).ok(), Some(3));
+        let stalled = source.read(&mut buf);
+
+        assert_eq!(stalled.map_err(|e| e.kind()), Err(ErrorKind::TimedOut));
+        assert!(source.status.is_buffering());
+        assert!(download.lock().cancelled);
+    }
+}
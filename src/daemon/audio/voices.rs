@@ -0,0 +1,208 @@
+//! Caps on how many sounds play at once, so that a button tapped over and over cannot pile up
+//! overlapping instances until mixing them takes more CPU than the machine can spare.
+//!
+//! Each track can have its own cap, see [`crate::config::PlaySoundSettings::max_instances`], and
+//! the daemon has one across all tracks. A sound that would go over either is treated the same.
+
+use super::Track;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OverLimit {
+    /// The sound does not play
+    #[default]
+    Refuse,
+    /// The oldest instance stops to make room, of the same track if that track is at its limit
+    Steal,
+    /// The sound plays as soon as enough of the others have stopped
+    Queue,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VoiceLimits {
+    /// `None` lets any number of sounds play at once.
+    pub max_sounds: Option<usize>,
+    pub over_limit: OverLimit,
+}
+
+pub(super) enum Admission {
+    Play,
+    /// Play once the oldest instance of this track has been stopped.
+    Steal(Arc<Track>),
+    Queue,
+    Refuse,
+}
+
+pub(super) struct Voices {
+    limits: VoiceLimits,
+    queued: VecDeque<Arc<Track>>,
+}
+
+impl Voices {
+    pub fn new(limits: VoiceLimits) -> Self {
+        Voices {
+            limits,
+            queued: VecDeque::new(),
+        }
+    }
+
+    /// `playing` lists the engine's tracks oldest first, see [`super::AudioEngine::playing`].
+    pub fn admit(&self, playing: &[Arc<Track>], track: &Arc<Track>) -> Admission {
+        let instances = instances_of(track);
+        // Tracks that do not overlap are not started again while they play anyway
+        if instances > 0 && !track.mode().overlaps() {
+            return Admission::Play;
+        }
+        let track_full = track
+            .settings
+            .max_instances
+            .is_some_and(|max| instances >= max);
+        let all_full = self
+            .limits
+            .max_sounds
+            .is_some_and(|max| voices(playing) >= max);
+        if !track_full && !all_full {
+            return Admission::Play;
+        }
+        match self.limits.over_limit {
+            OverLimit::Refuse => Admission::Refuse,
+            OverLimit::Queue => Admission::Queue,
+            OverLimit::Steal => {
+                let victim = if track_full {
+                    Some(track).filter(|_| instances > 0)
+                } else {
+                    playing.iter().find(|t| instances_of(t) > 0)
+                };
+                victim.map_or(Admission::Refuse, |t| Admission::Steal(t.clone()))
+            }
+        }
+    }
+
+    /// A track is queued at most once, so that impatient taps do not all play later on.
+    pub fn queue(&mut self, track: Arc<Track>) {
+        if self.queued.iter().any(|t| Arc::ptr_eq(t, &track)) {
+            info!("{:?} is already waiting to play", track.path);
+        } else {
+            info!(
+                "Too many sounds playing, {:?} waits for its turn",
+                track.path
+            );
+            self.queued.push_back(track);
+        }
+    }
+
+    /// A stopped track no longer waits either, since whoever stopped it did not want to hear it.
+    pub fn dequeue(&mut self, track: &Arc<Track>) {
+        self.queued.retain(|t| !Arc::ptr_eq(t, track));
+    }
+
+    /// The next queued track that fits now, in the order they were queued.
+    pub fn next_ready(&mut self, playing: &[Arc<Track>]) -> Option<Arc<Track>> {
+        let next = self.queued.front()?;
+        match self.admit(playing, next) {
+            Admission::Play => self.queued.pop_front(),
+            _ => None,
+        }
+    }
+}
+
+fn instances_of(track: &Track) -> usize {
+    track.state.blocking_lock().instances()
+}
+
+/// Engines may list an overlapping track once per instance.
+fn voices(playing: &[Arc<Track>]) -> usize {
+    let mut seen = HashSet::new();
+    playing
+        .iter()
+        .filter(|t| seen.insert(Arc::as_ptr(t)))
+        .map(|t| instances_of(t))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Admission, OverLimit, VoiceLimits, Voices};
+    use crate::config::{PlaySoundSettings, PlaybackMode};
+    use crate::daemon::audio::Track;
+    use crate::daemon::ui::tests::harness::MockTrackState;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    fn overlapping(path: &str, instances: usize, max_instances: Option<usize>) -> Arc<Track> {
+        let settings = PlaySoundSettings {
+            max_instances,
+            ..PlaySoundSettings::new(PlaybackMode::PlayOverlap)
+        };
+        Arc::new(Track::with_state(
+            Arc::new(PathBuf::from(path)),
+            settings,
+            Box::new(MockTrackState {
+                instances,
+                ..Default::default()
+            }),
+        ))
+    }
+
+    fn voices(max_sounds: Option<usize>, over_limit: OverLimit) -> Voices {
+        Voices::new(VoiceLimits {
+            max_sounds,
+            over_limit,
+        })
+    }
+
+    #[test]
+    fn test_track_limit_steals_from_the_same_track() {
+        let crowd = overlapping("crowd.mp3", 2, Some(2));
+        let rain = overlapping("rain.mp3", 1, None);
+        let playing = [rain.clone(), crowd.clone()];
+
+        let voices = voices(None, OverLimit::Steal);
+        assert!(matches!(
+            voices.admit(&playing, &crowd),
+            Admission::Steal(t) if Arc::ptr_eq(&t, &crowd)
+        ));
+        assert!(matches!(voices.admit(&playing, &rain), Admission::Play));
+    }
+
+    #[test]
+    fn test_global_limit_steals_the_oldest_sound() {
+        let rain = overlapping("rain.mp3", 1, None);
+        let crowd = overlapping("crowd.mp3", 2, None);
+        let playing = [rain.clone(), crowd.clone(), crowd.clone()];
+
+        assert!(matches!(
+            voices(Some(3), OverLimit::Steal).admit(&playing, &crowd),
+            Admission::Steal(t) if Arc::ptr_eq(&t, &rain)
+        ));
+        assert!(matches!(
+            voices(Some(3), OverLimit::Refuse).admit(&playing, &crowd),
+            Admission::Refuse
+        ));
+        assert!(matches!(
+            voices(Some(4), OverLimit::Refuse).admit(&playing, &crowd),
+            Admission::Play
+        ));
+    }
+
+    #[test]
+    fn test_queued_tracks_wait_until_there_is_room() {
+        let rain = overlapping("rain.mp3", 1, None);
+        let crowd = overlapping("crowd.mp3", 0, None);
+        let playing = [rain];
+        let mut voices = voices(Some(1), OverLimit::Queue);
+        assert!(matches!(voices.admit(&playing, &crowd), Admission::Queue));
+        voices.queue(crowd.clone());
+        voices.queue(crowd.clone());
+
+        assert!(voices.next_ready(&playing).is_none());
+        assert!(
+            voices
+                .next_ready(&[])
+                .is_some_and(|t| Arc::ptr_eq(&t, &crowd))
+        );
+        assert!(voices.next_ready(&[]).is_none());
+    }
+}
@@ -0,0 +1,393 @@
+//! What the daemon needs from a device with a grid of keys that show images. Elgato's Stream
+//! Decks are the only devices so far; others, such as a Loupedeck or a generic HID key grid,
+//! only have to implement [`DeckBackend`].
+
+use elgato_streamdeck::asynchronous::AsyncDeviceStateReader;
+use elgato_streamdeck::images::ImageRect;
+use elgato_streamdeck::info::Kind;
+use elgato_streamdeck::{AsyncStreamDeck, DeviceStateUpdate};
+use eyre::Context;
+use hidapi::HidApi;
+use image::DynamicImage;
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// Keys are numbered row by row from the top left, like the buttons of a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEvent {
+    Down(u8),
+    Up(u8),
+    /// Horizontal position on the touch strip, in pixels from the left.
+    StripTap(u16),
+    StripSwipe {
+        from: u16,
+        to: u16,
+    },
+}
+
+pub trait DeckBackend {
+    type Reader: KeyReader;
+
+    /// Rows and columns.
+    fn key_layout(&self) -> (u8, u8);
+
+    async fn set_brightness(&self, percent: u8) -> eyre::Result<()>;
+
+    async fn set_key_image(&self, key: u8, image: DynamicImage) -> eyre::Result<()>;
+
+    async fn clear_key(&self, key: u8) -> eyre::Result<()>;
+
+    async fn clear_all_keys(&self) -> eyre::Result<()>;
+
+    /// Width and height in pixels of the touch strip, if the device has one.
+    fn strip_size(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    /// Only called on devices with a [`DeckBackend::strip_size`].
+    async fn set_strip_image(&self, _image: DynamicImage) -> eyre::Result<()> {
+        eyre::bail!("The device has no touch strip")
+    }
+
+    /// Images may only show up on the device once flushed.
+    async fn flush(&self) -> eyre::Result<()>;
+
+    /// Reads independently of the device, so that waiting for key presses does not hold up
+    /// image uploads.
+    fn reader(&self) -> Self::Reader;
+
+    /// Leaves the device dark, or at least dimmed, once the daemon exits.
+    async fn shut_down(&self) -> eyre::Result<()>;
+}
+
+pub trait KeyReader {
+    /// Waits briefly for key presses, and returns no events if there were none.
+    async fn read(&self) -> eyre::Result<Vec<KeyEvent>>;
+}
+
+pub struct StreamDeck {
+    device: AsyncStreamDeck,
+}
+
+impl StreamDeck {
+    pub async fn connect(hid: &HidApi, kind: Kind, serial: &str) -> eyre::Result<StreamDeck> {
+        let device = AsyncStreamDeck::connect(hid, kind, serial)
+            .with_context(|| format!("Failed to connect to device {kind:?} {serial}"))?;
+        debug!(
+            "Connected to '{}' with version '{}'. Key count {}",
+            device.serial_number().await?,
+            device.firmware_version().await?,
+            kind.key_count()
+        );
+        Ok(StreamDeck { device })
+    }
+}
+
+impl DeckBackend for StreamDeck {
+    type Reader = StreamDeckReader;
+
+    fn key_layout(&self) -> (u8, u8) {
+        self.device.kind().key_layout()
+    }
+
+    async fn set_brightness(&self, percent: u8) -> eyre::Result<()> {
+        Ok(self.device.set_brightness(percent).await?)
+    }
+
+    async fn set_key_image(&self, key: u8, image: DynamicImage) -> eyre::Result<()> {
+        Ok(self.device.set_button_image(key, image).await?)
+    }
+
+    async fn clear_key(&self, key: u8) -> eyre::Result<()> {
+        Ok(self.device.clear_button_image(key).await?)
+    }
+
+    async fn clear_all_keys(&self) -> eyre::Result<()> {
+        Ok(self.device.clear_all_button_images().await?)
+    }
+
+    fn strip_size(&self) -> Option<(u32, u32)> {
+        let (width, height) = self.device.kind().lcd_strip_size()?;
+        Some((u32::try_from(width).ok()?, u32::try_from(height).ok()?))
+    }
+
+    async fn set_strip_image(&self, image: DynamicImage) -> eyre::Result<()> {
+        let rect = ImageRect::from_image(image)?;
+        Ok(self.device.write_lcd(0, 0, &rect).await?)
+    }
+
+    async fn flush(&self) -> eyre::Result<()> {
+        Ok(self.device.flush().await?)
+    }
+
+    fn reader(&self) -> StreamDeckReader {
+        StreamDeckReader(self.device.get_reader())
+    }
+
+    /// Older firmware can neither shut down nor sleep.
+    async fn shut_down(&self) -> eyre::Result<()> {
+        if self.device.shutdown().await.is_err() && self.device.sleep().await.is_err() {
+            self.device.set_brightness(15).await?;
+        }
+        Ok(())
+    }
+}
+
+pub struct StreamDeckReader(Arc<AsyncDeviceStateReader>);
+
+impl KeyReader for StreamDeckReader {
+    async fn read(&self) -> eyre::Result<Vec<KeyEvent>> {
+        let updates = self.0.read(100.0).await?;
+        Ok(updates
+            .into_iter()
+            .filter_map(|update| match update {
+                DeviceStateUpdate::ButtonDown(key) => Some(KeyEvent::Down(key)),
+                DeviceStateUpdate::ButtonUp(key) => Some(KeyEvent::Up(key)),
+                DeviceStateUpdate::TouchScreenPress(x, _)
+                | DeviceStateUpdate::TouchScreenLongPress(x, _) => Some(KeyEvent::StripTap(x)),
+                DeviceStateUpdate::TouchScreenSwipe((from, _), (to, _)) => {
+                    Some(KeyEvent::StripSwipe { from, to })
+                }
+                unknown => {
+                    info!("Ignoring device update: {:?}", unknown);
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// A device, and possibly a second one that shows the same keys. Presses on either count.
+pub struct Mirrored<B, C = B> {
+    primary: B,
+    copy: Option<C>,
+}
+
+impl<B: DeckBackend, C: DeckBackend> Mirrored<B, C> {
+    pub fn new(primary: B) -> Self {
+        Mirrored {
+            primary,
+            copy: None,
+        }
+    }
+
+    /// Keys are shown by number, so both devices need the same layout.
+    pub fn with_copy(primary: B, copy: C) -> eyre::Result<Self> {
+        if primary.key_layout() != copy.key_layout() {
+            eyre::bail!(
+                "Cannot mirror a deck with {:?} keys onto one with {:?}",
+                primary.key_layout(),
+                copy.key_layout()
+            );
+        }
+        Ok(Mirrored {
+            primary,
+            copy: Some(copy),
+        })
+    }
+}
+
+impl<B: DeckBackend, C: DeckBackend> DeckBackend for Mirrored<B, C> {
+    type Reader = MirroredReader<B::Reader, C::Reader>;
+
+    fn key_layout(&self) -> (u8, u8) {
+        self.primary.key_layout()
+    }
+
+    async fn set_brightness(&self, percent: u8) -> eyre::Result<()> {
+        self.primary.set_brightness(percent).await?;
+        if let Some(copy) = &self.copy {
+            copy.set_brightness(percent).await?;
+        }
+        Ok(())
+    }
+
+    async fn set_key_image(&self, key: u8, image: DynamicImage) -> eyre::Result<()> {
+        if let Some(copy) = &self.copy {
+            copy.set_key_image(key, image.clone()).await?;
+        }
+        self.primary.set_key_image(key, image).await
+    }
+
+    async fn clear_key(&self, key: u8) -> eyre::Result<()> {
+        self.primary.clear_key(key).await?;
+        if let Some(copy) = &self.copy {
+            copy.clear_key(key).await?;
+        }
+        Ok(())
+    }
+
+    async fn clear_all_keys(&self) -> eyre::Result<()> {
+        self.primary.clear_all_keys().await?;
+        if let Some(copy) = &self.copy {
+            copy.clear_all_keys().await?;
+        }
+        Ok(())
+    }
+
+    fn strip_size(&self) -> Option<(u32, u32)> {
+        self.primary.strip_size()
+    }
+
+    /// A copy without a strip of the same size just goes without.
+    async fn set_strip_image(&self, image: DynamicImage) -> eyre::Result<()> {
+        if let Some(copy) = &self.copy
+            && copy.strip_size() == self.primary.strip_size()
+        {
+            copy.set_strip_image(image.clone()).await?;
+        }
+        self.primary.set_strip_image(image).await
+    }
+
+    async fn flush(&self) -> eyre::Result<()> {
+        self.primary.flush().await?;
+        if let Some(copy) = &self.copy {
+            copy.flush().await?;
+        }
+        Ok(())
+    }
+
+    fn reader(&self) -> Self::Reader {
+        MirroredReader {
+            primary: self.primary.reader(),
+            copy: self.copy.as_ref().map(C::reader),
+        }
+    }
+
+    /// Both devices are shut down even if the first one fails to.
+    async fn shut_down(&self) -> eyre::Result<()> {
+        let primary = self.primary.shut_down().await;
+        if let Some(copy) = &self.copy {
+            copy.shut_down().await?;
+        }
+        primary
+    }
+}
+
+pub struct MirroredReader<R, S = R> {
+    primary: R,
+    copy: Option<S>,
+}
+
+impl<R: KeyReader, S: KeyReader> KeyReader for MirroredReader<R, S> {
+    async fn read(&self) -> eyre::Result<Vec<KeyEvent>> {
+        match &self.copy {
+            None => self.primary.read().await,
+            Some(copy) => tokio::select! {
+                events = self.primary.read() => events,
+                events = copy.read() => events,
+            },
+        }
+    }
+}
+
+/// A device that keeps what it is sent, and presses the keys that it is told to, for tests.
+#[cfg(test)]
+pub mod fake {
+    use super::{DeckBackend, KeyEvent, KeyReader};
+    use image::DynamicImage;
+    use std::sync::{Arc, Mutex, PoisonError};
+    use std::time::Duration;
+    use tokio::sync::mpsc::{self, Receiver, Sender};
+
+    /// Key images are `None` where a key was cleared.
+    #[derive(Debug, Default)]
+    pub struct Sent {
+        pub brightness: Option<u8>,
+        /// Sent since the last flush.
+        pub pending: Vec<(u8, Option<DynamicImage>)>,
+        pub flushes: Vec<Vec<(u8, Option<DynamicImage>)>>,
+    }
+
+    #[derive(Clone)]
+    pub struct FakeDeck {
+        layout: (u8, u8),
+        sent: Arc<Mutex<Sent>>,
+        presses: Arc<tokio::sync::Mutex<Receiver<KeyEvent>>>,
+    }
+
+    impl FakeDeck {
+        pub fn new(layout: (u8, u8)) -> (FakeDeck, Sender<KeyEvent>) {
+            let (presses_tx, presses_rx) = mpsc::channel(16);
+            let deck = FakeDeck {
+                layout,
+                sent: Arc::default(),
+                presses: Arc::new(tokio::sync::Mutex::new(presses_rx)),
+            };
+            (deck, presses_tx)
+        }
+
+        pub fn sent<R>(&self, f: impl FnOnce(&mut Sent) -> R) -> R {
+            f(&mut self.sent.lock().unwrap_or_else(PoisonError::into_inner))
+        }
+
+        fn show(&self, key: u8, image: Option<DynamicImage>) {
+            self.sent(|sent| sent.pending.push((key, image)));
+        }
+    }
+
+    impl DeckBackend for FakeDeck {
+        type Reader = FakeReader;
+
+        fn key_layout(&self) -> (u8, u8) {
+            self.layout
+        }
+
+        async fn set_brightness(&self, percent: u8) -> eyre::Result<()> {
+            self.sent(|sent| sent.brightness = Some(percent));
+            Ok(())
+        }
+
+        async fn set_key_image(&self, key: u8, image: DynamicImage) -> eyre::Result<()> {
+            self.show(key, Some(image));
+            Ok(())
+        }
+
+        async fn clear_key(&self, key: u8) -> eyre::Result<()> {
+            self.show(key, None);
+            Ok(())
+        }
+
+        async fn clear_all_keys(&self) -> eyre::Result<()> {
+            let (rows, cols) = self.layout;
+            for key in 0..rows * cols {
+                self.show(key, None);
+            }
+            Ok(())
+        }
+
+        async fn flush(&self) -> eyre::Result<()> {
+            self.sent(|sent| {
+                let pending = std::mem::take(&mut sent.pending);
+                sent.flushes.push(pending);
+            });
+            Ok(())
+        }
+
+        fn reader(&self) -> FakeReader {
+            FakeReader(self.presses.clone())
+        }
+
+        async fn shut_down(&self) -> eyre::Result<()> {
+            self.clear_all_keys().await
+        }
+    }
+
+    pub struct FakeReader(Arc<tokio::sync::Mutex<Receiver<KeyEvent>>>);
+
+    impl KeyReader for FakeReader {
+        async fn read(&self) -> eyre::Result<Vec<KeyEvent>> {
+            let wait = Duration::from_millis(100);
+            let mut presses = self.0.lock().await;
+            match tokio::time::timeout(wait, presses.recv()).await {
+                Ok(Some(event)) => Ok(vec![event]),
+                // Like a device that nobody touches
+                Ok(None) => {
+                    tokio::time::sleep(wait).await;
+                    Ok(Vec::new())
+                }
+                Err(_) => Ok(Vec::new()),
+            }
+        }
+    }
+}
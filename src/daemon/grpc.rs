@@ -0,0 +1,280 @@
+//! Serves the control API of the `noisedeck-api` crate, so that other programs, such as a
+//! lighting desk or a companion app, can play and stop sounds and follow what is playing.
+//!
+//! Requests take the same way into the deck as button presses, and the deck answers them once it
+//! has acted on them. Nothing is authenticated; the address should only be reachable by trusted
+//! machines.
+
+use crate::daemon::history::{Entry, History, HistoryEvent};
+use crate::daemon::ui::{MediaPlayback, MediaStatus, Remote, UiEvent};
+use crate::util::{canonical_path, is_stream_url};
+use eyre::Context;
+use noisedeck_api::noise_deck_server::{NoiseDeck, NoiseDeckServer};
+use noisedeck_api::{
+    Done, Event, HistoryEntry, HistoryReply, HistoryRequest, PlayRequest, Playback,
+    SetVolumeRequest, StopRequest, SubscribeRequest,
+};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::{oneshot, watch};
+use tokio_stream::wrappers::WatchStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+/// Fails if the address cannot be listened on. Otherwise keeps serving in the background.
+pub async fn serve(
+    address: &str,
+    audio_path: PathBuf,
+    ui_event_tx: Sender<UiEvent>,
+    status: watch::Receiver<MediaStatus>,
+    history: Arc<History>,
+) -> eyre::Result<()> {
+    let listener = TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to listen for gRPC requests on {address}"))?;
+    info!("Listening for gRPC requests on {}", listener.local_addr()?);
+    spawn(listener, audio_path, ui_event_tx, status, history);
+    Ok(())
+}
+
+fn spawn(
+    listener: TcpListener,
+    audio_path: PathBuf,
+    ui_event_tx: Sender<UiEvent>,
+    status: watch::Receiver<MediaStatus>,
+    history: Arc<History>,
+) {
+    let api = Api {
+        audio_path,
+        ui_event_tx,
+        status,
+        history,
+    };
+    tokio::spawn(async move {
+        let result = Server::builder()
+            .add_service(NoiseDeckServer::new(api))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await;
+        if let Err(e) = result {
+            warn!(error = %e, "Stopped serving gRPC requests");
+        }
+    });
+}
+
+struct Api {
+    audio_path: PathBuf,
+    ui_event_tx: Sender<UiEvent>,
+    status: watch::Receiver<MediaStatus>,
+    history: Arc<History>,
+}
+
+impl Api {
+    /// Spelled like the paths in the configuration, which the deck knows its tracks by once they
+    /// are rebased onto the audio directory.
+    fn track_path(&self, path: &str) -> Result<Arc<PathBuf>, Status> {
+        if path.is_empty() {
+            return Err(Status::invalid_argument("path is missing"));
+        }
+        if is_stream_url(path) {
+            return Ok(Arc::new(PathBuf::from(path)));
+        }
+        Ok(Arc::new(canonical_path(&self.audio_path.join(path))))
+    }
+
+    async fn send(&self, remote: Remote) -> Result<Response<Done>, Status> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let gone = || Status::unavailable("the deck is shutting down");
+        self.ui_event_tx
+            .send(UiEvent::Remote(remote, reply_tx))
+            .await
+            .map_err(|_| gone())?;
+        match reply_rx.await.map_err(|_| gone())? {
+            Ok(()) => Ok(Response::new(Done {})),
+            Err(e) => Err(Status::failed_precondition(format!("{e:#}"))),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl NoiseDeck for Api {
+    async fn play(&self, request: Request<PlayRequest>) -> Result<Response<Done>, Status> {
+        let path = self.track_path(&request.into_inner().path)?;
+        self.send(Remote::Play(path)).await
+    }
+
+    async fn stop(&self, request: Request<StopRequest>) -> Result<Response<Done>, Status> {
+        let path = self.track_path(&request.into_inner().path)?;
+        self.send(Remote::Stop(path)).await
+    }
+
+    async fn set_volume(
+        &self,
+        request: Request<SetVolumeRequest>,
+    ) -> Result<Response<Done>, Status> {
+        let volume_db = request.into_inner().volume_db;
+        if !volume_db.is_finite() {
+            return Err(Status::invalid_argument("volume_db must be a number"));
+        }
+        self.send(Remote::SetVolume(volume_db)).await
+    }
+
+    type SubscribeStream = Pin<Box<dyn Stream<Item = Result<Event, Status>> + Send>>;
+
+    async fn subscribe(
+        &self,
+        _request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let events = WatchStream::new(self.status.clone()).map(|status| Ok(event_of(&status)));
+        Ok(Response::new(Box::pin(events)))
+    }
+
+    async fn history(
+        &self,
+        _request: Request<HistoryRequest>,
+    ) -> Result<Response<HistoryReply>, Status> {
+        let entries = self.history.recent().iter().map(history_entry_of).collect();
+        Ok(Response::new(HistoryReply { entries }))
+    }
+}
+
+fn history_entry_of(entry: &Entry) -> HistoryEntry {
+    let path = |path: &PathBuf| path.to_string_lossy().into_owned();
+    let (kind, subject, volume_db) = match &entry.event {
+        HistoryEvent::Pressed(label) => ("pressed", label.clone(), 0.0),
+        HistoryEvent::Held(label) => ("held", label.clone(), 0.0),
+        HistoryEvent::Played(sound) => ("played", path(sound), 0.0),
+        HistoryEvent::Stopped(sound) => ("stopped", path(sound), 0.0),
+        HistoryEvent::PausedAll => ("paused", String::new(), 0.0),
+        HistoryEvent::ResumedAll => ("resumed", String::new(), 0.0),
+        HistoryEvent::Volume(db) => ("volume", String::new(), *db),
+        HistoryEvent::TrackVolume(sound, db) => ("volume", path(sound), *db),
+    };
+    HistoryEntry {
+        at: entry.at.to_string(),
+        kind: kind.to_string(),
+        subject,
+        volume_db,
+    }
+}
+
+fn event_of(status: &MediaStatus) -> Event {
+    let playback = match status.playback {
+        MediaPlayback::Stopped => Playback::Stopped,
+        MediaPlayback::Playing => Playback::Playing,
+        MediaPlayback::Paused => Playback::Paused,
+    };
+    let (latest_label, latest_path) = match &status.latest {
+        Some((label, path)) => (label.to_string(), path.to_string_lossy().into_owned()),
+        None => (String::new(), String::new()),
+    };
+    Event {
+        playback: playback.into(),
+        latest_label,
+        latest_path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::spawn;
+    use crate::daemon::history::{History, HistoryEvent};
+    use crate::daemon::ui::{MediaPlayback, MediaStatus, Remote, UiEvent};
+    use noisedeck_api::noise_deck_client::NoiseDeckClient;
+    use noisedeck_api::{
+        HistoryRequest, PlayRequest, Playback, SetVolumeRequest, SubscribeRequest,
+    };
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::sync::{mpsc, watch};
+    use tokio::time::timeout;
+    use tonic::Code;
+
+    #[tokio::test]
+    async fn test_clients_control_the_deck_and_follow_it() -> eyre::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let (ui_event_tx, mut ui_event_rx) = mpsc::channel(4);
+        let (status_tx, status_rx) = watch::channel(MediaStatus::default());
+        let history = Arc::new(History::default());
+        history.record(HistoryEvent::Pressed("Rain".to_string()));
+        history.record(HistoryEvent::Volume(-6.0));
+        let audio_path = PathBuf::from("/sounds");
+        spawn(listener, audio_path, ui_event_tx, status_rx, history);
+        // Stands in for the deck, which knows only one track
+        tokio::spawn(async move {
+            while let Some(event) = ui_event_rx.recv().await {
+                let UiEvent::Remote(remote, reply) = event else {
+                    continue;
+                };
+                let result = match &remote {
+                    Remote::Play(path) if path.as_path() == Path::new("/sounds/rain.mp3") => Ok(()),
+                    Remote::Play(path) => Err(eyre::eyre!("{} is not on any page", path.display())),
+                    _ => Ok(()),
+                };
+                let _ = reply.send(result);
+            }
+        });
+
+        let mut client = NoiseDeckClient::connect(format!("http://{address}")).await?;
+        let mut events = client.subscribe(SubscribeRequest {}).await?.into_inner();
+        let next_event = async |events: &mut tonic::Streaming<_>| -> eyre::Result<_> {
+            timeout(Duration::from_secs(2), events.message())
+                .await??
+                .ok_or_else(|| eyre::eyre!("stream ended"))
+        };
+        assert_eq!(next_event(&mut events).await?.playback(), Playback::Stopped);
+
+        client
+            .play(PlayRequest {
+                path: "rain.mp3".into(),
+            })
+            .await?;
+        let refused = client
+            .play(PlayRequest {
+                path: "thunder.mp3".into(),
+            })
+            .await
+            .expect_err("thunder.mp3 is not on the deck");
+        assert_eq!(refused.code(), Code::FailedPrecondition);
+        assert!(refused.message().contains("thunder.mp3 is not on any page"));
+        let invalid = client
+            .set_volume(SetVolumeRequest {
+                volume_db: f64::NAN,
+            })
+            .await
+            .expect_err("NaN is no volume");
+        assert_eq!(invalid.code(), Code::InvalidArgument);
+
+        status_tx.send(MediaStatus {
+            playback: MediaPlayback::Playing,
+            latest: Some((
+                Arc::new("Rain".to_string()),
+                Arc::new(PathBuf::from("/sounds/rain.mp3")),
+            )),
+        })?;
+        let event = next_event(&mut events).await?;
+        assert_eq!(event.playback(), Playback::Playing);
+        assert_eq!(event.latest_label, "Rain");
+        assert_eq!(event.latest_path, "/sounds/rain.mp3");
+
+        let history = client
+            .history(HistoryRequest {})
+            .await?
+            .into_inner()
+            .entries;
+        let entries: Vec<_> = history
+            .iter()
+            .map(|e| (e.kind.as_str(), e.subject.as_str(), e.volume_db))
+            .collect();
+        assert_eq!(entries, [("pressed", "Rain", 0.0), ("volume", "", -6.0)]);
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+//! What happened at the deck and when: buttons pressed, sounds started and stopped, volume
+//! changed. Meant for finding the track that was played an hour ago, and for retracing how the
+//! deck got into a state.
+//!
+//! The daemon keeps the latest entries, which the gRPC API hands out, and with
+//! `--history-file` also appends every entry to a file as a line of JSON, which
+//! `noisedeck history` lists.
+
+use crate::daemon::audio::{AudioCommand, Track};
+use clap::Args;
+use eyre::Context;
+use jiff::{SignedDuration, Timestamp, tz::TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc::{self, Receiver};
+use tracing::warn;
+
+/// How many entries the daemon keeps; older ones are only in the file.
+const RECENT: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub at: Timestamp,
+    pub event: HistoryEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HistoryEvent {
+    /// By the label the button had.
+    Pressed(String),
+    Held(String),
+    Played(PathBuf),
+    /// Also when only some of the instances of an overlapping sound were stopped.
+    Stopped(PathBuf),
+    PausedAll,
+    ResumedAll,
+    /// The global volume in dB.
+    Volume(f64),
+    /// By how many dB the track's volume was changed.
+    TrackVolume(PathBuf, f64),
+}
+
+impl HistoryEvent {
+    /// `None` for the commands that change nothing one would look for later, e.g. preloading.
+    fn of(command: &AudioCommand) -> Option<HistoryEvent> {
+        let path = |track: &Track| PathBuf::clone(&track.path);
+        match command {
+            AudioCommand::Play(track) => Some(HistoryEvent::Played(path(track))),
+            AudioCommand::Stop(track) | AudioCommand::StopInstances(track, _) => {
+                Some(HistoryEvent::Stopped(path(track)))
+            }
+            AudioCommand::PauseAll => Some(HistoryEvent::PausedAll),
+            AudioCommand::ResumeAll => Some(HistoryEvent::ResumedAll),
+            AudioCommand::SetGlobalVolume(db) => Some(HistoryEvent::Volume(*db)),
+            AudioCommand::AdjustTrackVolume(track, db) => {
+                Some(HistoryEvent::TrackVolume(path(track), *db))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for HistoryEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryEvent::Pressed(label) => write!(f, "pressed  {label}"),
+            HistoryEvent::Held(label) => write!(f, "held     {label}"),
+            HistoryEvent::Played(path) => write!(f, "played   {}", path.display()),
+            HistoryEvent::Stopped(path) => write!(f, "stopped  {}", path.display()),
+            HistoryEvent::PausedAll => write!(f, "paused   everything"),
+            HistoryEvent::ResumedAll => write!(f, "resumed  everything"),
+            HistoryEvent::Volume(db) => write!(f, "volume   {db:+.1} dB"),
+            HistoryEvent::TrackVolume(path, db) => {
+                write!(f, "volume   {db:+.1} dB on {}", path.display())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    recent: Mutex<VecDeque<Entry>>,
+    /// Hands entries to the task that appends them to the file.
+    file: Option<mpsc::UnboundedSender<Entry>>,
+}
+
+impl History {
+    /// Fails if the file cannot be opened, rather than losing the history without notice.
+    pub async fn with_file(path: &Path) -> eyre::Result<History> {
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("Failed to open the history file {}", path.display()))?;
+        let (file_tx, mut file_rx) = mpsc::unbounded_channel::<Entry>();
+        let path = path.to_path_buf();
+        // Of its own, so that recording never waits for the disk. tokio only hands what was
+        // written to the system on a flush, which each batch ends with, so that nothing is lost
+        // when the daemon stops.
+        tokio::spawn(async move {
+            while let Some(entry) = file_rx.recv().await {
+                let mut lines = String::new();
+                let batch =
+                    std::iter::once(entry).chain(std::iter::from_fn(|| file_rx.try_recv().ok()));
+                for entry in batch {
+                    if let Ok(line) = serde_json::to_string(&entry) {
+                        lines.push_str(&line);
+                        lines.push('\n');
+                    }
+                }
+                let written = match file.write_all(lines.as_bytes()).await {
+                    Ok(()) => file.flush().await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = written {
+                    warn!(error = %e, "Failed to append to the history file {}", path.display());
+                }
+            }
+        });
+        Ok(History {
+            recent: Mutex::default(),
+            file: Some(file_tx),
+        })
+    }
+
+    pub fn record(&self, event: HistoryEvent) {
+        let entry = Entry {
+            at: Timestamp::now(),
+            event,
+        };
+        if let Some(file) = &self.file {
+            let _ = file.send(entry.clone());
+        }
+        let mut recent = self.recent.lock().unwrap_or_else(PoisonError::into_inner);
+        if recent.len() == RECENT {
+            recent.pop_front();
+        }
+        recent.push_back(entry);
+    }
+
+    /// Oldest first.
+    pub fn recent(&self) -> Vec<Entry> {
+        let recent = self.recent.lock().unwrap_or_else(PoisonError::into_inner);
+        recent.iter().cloned().collect()
+    }
+
+    /// Records the commands on their way to the audio engine, which is where every sound that
+    /// is started or stopped passes, whichever part of the deck asked for it.
+    pub fn tap(self: Arc<Self>, mut commands: Receiver<AudioCommand>) -> Receiver<AudioCommand> {
+        let (tapped_tx, tapped_rx) = mpsc::channel(commands.max_capacity());
+        tokio::spawn(async move {
+            while let Some(command) = commands.recv().await {
+                if let Some(event) = HistoryEvent::of(&command) {
+                    self.record(event);
+                }
+                if tapped_tx.send(command).await.is_err() {
+                    break;
+                }
+            }
+        });
+        tapped_rx
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Args)]
+pub struct HistoryArgs {
+    /// The daemon's `--history-file`.
+    #[arg(long = "history-file", env = "history_file")]
+    path: PathBuf,
+
+    /// Only what happened this long ago or later, e.g. `1h` or `20m`.
+    #[arg(long)]
+    since: Option<SignedDuration>,
+
+    /// Writes the entries as they are in the file, e.g. for `jq`.
+    #[arg(long)]
+    json: bool,
+}
+
+/// Lists the history, oldest first. Lines that cannot be read, e.g. one that the daemon was
+/// writing when it was stopped, are skipped.
+pub async fn run(args: HistoryArgs) -> eyre::Result<()> {
+    let content = tokio::fs::read_to_string(&args.path)
+        .await
+        .with_context(|| format!("Failed to read the history file {}", args.path.display()))?;
+    let since = args
+        .since
+        .map(|ago| Timestamp::now().checked_sub(ago))
+        .transpose()
+        .context("--since reaches too far back")?;
+    #[cfg(not(feature = "local-time"))]
+    warn!("Listing times in UTC, noisedeck was built without the `local-time` feature");
+    let tz = TimeZone::system();
+    for line in content.lines() {
+        let Ok(entry) = serde_json::from_str::<Entry>(line) else {
+            continue;
+        };
+        if since.is_some_and(|since| entry.at < since) {
+            continue;
+        }
+        if args.json {
+            println!("{line}");
+        } else {
+            let at = entry.at.to_zoned(tz.clone());
+            println!("{}  {}", at.strftime("%Y-%m-%d %H:%M:%S"), entry.event);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Entry, History, HistoryEvent, RECENT};
+    use crate::config::{PlaySoundSettings, PlaybackMode};
+    use crate::daemon::audio::{AudioCommand, Track};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tokio::time::sleep;
+
+    fn track(path: &str) -> Arc<Track> {
+        let settings = PlaySoundSettings::new(PlaybackMode::PlayStop);
+        Arc::new(Track::new(Arc::new(PathBuf::from(path)), settings))
+    }
+
+    #[tokio::test]
+    async fn test_commands_to_the_engine_are_recorded_and_passed_on() -> eyre::Result<()> {
+        let history = Arc::new(History::default());
+        let (command_tx, command_rx) = mpsc::channel(4);
+        let mut tapped = history.clone().tap(command_rx);
+
+        command_tx
+            .send(AudioCommand::Play(track("rain.mp3")))
+            .await?;
+        command_tx.send(AudioCommand::GetGlobalVolume).await?;
+        command_tx.send(AudioCommand::SetGlobalVolume(-6.0)).await?;
+        for _ in 0..3 {
+            assert!(tapped.recv().await.is_some());
+        }
+
+        let events: Vec<_> = history.recent().into_iter().map(|e| e.event).collect();
+        assert_eq!(
+            events,
+            [
+                HistoryEvent::Played(PathBuf::from("rain.mp3")),
+                HistoryEvent::Volume(-6.0)
+            ]
+        );
+        drop(command_tx);
+        assert!(tapped.recv().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_only_the_latest_entries_are_kept_but_all_are_written() -> eyre::Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "noisedeck-history-test-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = tokio::fs::remove_file(&path).await;
+        let history = History::with_file(&path).await?;
+        for i in 0..=RECENT {
+            history.record(HistoryEvent::Pressed(format!("Button {i}")));
+        }
+
+        let recent = history.recent();
+        assert_eq!(recent.len(), RECENT);
+        assert_eq!(
+            recent[0].event,
+            HistoryEvent::Pressed("Button 1".to_string())
+        );
+        let mut written = Vec::new();
+        for _ in 0..100 {
+            let content = tokio::fs::read_to_string(&path).await?;
+            // The last line may still be in the making
+            written = content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<Entry>(line).ok())
+                .collect();
+            if written.len() > RECENT {
+                break;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        tokio::fs::remove_file(&path).await?;
+        assert_eq!(written.len(), RECENT + 1);
+        assert_eq!(written[RECENT], recent[RECENT - 1]);
+        Ok(())
+    }
+}
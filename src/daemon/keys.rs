@@ -0,0 +1,136 @@
+//! Sends key chords to whatever application has the focus, so that a deck key can double as a
+//! hotkey, e.g. to mute the microphone in the voice chat, without the vendor software running
+//! next to the daemon.
+
+#[cfg(feature = "keystrokes")]
+use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+#[cfg(feature = "keystrokes")]
+use eyre::Context;
+
+/// Presses the chord, see [`parse_chord`].
+#[cfg(feature = "keystrokes")]
+pub async fn send(chord: &str) -> eyre::Result<()> {
+    let keys = parse_chord(chord)?;
+    tokio::task::spawn_blocking(move || send_chord(&keys)).await?
+}
+
+#[cfg(not(feature = "keystrokes"))]
+pub async fn send(chord: &str) -> eyre::Result<()> {
+    eyre::bail!("Cannot send {chord}, noisedeck was built without the `keystrokes` feature")
+}
+
+/// Parses chords such as `Ctrl+Shift+M`. Names are case-insensitive, and any other single
+/// character stands for the key that types it.
+#[cfg(feature = "keystrokes")]
+fn parse_chord(chord: &str) -> eyre::Result<Vec<Key>> {
+    chord
+        .split('+')
+        .map(|name| {
+            let name = name.trim();
+            let key = match name.to_lowercase().as_str() {
+                "ctrl" | "control" => Key::Control,
+                "shift" => Key::Shift,
+                "alt" => Key::Alt,
+                "meta" | "super" | "win" | "cmd" => Key::Meta,
+                "space" => Key::Space,
+                "enter" | "return" => Key::Return,
+                "tab" => Key::Tab,
+                "esc" | "escape" => Key::Escape,
+                "backspace" => Key::Backspace,
+                "delete" => Key::Delete,
+                "home" => Key::Home,
+                "end" => Key::End,
+                "pageup" => Key::PageUp,
+                "pagedown" => Key::PageDown,
+                "up" => Key::UpArrow,
+                "down" => Key::DownArrow,
+                "left" => Key::LeftArrow,
+                "right" => Key::RightArrow,
+                "mute" => Key::VolumeMute,
+                "playpause" => Key::MediaPlayPause,
+                lower => {
+                    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                        function_key(n)
+                            .ok_or_else(|| eyre::eyre!("There is no function key F{n}"))?
+                    } else {
+                        let mut chars = lower.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => Key::Unicode(c),
+                            _ => eyre::bail!("Unknown key '{name}' in '{chord}'"),
+                        }
+                    }
+                }
+            };
+            Ok(key)
+        })
+        .collect()
+}
+
+#[cfg(feature = "keystrokes")]
+fn function_key(n: u8) -> Option<Key> {
+    const KEYS: [Key; 20] = [
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+        Key::F13,
+        Key::F14,
+        Key::F15,
+        Key::F16,
+        Key::F17,
+        Key::F18,
+        Key::F19,
+        Key::F20,
+    ];
+    KEYS.get(usize::from(n).checked_sub(1)?).copied()
+}
+
+/// Presses the keys in order and releases them in reverse, like a person would. Blocks, because
+/// the input systems are synchronous.
+#[cfg(feature = "keystrokes")]
+fn send_chord(keys: &[Key]) -> eyre::Result<()> {
+    let mut enigo =
+        Enigo::new(&Settings::default()).context("Failed to connect to the input system")?;
+    let mut pressed = 0;
+    let mut result = Ok(());
+    for key in keys {
+        if let Err(e) = enigo.key(*key, Direction::Press) {
+            result = Err(e).with_context(|| format!("Failed to press {key:?}"));
+            break;
+        }
+        pressed += 1;
+    }
+    // A modifier that stays down would garble everything typed afterwards
+    for key in keys[..pressed].iter().rev() {
+        if let Err(e) = enigo.key(*key, Direction::Release) {
+            result = result.and(Err(e).with_context(|| format!("Failed to release {key:?}")));
+        }
+    }
+    result
+}
+
+#[cfg(all(test, feature = "keystrokes"))]
+mod tests {
+    use super::parse_chord;
+    use enigo::Key;
+
+    #[test]
+    fn test_parse_chord() -> eyre::Result<()> {
+        assert_eq!(
+            parse_chord("Ctrl+Shift+M")?,
+            vec![Key::Control, Key::Shift, Key::Unicode('m')]
+        );
+        assert_eq!(parse_chord("alt + F13")?, vec![Key::Alt, Key::F13]);
+        assert!(parse_chord("Ctrl+F21").is_err());
+        assert!(parse_chord("Ctrl+Mouse").is_err());
+        Ok(())
+    }
+}
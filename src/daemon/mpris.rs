@@ -0,0 +1,243 @@
+//! Exposes the daemon as an MPRIS player on the session bus, so that media keys and desktop
+//! widgets, such as the one in KDE's system tray, can pause and resume the soundboard and show
+//! which track was started last.
+
+use crate::daemon::ui::{MediaPlayback, MediaStatus, Transport, UiEvent};
+use crate::util::is_stream_url;
+use eyre::Context;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::watch;
+use tracing::{debug, warn};
+use zbus::interface;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// Fails without a session bus, e.g. on a headless machine. Otherwise keeps serving in the
+/// background for as long as the deck publishes its status.
+pub async fn serve(
+    ui_event_tx: Sender<UiEvent>,
+    mut status: watch::Receiver<MediaStatus>,
+) -> eyre::Result<()> {
+    let player = Player {
+        ui_event_tx,
+        status: status.clone(),
+    };
+    let connection = zbus::connection::Builder::session()?
+        .name("org.mpris.MediaPlayer2.noisedeck")?
+        .serve_at(OBJECT_PATH, Root)?
+        .serve_at(OBJECT_PATH, player)?
+        .build()
+        .await
+        .context("Failed to register as media player on the session bus")?;
+    let player = connection
+        .object_server()
+        .interface::<_, Player>(OBJECT_PATH)
+        .await?;
+
+    tokio::spawn(async move {
+        while status.changed().await.is_ok() {
+            let emitter = player.signal_emitter();
+            let iface = player.get().await;
+            let result = match iface.playback_status_changed(emitter).await {
+                Ok(()) => iface.metadata_changed(emitter).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!(error = %e, "Error announcing media status");
+            }
+        }
+        debug!("Deck stopped publishing its status, leaving the session bus");
+        drop(connection);
+    });
+    Ok(())
+}
+
+struct Root;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    /// The daemon has no window to raise.
+    fn raise(&self) {}
+
+    /// Stopping the daemon is left to whatever started it.
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "noisedeck"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+struct Player {
+    ui_event_tx: Sender<UiEvent>,
+    status: watch::Receiver<MediaStatus>,
+}
+
+impl Player {
+    async fn send(&self, transport: Transport) {
+        if let Err(e) = self.ui_event_tx.send(UiEvent::Transport(transport)).await {
+            warn!(error = %e, "Error forwarding {transport:?} to the deck");
+        }
+    }
+}
+
+/// A soundboard has no playlist to move through or position to seek to, so those methods do
+/// nothing, as the specification asks of players that cannot do them.
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    async fn play(&self) {
+        self.send(Transport::Resume).await
+    }
+
+    async fn pause(&self) {
+        self.send(Transport::Pause).await
+    }
+
+    async fn play_pause(&self) {
+        self.send(Transport::TogglePause).await
+    }
+
+    async fn stop(&self) {
+        self.send(Transport::Stop).await
+    }
+
+    fn next(&self) {}
+
+    fn previous(&self) {}
+
+    fn seek(&self, _offset: i64) {}
+
+    fn set_position(&self, _track_id: ObjectPath<'_>, _position: i64) {}
+
+    fn open_uri(&self, _uri: &str) {}
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        match self.status.borrow().playback {
+            MediaPlayback::Playing => "Playing",
+            MediaPlayback::Paused => "Paused",
+            MediaPlayback::Stopped => "Stopped",
+        }
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let status = self.status.borrow();
+        let mut metadata = HashMap::new();
+        let Some((label, path)) = &status.latest else {
+            insert(
+                &mut metadata,
+                "mpris:trackid",
+                ObjectPath::from_static_str_unchecked(NO_TRACK),
+            );
+            return metadata;
+        };
+        // Only has to stay the same while the track is the latest one
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        let track_id = format!("/org/noisedeck/track/t{:x}", hasher.finish());
+        if let Ok(track_id) = ObjectPath::try_from(track_id) {
+            insert(&mut metadata, "mpris:trackid", track_id);
+        }
+        insert(&mut metadata, "xesam:title", label.as_str());
+        let url = match path.to_str() {
+            Some(url) if is_stream_url(url) => url.to_string(),
+            _ => format!("file://{}", path.display()),
+        };
+        insert(&mut metadata, "xesam:url", url);
+        metadata
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn position(&self) -> i64 {
+        0
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+fn insert<'a>(metadata: &mut HashMap<String, OwnedValue>, key: &str, value: impl Into<Value<'a>>) {
+    match OwnedValue::try_from(value.into()) {
+        Ok(value) => {
+            metadata.insert(key.to_string(), value);
+        }
+        Err(e) => warn!(error = %e, "Error converting {key} for the media status"),
+    }
+}
@@ -0,0 +1,694 @@
+//! Announces the deck's controls to Home Assistant over an MQTT broker, so that they show up
+//! there without any YAML: a button that stops everything, a switch that pauses, a slider for
+//! the volume of each bus and a scene for each `Sequence` button. Home Assistant finds them
+//! through its MQTT discovery, and what it sends to them reaches the deck like a tap on it.
+//!
+//! Only the part of MQTT 3.1.1 that this needs is spoken: messages go either way without being
+//! acknowledged (QoS 0), over plain TCP.
+
+use crate::config::{ButtonBehavior, Config};
+use crate::daemon::ui::{MediaPlayback, MediaStatus, Transport, UiEvent};
+use eyre::Context;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// How often the broker hears from the daemon when nothing else happens.
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+/// E.g. while the broker restarts.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Far more than any command from Home Assistant, and keeps a broker from making the daemon
+/// buffer without bound.
+const MAX_PACKET_LEN: usize = 64 * 1024;
+
+const CONNECT: u8 = 1;
+const CONNACK: u8 = 2;
+const PUBLISH: u8 = 3;
+const SUBACK: u8 = 9;
+const PINGRESP: u8 = 13;
+
+#[derive(Debug, Clone)]
+pub struct MqttSettings {
+    /// `host:port` of the broker.
+    pub address: String,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    /// Starts the topics of this daemon and names it in Home Assistant, so that several
+    /// daemons can share a broker.
+    pub name: String,
+    /// Where Home Assistant looks for devices, `homeassistant` unless it was configured
+    /// otherwise.
+    pub discovery_prefix: String,
+}
+
+/// Keeps connecting to the broker in the background for as long as the deck runs, so that a
+/// broker that starts later or restarts is picked up.
+pub fn spawn(
+    settings: MqttSettings,
+    ui_event_tx: Sender<UiEvent>,
+    status: watch::Receiver<MediaStatus>,
+    config: watch::Receiver<Arc<Config>>,
+) {
+    let mut bridge = Bridge {
+        settings,
+        ui_event_tx,
+        status,
+        config,
+        bus_volumes: HashMap::new(),
+        announced: HashSet::new(),
+    };
+    tokio::spawn(async move {
+        loop {
+            match bridge.connect().await {
+                Ok(()) => {
+                    debug!("Deck stopped, leaving the MQTT broker");
+                    return;
+                }
+                Err(e) => warn!(
+                    "Lost the MQTT broker at {}, trying again in {RECONNECT_DELAY:?}: {e:#}",
+                    bridge.settings.address
+                ),
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+struct Bridge {
+    settings: MqttSettings,
+    ui_event_tx: Sender<UiEvent>,
+    status: watch::Receiver<MediaStatus>,
+    config: watch::Receiver<Arc<Config>>,
+    /// In percent, by the name of the bus. Buses that are not in here are at full volume.
+    bus_volumes: HashMap<String, f64>,
+    /// Discovery topics that the broker keeps, so that entities that went away with a
+    /// configuration change can be removed from Home Assistant.
+    announced: HashSet<String>,
+}
+
+impl Bridge {
+    /// Ends without an error once the deck is gone.
+    async fn connect(&mut self) -> eyre::Result<()> {
+        let stream = TcpStream::connect(&self.settings.address)
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.settings.address))?;
+        let (read, mut write) = stream.into_split();
+        write.write_all(&connect_packet(&self.settings)?).await?;
+        // A read that is cancelled halfway would lose its part of a packet, so reading has a
+        // task of its own.
+        let (packets_tx, mut packets) = mpsc::channel(16);
+        let reader = tokio::spawn(async move {
+            let mut read = read;
+            loop {
+                let packet = read_packet(&mut read).await;
+                let end = !matches!(packet, Ok(Some(_)));
+                if packets_tx.send(packet).await.is_err() || end {
+                    return;
+                }
+            }
+        });
+        let result = self.serve(&mut write, &mut packets).await;
+        reader.abort();
+        result
+    }
+
+    async fn serve(
+        &mut self,
+        write: &mut OwnedWriteHalf,
+        packets: &mut Receiver<eyre::Result<Option<Packet>>>,
+    ) -> eyre::Result<()> {
+        let connack = next_packet(packets).await?;
+        match connack.body.get(1) {
+            Some(0) if connack.kind == CONNACK => {}
+            Some(code) if connack.kind == CONNACK => {
+                eyre::bail!("The broker refused the connection with code {code}")
+            }
+            _ => eyre::bail!("The broker did not acknowledge the connection"),
+        }
+        info!("Connected to the MQTT broker at {}", self.settings.address);
+        let name = &self.settings.name;
+        let filters = [
+            format!("{name}/+/set"),
+            format!("{name}/+/+/set"),
+            format!("{}/status", self.settings.discovery_prefix),
+        ];
+        write.write_all(&subscribe_packet(&filters)?).await?;
+        self.announce(write).await?;
+
+        let mut ping = tokio::time::interval(KEEP_ALIVE);
+        ping.reset();
+        loop {
+            tokio::select! {
+                packet = next_packet(packets) => self.handle(packet?, write).await?,
+                changed = self.status.changed() => {
+                    if changed.is_err() {
+                        return Ok(());
+                    }
+                    self.publish_pause_state(write).await?;
+                }
+                changed = self.config.changed() => {
+                    if changed.is_err() {
+                        return Ok(());
+                    }
+                    self.announce(write).await?;
+                }
+                _ = ping.tick() => write.write_all(&[0xc0, 0]).await?,
+            }
+        }
+    }
+
+    async fn handle(&mut self, packet: Packet, write: &mut OwnedWriteHalf) -> eyre::Result<()> {
+        match packet.kind {
+            PUBLISH => {
+                let (topic, payload) = packet.publication()?;
+                let payload = String::from_utf8_lossy(payload);
+                self.command(&topic, payload.trim(), write).await
+            }
+            SUBACK | PINGRESP => Ok(()),
+            kind => {
+                debug!("Ignoring MQTT packet of type {kind}");
+                Ok(())
+            }
+        }
+    }
+
+    async fn command(
+        &mut self,
+        topic: &str,
+        payload: &str,
+        write: &mut OwnedWriteHalf,
+    ) -> eyre::Result<()> {
+        // Home Assistant forgets what it discovered when it restarts
+        if topic == format!("{}/status", self.settings.discovery_prefix) {
+            if payload == "online" {
+                self.announce(write).await?;
+            }
+            return Ok(());
+        }
+        let Some(command) = topic
+            .strip_prefix(&self.settings.name)
+            .and_then(|t| t.strip_prefix('/'))
+            .and_then(|t| t.strip_suffix("/set"))
+        else {
+            return Ok(());
+        };
+        let event = match command.split_once('/') {
+            None if command == "stop_all" => UiEvent::Transport(Transport::Stop),
+            None if command == "pause" && payload == "ON" => UiEvent::Transport(Transport::Pause),
+            None if command == "pause" && payload == "OFF" => UiEvent::Transport(Transport::Resume),
+            Some(("bus", slug)) => {
+                let bus = self
+                    .config
+                    .borrow()
+                    .buses
+                    .iter()
+                    .find(|bus| object_id(&bus.name) == slug)
+                    .map(|bus| bus.name.clone());
+                let (Some(bus), Ok(percent)) = (bus, payload.parse::<f64>()) else {
+                    warn!("Ignoring volume {payload:?} for bus {slug} from MQTT");
+                    return Ok(());
+                };
+                let percent = percent.clamp(0.0, 100.0);
+                self.bus_volumes.insert(bus.clone(), percent);
+                let state = format!("{}/bus/{slug}/state", self.settings.name);
+                write
+                    .write_all(&publish_packet(
+                        &state,
+                        percent.to_string().as_bytes(),
+                        true,
+                    )?)
+                    .await?;
+                UiEvent::BusVolume(bus, percent / 100.0)
+            }
+            Some(("scene", id)) => match parse_scene(id) {
+                Some((page, index)) => UiEvent::TapConfigured(page, index),
+                None => {
+                    warn!("Ignoring unknown scene {id} from MQTT");
+                    return Ok(());
+                }
+            },
+            _ => {
+                warn!("Ignoring {payload:?} on {topic} from MQTT");
+                return Ok(());
+            }
+        };
+        debug!("{event:?} from MQTT");
+        self.ui_event_tx
+            .send(event)
+            .await
+            .context("Deck stopped taking events")
+    }
+
+    /// Publishes the entities of the current configuration and what they show.
+    async fn announce(&mut self, write: &mut OwnedWriteHalf) -> eyre::Result<()> {
+        let config = self.config.borrow_and_update().clone();
+        let entities = entities(&self.settings.name, &config);
+        let topics: HashSet<_> = entities.iter().map(|e| e.topic(&self.settings)).collect();
+        for stale in self.announced.difference(&topics) {
+            write.write_all(&publish_packet(stale, b"", true)?).await?;
+        }
+        for entity in &entities {
+            let payload = entity.config.to_string();
+            let topic = entity.topic(&self.settings);
+            write
+                .write_all(&publish_packet(&topic, payload.as_bytes(), true)?)
+                .await?;
+        }
+        self.announced = topics;
+
+        let name = &self.settings.name;
+        let availability = format!("{name}/availability");
+        write
+            .write_all(&publish_packet(&availability, b"online", true)?)
+            .await?;
+        for bus in &config.buses {
+            let state = format!("{name}/bus/{}/state", object_id(&bus.name));
+            let percent = self.bus_volumes.get(&bus.name).copied().unwrap_or(100.0);
+            write
+                .write_all(&publish_packet(
+                    &state,
+                    percent.to_string().as_bytes(),
+                    true,
+                )?)
+                .await?;
+        }
+        self.publish_pause_state(write).await
+    }
+
+    async fn publish_pause_state(&mut self, write: &mut OwnedWriteHalf) -> eyre::Result<()> {
+        let paused = self.status.borrow_and_update().playback == MediaPlayback::Paused;
+        let state = format!("{}/pause/state", self.settings.name);
+        let payload: &[u8] = if paused { b"ON" } else { b"OFF" };
+        write
+            .write_all(&publish_packet(&state, payload, true)?)
+            .await?;
+        Ok(())
+    }
+}
+
+/// What Home Assistant needs to know about one of the controls.
+struct Entity {
+    component: &'static str,
+    object_id: String,
+    config: Value,
+}
+
+impl Entity {
+    fn topic(&self, settings: &MqttSettings) -> String {
+        format!(
+            "{}/{}/{}/{}/config",
+            settings.discovery_prefix, self.component, settings.name, self.object_id
+        )
+    }
+}
+
+fn entities(name: &str, config: &Config) -> Vec<Entity> {
+    let device = json!({
+        "identifiers": [name],
+        "name": name,
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+    let entity = |component, object_id: String, mut config: Value| {
+        config["unique_id"] = json!(format!("{name}_{object_id}"));
+        config["availability_topic"] = json!(format!("{name}/availability"));
+        config["device"] = device.clone();
+        Entity {
+            component,
+            object_id,
+            config,
+        }
+    };
+
+    let mut entities = vec![
+        entity(
+            "button",
+            "stop_all".to_string(),
+            json!({
+                "name": "Stop all",
+                "command_topic": format!("{name}/stop_all/set"),
+                "icon": "mdi:stop",
+            }),
+        ),
+        entity(
+            "switch",
+            "pause".to_string(),
+            json!({
+                "name": "Pause",
+                "command_topic": format!("{name}/pause/set"),
+                "state_topic": format!("{name}/pause/state"),
+                "icon": "mdi:pause",
+            }),
+        ),
+    ];
+    for bus in &config.buses {
+        let slug = object_id(&bus.name);
+        entities.push(entity(
+            "number",
+            format!("bus_{slug}"),
+            json!({
+                "name": format!("{} volume", bus.name),
+                "command_topic": format!("{name}/bus/{slug}/set"),
+                "state_topic": format!("{name}/bus/{slug}/state"),
+                "min": 0,
+                "max": 100,
+                "step": 1,
+                "unit_of_measurement": "%",
+                "mode": "slider",
+                "icon": "mdi:volume-high",
+            }),
+        ));
+    }
+    for (page_id, page) in &config.pages {
+        for (index, button) in page.buttons.iter().enumerate() {
+            if !matches!(button.behavior, ButtonBehavior::Sequence(_)) {
+                continue;
+            }
+            let id = format!("{}_{index}", page_id.simple());
+            entities.push(entity(
+                "scene",
+                format!("scene_{id}"),
+                json!({
+                    "name": button.label.as_str(),
+                    "command_topic": format!("{name}/scene/{id}/set"),
+                    "payload_on": "ON",
+                }),
+            ));
+        }
+    }
+    entities
+}
+
+/// Bus names may contain anything, but topics and ids of Home Assistant should not.
+fn object_id(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' => c,
+            'A'..='Z' => c.to_ascii_lowercase(),
+            _ => '_',
+        })
+        .collect()
+}
+
+/// The page and the index of the button on it, see [`entities`].
+fn parse_scene(id: &str) -> Option<(Uuid, usize)> {
+    let (page, index) = id.split_once('_')?;
+    Some((Uuid::parse_str(page).ok()?, index.parse().ok()?))
+}
+
+struct Packet {
+    kind: u8,
+    flags: u8,
+    body: Vec<u8>,
+}
+
+impl Packet {
+    /// Topic and payload of a [`PUBLISH`].
+    fn publication(&self) -> eyre::Result<(String, &[u8])> {
+        let malformed = || eyre::eyre!("The broker sent a malformed message");
+        let len = self.body.get(..2).ok_or_else(malformed)?;
+        let len = usize::from(u16::from_be_bytes([len[0], len[1]]));
+        let topic = self.body.get(2..2 + len).ok_or_else(malformed)?;
+        let topic = String::from_utf8(topic.to_vec()).map_err(|_| malformed())?;
+        // Messages above QoS 0 carry an id, which is of no use without acknowledging them
+        let qos = (self.flags >> 1) & 3;
+        let start = 2 + len + if qos > 0 { 2 } else { 0 };
+        let payload = self.body.get(start..).ok_or_else(malformed)?;
+        Ok((topic, payload))
+    }
+}
+
+async fn next_packet(packets: &mut Receiver<eyre::Result<Option<Packet>>>) -> eyre::Result<Packet> {
+    match packets.recv().await {
+        Some(Ok(Some(packet))) => Ok(packet),
+        Some(Err(e)) => Err(e),
+        Some(Ok(None)) | None => eyre::bail!("The broker closed the connection"),
+    }
+}
+
+/// `None` once the broker closed the connection.
+async fn read_packet(read: &mut (impl AsyncRead + Unpin)) -> eyre::Result<Option<Packet>> {
+    let first = match read.read_u8().await {
+        Ok(first) => first,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut len = 0;
+    let mut shift = 0;
+    loop {
+        let byte = read.read_u8().await?;
+        len |= usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+        if shift > 21 {
+            eyre::bail!("The broker sent a malformed packet length");
+        }
+    }
+    if len > MAX_PACKET_LEN {
+        eyre::bail!("The broker sent a packet of {len} bytes, more than {MAX_PACKET_LEN}");
+    }
+    let mut body = vec![0; len];
+    read.read_exact(&mut body).await?;
+    Ok(Some(Packet {
+        kind: first >> 4,
+        flags: first & 0x0f,
+        body,
+    }))
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut len = body.len();
+    loop {
+        let byte = (len % 128) as u8;
+        len /= 128;
+        if len == 0 {
+            packet.push(byte);
+            break;
+        }
+        packet.push(byte | 0x80);
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn put_str(body: &mut Vec<u8>, s: &str) -> eyre::Result<()> {
+    let len = u16::try_from(s.len()).with_context(|| format!("{s:.40}… is too long for MQTT"))?;
+    body.extend_from_slice(&len.to_be_bytes());
+    body.extend_from_slice(s.as_bytes());
+    Ok(())
+}
+
+/// Without a clean disconnect, e.g. when the daemon is killed, the broker tells Home Assistant
+/// that the controls are unavailable.
+fn connect_packet(settings: &MqttSettings) -> eyre::Result<Vec<u8>> {
+    const CLEAN_SESSION: u8 = 0x02;
+    const WILL: u8 = 0x04;
+    const WILL_RETAIN: u8 = 0x20;
+    const PASSWORD: u8 = 0x40;
+    const USER: u8 = 0x80;
+
+    let mut flags = CLEAN_SESSION | WILL | WILL_RETAIN;
+    if settings.user.is_some() {
+        flags |= USER;
+    }
+    if settings.password.is_some() {
+        flags |= PASSWORD;
+    }
+    let mut body = Vec::new();
+    put_str(&mut body, "MQTT")?;
+    body.push(4); // 3.1.1
+    body.push(flags);
+    body.extend_from_slice(&(KEEP_ALIVE.as_secs() as u16).to_be_bytes());
+    put_str(&mut body, &settings.name)?;
+    put_str(&mut body, &format!("{}/availability", settings.name))?;
+    put_str(&mut body, "offline")?;
+    if let Some(user) = &settings.user {
+        put_str(&mut body, user)?;
+    }
+    if let Some(password) = &settings.password {
+        put_str(&mut body, password)?;
+    }
+    Ok(packet(CONNECT << 4, &body))
+}
+
+/// Retained messages are kept by the broker for whoever subscribes later, e.g. Home Assistant
+/// after a restart.
+fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> eyre::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    put_str(&mut body, topic)?;
+    body.extend_from_slice(payload);
+    Ok(packet(PUBLISH << 4 | u8::from(retain), &body))
+}
+
+fn subscribe_packet(filters: &[String]) -> eyre::Result<Vec<u8>> {
+    let mut body = 1u16.to_be_bytes().to_vec(); // the only subscription, so any id will do
+    for filter in filters {
+        put_str(&mut body, filter)?;
+        body.push(0); // QoS
+    }
+    Ok(packet(0x82, &body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MqttSettings, PUBLISH, Packet, packet, publish_packet, read_packet, spawn};
+    use crate::config::ButtonBehavior;
+    use crate::daemon::ui::tests::harness::{create_test_config, sound_button};
+    use crate::daemon::ui::{MediaStatus, Transport, UiEvent};
+    use assert_matches::assert_matches;
+    use eyre::OptionExt;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+    use tokio::net::tcp::OwnedReadHalf;
+    use tokio::sync::{mpsc, watch};
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    async fn test_packet_lengths_take_as_many_bytes_as_they_need() -> eyre::Result<()> {
+        for len in [0, 127, 128, 16_383, 16_384, 65_535] {
+            let encoded = packet(0x30, &vec![7; len]);
+            let decoded = read_packet(&mut &encoded[..])
+                .await?
+                .ok_or_eyre("Packet should be read")?;
+            assert_eq!(decoded.body.len(), len);
+            assert_eq!(
+                encoded.len() - len,
+                2 + usize::from(len >= 128) + usize::from(len >= 16_384)
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_oversized_packets_are_refused() {
+        let header = [0x30, 0xff, 0xff, 0x7f];
+        assert!(read_packet(&mut &header[..]).await.is_err());
+    }
+
+    async fn next_publication(read: &mut OwnedReadHalf) -> eyre::Result<(String, String)> {
+        loop {
+            let packet = timeout(Duration::from_secs(1), read_packet(read))
+                .await??
+                .ok_or_eyre("Daemon should stay connected")?;
+            if packet.kind == PUBLISH {
+                let (topic, payload) = packet.publication()?;
+                return Ok((topic, String::from_utf8(payload.to_vec())?));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_home_assistant_discovers_the_controls_and_uses_them() -> eyre::Result<()> {
+        let mut config = create_test_config();
+        config.buses.push(serde_json::from_value(
+            serde_json::json!({"name": "Cave Echo", "effects": []}),
+        )?);
+        let mut ambush = sound_button("Ambush", "ambush.mp3");
+        ambush.behavior = ButtonBehavior::Sequence(vec![ButtonBehavior::StopAll]);
+        let start_page = config.start_page;
+        let page = Arc::make_mut(config.pages.get_mut(&start_page).unwrap());
+        page.buttons.push(ambush);
+        let scene_index = page.buttons.len() - 1;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let (ui_event_tx, mut ui_event_rx) = mpsc::channel(16);
+        let (_status_tx, status) = watch::channel(MediaStatus::default());
+        let (_config_tx, config) = watch::channel(Arc::new(config));
+        let settings = MqttSettings {
+            address: listener.local_addr()?.to_string(),
+            user: Some("deck".to_string()),
+            password: Some("secret".to_string()),
+            name: "noisedeck".to_string(),
+            discovery_prefix: "homeassistant".to_string(),
+        };
+        spawn(settings, ui_event_tx, status, config);
+
+        let (stream, _) = timeout(Duration::from_secs(1), listener.accept()).await??;
+        let (mut read, mut write) = stream.into_split();
+        let connect = read_packet(&mut read)
+            .await?
+            .ok_or_eyre("Daemon should connect")?;
+        assert_eq!(connect.kind, super::CONNECT);
+        assert!(connect.body.ends_with(b"\0\x04deck\0\x06secret"));
+        write.write_all(&[0x20, 2, 0, 0]).await?;
+
+        let mut published = HashMap::new();
+        loop {
+            let (topic, payload) = next_publication(&mut read).await?;
+            if topic == "noisedeck/availability" {
+                assert_eq!(payload, "online");
+                break;
+            }
+            published.insert(topic, payload);
+        }
+        let scene_id = format!("{}_{scene_index}", start_page.simple());
+        for topic in [
+            "homeassistant/button/noisedeck/stop_all/config".to_string(),
+            "homeassistant/switch/noisedeck/pause/config".to_string(),
+            "homeassistant/number/noisedeck/bus_cave_echo/config".to_string(),
+            format!("homeassistant/scene/noisedeck/scene_{scene_id}/config"),
+        ] {
+            let config: serde_json::Value = serde_json::from_str(
+                published
+                    .get(&topic)
+                    .ok_or_else(|| eyre::eyre!("{topic} should be announced"))?,
+            )?;
+            assert_eq!(config["availability_topic"], "noisedeck/availability");
+        }
+
+        let commands = [
+            ("noisedeck/stop_all/set", "PRESS"),
+            ("noisedeck/pause/set", "ON"),
+            ("noisedeck/bus/cave_echo/set", "25"),
+            (&format!("noisedeck/scene/{scene_id}/set"), "ON"),
+        ];
+        for (topic, payload) in commands {
+            write
+                .write_all(&publish_packet(topic, payload.as_bytes(), false)?)
+                .await?;
+        }
+        let mut next_event = async || {
+            timeout(Duration::from_secs(1), ui_event_rx.recv())
+                .await?
+                .ok_or_eyre("Bridge should send events")
+        };
+        assert_matches!(next_event().await?, UiEvent::Transport(Transport::Stop));
+        assert_matches!(next_event().await?, UiEvent::Transport(Transport::Pause));
+        assert_matches!(
+            next_event().await?,
+            UiEvent::BusVolume(bus, volume) if bus == "Cave Echo" && volume == 0.25
+        );
+        assert_matches!(
+            next_event().await?,
+            UiEvent::TapConfigured(page, index) if page == start_page && index == scene_index
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_messages_above_qos_0_skip_their_id() -> eyre::Result<()> {
+        let packet = Packet {
+            kind: PUBLISH,
+            flags: 0x02,
+            body: b"\0\x01a\0\x07ON".to_vec(),
+        };
+        let (topic, payload) = packet.publication()?;
+        assert_eq!((topic.as_str(), payload), ("a", &b"ON"[..]));
+        Ok(())
+    }
+}
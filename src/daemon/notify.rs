@@ -0,0 +1,30 @@
+//! Desktop notifications for problems that would otherwise only show up in the logs. The daemon
+//! usually runs in the background, and the deck itself may be the thing that went away.
+
+use tracing::warn;
+
+/// Fire and forget: a missing notification service must not hold up the caller.
+#[cfg(feature = "notifications")]
+pub fn error(summary: &str, body: impl std::fmt::Display) {
+    let summary = summary.to_string();
+    let body = body.to_string();
+    tokio::task::spawn_blocking(move || {
+        let result = notify_rust::Notification::new()
+            .appname("noisedeck")
+            .summary(&summary)
+            .body(&body)
+            .show();
+        if let Err(e) = result {
+            warn!(error = %e, "Error showing desktop notification '{summary}'");
+        }
+    });
+}
+
+/// The problem at least makes it to the logs.
+#[cfg(not(feature = "notifications"))]
+pub fn error(summary: &str, body: impl std::fmt::Display) {
+    warn!(
+        "Not showing desktop notification '{summary}: {body}', noisedeck was built without the \
+         `notifications` feature"
+    );
+}
@@ -0,0 +1,479 @@
+//! WebAssembly plugins add behaviors, or follow what the deck is playing, without noisedeck
+//! being recompiled. Each plugin comes with a JSON manifest, e.g.
+//!
+//! ```json
+//! {
+//!   "name": "lights",
+//!   "module": "lights.wasm",
+//!   "behaviors": ["lights"],
+//!   "events": ["status"],
+//!   "capabilities": ["log", "label"]
+//! }
+//! ```
+//!
+//! The module path is relative to the manifest. The module must export its `memory` and an
+//! `alloc(len: i32) -> i32` that returns room for the strings the daemon passes in; these only
+//! need to stay valid until the export they are passed to returns. Depending on the manifest it
+//! also exports
+//!
+//! - `invoke(kind_ptr: i32, kind_len: i32, params_ptr: i32, params_len: i32) -> i32`, which runs
+//!   when a `Custom` button of one of its `behaviors` is tapped. The params are the button's
+//!   JSON, and anything but 0 fails the tap.
+//! - `on_status(ptr: i32, len: i32)` for the `status` event, which runs with the deck's playback
+//!   status as JSON whenever it changes.
+//!
+//! From the `noisedeck` module it may import the functions that the capabilities in its manifest
+//! cover, and no others. Strings are passed as pointer and length into its memory.
+//!
+//! | capability   | functions                                                                 |
+//! |--------------|---------------------------------------------------------------------------|
+//! | `log`        | `log(ptr, len)`                                                           |
+//! | `playback`   | `play(ptr, len)`, `stop(ptr, len)`, `stop_all()`, `volume_up()`, `volume_down()` |
+//! | `navigation` | `push(ptr, len)`, `goto(ptr, len)`, `back()`, `home()`                    |
+//! | `label`      | `set_label(ptr, len)`                                                     |
+//!
+//! They work like the functions of the same name in scripts: they take effect only once `invoke`
+//! has returned 0. During `on_status`, only `log` does anything.
+
+use crate::daemon::ui::{
+    Behavior, BehaviorRegistry, BoxFuture, BtnInvokeStatus, ButtonRef, Call, MediaPlayback,
+    MediaStatus, NoiseDeck, carry_out,
+};
+use eyre::{Context, OptionExt};
+use serde::Deserialize;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::sync::watch;
+use tracing::{info, warn};
+use wasmtime::{Caller, Engine, Instance, Linker, Module, Store};
+
+const HOST_MODULE: &str = "noisedeck";
+
+/// The deck waits for a tap's plugin before it goes on, so this has to run out long before a tap
+/// feels slow.
+const FUEL_PER_CALL: u64 = 10_000_000;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    name: String,
+    module: PathBuf,
+    #[serde(default)]
+    behaviors: Vec<String>,
+    #[serde(default)]
+    events: Vec<Event>,
+    #[serde(default)]
+    capabilities: Vec<Capability>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Event {
+    Status,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Capability {
+    Log,
+    Playback,
+    Navigation,
+    Label,
+}
+
+impl Capability {
+    /// Which capability an import from the host module needs, if the host has it at all.
+    fn of_import(name: &str) -> Option<Capability> {
+        match name {
+            "log" => Some(Capability::Log),
+            "play" | "stop" | "stop_all" | "volume_up" | "volume_down" => {
+                Some(Capability::Playback)
+            }
+            "push" | "goto" | "back" | "home" => Some(Capability::Navigation),
+            "set_label" => Some(Capability::Label),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Capability::Log => "log",
+            Capability::Playback => "playback",
+            Capability::Navigation => "navigation",
+            Capability::Label => "label",
+        }
+    }
+}
+
+struct Host {
+    plugin: String,
+    calls: Vec<Call>,
+}
+
+struct Running {
+    store: Store<Host>,
+    instance: Instance,
+}
+
+pub struct Plugin {
+    name: String,
+    behaviors: Vec<String>,
+    events: Vec<Event>,
+    running: Mutex<Running>,
+}
+
+/// Fails on the first plugin that cannot be loaded: a deck that does not start is easier to
+/// notice than a button that does nothing. Compiling the modules takes a while, so this blocks.
+pub fn load(manifests: &[PathBuf]) -> eyre::Result<Vec<Arc<Plugin>>> {
+    if manifests.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut config = wasmtime::Config::new();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config).map_err(wasm_error)?;
+    let linker = linker(&engine)?;
+    manifests
+        .iter()
+        .map(|path| {
+            let plugin = Plugin::load(&engine, &linker, path)
+                .with_context(|| format!("Failed to load plugin {}", path.display()))?;
+            Ok(Arc::new(plugin))
+        })
+        .collect()
+}
+
+pub fn register_behaviors(plugins: &[Arc<Plugin>], registry: &mut BehaviorRegistry) {
+    for plugin in plugins {
+        for kind in &plugin.behaviors {
+            let plugin = plugin.clone();
+            let kind = kind.clone();
+            registry.register(kind.clone(), move |params| {
+                Ok(Box::new(PluginBehavior {
+                    plugin: plugin.clone(),
+                    kind: kind.clone(),
+                    params: params.to_string(),
+                }))
+            });
+        }
+    }
+}
+
+/// Hands the deck's playback status to the plugins that subscribed to it, like the MPRIS player
+/// does for the desktop.
+pub fn subscribe(plugins: &[Arc<Plugin>], mut status: watch::Receiver<MediaStatus>) {
+    let plugins: Vec<_> = plugins
+        .iter()
+        .filter(|plugin| plugin.events.contains(&Event::Status))
+        .cloned()
+        .collect();
+    if plugins.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        while status.changed().await.is_ok() {
+            let json = status_json(&status.borrow_and_update());
+            let plugins = plugins.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                for plugin in &plugins {
+                    if let Err(e) = plugin.on_status(&json) {
+                        warn!(error = ?e, "Plugin '{}' failed to handle the status", plugin.name);
+                    }
+                }
+            })
+            .await;
+            if let Err(e) = result {
+                warn!(error = %e, "Error handing the status to plugins");
+            }
+        }
+    });
+}
+
+impl Plugin {
+    fn load(engine: &Engine, linker: &Linker<Host>, manifest_path: &Path) -> eyre::Result<Plugin> {
+        let manifest: Manifest = serde_json::from_reader(File::open(manifest_path)?)
+            .context("Failed to parse the manifest")?;
+        let module_path = manifest_path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(&manifest.module);
+        let module = Module::from_file(engine, &module_path)
+            .map_err(wasm_error)
+            .with_context(|| format!("Failed to compile {}", module_path.display()))?;
+        check_imports(&manifest, &module)?;
+
+        let host = Host {
+            plugin: manifest.name.clone(),
+            calls: Vec::new(),
+        };
+        let mut store = Store::new(engine, host);
+        store.set_fuel(FUEL_PER_CALL).map_err(wasm_error)?;
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .map_err(wasm_error)?;
+        info!(
+            capabilities = ?manifest.capabilities,
+            behaviors = ?manifest.behaviors,
+            "Loaded plugin '{}'",
+            manifest.name
+        );
+        Ok(Plugin {
+            name: manifest.name,
+            behaviors: manifest.behaviors,
+            events: manifest.events,
+            running: Mutex::new(Running { store, instance }),
+        })
+    }
+
+    /// Returns the calls the plugin queued, for the deck to carry out. Blocks while the plugin
+    /// runs, like [`Plugin::on_status`].
+    fn invoke(&self, kind: &str, params: &str) -> eyre::Result<Vec<Call>> {
+        let mut running = self.lock();
+        let running = &mut *running;
+        running.prepare()?;
+        let (kind_ptr, kind_len) = running.write_str(kind)?;
+        let (params_ptr, params_len) = running.write_str(params)?;
+        let invoke = running
+            .instance
+            .get_typed_func::<(u32, u32, u32, u32), u32>(&mut running.store, "invoke")
+            .map_err(wasm_error)?;
+        let code = invoke
+            .call(
+                &mut running.store,
+                (kind_ptr, kind_len, params_ptr, params_len),
+            )
+            .map_err(wasm_error)?;
+        let calls = std::mem::take(&mut running.store.data_mut().calls);
+        eyre::ensure!(code == 0, "Plugin '{}' failed with {code}", self.name);
+        Ok(calls)
+    }
+
+    fn on_status(&self, json: &str) -> eyre::Result<()> {
+        let mut running = self.lock();
+        let running = &mut *running;
+        running.prepare()?;
+        let (ptr, len) = running.write_str(json)?;
+        let on_status = running
+            .instance
+            .get_typed_func::<(u32, u32), ()>(&mut running.store, "on_status")
+            .map_err(wasm_error)?;
+        on_status
+            .call(&mut running.store, (ptr, len))
+            .map_err(wasm_error)?;
+        let ignored = std::mem::take(&mut running.store.data_mut().calls);
+        if !ignored.is_empty() {
+            warn!(
+                ?ignored,
+                "Plugin '{}' cannot act on the deck from on_status", self.name
+            );
+        }
+        Ok(())
+    }
+
+    /// The instance stays usable after a failed call, so a poisoned lock is no reason to stop.
+    fn lock(&self) -> MutexGuard<'_, Running> {
+        self.running.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Running {
+    fn prepare(&mut self) -> eyre::Result<()> {
+        self.store.data_mut().calls.clear();
+        self.store.set_fuel(FUEL_PER_CALL).map_err(wasm_error)
+    }
+
+    fn write_str(&mut self, s: &str) -> eyre::Result<(u32, u32)> {
+        let len = u32::try_from(s.len()).context("String too long for a plugin")?;
+        let alloc = self
+            .instance
+            .get_typed_func::<u32, u32>(&mut self.store, "alloc")
+            .map_err(wasm_error)?;
+        let ptr = alloc.call(&mut self.store, len).map_err(wasm_error)?;
+        let memory = self
+            .instance
+            .get_memory(&mut self.store, "memory")
+            .ok_or_eyre("Plugin exports no memory")?;
+        memory
+            .write(&mut self.store, ptr as usize, s.as_bytes())
+            .context("Plugin allocated memory outside of its memory")?;
+        Ok((ptr, len))
+    }
+}
+
+struct PluginBehavior {
+    plugin: Arc<Plugin>,
+    kind: String,
+    params: String,
+}
+
+impl Behavior for PluginBehavior {
+    fn invoke<'a>(
+        &'a self,
+        deck: &'a mut NoiseDeck,
+        button: &'a ButtonRef,
+    ) -> BoxFuture<'a, eyre::Result<BtnInvokeStatus>> {
+        Box::pin(async move {
+            let plugin = self.plugin.clone();
+            let (kind, params) = (self.kind.clone(), self.params.clone());
+            let calls =
+                tokio::task::spawn_blocking(move || plugin.invoke(&kind, &params)).await??;
+            carry_out(deck, button, calls).await
+        })
+    }
+}
+
+/// Checked up front, so that a plugin that asks for more than its manifest admits to does not
+/// load at all, instead of failing once it gets to the call.
+fn check_imports(manifest: &Manifest, module: &Module) -> eyre::Result<()> {
+    for import in module.imports() {
+        let name = import.name();
+        eyre::ensure!(
+            import.module() == HOST_MODULE,
+            "The plugin imports {}::{name}, but only {HOST_MODULE} functions are available",
+            import.module()
+        );
+        let capability = Capability::of_import(name)
+            .ok_or_else(|| eyre::eyre!("The plugin imports {name}, which {HOST_MODULE} lacks"))?;
+        eyre::ensure!(
+            manifest.capabilities.contains(&capability),
+            "The plugin imports {name}, but its manifest does not list the '{}' capability",
+            capability.name()
+        );
+    }
+    Ok(())
+}
+
+type CallWithArg = fn(String) -> Call;
+
+fn linker(engine: &Engine) -> eyre::Result<Linker<Host>> {
+    let mut linker = Linker::new(engine);
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "log",
+            |mut caller: Caller<'_, Host>, ptr: u32, len: u32| -> wasmtime::Result<()> {
+                let text = read_str(&mut caller, ptr, len)?;
+                info!("Plugin '{}': {text}", caller.data().plugin);
+                Ok(())
+            },
+        )
+        .map_err(wasm_error)?;
+    let with_arg: [(&str, CallWithArg); 5] = [
+        ("play", Call::Play),
+        ("stop", Call::Stop),
+        ("push", Call::Push),
+        ("goto", Call::Goto),
+        ("set_label", Call::SetLabel),
+    ];
+    for (name, call) in with_arg {
+        linker
+            .func_wrap(
+                HOST_MODULE,
+                name,
+                move |mut caller: Caller<'_, Host>, ptr: u32, len: u32| -> wasmtime::Result<()> {
+                    let arg = read_str(&mut caller, ptr, len)?;
+                    caller.data_mut().calls.push(call(arg));
+                    Ok(())
+                },
+            )
+            .map_err(wasm_error)?;
+    }
+    let without_arg = [
+        ("stop_all", Call::StopAll),
+        ("volume_up", Call::VolumeUp),
+        ("volume_down", Call::VolumeDown),
+        ("back", Call::Back),
+        ("home", Call::Home),
+    ];
+    for (name, call) in without_arg {
+        linker
+            .func_wrap(HOST_MODULE, name, move |mut caller: Caller<'_, Host>| {
+                caller.data_mut().calls.push(call.clone());
+            })
+            .map_err(wasm_error)?;
+    }
+    Ok(linker)
+}
+
+fn read_str(caller: &mut Caller<'_, Host>, ptr: u32, len: u32) -> wasmtime::Result<String> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| wasmtime::Error::msg("The plugin exports no memory"))?;
+    let start = ptr as usize;
+    let bytes = memory
+        .data(&caller)
+        .get(start..start.saturating_add(len as usize))
+        .ok_or_else(|| wasmtime::Error::msg("String outside of the plugin's memory"))?;
+    Ok(String::from_utf8(bytes.to_vec())?)
+}
+
+fn status_json(status: &MediaStatus) -> String {
+    let playback = match status.playback {
+        MediaPlayback::Playing => "playing",
+        MediaPlayback::Paused => "paused",
+        MediaPlayback::Stopped => "stopped",
+    };
+    let (label, path) = match &status.latest {
+        Some((label, path)) => (Some(label.as_str()), Some(path.display().to_string())),
+        None => (None, None),
+    };
+    serde_json::json!({ "playback": playback, "label": label, "path": path }).to_string()
+}
+
+/// wasmtime reports its errors as `anyhow` errors, which do not convert on their own.
+fn wasm_error(e: wasmtime::Error) -> eyre::Report {
+    eyre::eyre!("{e:#}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Call, load};
+    use std::path::{Path, PathBuf};
+
+    const MODULE: &str = r#"
+        (module
+          (import "noisedeck" "play" (func $play (param i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 16) "rain.mp3")
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "invoke") (param i32 i32 i32 i32) (result i32)
+            (call $play (i32.const 16) (i32.const 8))
+            (i32.const 0)))
+    "#;
+
+    fn write_plugin(dir: &Path, name: &str, capabilities: &str) -> eyre::Result<PathBuf> {
+        std::fs::write(dir.join("rain.wasm"), wat::parse_str(MODULE)?)?;
+        let manifest = dir.join("plugin.json");
+        std::fs::write(
+            &manifest,
+            format!(
+                r#"{{"name": "{name}", "module": "rain.wasm", "behaviors": ["rain"], "capabilities": {capabilities}}}"#
+            ),
+        )?;
+        Ok(manifest)
+    }
+
+    #[test]
+    fn test_plugin_queues_calls() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let manifest = write_plugin(dir.path(), "allowed", r#"["playback"]"#)?;
+        let plugins = load(&[manifest])?;
+        assert_eq!(
+            plugins[0].invoke("rain", "{}")?,
+            vec![Call::Play("rain.mp3".to_string())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_plugin_needs_capabilities_for_its_imports() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let manifest = write_plugin(dir.path(), "denied", r#"["log"]"#)?;
+        let Err(e) = load(&[manifest]) else {
+            eyre::bail!("Plugin should not load without the playback capability");
+        };
+        assert!(format!("{e:#}").contains("'playback' capability"));
+        Ok(())
+    }
+}
@@ -0,0 +1,567 @@
+//! Splits the daemon in two, for when the StreamDeck sits at the table and the speakers hang off a
+//! PC elsewhere. The daemon with `--remote-deck` runs the deck and the audio engine without a
+//! device of its own, and `noisedeck deck` runs next to the StreamDeck, draws its keys and reports
+//! the presses back.
+//!
+//! The two exchange lines of JSON over TCP: the deck's commands for its device loop as
+//! [`IpcCommand`]s, along with the data of the buttons they name, and the events that come back as
+//! [`IpcEvent`]s. The audio engine stays with the deck, which shares the tracks' state with it.
+//!
+//! A frontend has to know the daemon's `--remote-deck-token` to be served. Only one frontend is
+//! served at a time; the one that said hello last takes over.
+
+use crate::daemon::ui::{
+    ButtonData, ButtonId, ButtonRef, IpcCommand, IpcEvent, StripSegment, UiCommand, UiEvent,
+};
+use eyre::{Context, ContextCompat};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::time::timeout;
+use tracing::{debug, info, warn};
+
+/// A frontend that does not say hello by then is not one.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Far more than a hello with any token that one would type, and keeps whoever connects from
+/// making the daemon buffer without bound before the token is checked.
+const MAX_HELLO_LEN: u64 = 4096;
+
+#[derive(Debug, Serialize, Deserialize)]
+enum HostMessage {
+    /// Buttons that the frontend has not seen like this, ahead of the command that shows them.
+    Buttons(Vec<(ButtonId, ButtonData)>),
+    Command(IpcCommand),
+    /// Sent instead of the first page to a frontend that cannot show it, before hanging up.
+    Refused(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum DeckMessage {
+    /// The first message of a frontend, with the rows and columns of its keys.
+    Hello {
+        layout: (u8, u8),
+        token: String,
+    },
+    Event(IpcEvent),
+}
+
+/// Stands in for the device loop of a StreamDeck on the daemon's side.
+pub struct RemoteDeck {
+    command_tx: Sender<UiCommand>,
+}
+
+impl RemoteDeck {
+    pub async fn listen(
+        address: &str,
+        layout: (u8, u8),
+        token: String,
+        event_tx: Sender<UiEvent>,
+    ) -> eyre::Result<RemoteDeck> {
+        let listener = TcpListener::bind(address)
+            .await
+            .with_context(|| format!("Failed to listen for a remote deck on {address}"))?;
+        info!("Waiting for a remote deck on {}", listener.local_addr()?);
+        Ok(RemoteDeck::serve(listener, layout, token, event_tx))
+    }
+
+    fn serve(
+        listener: TcpListener,
+        layout: (u8, u8),
+        token: String,
+        event_tx: Sender<UiEvent>,
+    ) -> RemoteDeck {
+        let (command_tx, command_rx) = mpsc::channel(16);
+        let host = Host {
+            layout,
+            token: Arc::new(token),
+            event_tx,
+            page: vec![],
+            shown: HashMap::new(),
+            strip: None,
+        };
+        tokio::spawn(host.run(listener, command_rx));
+        RemoteDeck { command_tx }
+    }
+
+    /// Fails once the connection to the deck can no longer be served.
+    pub async fn send(&self, command: UiCommand) -> eyre::Result<()> {
+        self.command_tx
+            .send(command)
+            .await
+            .context("Stopped serving the remote deck")
+    }
+}
+
+struct Host {
+    layout: (u8, u8),
+    token: Arc<String>,
+    event_tx: Sender<UiEvent>,
+    page: Vec<Option<ButtonRef>>,
+    /// What the frontend was told that the buttons of the page show.
+    shown: HashMap<ButtonId, ButtonData>,
+    /// For a frontend that connects later.
+    strip: Option<Vec<StripSegment>>,
+}
+
+struct Frontend {
+    peer: SocketAddr,
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    write: OwnedWriteHalf,
+}
+
+impl Host {
+    async fn run(mut self, listener: TcpListener, mut command_rx: Receiver<UiCommand>) {
+        let mut frontend: Option<Frontend> = None;
+        // Frontends say hello on tasks of their own, so that one that keeps quiet does not hold up
+        // the deck
+        let (greeted_tx, mut greeted_rx) = mpsc::channel(4);
+        loop {
+            tokio::select! {
+                command = command_rx.recv() => {
+                    let Some(command) = command else {
+                        break;
+                    };
+                    let messages = self.handle_command(command).await;
+                    if let Some(f) = &mut frontend
+                        && let Err(e) = send_all(&mut f.write, &messages).await
+                    {
+                        warn!("Lost the remote deck at {}: {e:#}", f.peer);
+                        frontend = None;
+                    }
+                },
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        let (layout, token) = (self.layout, self.token.clone());
+                        let greeted_tx = greeted_tx.clone();
+                        tokio::spawn(async move {
+                            match hello(stream, peer, layout, &token).await {
+                                Ok(f) => {
+                                    // Only fails once the host stopped
+                                    let _ = greeted_tx.send(f).await;
+                                }
+                                Err(e) => warn!("Turned away a remote deck from {peer}: {e:#}"),
+                            }
+                        });
+                    }
+                    Err(e) => warn!(error = %e, "Error accepting a remote deck"),
+                },
+                Some(mut f) = greeted_rx.recv() => match self.welcome(&mut f).await {
+                    Ok(()) => {
+                        info!("Remote deck connected from {}", f.peer);
+                        frontend = Some(f);
+                    }
+                    Err(e) => warn!("Lost the remote deck at {}: {e:#}", f.peer),
+                },
+                message = async { receive(&mut frontend.as_mut().unwrap().lines).await },
+                    if frontend.is_some() =>
+                {
+                    match message {
+                        Ok(Some(DeckMessage::Event(event))) => {
+                            if self.event_tx.send(UiEvent::Ipc(event)).await.is_err() {
+                                debug!("Deck stopped, no longer serving the remote deck");
+                                break;
+                            }
+                        }
+                        Ok(Some(DeckMessage::Hello { .. })) => {
+                            warn!("Remote deck said hello twice");
+                        }
+                        Ok(None) => {
+                            info!("Remote deck disconnected");
+                            frontend = None;
+                        }
+                        Err(e) => {
+                            warn!("Lost the remote deck: {e:#}");
+                            frontend = None;
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    /// Brings a new frontend up to date with the page and the touch strip.
+    async fn welcome(&mut self, frontend: &mut Frontend) -> eyre::Result<()> {
+        self.shown.clear();
+        let mut messages = self
+            .handle_command(UiCommand::Flip(self.page.clone()))
+            .await;
+        if let Some(segments) = &self.strip {
+            messages.push(HostMessage::Command(IpcCommand::Strip(segments.clone())));
+        }
+        send_all(&mut frontend.write, &messages).await
+    }
+
+    async fn handle_command(&mut self, command: UiCommand) -> Vec<HostMessage> {
+        let changed = match &command {
+            UiCommand::Flip(page) => {
+                self.page = page.clone();
+                let on_page: HashSet<_> = page.iter().flatten().map(ButtonRef::id).collect();
+                self.shown.retain(|id, _| on_page.contains(id));
+                self.changed().await
+            }
+            UiCommand::Refresh => {
+                let changed = self.changed().await;
+                if changed.is_empty() {
+                    return vec![];
+                }
+                changed
+            }
+            UiCommand::Strip(segments) => {
+                self.strip = Some(segments.clone());
+                vec![]
+            }
+            UiCommand::Toast(..) => vec![],
+            // There is no second deck at the table, and campaigns are loaded by the daemon
+            UiCommand::FlipNowPlaying(_) | UiCommand::LoadCampaign(_) => return vec![],
+        };
+        let mut messages = Vec::with_capacity(2);
+        if !changed.is_empty() {
+            messages.push(HostMessage::Buttons(changed));
+        }
+        messages.push(HostMessage::Command(IpcCommand::from(&command)));
+        messages
+    }
+
+    /// The buttons of the page that the frontend has not seen like this.
+    async fn changed(&mut self) -> Vec<(ButtonId, ButtonData)> {
+        let mut changed = Vec::new();
+        for button in self.page.iter().flatten() {
+            let data = button.read().await;
+            if self.shown.get(&button.id()) != Some(&data) {
+                self.shown.insert(button.id(), data.clone());
+                changed.push((button.id(), data));
+            }
+        }
+        changed
+    }
+}
+
+/// Waits for a new frontend to say hello, and makes sure that it may be served and can show the
+/// pages.
+async fn hello(
+    stream: TcpStream,
+    peer: SocketAddr,
+    expected_layout: (u8, u8),
+    expected_token: &str,
+) -> eyre::Result<Frontend> {
+    let (read, mut write) = stream.into_split();
+    let mut read = BufReader::new(read);
+    let hello = timeout(HELLO_TIMEOUT, receive_hello(&mut read))
+        .await
+        .context("No hello")??;
+    let (layout, token) = match hello {
+        Some(DeckMessage::Hello { layout, token }) => (layout, token),
+        Some(message) => eyre::bail!("Expected a hello, got {message:?}"),
+        None => eyre::bail!("Hung up before saying hello"),
+    };
+    if !same_secret(&token, expected_token) {
+        send(&mut write, &HostMessage::Refused("Wrong token".to_string())).await?;
+        eyre::bail!("Wrong token, see --remote-deck-token");
+    }
+    if layout != expected_layout {
+        let (rows, cols) = layout;
+        let (page_rows, page_cols) = expected_layout;
+        let reason = format!(
+            "The pages are laid out for {page_rows}x{page_cols} keys rather than \
+             {rows}x{cols}, see --remote-deck-model"
+        );
+        send(&mut write, &HostMessage::Refused(reason.clone())).await?;
+        eyre::bail!(reason);
+    }
+    let lines = read.lines();
+    Ok(Frontend { peer, lines, write })
+}
+
+/// Like [`receive`], but only up to [`MAX_HELLO_LEN`], since the sender is not known to be a
+/// frontend yet.
+async fn receive_hello(read: &mut BufReader<OwnedReadHalf>) -> eyre::Result<Option<DeckMessage>> {
+    let mut line = String::new();
+    if read.take(MAX_HELLO_LEN).read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') {
+        eyre::bail!("Expected a hello of at most {MAX_HELLO_LEN} bytes");
+    }
+    let hello = serde_json::from_str(&line).context("Not a message of the remote deck")?;
+    Ok(Some(hello))
+}
+
+/// Compares all of both, so that how long it takes does not tell how much of a guess was right.
+fn same_secret(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+/// Connects to a daemon with `--remote-deck` and hands its commands to the device loop of a
+/// StreamDeck here, until either of them stops.
+pub async fn follow(
+    address: String,
+    token: String,
+    layout: (u8, u8),
+    command_tx: Sender<UiCommand>,
+    mut event_rx: Receiver<UiEvent>,
+) -> eyre::Result<()> {
+    let stream = TcpStream::connect(&address)
+        .await
+        .with_context(|| format!("Failed to connect to the daemon on {address}"))?;
+    info!("Connected to the daemon on {address}");
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    send(&mut write, &DeckMessage::Hello { layout, token }).await?;
+
+    // Stand-ins for the daemon's buttons, which keep their ids, so that presses name the
+    // daemon's buttons
+    let mut buttons: HashMap<ButtonId, ButtonRef> = HashMap::new();
+    loop {
+        tokio::select! {
+            message = receive(&mut lines) => {
+                let message = message?
+                    .with_context(|| format!("The daemon on {address} hung up"))?;
+                let command = match message {
+                    HostMessage::Buttons(changed) => {
+                        for (id, data) in changed {
+                            match buttons.get(&id) {
+                                Some(button) => button.show(data).await,
+                                None => {
+                                    buttons.insert(id, ButtonRef::detached(id, data));
+                                }
+                            }
+                        }
+                        continue;
+                    }
+                    HostMessage::Command(IpcCommand::Flip(page)) => {
+                        buttons.retain(|id, _| page.contains(&Some(*id)));
+                        let keys = page
+                            .iter()
+                            .map(|id| id.and_then(|id| buttons.get(&id).cloned()))
+                            .collect();
+                        UiCommand::Flip(keys)
+                    }
+                    HostMessage::Command(IpcCommand::Refresh) => UiCommand::Refresh,
+                    HostMessage::Command(IpcCommand::Toast(text, duration)) => {
+                        UiCommand::Toast(text, duration)
+                    }
+                    HostMessage::Command(IpcCommand::Strip(segments)) => UiCommand::Strip(segments),
+                    HostMessage::Command(
+                        IpcCommand::FlipNowPlaying(_) | IpcCommand::LoadCampaign(_),
+                    ) => continue,
+                    HostMessage::Refused(reason) => {
+                        eyre::bail!("The daemon on {address} refused this deck: {reason}")
+                    }
+                };
+                if command_tx.send(command).await.is_err() {
+                    // The device loop stopped and has its own story to tell
+                    return Ok(());
+                }
+            },
+            event = event_rx.recv() => {
+                let Some(event) = event else {
+                    return Ok(());
+                };
+                if let Some(event) = IpcEvent::of(&event) {
+                    send(&mut write, &DeckMessage::Event(event)).await?;
+                }
+            },
+        }
+    }
+}
+
+async fn send(write: &mut OwnedWriteHalf, message: &impl Serialize) -> eyre::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    write.write_all(&line).await?;
+    Ok(())
+}
+
+async fn send_all(write: &mut OwnedWriteHalf, messages: &[HostMessage]) -> eyre::Result<()> {
+    for message in messages {
+        send(write, message).await?;
+    }
+    Ok(())
+}
+
+/// `None` once the other side hung up.
+async fn receive<T: DeserializeOwned>(
+    lines: &mut Lines<BufReader<OwnedReadHalf>>,
+) -> eyre::Result<Option<T>> {
+    match lines.next_line().await? {
+        Some(line) => Ok(Some(
+            serde_json::from_str(&line).context("Not a message of the remote deck")?,
+        )),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RemoteDeck, follow};
+    use crate::daemon::ui::{ButtonData, ButtonId, ButtonRef, IpcEvent, UiCommand, UiEvent};
+    use assert_matches::assert_matches;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc::{self, Receiver};
+    use tokio::time::timeout;
+
+    const TOKEN: &str = "sesame";
+
+    async fn next<T>(rx: &mut Receiver<T>) -> eyre::Result<T> {
+        timeout(Duration::from_secs(2), rx.recv())
+            .await?
+            .ok_or_else(|| eyre::eyre!("channel closed"))
+    }
+
+    fn labelled(label: &str) -> ButtonData {
+        ButtonData {
+            label: Arc::new(label.to_string()),
+            ..ButtonData::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_the_frontend_shows_the_page_and_presses_its_buttons() -> eyre::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?.to_string();
+        let (host_event_tx, mut host_event_rx) = mpsc::channel(4);
+        let remote = RemoteDeck::serve(listener, (3, 5), TOKEN.to_string(), host_event_tx);
+        let rain = ButtonRef::detached(ButtonId::default(), labelled("Rain"));
+        remote
+            .send(UiCommand::Flip(vec![
+                Some(rain.clone()),
+                None,
+                Some(rain.clone()),
+            ]))
+            .await?;
+
+        let (command_tx, mut command_rx) = mpsc::channel(4);
+        let (event_tx, event_rx) = mpsc::channel(4);
+        tokio::spawn(follow(
+            address,
+            TOKEN.to_string(),
+            (3, 5),
+            command_tx,
+            event_rx,
+        ));
+        let page = assert_matches!(next(&mut command_rx).await?, UiCommand::Flip(page) => page);
+        assert_eq!(page.len(), 3);
+        assert!(page[1].is_none());
+        let shown = page[0].clone().expect("rain is on the first key");
+        assert_eq!(page[2].as_ref(), Some(&shown));
+        assert_eq!(*shown.read().await.label, "Rain");
+
+        rain.show(labelled("Rain 0:42")).await;
+        remote.send(UiCommand::Refresh).await?;
+        assert_matches!(next(&mut command_rx).await?, UiCommand::Refresh);
+        assert_eq!(*shown.read().await.label, "Rain 0:42");
+
+        event_tx.send(UiEvent::ButtonTap(shown.clone())).await?;
+        // The deck knows which of its buttons that is
+        assert_matches!(
+            next(&mut host_event_rx).await?,
+            UiEvent::Ipc(IpcEvent::ButtonTap(id)) if id == rain.id()
+        );
+
+        // Buttons that stay where they are keep their stand-ins
+        let thunder = ButtonRef::detached(ButtonId::default(), labelled("Thunder"));
+        remote
+            .send(UiCommand::Flip(vec![Some(rain.clone()), Some(thunder)]))
+            .await?;
+        let page = assert_matches!(next(&mut command_rx).await?, UiCommand::Flip(page) => page);
+        assert_eq!(page[0].as_ref(), Some(&shown));
+        let thunder = page[1].clone().expect("thunder is on the second key");
+        assert_eq!(*thunder.read().await.label, "Thunder");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_a_frontend_with_other_keys_is_refused() -> eyre::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?.to_string();
+        let (host_event_tx, _host_event_rx) = mpsc::channel(4);
+        let _remote = RemoteDeck::serve(listener, (3, 5), TOKEN.to_string(), host_event_tx);
+
+        let (command_tx, _command_rx) = mpsc::channel(4);
+        let (_event_tx, event_rx) = mpsc::channel(4);
+        let followed = timeout(
+            Duration::from_secs(2),
+            follow(address, TOKEN.to_string(), (2, 4), command_tx, event_rx),
+        )
+        .await?;
+        let error = followed.expect_err("a Plus cannot show pages for 3x5 keys");
+        assert!(format!("{error:#}").contains("refused this deck"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_a_frontend_without_the_token_is_refused() -> eyre::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?.to_string();
+        let (host_event_tx, _host_event_rx) = mpsc::channel(4);
+        let _remote = RemoteDeck::serve(listener, (3, 5), TOKEN.to_string(), host_event_tx);
+
+        let (command_tx, _command_rx) = mpsc::channel(4);
+        let (_event_tx, event_rx) = mpsc::channel(4);
+        let followed = timeout(
+            Duration::from_secs(2),
+            follow(address, "guess".to_string(), (3, 5), command_tx, event_rx),
+        )
+        .await?;
+        let error = followed.expect_err("the token is wrong");
+        assert!(format!("{error:#}").contains("Wrong token"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_an_endless_hello_is_cut_off() -> eyre::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?.to_string();
+        let (host_event_tx, _host_event_rx) = mpsc::channel(4);
+        let _remote = RemoteDeck::serve(listener, (3, 5), TOKEN.to_string(), host_event_tx);
+
+        let mut client = TcpStream::connect(&address).await?;
+        // The daemon may hang up before all of it arrives
+        let _ = client.write_all(&[b'x'; 64 * 1024]).await;
+        // Well before the client runs out of time to say hello
+        let mut rest = Vec::new();
+        let read = timeout(Duration::from_secs(2), client.read_to_end(&mut rest)).await?;
+        assert!(read.is_err() || rest.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_a_silent_client_does_not_hold_up_the_deck() -> eyre::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?.to_string();
+        let (host_event_tx, _host_event_rx) = mpsc::channel(4);
+        let remote = RemoteDeck::serve(listener, (3, 5), TOKEN.to_string(), host_event_tx);
+        let _silent = TcpStream::connect(&address).await?;
+        let rain = ButtonRef::detached(ButtonId::default(), labelled("Rain"));
+        remote.send(UiCommand::Flip(vec![Some(rain)])).await?;
+
+        let (command_tx, mut command_rx) = mpsc::channel(4);
+        let (_event_tx, event_rx) = mpsc::channel(4);
+        tokio::spawn(follow(
+            address,
+            TOKEN.to_string(),
+            (3, 5),
+            command_tx,
+            event_rx,
+        ));
+        // Well before the silent client runs out of time to say hello
+        let page = assert_matches!(next(&mut command_rx).await?, UiCommand::Flip(page) => page);
+        assert_eq!(page.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,468 @@
+//! Renders key images on a dedicated thread. Shaping text with cosmic_text takes long enough
+//! that doing it in the device loop would delay button presses during big refreshes.
+
+use crate::daemon::ui::{ButtonData, ButtonStyle, StripSegment};
+use cosmic_text::{Attrs, Buffer, Color, FontSystem, Metrics, Shaping, SwashCache, Weight};
+use eyre::Context;
+use image::imageops::{crop_imm, overlay};
+use image::{DynamicImage, ImageBuffer, Rgb};
+use imageproc::image::RgbImage;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::sync::OnceLock;
+use tokio::sync::mpsc::{Sender, channel};
+use tracing::{instrument, trace, warn};
+
+/// Width and height of a key's image in pixels, which toasts span several of.
+const KEY_SIZE: u32 = 72;
+
+/// How far a scrolling label moves with each step, in pixels.
+const MARQUEE_STEP: i32 = 12;
+/// Between the end of a scrolling label and its next start, in pixels.
+const MARQUEE_GAP: i32 = 24;
+/// Of the label of a playing button; the rest of the key shows the notification.
+const MARQUEE_LINES: usize = 2;
+
+/// Shortcodes for what sound boards show most, next to the usual ones such as `:fire:`.
+const GLYPHS: [(&str, &str); 3] = [("play", "▶️"), ("loop", "🔁"), ("note", "🎵")];
+
+/// How the keys and the touch strip look.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DisplayMode {
+    #[default]
+    Standard,
+    /// Bigger and bolder text in black and white, for reading the deck at arm's length. Warnings
+    /// and toasts stay amber with black text, which reads as well and still stands out.
+    HighContrast,
+}
+
+/// How much a text stands out from the text next to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Emphasis {
+    Strong,
+    Normal,
+}
+
+/// A key whose image is out of date.
+pub struct RenderJob {
+    pub key: usize,
+    pub button: ButtonData,
+}
+
+pub struct Rendered {
+    pub key: usize,
+    /// What the image shows, so that images outdated by a later refresh can be dropped.
+    pub button: ButtonData,
+    pub image: DynamicImage,
+}
+
+pub enum RenderRequest {
+    /// Sent in batches, one per refresh, so that each refresh ends in a single flush.
+    Buttons(Vec<RenderJob>),
+    /// A message spread across `keys` keys side by side.
+    Toast { text: String, keys: usize },
+    /// The touch strip, with the segments side by side.
+    Strip {
+        segments: Vec<StripSegment>,
+        size: (u32, u32),
+    },
+}
+
+pub enum RenderResult {
+    Buttons(Vec<Rendered>),
+    /// One image per key, from left to right.
+    Toast(Vec<DynamicImage>),
+    Strip {
+        /// What the image shows, so that an image outdated by a later update can be dropped.
+        segments: Vec<StripSegment>,
+        image: DynamicImage,
+    },
+}
+
+/// The results come back on `rendered_tx` in the order of the requests.
+pub fn spawn(
+    font_system: FontSystem,
+    mode: DisplayMode,
+    rendered_tx: Sender<RenderResult>,
+) -> eyre::Result<Sender<RenderRequest>> {
+    let (job_tx, mut job_rx) = channel::<RenderRequest>(16);
+    let mut renderer = ButtonRenderer {
+        font_system,
+        swash_cache: SwashCache::new(),
+        mode,
+    };
+    std::thread::Builder::new()
+        .name("render".to_string())
+        .spawn(move || {
+            while let Some(request) = job_rx.blocking_recv() {
+                let result = match request {
+                    RenderRequest::Buttons(jobs) => RenderResult::Buttons(
+                        jobs.into_iter()
+                            .map(|RenderJob { key, button }| Rendered {
+                                key,
+                                image: renderer.render_button_image(&button),
+                                button,
+                            })
+                            .collect(),
+                    ),
+                    RenderRequest::Toast { text, keys } => {
+                        RenderResult::Toast(renderer.render_toast(&text, keys))
+                    }
+                    RenderRequest::Strip { segments, size } => RenderResult::Strip {
+                        image: renderer.render_strip(&segments, size),
+                        segments,
+                    },
+                };
+                if rendered_tx.blocking_send(result).is_err() {
+                    break;
+                }
+            }
+            trace!("Render channel closed, stopping render thread");
+        })
+        .context("Failed to start render thread")?;
+    Ok(job_tx)
+}
+
+struct ButtonRenderer {
+    font_system: FontSystem,
+    swash_cache: SwashCache,
+    mode: DisplayMode,
+}
+
+impl ButtonRenderer {
+    /// Of the text on keys and the strip. Three lines still fit on a key in high contrast.
+    fn metrics(&self) -> Metrics {
+        match self.mode {
+            DisplayMode::Standard => Metrics::new(16.0, 24.0),
+            DisplayMode::HighContrast => Metrics::new(20.0, 24.0),
+        }
+    }
+
+    fn weight(&self, emphasis: Emphasis) -> Weight {
+        match (self.mode, emphasis) {
+            (DisplayMode::Standard, Emphasis::Strong) => Weight::EXTRA_BOLD,
+            (DisplayMode::Standard, Emphasis::Normal) => Weight::NORMAL,
+            (DisplayMode::HighContrast, Emphasis::Strong) => Weight::BLACK,
+            (DisplayMode::HighContrast, Emphasis::Normal) => Weight::BOLD,
+        }
+    }
+
+    #[instrument(skip(self), level = "TRACE")]
+    fn render_button_image(&mut self, button: &ButtonData) -> DynamicImage {
+        let mut bg_color = Rgb([0u8, 0u8, 0u8]);
+        let mut text_color = Rgb([0xFFu8, 0xFFu8, 0xFFu8]);
+        if button.notification.is_some() {
+            std::mem::swap(&mut bg_color, &mut text_color);
+        };
+        match button.style {
+            ButtonStyle::Normal => {}
+            ButtonStyle::Warning => {
+                // Amber stands out from both the idle and the playing look
+                bg_color = Rgb([0xFFu8, 0xA0u8, 0x00u8]);
+                text_color = Rgb([0u8, 0u8, 0u8]);
+            }
+            ButtonStyle::Pressed => std::mem::swap(&mut bg_color, &mut text_color),
+            ButtonStyle::Cooldown | ButtonStyle::Disabled
+                if self.mode == DisplayMode::HighContrast =>
+            {
+                // Grey text would be hard to read, a frame sets the key apart instead
+                bg_color = Rgb([0u8, 0u8, 0u8]);
+                text_color = Rgb([0xFFu8, 0xFFu8, 0xFFu8]);
+            }
+            ButtonStyle::Cooldown | ButtonStyle::Disabled => {
+                // Greyed out, like a disabled control
+                bg_color = Rgb([0x40u8, 0x40u8, 0x40u8]);
+                text_color = Rgb([0xA0u8, 0xA0u8, 0xA0u8]);
+            }
+        }
+        let mut image = RgbImage::from_pixel(72, 72, bg_color);
+        if self.mode == DisplayMode::HighContrast
+            && matches!(button.style, ButtonStyle::Cooldown | ButtonStyle::Disabled)
+        {
+            draw_frame(&mut image, text_color);
+        }
+        let metrics = self.metrics();
+        let text_color = Color::rgb(text_color.0[0], text_color.0[1], text_color.0[2]);
+
+        let label = expand_shortcodes(&button.label);
+        // The notification stands out rather than the label
+        let weight = self.weight(match button.notification {
+            Some(_) => Emphasis::Normal,
+            None => Emphasis::Strong,
+        });
+        match button.marquee {
+            Some(step) if self.line_count(&label, metrics, weight) > MARQUEE_LINES => {
+                self.render_marquee(
+                    &mut image, &label, metrics, bg_color, text_color, weight, step,
+                );
+            }
+            _ => self.render_text(
+                &mut image, &label, metrics, bg_color, text_color, weight, 72,
+            ),
+        }
+        if let Some(notification) = &button.notification {
+            self.render_text(
+                &mut image,
+                notification,
+                metrics,
+                bg_color,
+                text_color,
+                self.weight(Emphasis::Strong),
+                32,
+            );
+        }
+
+        image.into()
+    }
+
+    /// Renders one wide image so that the text flows across the gaps between the keys.
+    #[instrument(skip(self), level = "TRACE")]
+    fn render_toast(&mut self, text: &str, keys: usize) -> Vec<DynamicImage> {
+        let bg_color = Rgb([0xFFu8, 0xA0u8, 0x00u8]);
+        let width = KEY_SIZE * keys as u32;
+        let mut image = RgbImage::from_pixel(width, KEY_SIZE, bg_color);
+        self.render_text(
+            &mut image,
+            text,
+            match self.mode {
+                DisplayMode::Standard => Metrics::new(16.0, 20.0),
+                DisplayMode::HighContrast => Metrics::new(20.0, 24.0),
+            },
+            bg_color,
+            Color::rgb(0, 0, 0),
+            self.weight(Emphasis::Strong),
+            72,
+        );
+        (0..keys as u32)
+            .map(|i| {
+                crop_imm(&image, i * KEY_SIZE, 0, KEY_SIZE, KEY_SIZE)
+                    .to_image()
+                    .into()
+            })
+            .collect()
+    }
+
+    /// Segments are as tall as a key, centered vertically, so that text looks the same as on
+    /// the keys.
+    #[instrument(skip(self), level = "TRACE")]
+    fn render_strip(
+        &mut self,
+        segments: &[StripSegment],
+        (width, height): (u32, u32),
+    ) -> DynamicImage {
+        let bg_color = Rgb([0u8, 0u8, 0u8]);
+        let text_color = Color::rgb(0xFF, 0xFF, 0xFF);
+        let metrics = self.metrics();
+        let mut strip = RgbImage::from_pixel(width, height, bg_color);
+        let segment_width = width / segments.len().max(1) as u32;
+        for (i, segment) in segments.iter().enumerate() {
+            let mut image = RgbImage::from_pixel(segment_width, KEY_SIZE, bg_color);
+            self.render_text(
+                &mut image,
+                &segment.label,
+                metrics,
+                bg_color,
+                text_color,
+                self.weight(Emphasis::Normal),
+                72,
+            );
+            self.render_text(
+                &mut image,
+                &segment.detail,
+                metrics,
+                bg_color,
+                text_color,
+                self.weight(Emphasis::Strong),
+                32,
+            );
+            let y = i64::from(height.saturating_sub(KEY_SIZE) / 2);
+            overlay(&mut strip, &image, i as i64 * i64::from(segment_width), y);
+        }
+        strip.into()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_text(
+        &mut self,
+        image: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+        text: &str,
+        metrics: Metrics,
+        bg_color: Rgb<u8>,
+        text_color: Color,
+        weight: Weight,
+        height: i32,
+    ) {
+        let width = image.width() as i32;
+        let mut buffer = Buffer::new(&mut self.font_system, metrics);
+        let mut buffer = buffer.borrow_with(&mut self.font_system);
+        buffer.set_size(Some((width - 2) as f32), Some((height - 2) as f32));
+        let mut attrs = Attrs::new();
+        attrs.weight = weight;
+        buffer.set_text(text, &attrs, Shaping::Advanced);
+
+        buffer.shape_until_scroll(true);
+        let swash_cache = &mut self.swash_cache;
+        buffer.draw(swash_cache, text_color, |x, y, _w, _h, color| {
+            let x = x + 1;
+            let y = y + 1 + (72 - height);
+            if x < 0 || y < 0 || x > width - 1 || y > 71 {
+                if x < -1 || y < -1 || x > width || y > 72 {
+                    warn!("Out of bounds: x: {}, y: {}", x, y);
+                }
+                return;
+            }
+            image.put_pixel(x as u32, y as u32, blend(color, bg_color))
+        });
+    }
+
+    /// How many lines the text of a key wraps to.
+    fn line_count(&mut self, text: &str, metrics: Metrics, weight: Weight) -> usize {
+        let mut buffer = Buffer::new(&mut self.font_system, metrics);
+        let mut buffer = buffer.borrow_with(&mut self.font_system);
+        buffer.set_size(Some((KEY_SIZE - 2) as f32), None);
+        let mut attrs = Attrs::new();
+        attrs.weight = weight;
+        buffer.set_text(text, &attrs, Shaping::Advanced);
+        buffer.shape_until_scroll(true);
+        buffer.layout_runs().count()
+    }
+
+    /// Draws the text as a single line, shifted left by `step` steps and wrapping around, so
+    /// that successive steps scroll through a label too long for the key.
+    #[allow(clippy::too_many_arguments)]
+    fn render_marquee(
+        &mut self,
+        image: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+        text: &str,
+        metrics: Metrics,
+        bg_color: Rgb<u8>,
+        text_color: Color,
+        weight: Weight,
+        step: u32,
+    ) {
+        let width = image.width() as i32;
+        let mut buffer = Buffer::new(&mut self.font_system, metrics);
+        let mut buffer = buffer.borrow_with(&mut self.font_system);
+        buffer.set_size(None, Some(metrics.line_height));
+        let mut attrs = Attrs::new();
+        attrs.weight = weight;
+        // Line breaks of the label would end up stacked on top of each other
+        buffer.set_text(&text.replace('\n', " "), &attrs, Shaping::Advanced);
+        buffer.shape_until_scroll(true);
+        let text_width = buffer
+            .layout_runs()
+            .map(|run| run.line_w)
+            .fold(0.0, f32::max);
+        let period = text_width.ceil() as i32 + MARQUEE_GAP;
+        let offset = (step % period as u32) as i32 * MARQUEE_STEP % period;
+        let swash_cache = &mut self.swash_cache;
+        buffer.draw(swash_cache, text_color, |x, y, _w, _h, color| {
+            let y = y + 1;
+            if !(0..KEY_SIZE as i32).contains(&y) {
+                return;
+            }
+            // The end of one pass and the start of the next can both be on the key
+            for x in [x + 1 - offset, x + 1 - offset + period] {
+                if (0..width).contains(&x) {
+                    image.put_pixel(x as u32, y as u32, blend(color, bg_color));
+                }
+            }
+        });
+    }
+}
+
+/// Replaces shortcodes such as `:fire:` with their emoji, which the bundled emoji font draws,
+/// so that labels can ask for icons without an emoji keyboard. Unknown ones, such as the
+/// `:30:` of a time, stay as they are.
+fn expand_shortcodes(text: &str) -> Cow<'_, str> {
+    static SHORTCODE: OnceLock<Regex> = OnceLock::new();
+    let shortcode = SHORTCODE
+        .get_or_init(|| Regex::new(r":([a-z0-9_+-]+):").expect("Regular expression to be valid"));
+    shortcode.replace_all(text, |captures: &Captures| {
+        let name = &captures[1];
+        GLYPHS
+            .iter()
+            .find(|(glyph, _)| *glyph == name)
+            .map(|(_, emoji)| *emoji)
+            .or_else(|| emojis::get_by_shortcode(name).map(emojis::Emoji::as_str))
+            .unwrap_or(&captures[0])
+            .to_string()
+    })
+}
+
+/// Along the edges of the key.
+fn draw_frame(image: &mut RgbImage, color: Rgb<u8>) {
+    const THICKNESS: u32 = 3;
+    let (width, height) = image.dimensions();
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        if x < THICKNESS || y < THICKNESS || x >= width - THICKNESS || y >= height - THICKNESS {
+            *pixel = color;
+        }
+    }
+}
+
+fn blend(color: Color, bg_color: Rgb<u8>) -> Rgb<u8> {
+    let alpha_f = color.a() as f32 / 255.0;
+    Rgb([
+        (color.r() as f32 * alpha_f + bg_color.0[0] as f32 * (1.0 - alpha_f)) as u8,
+        (color.g() as f32 * alpha_f + bg_color.0[1] as f32 * (1.0 - alpha_f)) as u8,
+        (color.b() as f32 * alpha_f + bg_color.0[2] as f32 * (1.0 - alpha_f)) as u8,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ButtonRenderer, DisplayMode, expand_shortcodes};
+    use crate::daemon::ui::{ButtonData, ButtonStyle};
+    use cosmic_text::{FontSystem, SwashCache};
+    use image::Rgb;
+
+    #[test]
+    fn test_shortcodes_become_emoji() {
+        assert_eq!(
+            expand_shortcodes(":fire: Campfire :loop:"),
+            "🔥 Campfire 🔁"
+        );
+        assert_eq!(expand_shortcodes(":note::play:"), "🎵▶️");
+        assert_eq!(
+            expand_shortcodes("Ends 1:30:00 :nonsense:"),
+            "Ends 1:30:00 :nonsense:"
+        );
+    }
+
+    #[test]
+    fn test_disabled_keys_are_framed_in_high_contrast() {
+        let mut db = cosmic_text::fontdb::Database::new();
+        db.load_font_data(Vec::from(include_bytes!(
+            "../../font/noto-sans/static/NotoSans-Medium.ttf"
+        )));
+        db.set_sans_serif_family("Noto Sans".to_owned());
+        let mut renderer = ButtonRenderer {
+            font_system: FontSystem::new_with_locale_and_db("en-US".to_owned(), db),
+            swash_cache: SwashCache::new(),
+            mode: DisplayMode::HighContrast,
+        };
+        let button = ButtonData {
+            label: "Thunder".to_string().into(),
+            style: ButtonStyle::Disabled,
+            ..Default::default()
+        };
+
+        let image = renderer.render_button_image(&button).into_rgb8();
+
+        let (white, black) = (Rgb([0xFF, 0xFF, 0xFF]), Rgb([0, 0, 0]));
+        assert_eq!(*image.get_pixel(0, 0), white, "frame");
+        assert_eq!(*image.get_pixel(71, 71), white, "frame");
+        assert_eq!(*image.get_pixel(4, 4), black, "background");
+        let inside = image
+            .enumerate_pixels()
+            .filter(|&(x, y, _)| (4..68).contains(&x) && (4..68).contains(&y))
+            .map(|(_, _, &pixel)| pixel)
+            .collect::<Vec<_>>();
+        assert!(inside.contains(&white), "white text");
+        assert!(
+            inside.iter().all(|Rgb([r, g, b])| r == g && g == b),
+            "grey levels only"
+        );
+    }
+}
@@ -0,0 +1,890 @@
+//! Bitfocus Companion's line-based Satellite protocol, both ways round, for AV techs who run the
+//! rest of the show from Companion.
+//!
+//! As the server, the daemon takes the place of Companion and serves the deck to Satellite
+//! surfaces, e.g. a Stream Deck on the desk of the AV tech: a surface with the same keys as the
+//! StreamDeck shows what it shows, and presses on it count like presses on the deck. The protocol
+//! has no means to authenticate a client, so only clients on this machine or on the addresses of
+//! `--satellite-peer` are served. Only one surface is served at a time; the others are turned
+//! away until it goes.
+//!
+//! As a client, the daemon hands a second StreamDeck to Companion as a surface of its own, see
+//! [`CompanionSurface`], so that Companion's pages sit next to the deck.
+
+use super::backend::{DeckBackend, KeyEvent, KeyReader, StreamDeck};
+use base64::prelude::{BASE64_STANDARD, Engine};
+use eyre::{Context, ContextCompat};
+use image::imageops::FilterType;
+use image::{DynamicImage, RgbImage};
+use std::collections::{HashMap, HashSet};
+use std::iter;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// The version of the protocol that surfaces check before they add themselves.
+const API_VERSION: &str = "1.5.1";
+
+/// Larger than the keys of any surface, and keeps a client from asking for huge images.
+const MAX_BITMAP_SIZE: u32 = 512;
+
+/// Like the wait for presses on a StreamDeck.
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Lines queued for a client, enough for a few pages. A client that falls further behind loses
+/// its surface rather than the daemon's memory.
+const QUEUED_LINES: usize = 128;
+
+/// Key images that the StreamDeck handed to Companion asks for.
+const COMPANION_BITMAP_SIZE: u32 = 72;
+
+/// Companion lets go of surfaces that it does not hear from for a while.
+const COMPANION_PING_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Companion may be restarted in the middle of the show.
+const COMPANION_RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Clones show the same keys, so that the surfaces stay connected while the StreamDeck that the
+/// daemon mirrors to them is unplugged.
+#[derive(Clone)]
+pub struct Satellite {
+    layout: (u8, u8),
+    shared: Arc<Mutex<Shared>>,
+    events: Arc<tokio::sync::Mutex<Receiver<KeyEvent>>>,
+}
+
+struct Shared {
+    /// What the keys show, so that a surface that is added later starts out on the same page.
+    keys: Vec<Option<DynamicImage>>,
+    brightness: u8,
+    surface: Option<Surface>,
+}
+
+struct Surface {
+    device_id: String,
+    /// Width and height of the key images that the surface takes.
+    bitmap_size: u32,
+    lines: Sender<String>,
+}
+
+impl Surface {
+    /// Fails once the client falls behind.
+    fn send(&self, line: String) -> Result<(), FallenBehind> {
+        send(&self.lines, line)
+    }
+
+    fn key_state(&self, key: usize, image: Option<&DynamicImage>) -> String {
+        let size = self.bitmap_size;
+        let pixels = match image {
+            Some(image) => image
+                .resize_exact(size, size, FilterType::Triangle)
+                .to_rgb8()
+                .into_raw(),
+            None => vec![0; (size * size * 3) as usize],
+        };
+        format!(
+            "KEY-STATE DEVICEID={} KEY={key} TYPE=BUTTON BITMAP={}",
+            self.device_id,
+            BASE64_STANDARD.encode(pixels)
+        )
+    }
+}
+
+struct FallenBehind;
+
+/// A connection that went away forgets its surface on its own.
+fn send(lines: &Sender<String>, mut line: String) -> Result<(), FallenBehind> {
+    line.push('\n');
+    match lines.try_send(line) {
+        Ok(()) => Ok(()),
+        Err(TrySendError::Full(_)) => Err(FallenBehind),
+        Err(TrySendError::Closed(_)) => {
+            debug!("Not sending to a Companion Satellite client that disconnected");
+            Ok(())
+        }
+    }
+}
+
+impl Shared {
+    /// Sends to the surface that is served, and lets go of it once it falls behind.
+    fn send_to_surface(&mut self, line: impl FnOnce(&Surface) -> String) {
+        let Some(surface) = &self.surface else {
+            return;
+        };
+        if surface.send(line(surface)).is_err() {
+            warn!(
+                "Companion Satellite surface {} falls behind, no longer serving it",
+                surface.device_id
+            );
+            self.surface = None;
+        }
+    }
+}
+
+impl Satellite {
+    /// Serves clients on this machine, and on `peers`.
+    pub async fn listen(
+        address: &str,
+        layout: (u8, u8),
+        peers: Vec<IpAddr>,
+    ) -> eyre::Result<Satellite> {
+        let listener = TcpListener::bind(address)
+            .await
+            .with_context(|| format!("Failed to listen for Companion Satellite on {address}"))?;
+        info!(
+            "Listening for Companion Satellite surfaces on {}",
+            listener.local_addr()?
+        );
+        Ok(Satellite::serve(listener, layout, peers))
+    }
+
+    fn serve(listener: TcpListener, layout: (u8, u8), peers: Vec<IpAddr>) -> Satellite {
+        let (rows, cols) = layout;
+        let shared = Arc::new(Mutex::new(Shared {
+            keys: vec![None; usize::from(rows) * usize::from(cols)],
+            brightness: 100,
+            surface: None,
+        }));
+        let (events_tx, events_rx) = mpsc::channel(64);
+        tokio::spawn(accept(listener, shared.clone(), events_tx, layout, peers));
+        Satellite {
+            layout,
+            shared,
+            events: Arc::new(tokio::sync::Mutex::new(events_rx)),
+        }
+    }
+
+    fn with_shared<R>(&self, f: impl FnOnce(&mut Shared) -> R) -> R {
+        f(&mut self.shared.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    fn set_key(&self, key: u8, image: Option<DynamicImage>) {
+        self.with_shared(|shared| {
+            let key = usize::from(key);
+            if key >= shared.keys.len() {
+                return;
+            }
+            shared.send_to_surface(|surface| surface.key_state(key, image.as_ref()));
+            if let Some(slot) = shared.keys.get_mut(key) {
+                *slot = image;
+            }
+        });
+    }
+}
+
+impl DeckBackend for Satellite {
+    type Reader = SatelliteReader;
+
+    fn key_layout(&self) -> (u8, u8) {
+        self.layout
+    }
+
+    async fn set_brightness(&self, percent: u8) -> eyre::Result<()> {
+        self.with_shared(|shared| {
+            shared.brightness = percent;
+            shared.send_to_surface(|surface| {
+                format!("BRIGHTNESS DEVICEID={} VALUE={percent}", surface.device_id)
+            });
+        });
+        Ok(())
+    }
+
+    async fn set_key_image(&self, key: u8, image: DynamicImage) -> eyre::Result<()> {
+        self.set_key(key, Some(image));
+        Ok(())
+    }
+
+    async fn clear_key(&self, key: u8) -> eyre::Result<()> {
+        self.set_key(key, None);
+        Ok(())
+    }
+
+    async fn clear_all_keys(&self) -> eyre::Result<()> {
+        self.with_shared(|shared| {
+            shared.keys.fill(None);
+            shared.send_to_surface(|surface| format!("KEYS-CLEAR DEVICEID={}", surface.device_id));
+        });
+        Ok(())
+    }
+
+    /// Key states are sent as they change.
+    async fn flush(&self) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    fn reader(&self) -> SatelliteReader {
+        SatelliteReader(self.events.clone())
+    }
+
+    async fn shut_down(&self) -> eyre::Result<()> {
+        self.clear_all_keys().await
+    }
+}
+
+pub struct SatelliteReader(Arc<tokio::sync::Mutex<Receiver<KeyEvent>>>);
+
+impl KeyReader for SatelliteReader {
+    async fn read(&self) -> eyre::Result<Vec<KeyEvent>> {
+        let mut events = self.0.lock().await;
+        let mut read = Vec::new();
+        match tokio::time::timeout(READ_TIMEOUT, events.recv()).await {
+            Ok(Some(event)) => read.push(event),
+            Ok(None) => eyre::bail!("Companion Satellite surfaces are no longer served"),
+            Err(_) => return Ok(read),
+        }
+        while let Ok(event) = events.try_recv() {
+            read.push(event);
+        }
+        Ok(read)
+    }
+}
+
+/// Ends once the deck no longer reads presses.
+async fn accept(
+    listener: TcpListener,
+    shared: Arc<Mutex<Shared>>,
+    events: Sender<KeyEvent>,
+    layout: (u8, u8),
+    peers: Vec<IpAddr>,
+) {
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Failed to accept a Companion Satellite client: {e}");
+                    continue;
+                }
+            },
+            _ = events.closed() => break,
+        };
+        if !peer.ip().is_loopback() && !peers.contains(&peer.ip()) {
+            warn!("Turned away a Companion Satellite client from {peer}, see --satellite-peer");
+            continue;
+        }
+        info!("Companion Satellite client connected from {peer}");
+        let connection = Connection {
+            shared: shared.clone(),
+            events: events.clone(),
+            layout,
+            lines: None,
+            pressed: HashSet::new(),
+        };
+        tokio::spawn(async move {
+            match connection.run(stream).await {
+                Ok(()) => info!("Companion Satellite client {peer} disconnected"),
+                Err(e) => warn!("Companion Satellite client {peer} failed: {e:#}"),
+            }
+        });
+    }
+}
+
+/// A client of the protocol, which adds and removes the surfaces that it is attached to.
+struct Connection {
+    shared: Arc<Mutex<Shared>>,
+    events: Sender<KeyEvent>,
+    layout: (u8, u8),
+    lines: Option<Sender<String>>,
+    /// Keys that are held down on the surface, which are let go if it goes away.
+    pressed: HashSet<u8>,
+}
+
+impl Connection {
+    async fn run(mut self, stream: TcpStream) -> eyre::Result<()> {
+        let (read, mut write) = stream.into_split();
+        let (lines, mut outgoing) = mpsc::channel::<String>(QUEUED_LINES);
+        tokio::spawn(async move {
+            while let Some(line) = outgoing.recv().await {
+                if let Err(e) = write.write_all(line.as_bytes()).await {
+                    debug!("Companion Satellite client went away: {e}");
+                    break;
+                }
+            }
+        });
+        let version = env!("CARGO_PKG_VERSION");
+        self.lines = Some(lines);
+        self.send(format!(
+            "BEGIN CompanionVersion={version} ApiVersion={API_VERSION}"
+        ));
+
+        let mut incoming = BufReader::new(read).lines();
+        let result = async {
+            while let Some(line) = incoming.next_line().await? {
+                if !self.handle(&line).await? {
+                    break;
+                }
+            }
+            Ok(())
+        }
+        .await;
+        self.remove_surface(None).await?;
+        result
+    }
+
+    /// Returns whether the client wants to go on.
+    async fn handle(&mut self, line: &str) -> eyre::Result<bool> {
+        let line = line.trim();
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "PING" => self.send(format!("PONG {rest}")),
+            "PONG" => {}
+            "ADD-DEVICE" => self.add_surface(&parse_params(rest)),
+            "REMOVE-DEVICE" => {
+                let params = parse_params(rest);
+                if let Some(device_id) = params.get("DEVICEID") {
+                    self.remove_surface(Some(device_id)).await?;
+                }
+            }
+            "KEY-PRESS" => self.press(&parse_params(rest)).await?,
+            "QUIT" => return Ok(false),
+            _ => debug!("Ignoring '{command}' from a Companion Satellite client"),
+        }
+        Ok(true)
+    }
+
+    /// Replies are few, so a client that cannot take them has gone away anyway.
+    fn send(&self, line: String) {
+        if let Some(lines) = &self.lines
+            && send(lines, line).is_err()
+        {
+            debug!("Not replying to a Companion Satellite client that falls behind");
+        }
+    }
+
+    fn add_surface(&mut self, params: &HashMap<String, String>) {
+        let Some(device_id) = params.get("DEVICEID") else {
+            self.send("ADD-DEVICE ERROR MESSAGE=\"Missing DEVICEID\"".to_string());
+            return;
+        };
+        let Some(lines) = self.lines.clone() else {
+            return;
+        };
+        let (rows, cols) = self.layout;
+        let number = |name: &str| params.get(name).and_then(|n| n.parse::<u32>().ok());
+        let bitmap_size = match params.get("BITMAPS").map(String::as_str) {
+            // What surfaces meant before they could ask for a size
+            Some("true") => Some(72),
+            Some(size) => size
+                .parse()
+                .ok()
+                .filter(|s| (1..=MAX_BITMAP_SIZE).contains(s)),
+            None => None,
+        };
+        let accepted = if number("KEYS_TOTAL") != Some(u32::from(rows) * u32::from(cols))
+            || number("KEYS_PER_ROW") != Some(u32::from(cols))
+        {
+            Err(format!("The deck has {rows} rows of {cols} keys"))
+        } else {
+            bitmap_size.ok_or_else(|| "The deck only shows images".to_string())
+        };
+        let bitmap_size = match accepted {
+            Ok(bitmap_size) => bitmap_size,
+            Err(message) => {
+                info!("Refusing Companion Satellite surface {device_id}: {message}");
+                self.send(format!(
+                    "ADD-DEVICE ERROR DEVICEID={device_id} MESSAGE=\"{message}\""
+                ));
+                return;
+            }
+        };
+
+        let mut shared = self.shared.lock().unwrap_or_else(PoisonError::into_inner);
+        // Whoever can reach the port would otherwise take the deck from the surface in use
+        if let Some(served) = &shared.surface
+            && !served.lines.same_channel(&lines)
+        {
+            let message = format!("Surface {} shows the deck", served.device_id);
+            info!("Refusing Companion Satellite surface {device_id}: {message}");
+            self.send(format!(
+                "ADD-DEVICE ERROR DEVICEID={device_id} MESSAGE=\"{message}\""
+            ));
+            return;
+        }
+        let surface = Surface {
+            device_id: device_id.clone(),
+            bitmap_size,
+            lines,
+        };
+        let mut lines = vec![
+            format!("ADD-DEVICE OK DEVICEID={device_id}"),
+            format!(
+                "BRIGHTNESS DEVICEID={device_id} VALUE={}",
+                shared.brightness
+            ),
+        ];
+        for (key, image) in shared.keys.iter().enumerate() {
+            lines.push(surface.key_state(key, image.as_ref()));
+        }
+        if lines.into_iter().any(|line| surface.send(line).is_err()) {
+            warn!("Companion Satellite surface {device_id} falls behind right away");
+            shared.surface = None;
+            return;
+        }
+        info!("Showing the deck on Companion Satellite surface {device_id}");
+        shared.surface = Some(surface);
+    }
+
+    /// Whether the surface that is served is this client's, and has the id if one is given.
+    fn serves(&self, device_id: Option<&String>) -> bool {
+        let shared = self.shared.lock().unwrap_or_else(PoisonError::into_inner);
+        shared.surface.as_ref().is_some_and(|surface| {
+            self.lines
+                .as_ref()
+                .is_some_and(|lines| lines.same_channel(&surface.lines))
+                && device_id.is_none_or(|id| *id == surface.device_id)
+        })
+    }
+
+    async fn remove_surface(&mut self, device_id: Option<&String>) -> eyre::Result<()> {
+        if !self.serves(device_id) {
+            return Ok(());
+        }
+        let removed = self
+            .shared
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .surface
+            .take();
+        if let Some(removed) = removed {
+            info!(
+                "Companion Satellite surface {} went away",
+                removed.device_id
+            );
+        }
+        for key in std::mem::take(&mut self.pressed) {
+            self.events.send(KeyEvent::Up(key)).await?;
+        }
+        Ok(())
+    }
+
+    async fn press(&mut self, params: &HashMap<String, String>) -> eyre::Result<()> {
+        if !self.serves(params.get("DEVICEID")) {
+            return Ok(());
+        }
+        let Some(key) = params.get("KEY").and_then(|key| self.key_index(key)) else {
+            debug!("Ignoring press of an unknown key: {params:?}");
+            return Ok(());
+        };
+        let event = match params.get("PRESSED").map(String::as_str) {
+            Some("true" | "1") => {
+                self.pressed.insert(key);
+                KeyEvent::Down(key)
+            }
+            _ => {
+                self.pressed.remove(&key);
+                KeyEvent::Up(key)
+            }
+        };
+        self.events.send(event).await?;
+        Ok(())
+    }
+
+    /// Keys are numbered row by row, or given as `row/column`.
+    fn key_index(&self, key: &str) -> Option<u8> {
+        let (rows, cols) = self.layout;
+        let index = match key.split_once('/') {
+            Some((row, col)) => {
+                let (row, col) = (row.parse::<u8>().ok()?, col.parse::<u8>().ok()?);
+                if col >= cols {
+                    return None;
+                }
+                row.checked_mul(cols)?.checked_add(col)?
+            }
+            None => key.parse::<u8>().ok()?,
+        };
+        (u16::from(index) < u16::from(rows) * u16::from(cols)).then_some(index)
+    }
+}
+
+/// A StreamDeck that shows Companion's pages, as a Satellite surface of Companion's, rather than
+/// the deck's.
+pub struct CompanionSurface {
+    stop_tx: oneshot::Sender<()>,
+    finished: JoinHandle<eyre::Result<()>>,
+}
+
+impl CompanionSurface {
+    /// Connects to Companion's Satellite port at `address`, e.g. `192.168.1.30:16622`, and keeps
+    /// reconnecting whenever the connection is lost.
+    pub fn spawn(device: StreamDeck, address: String, device_id: String) -> CompanionSurface {
+        let (stop_tx, stop_rx) = oneshot::channel();
+        CompanionSurface {
+            stop_tx,
+            finished: tokio::spawn(attach(device, address, device_id, stop_rx)),
+        }
+    }
+
+    pub async fn stop(self) -> eyre::Result<()> {
+        // Only fails if the surface already stopped on its own
+        let _ = self.stop_tx.send(());
+        self.finished.await?
+    }
+}
+
+/// Ends once `stop` resolves, and leaves the device dark.
+async fn attach<B: DeckBackend>(
+    device: B,
+    address: String,
+    device_id: String,
+    mut stop: oneshot::Receiver<()>,
+) -> eyre::Result<()> {
+    let reader = device.reader();
+    loop {
+        device.clear_all_keys().await?;
+        device.flush().await?;
+        let session = tokio::select! {
+            session = follow_companion(&device, &reader, &address, &device_id) => session,
+            _ = &mut stop => break,
+        };
+        match session {
+            Ok(()) => info!("Companion on {address} let go of the surface"),
+            Err(e) => warn!("Lost Companion on {address}: {e:#}"),
+        }
+        device.clear_all_keys().await?;
+        device.flush().await?;
+        tokio::select! {
+            _ = tokio::time::sleep(COMPANION_RECONNECT_INTERVAL) => {},
+            _ = &mut stop => break,
+        }
+    }
+    device.shut_down().await
+}
+
+/// Adds the device to Companion as a surface, then shows what Companion sends and reports the
+/// presses, until either side stops.
+async fn follow_companion<B: DeckBackend>(
+    device: &B,
+    reader: &B::Reader,
+    address: &str,
+    device_id: &str,
+) -> eyre::Result<()> {
+    let stream = TcpStream::connect(address)
+        .await
+        .with_context(|| format!("Failed to connect to Companion on {address}"))?;
+    let (read, mut write) = stream.into_split();
+    let mut incoming = BufReader::new(read).lines();
+    let mut ping = tokio::time::interval(COMPANION_PING_INTERVAL);
+    loop {
+        tokio::select! {
+            line = incoming.next_line() => {
+                let Some(line) = line? else {
+                    return Ok(());
+                };
+                if let Some(reply) = handle_companion(device, device_id, &line).await? {
+                    write_line(&mut write, reply).await?;
+                }
+            },
+            events = reader.read() => {
+                for event in events? {
+                    let (key, pressed) = match event {
+                        KeyEvent::Down(key) => (key, true),
+                        KeyEvent::Up(key) => (key, false),
+                        KeyEvent::StripTap(_) | KeyEvent::StripSwipe { .. } => continue,
+                    };
+                    let press =
+                        format!("KEY-PRESS DEVICEID={device_id} KEY={key} PRESSED={pressed}");
+                    write_line(&mut write, press).await?;
+                }
+            },
+            _ = ping.tick() => write_line(&mut write, "PING noisedeck".to_string()).await?,
+        }
+    }
+}
+
+/// Returns the reply to the line, if it needs one.
+async fn handle_companion<B: DeckBackend>(
+    device: &B,
+    device_id: &str,
+    line: &str,
+) -> eyre::Result<Option<String>> {
+    let line = line.trim();
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let params = parse_params(rest);
+    let ours = params.get("DEVICEID").is_some_and(|id| id == device_id);
+    match command {
+        "BEGIN" => {
+            let (rows, cols) = device.key_layout();
+            let keys = u16::from(rows) * u16::from(cols);
+            return Ok(Some(format!(
+                "ADD-DEVICE DEVICEID={device_id} PRODUCT_NAME=\"noisedeck\" KEYS_TOTAL={keys} \
+                 KEYS_PER_ROW={cols} BITMAPS={COMPANION_BITMAP_SIZE} COLORS=false TEXT=false"
+            )));
+        }
+        "PING" => return Ok(Some(format!("PONG {rest}"))),
+        "ADD-DEVICE" if rest.starts_with("OK") => info!("Showing Companion's pages"),
+        "ADD-DEVICE" => {
+            let message = params.get("MESSAGE").map_or(rest, String::as_str);
+            eyre::bail!("Companion refused the surface: {message}");
+        }
+        "KEY-STATE" if ours => {
+            let Some(bitmap) = params.get("BITMAP") else {
+                return Ok(None);
+            };
+            let key = params
+                .get("KEY")
+                .and_then(|key| key.parse::<u8>().ok())
+                .filter(|key| {
+                    let (rows, cols) = device.key_layout();
+                    u16::from(*key) < u16::from(rows) * u16::from(cols)
+                })
+                .with_context(|| format!("Companion sent the state of an unknown key: {rest}"))?;
+            let pixels = BASE64_STANDARD
+                .decode(bitmap)
+                .context("Companion sent a key image that is not base64")?;
+            let size = COMPANION_BITMAP_SIZE;
+            let image = RgbImage::from_raw(size, size, pixels)
+                .context("Companion sent a key image of another size")?;
+            device
+                .set_key_image(key, DynamicImage::ImageRgb8(image))
+                .await?;
+            device.flush().await?;
+        }
+        "KEYS-CLEAR" if ours => {
+            device.clear_all_keys().await?;
+            device.flush().await?;
+        }
+        "BRIGHTNESS" if ours => {
+            if let Some(percent) = params.get("VALUE").and_then(|v| v.parse().ok()) {
+                device.set_brightness(percent).await?;
+            }
+        }
+        _ => debug!("Ignoring '{command}' from Companion"),
+    }
+    Ok(None)
+}
+
+async fn write_line(write: &mut OwnedWriteHalf, mut line: String) -> eyre::Result<()> {
+    line.push('\n');
+    write.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// Splits `KEY=value KEY="quoted value"` into its parameters. One without a value is `true`.
+fn parse_params(params: &str) -> HashMap<String, String> {
+    let mut parsed = HashMap::new();
+    let mut chars = params.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+        let name = iter::from_fn(|| chars.next_if(|c| *c != '=' && !c.is_whitespace()))
+            .collect::<String>();
+        let value = if chars.next_if_eq(&'=').is_none() {
+            "true".to_string()
+        } else if chars.next_if_eq(&'"').is_some() {
+            let mut value = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => value.extend(chars.next()),
+                    c => value.push(c),
+                }
+            }
+            value
+        } else {
+            iter::from_fn(|| chars.next_if(|c| !c.is_whitespace())).collect()
+        };
+        parsed.insert(name, value);
+    }
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Satellite, attach, parse_params};
+    use crate::daemon::backend::fake::FakeDeck;
+    use crate::daemon::backend::{DeckBackend, KeyEvent, KeyReader};
+    use base64::prelude::{BASE64_STANDARD, Engine};
+    use image::{DynamicImage, Rgb, RgbImage};
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+    use tokio::net::tcp::OwnedReadHalf;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::oneshot;
+    use tokio::time::timeout;
+
+    async fn next_line(incoming: &mut Lines<BufReader<OwnedReadHalf>>) -> eyre::Result<String> {
+        timeout(Duration::from_secs(2), incoming.next_line())
+            .await??
+            .ok_or_else(|| eyre::eyre!("connection closed"))
+    }
+
+    #[test]
+    fn test_parameters_may_be_quoted() {
+        let params = parse_params(r#"DEVICEID=a1 PRODUCT_NAME="Stream Deck \"XL\"" TEXT"#);
+        let expected = [
+            ("DEVICEID", "a1"),
+            ("PRODUCT_NAME", "Stream Deck \"XL\""),
+            ("TEXT", "true"),
+        ];
+        let expected = expected
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(params, expected);
+    }
+
+    #[tokio::test]
+    async fn test_surfaces_show_the_keys_and_press_them() -> eyre::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let satellite = Satellite::serve(listener, (3, 5), vec![]);
+        let reader = satellite.reader();
+        let red = RgbImage::from_pixel(96, 96, Rgb([255, 0, 0]));
+        satellite
+            .set_key_image(1, DynamicImage::ImageRgb8(red))
+            .await?;
+
+        let (read, mut write) = TcpStream::connect(address).await?.into_split();
+        let mut incoming = BufReader::new(read).lines();
+        let mut next_line = async || -> eyre::Result<String> {
+            timeout(Duration::from_secs(2), incoming.next_line())
+                .await??
+                .ok_or_else(|| eyre::eyre!("connection closed"))
+        };
+        assert!(next_line().await?.starts_with("BEGIN "));
+
+        write
+            .write_all(b"ADD-DEVICE DEVICEID=mini KEYS_TOTAL=6 KEYS_PER_ROW=3 BITMAPS=72\n")
+            .await?;
+        assert!(
+            next_line()
+                .await?
+                .starts_with("ADD-DEVICE ERROR DEVICEID=mini ")
+        );
+        write
+            .write_all(
+                b"ADD-DEVICE DEVICEID=mk2 PRODUCT_NAME=\"Stream Deck MK.2\" \
+                KEYS_TOTAL=15 KEYS_PER_ROW=5 BITMAPS=72\n",
+            )
+            .await?;
+        assert_eq!(next_line().await?, "ADD-DEVICE OK DEVICEID=mk2");
+        assert_eq!(next_line().await?, "BRIGHTNESS DEVICEID=mk2 VALUE=100");
+        let mut bitmaps = Vec::new();
+        for key in 0..15 {
+            let line = next_line().await?;
+            let prefix = format!("KEY-STATE DEVICEID=mk2 KEY={key} TYPE=BUTTON BITMAP=");
+            let bitmap = line
+                .strip_prefix(&prefix)
+                .ok_or_else(|| eyre::eyre!("{line}"))?;
+            bitmaps.push(BASE64_STANDARD.decode(bitmap)?);
+        }
+        assert_eq!(bitmaps[1].len(), 72 * 72 * 3);
+        assert_eq!(bitmaps[1][..3], [255, 0, 0]);
+        assert!(bitmaps[0].iter().all(|b| *b == 0));
+
+        write.write_all(b"PING 42\n").await?;
+        assert_eq!(next_line().await?, "PONG 42");
+        write
+            .write_all(b"KEY-PRESS DEVICEID=mk2 KEY=1/2 PRESSED=true\n")
+            .await?;
+        let mut events = Vec::new();
+        while events.is_empty() {
+            events = timeout(Duration::from_secs(2), reader.read()).await??;
+        }
+        assert_eq!(events, [KeyEvent::Down(7)]);
+
+        // Keys that are held when the surface goes away are let go
+        drop(write);
+        let mut events = Vec::new();
+        while events.is_empty() {
+            events = timeout(Duration::from_secs(2), reader.read()).await??;
+        }
+        assert_eq!(events, [KeyEvent::Up(7)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_a_second_surface_does_not_take_over() -> eyre::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let _satellite = Satellite::serve(listener, (3, 5), vec![]);
+        let add = |id: &str| {
+            format!("ADD-DEVICE DEVICEID={id} KEYS_TOTAL=15 KEYS_PER_ROW=5 BITMAPS=72\n")
+        };
+
+        let (read, mut write) = TcpStream::connect(address).await?.into_split();
+        let mut first = BufReader::new(read).lines();
+        assert!(next_line(&mut first).await?.starts_with("BEGIN "));
+        write.write_all(add("desk").as_bytes()).await?;
+        assert_eq!(next_line(&mut first).await?, "ADD-DEVICE OK DEVICEID=desk");
+
+        let (read, mut write) = TcpStream::connect(address).await?.into_split();
+        let mut second = BufReader::new(read).lines();
+        assert!(next_line(&mut second).await?.starts_with("BEGIN "));
+        write.write_all(add("intruder").as_bytes()).await?;
+        assert_eq!(
+            next_line(&mut second).await?,
+            "ADD-DEVICE ERROR DEVICEID=intruder MESSAGE=\"Surface desk shows the deck\""
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_a_deck_is_handed_to_companion_as_a_surface() -> eyre::Result<()> {
+        let companion = TcpListener::bind("127.0.0.1:0").await?;
+        let address = companion.local_addr()?.to_string();
+        let (deck, presses) = FakeDeck::new((3, 5));
+        let (_stop_tx, stop_rx) = oneshot::channel();
+        tokio::spawn(attach(deck.clone(), address, "nd-1".to_string(), stop_rx));
+
+        let (stream, _) = timeout(Duration::from_secs(2), companion.accept()).await??;
+        let (read, mut write) = stream.into_split();
+        let mut incoming = BufReader::new(read).lines();
+        write
+            .write_all(b"BEGIN CompanionVersion=3.4.0 ApiVersion=1.5.1\n")
+            .await?;
+        let mut line = next_line(&mut incoming).await?;
+        while line.starts_with("PING ") {
+            line = next_line(&mut incoming).await?;
+        }
+        assert!(line.starts_with("ADD-DEVICE DEVICEID=nd-1 "), "{line}");
+        assert!(line.contains(" KEYS_TOTAL=15 KEYS_PER_ROW=5 BITMAPS=72"));
+
+        write.write_all(b"ADD-DEVICE OK DEVICEID=nd-1\n").await?;
+        let red = [255, 0, 0].repeat(72 * 72);
+        let state = format!(
+            "KEY-STATE DEVICEID=nd-1 KEY=7 TYPE=BUTTON BITMAP={}\n",
+            BASE64_STANDARD.encode(red)
+        );
+        write.write_all(state.as_bytes()).await?;
+        let shown_on_7 = || {
+            deck.sent(|sent| {
+                sent.flushes
+                    .iter()
+                    .flatten()
+                    .filter(|(key, _)| *key == 7)
+                    .filter_map(|(_, image)| image.clone())
+                    .next_back()
+            })
+        };
+        let shown = timeout(Duration::from_secs(2), async {
+            loop {
+                if let Some(image) = shown_on_7() {
+                    return image;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await?;
+        assert_eq!(shown.to_rgb8().get_pixel(0, 0).0, [255, 0, 0]);
+
+        presses.send(KeyEvent::Down(3)).await?;
+        let mut line = next_line(&mut incoming).await?;
+        while line.starts_with("PING ") {
+            line = next_line(&mut incoming).await?;
+        }
+        assert_eq!(line, "KEY-PRESS DEVICEID=nd-1 KEY=3 PRESSED=true");
+        Ok(())
+    }
+}
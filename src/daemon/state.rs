@@ -0,0 +1,223 @@
+//! What the game master changes on the deck that should survive a restart, such as pinned
+//! favorites. It lives in its own file because the configuration is imported from a Stream Deck
+//! profile and regenerated on every start, and is layered over it whenever the deck sets up its
+//! pages. Sounds are known by their file, since imported pages and buttons get new IDs on every
+//! import.
+
+use crate::config::{PlaySoundSettings, PlaybackMode};
+use crate::daemon::audio::{MAX_VOLUME_OFFSET_DB, Track};
+use crate::util::{canonical_path, is_stream_url};
+use eyre::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UserState {
+    /// Sound files pinned to the favorites page, in the order they were pinned.
+    #[serde(default)]
+    pub favorites: Vec<PathBuf>,
+    /// Settings changed in the deck's edit mode, by sound file.
+    #[serde(default)]
+    pub track_edits: BTreeMap<PathBuf, TrackEdits>,
+    /// Labels given on the deck, by sound file, in place of the imported ones.
+    #[serde(default)]
+    pub labels: BTreeMap<PathBuf, String>,
+    /// Restored on start. `None` until the volume is first changed.
+    #[serde(default)]
+    pub global_volume_db: Option<f64>,
+    /// Where sounds that resume were last stopped, by sound file, see
+    /// [`PlaySoundSettings::resume`].
+    #[serde(default)]
+    pub positions: BTreeMap<PathBuf, Duration>,
+}
+
+/// Changes to a track's configured settings. `None` keeps what the configuration says.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrackEdits {
+    /// Added to the configured gain, up to [`MAX_VOLUME_OFFSET_DB`].
+    #[serde(default)]
+    pub volume_offset_db: f64,
+    #[serde(default)]
+    pub mode: Option<PlaybackMode>,
+    /// A zero duration turns a configured fade off.
+    #[serde(default)]
+    pub fade_in: Option<Duration>,
+    #[serde(default)]
+    pub fade_out: Option<Duration>,
+}
+
+impl TrackEdits {
+    pub fn apply(&self, settings: &PlaySoundSettings) -> PlaySoundSettings {
+        PlaySoundSettings {
+            gain_db: settings.gain_db + self.volume_offset_db.min(MAX_VOLUME_OFFSET_DB),
+            mode: self.mode.unwrap_or(settings.mode),
+            fade_in: self.fade_in.or(settings.fade_in),
+            fade_out: self.fade_out.or(settings.fade_out),
+            ..settings.clone()
+        }
+    }
+}
+
+impl UserState {
+    /// A missing file is a fresh start rather than an error, and so is one that cannot be parsed.
+    /// That one is moved aside rather than overwritten, so that what it held can still be
+    /// recovered by hand.
+    ///
+    /// Sound files are looked up by their canonical path, which older state files did not use.
+    pub async fn load(path: &Path) -> eyre::Result<Self> {
+        let json = match tokio::fs::read(path).await {
+            Ok(json) => json,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(UserState::default()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        };
+        match serde_json::from_slice::<UserState>(&json) {
+            Ok(state) => Ok(tokio::task::spawn_blocking(move || state.canonicalized()).await?),
+            Err(e) => {
+                let mut broken = path.as_os_str().to_owned();
+                broken.push(".broken");
+                let broken = PathBuf::from(broken);
+                warn!(
+                    "Starting afresh, {} cannot be parsed and is moved to {}: {e}",
+                    path.display(),
+                    broken.display()
+                );
+                tokio::fs::rename(path, &broken)
+                    .await
+                    .with_context(|| format!("Failed to move {} aside", path.display()))?;
+                Ok(UserState::default())
+            }
+        }
+    }
+
+    /// Goes through a temporary file, so that a crash while writing cannot truncate the state.
+    pub async fn save(&self, path: &Path) -> eyre::Result<()> {
+        let json = serde_json::to_vec_pretty(self).context("Failed to serialize user state")?;
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, json)
+            .await
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        tokio::fs::rename(&tmp_path, path)
+            .await
+            .with_context(|| format!("Failed to replace {}", path.display()))
+    }
+
+    fn canonicalized(self) -> Self {
+        let canonical = |path: PathBuf| match path.to_str() {
+            Some(url) if is_stream_url(url) => path,
+            _ => canonical_path(&path),
+        };
+        let mut favorites = Vec::with_capacity(self.favorites.len());
+        for favorite in self.favorites.into_iter().map(canonical) {
+            if !favorites.contains(&favorite) {
+                favorites.push(favorite);
+            }
+        }
+        UserState {
+            favorites,
+            track_edits: self
+                .track_edits
+                .into_iter()
+                .map(|(k, v)| (canonical(k), v))
+                .collect(),
+            labels: self
+                .labels
+                .into_iter()
+                .map(|(k, v)| (canonical(k), v))
+                .collect(),
+            positions: self
+                .positions
+                .into_iter()
+                .map(|(k, v)| (canonical(k), v))
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Takes where `track` was last stopped, if it resumes, and tells whether that changed.
+    pub fn note_position(&mut self, track: &Track) -> bool {
+        if track.settings.resume.is_none() {
+            return false;
+        }
+        let position = track.resume_position();
+        if self.positions.get(track.path.as_ref()).copied() == position {
+            return false;
+        }
+        match position {
+            Some(position) => self.positions.insert(track.path.to_path_buf(), position),
+            None => self.positions.remove(track.path.as_ref()),
+        };
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UserState;
+    use crate::util::canonical_path;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    #[tokio::test]
+    async fn test_user_state_round_trip() -> eyre::Result<()> {
+        let path = std::env::temp_dir().join(format!("noisedeck-test-{}.json", std::process::id()));
+        assert!(UserState::load(&path).await?.favorites.is_empty());
+
+        let state = UserState {
+            favorites: vec![PathBuf::from("rain.mp3"), PathBuf::from("thunder.mp3")],
+            ..UserState::default()
+        };
+        state.save(&path).await?;
+        let loaded = UserState::load(&path).await?;
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(loaded.favorites, state.favorites);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_a_broken_state_file_is_moved_aside() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, "{\"favorites\": [")?;
+
+        let loaded = UserState::load(&path).await?;
+
+        assert!(loaded.favorites.is_empty());
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("state.json.broken"))?,
+            "{\"favorites\": ["
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_paths_are_canonicalized_on_load() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("state.json");
+        let rain = dir.path().join("rain.mp3");
+        std::fs::write(&rain, b"rain")?;
+        std::fs::create_dir(dir.path().join("sounds"))?;
+        let spelled = dir.path().join("sounds").join("..").join("rain.mp3");
+        let state = UserState {
+            favorites: vec![spelled.clone(), rain.clone()],
+            labels: BTreeMap::from([(spelled, "Rain".to_string())]),
+            ..UserState::default()
+        };
+        state.save(&path).await?;
+
+        let loaded = UserState::load(&path).await?;
+
+        let rain = canonical_path(&rain);
+        assert_eq!(loaded.favorites.len(), 1);
+        assert_eq!(loaded.favorites[0], rain);
+        assert_eq!(loaded.labels, BTreeMap::from([(rain, "Rain".to_string())]));
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+//! Lets systemd supervise the daemon as a `Type=notify` service, optionally with `WatchdogSec=`.
+//! Outside of systemd, and on other systems, all of this does nothing.
+
+use std::time::Duration;
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use sd_notify::NotifyState;
+    use std::time::Duration;
+    use tracing::warn;
+
+    pub fn ready() {
+        send(&[NotifyState::Ready]);
+    }
+
+    pub fn stopping() {
+        send(&[NotifyState::Stopping]);
+    }
+
+    pub fn pet_watchdog() {
+        send(&[NotifyState::Watchdog]);
+    }
+
+    pub fn watchdog_timeout() -> Option<Duration> {
+        let mut usec = 0;
+        sd_notify::watchdog_enabled(false, &mut usec).then(|| Duration::from_micros(usec))
+    }
+
+    fn send(state: &[NotifyState]) {
+        if let Err(e) = sd_notify::notify(false, state) {
+            warn!(error = %e, "Error notifying systemd");
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use std::time::Duration;
+
+    pub fn ready() {}
+
+    pub fn stopping() {}
+
+    pub fn pet_watchdog() {}
+
+    pub fn watchdog_timeout() -> Option<Duration> {
+        None
+    }
+}
+
+pub use imp::{pet_watchdog, ready, stopping};
+
+/// Half the timeout, as systemd recommends, so that a late tick does not get the daemon killed.
+pub fn watchdog_interval() -> Option<Duration> {
+    imp::watchdog_timeout().map(|timeout| timeout / 2)
+}
@@ -1,20 +1,89 @@
+use crate::config;
 use crate::config::PlaySoundSettings;
 use crate::daemon::audio::Track;
 use crate::daemon::ui::{
-    BtnInvokeStatus, ButtonData, NoiseDeck, btn_goto, btn_play_stop, btn_pop, btn_push,
-    btn_reset_offset, btn_rotate, btn_volume_up, btn_volume_down, btn_show_volume_control,
+    BtnInvokeStatus, ButtonData, ButtonId, Countdown, NoiseDeck, PAN_DELTA, PLAYBACK_RATE_DELTA,
+    TrackEdit, VOLUME_DELTA_DB, btn_adjust_input_volume, btn_adjust_playback_rate,
+    btn_adjust_track_pan, btn_adjust_track_volume, btn_broken, btn_cycle_playing_order,
+    btn_cycle_volume_unit, btn_edit_track, btn_goto, btn_pause_all, btn_placeholder, btn_play,
+    btn_play_stop, btn_play_tag, btn_pop, btn_pop_n, btn_push, btn_reset_offset, btn_reset_timer,
+    btn_resume_all, btn_roll_dice, btn_rotate, btn_rotate_back, btn_run_command, btn_send_keys,
+    btn_show_navigation, btn_show_now_playing, btn_show_volume_control, btn_stop, btn_stop_all,
+    btn_stop_instances, btn_stop_tag, btn_switch_profile, btn_toggle_edit_mode,
+    btn_toggle_input_mute, btn_toggle_recording, btn_toggle_timer, btn_volume_down, btn_volume_up,
 };
+use eyre::Context;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::pin::Pin;
 use std::sync::{Arc, LazyLock};
 use tracing::warn;
 use uuid::Uuid;
 
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// What a key does when it is tapped or held. The deck's own actions are [`ButtonBehavior`]s;
+/// others, such as webhooks or integrations with other software, implement this trait and are
+/// made available to the configuration through a [`BehaviorRegistry`].
+pub trait Behavior: Send + Sync {
+    fn invoke<'a>(
+        &'a self,
+        deck: &'a mut NoiseDeck,
+        button: &'a ButtonRef,
+    ) -> BoxFuture<'a, eyre::Result<BtnInvokeStatus>>;
+}
+
+type BehaviorFactory = dyn Fn(&serde_json::Value) -> eyre::Result<Box<dyn Behavior>> + Send + Sync;
+
+/// Behaviors that `Custom` buttons in the configuration can name by their kind.
+#[derive(Clone, Default)]
+pub struct BehaviorRegistry {
+    factories: HashMap<String, Arc<BehaviorFactory>>,
+}
+
+impl BehaviorRegistry {
+    /// The factory receives the button's `params`. It runs whenever the button's page is laid
+    /// out, which includes every reload of the configuration.
+    pub fn register(
+        &mut self,
+        kind: impl Into<String>,
+        factory: impl Fn(&serde_json::Value) -> eyre::Result<Box<dyn Behavior>> + Send + Sync + 'static,
+    ) {
+        self.factories.insert(kind.into(), Arc::new(factory));
+    }
+
+    pub(in crate::daemon::ui) fn create(
+        &self,
+        kind: &str,
+        params: &serde_json::Value,
+    ) -> eyre::Result<Box<dyn Behavior>> {
+        let factory = self
+            .factories
+            .get(kind)
+            .ok_or_else(|| eyre::eyre!("No behavior of kind '{kind}' is registered"))?;
+        factory(params).with_context(|| format!("Invalid parameters for '{kind}'"))
+    }
+}
+
+impl std::fmt::Debug for BehaviorRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.factories.keys()).finish()
+    }
+}
+
 #[derive(Default)]
 pub struct Button {
+    pub(in crate::daemon::ui) id: ButtonId,
     pub(in crate::daemon::ui) data: tokio::sync::RwLock<ButtonData>,
     pub(in crate::daemon::ui) track: Option<Arc<Track>>,
-    pub(in crate::daemon::ui) on_tap: Option<ButtonBehavior>,
-    pub(in crate::daemon::ui) on_hold: Option<ButtonBehavior>,
+    pub(in crate::daemon::ui) on_tap: Option<Box<dyn Behavior>>,
+    pub(in crate::daemon::ui) on_hold: Option<Box<dyn Behavior>>,
+    /// The content key of a library page that the button keeps, counting on over the pages, see
+    /// [`crate::config::Button::position`].
+    pub(in crate::daemon::ui) slot: Option<usize>,
+    /// The configured label while it still has a `{duration}` in it, which is only known once
+    /// the file was read.
+    pub(in crate::daemon::ui) label_template: Option<Arc<String>>,
 }
 impl Button {
     pub(in crate::daemon::ui) fn builder() -> ButtonBuilder {
@@ -35,49 +104,223 @@ pub(in crate::daemon::ui) struct ButtonBuilder {
 pub(in crate::daemon::ui) enum ButtonBehavior {
     Push(Uuid),
     PlayStop,
+    /// Starts the track of the button that plays this path, unless it is already playing.
+    Play(Arc<PathBuf>),
+    Stop(Arc<PathBuf>),
+    StopInstances(Arc<PathBuf>, config::Instances),
+    StopAll,
+    PauseAll,
+    ResumeAll,
+    /// Stops the playing tracks with this tag.
+    StopTag(String),
+    /// Starts a random track with this tag that is not playing yet.
+    PlayTag(String),
+    Sequence(Vec<Box<dyn Behavior>>),
     Pop,
+    /// Pops up to this many views, but never the last one.
+    PopN(usize),
     Goto(Uuid),
     Rotate,
+    RotateBack,
     ResetOffset,
     VolumeUp,
     VolumeDown,
+    TrackVolumeUp,
+    TrackVolumeDown,
+    TrackPanLeft,
+    TrackPanRight,
+    TrackFaster,
+    TrackSlower,
     ShowVolumeControl,
+    ToggleRecording,
+    InputVolumeUp,
+    InputVolumeDown,
+    ToggleInputMute,
+    ShowNavigation,
+    ShowNowPlaying,
+    CyclePlayingOrder,
+    CycleVolumeUnit,
+    ToggleEditMode,
+    EditTrack(TrackEdit),
+    /// Says that the imported action of this name is not supported.
+    Placeholder(String),
+    /// Says why the configured behavior could not be set up, e.g. a script that does not
+    /// compile.
+    Broken(String),
+    /// Switches to the profile of this name, see [`config::ButtonBehavior::SwitchProfile`].
+    SwitchProfile(String),
+    /// Starts or stops the stopwatch of the button, see [`config::ButtonBehavior::Stopwatch`],
+    /// or its countdown.
+    ToggleTimer(Option<Countdown>),
+    ResetTimer,
+    /// See [`config::ButtonBehavior::RollDice`].
+    RollDice(config::Dice, Option<Arc<PathBuf>>),
 }
+impl Behavior for ButtonBehavior {
+    fn invoke<'a>(
+        &'a self,
+        deck: &'a mut NoiseDeck,
+        button: &'a ButtonRef,
+    ) -> BoxFuture<'a, eyre::Result<BtnInvokeStatus>> {
+        Box::pin(self.run(deck, button))
+    }
+}
+
+impl From<ButtonBehavior> for Box<dyn Behavior> {
+    fn from(behavior: ButtonBehavior) -> Self {
+        Box::new(behavior)
+    }
+}
+
 impl ButtonBehavior {
-    pub(in crate::daemon::ui) async fn invoke(
-        &self,
-        deck: &mut NoiseDeck,
-        button: &Button,
-    ) -> eyre::Result<BtnInvokeStatus> {
+    async fn run(&self, deck: &mut NoiseDeck, button: &ButtonRef) -> eyre::Result<BtnInvokeStatus> {
         match self {
             ButtonBehavior::Pop => btn_pop(deck).await,
+            ButtonBehavior::PopN(n) => btn_pop_n(deck, *n).await,
             ButtonBehavior::Push(id) => btn_push(deck, *id).await,
             ButtonBehavior::Goto(id) => btn_goto(deck, *id).await,
             ButtonBehavior::PlayStop => {
-                if let Some(track) = &button.track {
+                if let Some(track) = &button.inner.track {
                     btn_play_stop(deck, track).await
                 } else {
                     warn!("Button has no track assigned");
                     Ok(BtnInvokeStatus::default())
                 }
             }
+            ButtonBehavior::Play(path) => btn_play(deck, path).await,
+            ButtonBehavior::Stop(path) => btn_stop(deck, path).await,
+            ButtonBehavior::StopInstances(path, instances) => {
+                btn_stop_instances(deck, path, *instances).await
+            }
+            ButtonBehavior::StopAll => btn_stop_all(deck).await,
+            ButtonBehavior::PauseAll => btn_pause_all(deck).await,
+            ButtonBehavior::ResumeAll => btn_resume_all(deck).await,
+            ButtonBehavior::StopTag(tag) => btn_stop_tag(deck, tag).await,
+            ButtonBehavior::PlayTag(tag) => btn_play_tag(deck, tag).await,
+            ButtonBehavior::Sequence(steps) => {
+                // Some steps skip the refresh because they redrew the page, others because they
+                // changed nothing, so only a sequence of skipping steps can skip it as a whole.
+                let mut status = BtnInvokeStatus {
+                    skip_refresh: true,
+                    ..BtnInvokeStatus::default()
+                };
+                for step in steps {
+                    let step_status = step.invoke(deck, button).await?;
+                    status.skip_refresh &= step_status.skip_refresh;
+                }
+                Ok(status)
+            }
             ButtonBehavior::Rotate => btn_rotate(deck).await,
+            ButtonBehavior::RotateBack => btn_rotate_back(deck).await,
             ButtonBehavior::ResetOffset => btn_reset_offset(deck).await,
             ButtonBehavior::VolumeUp => btn_volume_up(deck).await,
             ButtonBehavior::VolumeDown => btn_volume_down(deck).await,
+            ButtonBehavior::InputVolumeUp => btn_adjust_input_volume(deck, VOLUME_DELTA_DB).await,
+            ButtonBehavior::InputVolumeDown => {
+                btn_adjust_input_volume(deck, -VOLUME_DELTA_DB).await
+            }
+            ButtonBehavior::ToggleInputMute => btn_toggle_input_mute(deck).await,
+            ButtonBehavior::TrackVolumeUp | ButtonBehavior::TrackVolumeDown => {
+                let Some(track) = &button.inner.track else {
+                    warn!("Button has no track assigned");
+                    return Ok(BtnInvokeStatus::default());
+                };
+                let delta_db = if matches!(self, ButtonBehavior::TrackVolumeUp) {
+                    VOLUME_DELTA_DB
+                } else {
+                    -VOLUME_DELTA_DB
+                };
+                btn_adjust_track_volume(deck, track, delta_db).await
+            }
+            ButtonBehavior::TrackPanLeft | ButtonBehavior::TrackPanRight => {
+                let Some(track) = &button.inner.track else {
+                    warn!("Button has no track assigned");
+                    return Ok(BtnInvokeStatus::default());
+                };
+                let delta = if matches!(self, ButtonBehavior::TrackPanRight) {
+                    PAN_DELTA
+                } else {
+                    -PAN_DELTA
+                };
+                btn_adjust_track_pan(deck, track, delta).await
+            }
+            ButtonBehavior::TrackFaster | ButtonBehavior::TrackSlower => {
+                let Some(track) = &button.inner.track else {
+                    warn!("Button has no track assigned");
+                    return Ok(BtnInvokeStatus::default());
+                };
+                let delta = if matches!(self, ButtonBehavior::TrackFaster) {
+                    PLAYBACK_RATE_DELTA
+                } else {
+                    -PLAYBACK_RATE_DELTA
+                };
+                btn_adjust_playback_rate(deck, track, delta).await
+            }
             ButtonBehavior::ShowVolumeControl => btn_show_volume_control(deck).await,
+            ButtonBehavior::ToggleRecording => btn_toggle_recording(deck).await,
+            ButtonBehavior::ShowNavigation => btn_show_navigation(deck).await,
+            ButtonBehavior::ShowNowPlaying => btn_show_now_playing(deck).await,
+            ButtonBehavior::CyclePlayingOrder => btn_cycle_playing_order(deck).await,
+            ButtonBehavior::CycleVolumeUnit => btn_cycle_volume_unit(deck).await,
+            ButtonBehavior::ToggleEditMode => btn_toggle_edit_mode(deck).await,
+            ButtonBehavior::EditTrack(edit) => {
+                let Some(track) = &button.inner.track else {
+                    warn!("Button has no track assigned");
+                    return Ok(BtnInvokeStatus::default());
+                };
+                btn_edit_track(deck, track, *edit).await
+            }
+            ButtonBehavior::Placeholder(name) => btn_placeholder(deck, name).await,
+            ButtonBehavior::Broken(reason) => btn_broken(deck, reason).await,
+            ButtonBehavior::SwitchProfile(name) => btn_switch_profile(deck, name).await,
+            ButtonBehavior::ToggleTimer(countdown) => {
+                btn_toggle_timer(deck, button, countdown).await
+            }
+            ButtonBehavior::ResetTimer => btn_reset_timer(deck, button).await,
+            ButtonBehavior::RollDice(dice, sound) => {
+                btn_roll_dice(deck, button, dice, sound.as_ref()).await
+            }
         }
     }
 }
 
+/// Starts a program, see [`crate::config::ButtonBehavior::RunCommand`].
+pub(in crate::daemon::ui) struct RunCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Behavior for RunCommand {
+    fn invoke<'a>(
+        &'a self,
+        deck: &'a mut NoiseDeck,
+        button: &'a ButtonRef,
+    ) -> BoxFuture<'a, eyre::Result<BtnInvokeStatus>> {
+        Box::pin(btn_run_command(deck, button, &self.program, &self.args))
+    }
+}
+
+/// Presses a key chord, see [`crate::config::ButtonBehavior::SendKeys`].
+pub(in crate::daemon::ui) struct SendKeys(pub String);
+
+impl Behavior for SendKeys {
+    fn invoke<'a>(
+        &'a self,
+        deck: &'a mut NoiseDeck,
+        _button: &'a ButtonRef,
+    ) -> BoxFuture<'a, eyre::Result<BtnInvokeStatus>> {
+        Box::pin(btn_send_keys(deck, &self.0))
+    }
+}
+
 impl ButtonBuilder {
-    pub fn on_tap(mut self, behavior: ButtonBehavior) -> Self {
-        self.inner.on_tap = Some(behavior);
+    pub fn on_tap(mut self, behavior: impl Into<Box<dyn Behavior>>) -> Self {
+        self.inner.on_tap = Some(behavior.into());
         self
     }
 
-    pub fn on_hold(mut self, behavior: ButtonBehavior) -> Self {
-        self.inner.on_hold = Some(behavior);
+    pub fn on_hold(mut self, behavior: impl Into<Box<dyn Behavior>>) -> Self {
+        self.inner.on_hold = Some(behavior.into());
         self
     }
 
@@ -102,6 +345,22 @@ impl ButtonBuilder {
         self
     }
 
+    pub fn slot(mut self, slot: Option<usize>) -> Self {
+        self.inner.slot = slot;
+        self
+    }
+
+    pub fn label_template(mut self, template: Option<Arc<String>>) -> Self {
+        self.inner.label_template = template;
+        self
+    }
+
+    /// Attaches a track that is already owned by another button, e.g. for auxiliary controls.
+    pub fn shared_track(mut self, track: Arc<Track>) -> Self {
+        self.inner.track = Some(track);
+        self
+    }
+
     pub fn build(self) -> Button {
         self.inner
     }
@@ -118,9 +377,26 @@ pub struct ButtonRef {
     pub(in crate::daemon::ui) inner: Arc<Button>,
 }
 impl ButtonRef {
+    /// A button that only shows what another machine's deck says it shows, see `--remote-deck`.
+    /// Taps on it do nothing here, but its id names the button that they are meant for.
+    pub fn detached(id: ButtonId, data: ButtonData) -> ButtonRef {
+        let mut button = Button::builder().data(data).build();
+        button.id = id;
+        button.into()
+    }
+
+    pub fn id(&self) -> ButtonId {
+        self.inner.id
+    }
+
     pub async fn read(&self) -> ButtonData {
         self.inner.data.read().await.clone()
     }
+
+    /// Only meant for [`ButtonRef::detached`] buttons, the deck updates its own.
+    pub async fn show(&self, data: ButtonData) {
+        *self.inner.data.write().await = data;
+    }
 }
 impl From<Button> for ButtonRef {
     fn from(inner: Button) -> Self {
@@ -1,14 +1,133 @@
-use crate::daemon::ui::ButtonRef;
+use crate::config::Config;
+use crate::daemon::ui::{ButtonRef, IpcEvent};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+use tokio::sync::oneshot;
+use uuid::Uuid;
 
 #[derive(Debug)]
 pub enum UiEvent {
     ButtonTap(ButtonRef),
     ButtonHold(ButtonRef),
+    ConfigReloaded(Arc<Config>),
+    /// From media keys and desktop widgets rather than the deck.
+    Transport(Transport),
+    /// A segment of the touch strip was touched, see [`STRIP_SEGMENTS`].
+    StripTap(usize),
+    StripSwipe(Swipe),
+    /// Sound files that appeared in the audio directory, see `--watch-audio-path`.
+    FilesAdded(Vec<PathBuf>),
+    /// The configuration of another profile, in reply to [`UiCommand::LoadCampaign`].
+    CampaignLoaded(String, Arc<Config>),
+    /// From Home Assistant, see `--mqtt`: the volume of a bus, from 0.0 to 1.0.
+    BusVolume(String, f64),
+    /// Taps the button at this index of a configured page, e.g. a `Sequence` that sets a scene.
+    TapConfigured(Uuid, usize),
+    /// From another program, see `--grpc`. The deck replies once it has acted on it.
+    Remote(Remote, oneshot::Sender<eyre::Result<()>>),
+    /// From another process, with buttons and tracks named rather than shared.
+    Ipc(IpcEvent),
+}
+
+/// What other programs can ask of the deck, with the same effect as the buttons.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Remote {
+    /// A rebased path, like the ones in the configuration the deck runs with.
+    Play(Arc<PathBuf>),
+    Stop(Arc<PathBuf>),
+    SetVolume(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Swipe {
+    Left,
+    Right,
+}
+
+/// The touch strip of a StreamDeck+ is split into one segment per dial below it.
+pub const STRIP_SEGMENTS: usize = 4;
+
+/// What one segment of the touch strip shows.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StripSegment {
+    pub label: String,
+    /// Shown below the label, e.g. the remaining time.
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Transport {
+    /// Freezes everything that is playing where it is.
+    Pause,
+    /// Picks the paused tracks up where they were.
+    Resume,
+    TogglePause,
+    /// Stops everything, including what is paused.
+    Stop,
+}
+
+/// What desktop media controls show about the soundboard.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MediaStatus {
+    pub playback: MediaPlayback,
+    /// Label and path of the track that was started last.
+    pub latest: Option<(Arc<String>, Arc<PathBuf>)>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MediaPlayback {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
 }
 
 pub enum UiCommand {
     Refresh,
     Flip(Vec<Option<ButtonRef>>),
+    /// Shows a message across the top row for a while, then the buttons again.
+    Toast(String, Duration),
+    /// The keys of a second deck that only shows the playing tracks.
+    FlipNowPlaying(Vec<Option<ButtonRef>>),
+    /// Contents of the touch strip, one entry per segment.
+    Strip(Vec<StripSegment>),
+    /// Asks the daemon to import another profile of the archive, which only it knows where to
+    /// find. It replies with [`UiEvent::CampaignLoaded`].
+    LoadCampaign(String),
+}
+
+impl UiCommand {
+    /// Takes `first` along with the commands that queued up behind it while the device was busy,
+    /// leaving out the ones that a later or earlier one makes pointless. Track updates arrive
+    /// faster than a deck can draw, and without this the deck would lag behind by a full
+    /// channel of redraws.
+    ///
+    /// A refresh draws the buttons as they are when it is handled, so one is enough for all
+    /// that queued up. Of flips and strips only the last one is ever seen.
+    pub fn coalesce(first: UiCommand, command_rx: &mut Receiver<UiCommand>) -> Vec<UiCommand> {
+        let mut commands = vec![first];
+        while let Ok(command) = command_rx.try_recv() {
+            let superseded = |c: &UiCommand| {
+                matches!(
+                    (&command, c),
+                    (UiCommand::Flip(_), UiCommand::Flip(_))
+                        | (UiCommand::FlipNowPlaying(_), UiCommand::FlipNowPlaying(_))
+                        | (UiCommand::Strip(_), UiCommand::Strip(_))
+                )
+            };
+            if matches!(command, UiCommand::Refresh)
+                && commands.iter().any(|c| matches!(c, UiCommand::Refresh))
+            {
+                continue;
+            }
+            commands.retain(|c| !superseded(c));
+            commands.push(command);
+        }
+        commands
+    }
 }
 
 impl std::fmt::Debug for UiCommand {
@@ -16,6 +135,92 @@ impl std::fmt::Debug for UiCommand {
         match self {
             UiCommand::Refresh => f.write_str("Refresh"),
             UiCommand::Flip(_) => f.write_str("PushPage"),
+            UiCommand::FlipNowPlaying(_) => f.write_str("FlipNowPlaying"),
+            UiCommand::Strip(segments) => f.debug_tuple("Strip").field(segments).finish(),
+            UiCommand::LoadCampaign(name) => f.debug_tuple("LoadCampaign").field(name).finish(),
+            UiCommand::Toast(message, duration) => f
+                .debug_tuple("Toast")
+                .field(message)
+                .field(duration)
+                .finish(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{StripSegment, UiCommand};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tokio::time::{Instant, sleep};
+
+    fn strip(label: &str) -> UiCommand {
+        UiCommand::Strip(vec![StripSegment {
+            label: label.to_string(),
+            detail: String::new(),
+        }])
+    }
+
+    #[tokio::test]
+    async fn test_queued_redraws_are_coalesced() -> eyre::Result<()> {
+        let (command_tx, mut command_rx) = mpsc::channel(16);
+        for command in [
+            strip("Rain"),
+            UiCommand::Refresh,
+            UiCommand::Toast("Saved".to_string(), Duration::from_secs(1)),
+            strip("Thunder"),
+            UiCommand::Flip(vec![]),
+            UiCommand::Refresh,
+            UiCommand::Flip(vec![None]),
+        ] {
+            command_tx.send(command).await?;
+        }
+
+        let commands = UiCommand::coalesce(UiCommand::Refresh, &mut command_rx);
+        let commands: Vec<_> = commands.iter().map(|c| format!("{c:?}")).collect();
+        assert_eq!(
+            commands,
+            [
+                "Refresh",
+                "Toast(\"Saved\", 1s)",
+                "Strip([StripSegment { label: \"Thunder\", detail: \"\" }])",
+                "PushPage",
+            ]
+        );
+        assert!(command_rx.is_empty(), "Everything that queued up is taken");
+        Ok(())
+    }
+
+    /// However long the deck is flooded with refreshes, the last one is drawn right after the
+    /// one that the device is busy with, rather than after a full channel of them.
+    #[tokio::test(start_paused = true)]
+    async fn test_flooding_refreshes_does_not_build_up_lag() -> eyre::Result<()> {
+        const DRAW_TIME: Duration = Duration::from_millis(20);
+        let (command_tx, mut command_rx) = mpsc::channel(16);
+        let flood = tokio::spawn(async move {
+            for _ in 0..1000 {
+                command_tx.send(UiCommand::Refresh).await?;
+            }
+            eyre::Ok(Instant::now())
+        });
+
+        let mut draws = 0;
+        let mut drawn_at = Instant::now();
+        while let Some(command) = command_rx.recv().await {
+            for _ in UiCommand::coalesce(command, &mut command_rx) {
+                sleep(DRAW_TIME).await;
+                draws += 1;
+                drawn_at = Instant::now();
+            }
+        }
+        let last_sent_at = flood.await??;
+
+        assert!(draws < 100, "{draws} draws for 1000 refreshes");
+        assert!(
+            drawn_at - last_sent_at <= 2 * DRAW_TIME,
+            "The last refresh was drawn {:?} after it was sent",
+            drawn_at - last_sent_at
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+//! Forms of the deck's commands and events that can leave the process, e.g. for a deck or a
+//! remote control on another machine. Buttons are named by their [`ButtonId`] and tracks by their
+//! path; the deck resolves both when they come back as [`UiEvent::Ipc`].
+
+use crate::daemon::audio::IpcAudioCommand;
+use crate::daemon::ui::{ButtonRef, StripSegment, Swipe, Transport, UiCommand, UiEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Names a button for as long as it exists. Pages that are built again get new buttons, and so
+/// new ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ButtonId(u64);
+
+/// A fresh id, so that every button that is built gets its own.
+impl Default for ButtonId {
+    fn default() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        ButtonId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Display for ButtonId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// [`UiCommand`] without the buttons, whose data has to be sent along where it is needed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum IpcCommand {
+    Refresh,
+    Flip(Vec<Option<ButtonId>>),
+    Toast(String, Duration),
+    FlipNowPlaying(Vec<Option<ButtonId>>),
+    Strip(Vec<StripSegment>),
+    LoadCampaign(String),
+}
+
+impl From<&UiCommand> for IpcCommand {
+    fn from(command: &UiCommand) -> Self {
+        match command {
+            UiCommand::Refresh => IpcCommand::Refresh,
+            UiCommand::Flip(page) => IpcCommand::Flip(ids(page)),
+            UiCommand::Toast(text, duration) => IpcCommand::Toast(text.clone(), *duration),
+            UiCommand::FlipNowPlaying(page) => IpcCommand::FlipNowPlaying(ids(page)),
+            UiCommand::Strip(segments) => IpcCommand::Strip(segments.clone()),
+            UiCommand::LoadCampaign(name) => IpcCommand::LoadCampaign(name.clone()),
+        }
+    }
+}
+
+fn ids(page: &[Option<ButtonRef>]) -> Vec<Option<ButtonId>> {
+    page.iter().map(|b| b.as_ref().map(ButtonRef::id)).collect()
+}
+
+/// The [`UiEvent`]s that other programs can send, and commands for the audio engine.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum IpcEvent {
+    ButtonTap(ButtonId),
+    ButtonHold(ButtonId),
+    Transport(Transport),
+    StripTap(usize),
+    StripSwipe(Swipe),
+    FilesAdded(Vec<PathBuf>),
+    BusVolume(String, f64),
+    TapConfigured(Uuid, usize),
+    /// Goes through the deck, which knows the tracks by path, so that it sees what was asked of
+    /// the engine.
+    Audio(IpcAudioCommand),
+}
+
+impl IpcEvent {
+    /// `None` for the events that only the daemon itself sends, such as reloaded configurations.
+    pub fn of(event: &UiEvent) -> Option<IpcEvent> {
+        match event {
+            UiEvent::ButtonTap(button) => Some(IpcEvent::ButtonTap(button.id())),
+            UiEvent::ButtonHold(button) => Some(IpcEvent::ButtonHold(button.id())),
+            UiEvent::Transport(transport) => Some(IpcEvent::Transport(*transport)),
+            UiEvent::StripTap(segment) => Some(IpcEvent::StripTap(*segment)),
+            UiEvent::StripSwipe(swipe) => Some(IpcEvent::StripSwipe(*swipe)),
+            UiEvent::FilesAdded(paths) => Some(IpcEvent::FilesAdded(paths.clone())),
+            UiEvent::BusVolume(bus, volume) => Some(IpcEvent::BusVolume(bus.clone(), *volume)),
+            UiEvent::TapConfigured(page_id, index) => {
+                Some(IpcEvent::TapConfigured(*page_id, *index))
+            }
+            UiEvent::Ipc(event) => Some(event.clone()),
+            UiEvent::ConfigReloaded(_) | UiEvent::CampaignLoaded(..) | UiEvent::Remote(..) => None,
+        }
+    }
+}
+
+/// The buttons that the deck showed last, which are the ones that can be pressed.
+#[derive(Default)]
+pub(in crate::daemon::ui) struct ButtonIds {
+    deck: HashMap<ButtonId, ButtonRef>,
+    now_playing: HashMap<ButtonId, ButtonRef>,
+}
+
+impl ButtonIds {
+    pub fn flipped(&mut self, page: &[Option<ButtonRef>]) {
+        self.deck = Self::index(page);
+    }
+
+    pub fn flipped_now_playing(&mut self, page: &[Option<ButtonRef>]) {
+        self.now_playing = Self::index(page);
+    }
+
+    fn index(page: &[Option<ButtonRef>]) -> HashMap<ButtonId, ButtonRef> {
+        page.iter()
+            .flatten()
+            .map(|button| (button.id(), button.clone()))
+            .collect()
+    }
+
+    pub fn resolve(&self, id: ButtonId) -> Option<ButtonRef> {
+        self.deck
+            .get(&id)
+            .or_else(|| self.now_playing.get(&id))
+            .cloned()
+    }
+}
@@ -0,0 +1,204 @@
+//! The deck's own labels, such as "Back" or "Vol +", by name, so that `--labels` can translate
+//! or shorten them. Labels of imported buttons come from the profile and are left alone.
+//!
+//! The file is a JSON object from names to labels, e.g. `{"back": "Zurück", "volume_up": "+"}`.
+//! Names that are left out keep their English label.
+
+use eyre::Context;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Label {
+    Back,
+    /// Above the page number on pages of buttons.
+    Next,
+    /// Next on the volume page, which has no page number.
+    NextVolume,
+    /// Ends edit mode.
+    Done,
+    VolumeUp,
+    VolumeDown,
+    Record,
+    /// Cycles the order of the playing tracks.
+    Sort,
+    MicUp,
+    MicDown,
+    Mic,
+    /// Notification of the mic button.
+    Muted,
+    Live,
+    /// Opens the volume page without a track, on decks too narrow for the mic next to one.
+    Mixer,
+    TrackUp,
+    TrackDown,
+    PanLeft,
+    PanRight,
+    Faster,
+    Slower,
+    GainUp,
+    GainDown,
+    /// Cycles the playback mode of a track in edit mode.
+    Mode,
+    FadeIn,
+    FadeOut,
+    Favorites,
+    Search,
+    Library,
+    Unsorted,
+    Campaigns,
+    /// Segment of the touch strip, and the name of the volume page.
+    Volume,
+    /// Name of the page of playing tracks.
+    Playing,
+    /// Name of a track's editor.
+    Edit,
+    /// Toast on entering edit mode.
+    EditModeOn,
+    EditModeOff,
+    /// Toast while another campaign is imported, with `{name}` for its name.
+    Loading,
+    /// Toast on switching to the campaign that is loaded, with `{name}` for its name.
+    AlreadyLoaded,
+    /// Toast on a button that switches to a campaign the daemon wasn't given, with `{name}` for
+    /// its name.
+    UnknownCampaign,
+    /// Toast on tapping an imported action that the deck cannot carry out, with `{name}` for
+    /// the action.
+    NotSupported,
+    /// Toasts when sound files appear in the audio directory, with `{count}` for how many.
+    NewSound,
+    NewSounds,
+    /// Toasts on pinning a track to favorites, with `{label}` for the track's label.
+    Pinned,
+    Unpinned,
+    /// Playback modes, shown in edit mode.
+    ModeOnce,
+    ModeOverlap,
+    ModeLoop,
+    ModeStinger,
+    /// A fade that is turned off, in edit mode.
+    Off,
+    /// Orders of the playing tracks, see `Sort`.
+    OrderStart,
+    OrderNewest,
+    OrderAlphabetical,
+    OrderLongest,
+    OrderFixed,
+    /// Files that can be played, on the library status page.
+    Ok,
+    /// Files that are not there, on the library status page.
+    Missing,
+    /// Files that cannot be decoded, on the library status page.
+    Broken,
+    /// Shown instead of a page that buttons lead to but the configuration lacks, with `{id}` for
+    /// the page's id.
+    MissingPage,
+}
+
+impl Label {
+    fn english(self) -> &'static str {
+        match self {
+            Label::Back => "Back",
+            Label::Next => "Next",
+            Label::NextVolume => "Next\n(Vol)",
+            Label::Done => "Done",
+            Label::VolumeUp => "Vol +",
+            Label::VolumeDown => "Vol -",
+            Label::Record => "Rec",
+            Label::Sort => "Sort",
+            Label::MicUp => "Mic +",
+            Label::MicDown => "Mic -",
+            Label::Mic => "Mic",
+            Label::Muted => "Muted",
+            Label::Live => "Live",
+            Label::Mixer => "Mixer",
+            Label::TrackUp => "Trk +",
+            Label::TrackDown => "Trk -",
+            Label::PanLeft => "Pan L",
+            Label::PanRight => "Pan R",
+            Label::Faster => "Spd +",
+            Label::Slower => "Spd -",
+            Label::GainUp => "Gain +",
+            Label::GainDown => "Gain -",
+            Label::Mode => "Mode",
+            Label::FadeIn => "Fade\nin",
+            Label::FadeOut => "Fade\nout",
+            Label::Favorites => "Favorites",
+            Label::Search => "Search",
+            Label::Library => "Library",
+            Label::Unsorted => "Unsorted",
+            Label::Campaigns => "Campaigns",
+            Label::Volume => "Volume",
+            Label::Playing => "Playing",
+            Label::Edit => "Edit",
+            Label::EditModeOn => "Edit mode: tap a track to change it",
+            Label::EditModeOff => "Edit mode off",
+            Label::Loading => "Loading {name}…",
+            Label::AlreadyLoaded => "{name} is already loaded",
+            Label::UnknownCampaign => "{name} is not a campaign",
+            Label::NotSupported => "{name} is not supported",
+            Label::NewSound => "1 new sound",
+            Label::NewSounds => "{count} new sounds",
+            Label::Pinned => "Pinned {label}",
+            Label::Unpinned => "Unpinned {label}",
+            Label::ModeOnce => "Once",
+            Label::ModeOverlap => "Overlap",
+            Label::ModeLoop => "Loop",
+            Label::ModeStinger => "Stinger",
+            Label::Off => "Off",
+            Label::OrderStart => "Start",
+            Label::OrderNewest => "Newest",
+            Label::OrderAlphabetical => "A–Z",
+            Label::OrderLongest => "Longest",
+            Label::OrderFixed => "Fixed",
+            Label::Ok => "OK",
+            Label::Missing => "Missing",
+            Label::Broken => "Broken",
+            Label::MissingPage => "Page\n{id}\nmissing",
+        }
+    }
+}
+
+/// What the deck calls its own buttons, English unless `--labels` says otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Labels(HashMap<Label, String>);
+
+impl Labels {
+    /// Fails on names that the deck does not know, which are most likely typos.
+    pub async fn load(path: &Path) -> eyre::Result<Labels> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read the labels {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse the labels {}", path.display()))
+    }
+
+    pub fn get(&self, label: Label) -> &str {
+        self.0.get(&label).map_or(label.english(), String::as_str)
+    }
+}
+
+#[cfg(test)]
+impl Labels {
+    pub fn with(mut self, label: Label, text: &str) -> Labels {
+        self.0.insert(label, text.to_string());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Label, Labels};
+
+    #[test]
+    fn test_unknown_names_are_rejected() {
+        let labels: Labels = serde_json::from_str(r#"{"back": "Zurück"}"#).unwrap();
+        assert_eq!(labels.get(Label::Back), "Zurück");
+        assert_eq!(labels.get(Label::Next), "Next");
+        assert!(serde_json::from_str::<Labels>(r#"{"bakc": "Zurück"}"#).is_err());
+    }
+}
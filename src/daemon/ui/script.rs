@@ -0,0 +1,246 @@
+//! Buttons that run small Rhai scripts, for what the built-in behaviors cannot express, e.g.
+//! starting one of several sounds at random.
+//!
+//! A script does not touch the deck while it runs. Its calls queue up steps, which the deck only
+//! carries out once the whole script succeeded, so that a broken script cannot leave a scene
+//! half-started. The engine can neither read files nor import modules, and runs out of
+//! operations long before it could stall the deck. Plugins act on the deck through the same
+//! calls, see [`crate::daemon::plugin`].
+
+use crate::daemon::ui::btn::{Behavior, BoxFuture, ButtonBehavior};
+use crate::daemon::ui::{BtnInvokeStatus, ButtonRef, NoiseDeck};
+#[cfg(feature = "scripts")]
+use rhai::module_resolvers::DummyModuleResolver;
+#[cfg(feature = "scripts")]
+use rhai::{AST, Engine};
+use std::path::PathBuf;
+use std::sync::Arc;
+#[cfg(feature = "scripts")]
+use std::sync::{Mutex, MutexGuard, PoisonError};
+#[cfg(not(feature = "scripts"))]
+use tracing::warn;
+#[cfg(feature = "scripts")]
+use tracing::{debug, info};
+use uuid::Uuid;
+
+#[cfg(feature = "scripts")]
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// What a script asked for, by the names it used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(in crate::daemon) enum Call {
+    Play(String),
+    Stop(String),
+    StopAll,
+    VolumeUp,
+    VolumeDown,
+    Push(String),
+    Goto(String),
+    Back,
+    Home,
+    SetLabel(String),
+    /// Unlike [`Call::SetLabel`], outlives the daemon and re-imports of the configuration.
+    Rename {
+        sound: String,
+        label: String,
+    },
+}
+
+enum Step {
+    Behavior(ButtonBehavior),
+    SetLabel(String),
+    Rename(Arc<PathBuf>, String),
+}
+
+#[cfg(feature = "scripts")]
+pub(in crate::daemon::ui) struct Script {
+    engine: Engine,
+    ast: AST,
+    calls: Arc<Mutex<Vec<Call>>>,
+}
+
+#[cfg(feature = "scripts")]
+impl Script {
+    pub fn compile(source: &str) -> eyre::Result<Script> {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let engine = engine(&calls);
+        let ast = engine
+            .compile(source)
+            .map_err(|e| eyre::eyre!("Script does not compile: {e}"))?;
+        Ok(Script { engine, ast, calls })
+    }
+}
+
+/// Compiles a script with the engine the deck runs it on, for checking a configuration.
+#[cfg(feature = "scripts")]
+pub(crate) fn check(source: &str) -> eyre::Result<()> {
+    Script::compile(source).map(|_| ())
+}
+
+/// The deck disables script buttons, which is no reason to reject the configuration.
+#[cfg(not(feature = "scripts"))]
+pub(crate) fn check(_source: &str) -> eyre::Result<()> {
+    warn!("Not checking a script, noisedeck was built without the `scripts` feature");
+    Ok(())
+}
+
+/// There are no scripts to run.
+#[cfg(not(feature = "scripts"))]
+pub(in crate::daemon::ui) enum Script {}
+
+#[cfg(not(feature = "scripts"))]
+impl Script {
+    pub fn compile(_source: &str) -> eyre::Result<Script> {
+        eyre::bail!("noisedeck was built without the `scripts` feature")
+    }
+}
+
+#[cfg(not(feature = "scripts"))]
+impl Behavior for Script {
+    fn invoke<'a>(
+        &'a self,
+        _deck: &'a mut NoiseDeck,
+        _button: &'a ButtonRef,
+    ) -> BoxFuture<'a, eyre::Result<BtnInvokeStatus>> {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "scripts")]
+impl Behavior for Script {
+    fn invoke<'a>(
+        &'a self,
+        deck: &'a mut NoiseDeck,
+        button: &'a ButtonRef,
+    ) -> BoxFuture<'a, eyre::Result<BtnInvokeStatus>> {
+        Box::pin(async move {
+            lock(&self.calls).clear();
+            let result = self.engine.run_ast(&self.ast);
+            let calls = std::mem::take(&mut *lock(&self.calls));
+            result.map_err(|e| eyre::eyre!("Script failed: {e}"))?;
+            carry_out(deck, button, calls).await
+        })
+    }
+}
+
+pub(in crate::daemon) async fn carry_out(
+    deck: &mut NoiseDeck,
+    button: &ButtonRef,
+    calls: Vec<Call>,
+) -> eyre::Result<BtnInvokeStatus> {
+    // Names are resolved up front for the same reason the calls are queued
+    let steps = calls
+        .into_iter()
+        .map(|call| resolve(deck, call))
+        .collect::<eyre::Result<Vec<_>>>()?;
+    let mut status = BtnInvokeStatus {
+        skip_refresh: true,
+        ..BtnInvokeStatus::default()
+    };
+    for step in steps {
+        let step_status = match step {
+            Step::Behavior(behavior) => behavior.invoke(deck, button).await?,
+            Step::SetLabel(label) => {
+                button.inner.data.write().await.label = Arc::new(label);
+                BtnInvokeStatus::default()
+            }
+            Step::Rename(path, label) => {
+                deck.rename_track(&path, label).await?;
+                BtnInvokeStatus::default()
+            }
+        };
+        status.skip_refresh &= step_status.skip_refresh;
+    }
+    Ok(status)
+}
+
+#[cfg(feature = "scripts")]
+fn engine(calls: &Arc<Mutex<Vec<Call>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_module_resolver(DummyModuleResolver::new())
+        .disable_symbol("eval")
+        .on_print(|text| info!("Script: {text}"))
+        .on_debug(|text, _, pos| debug!("Script at {pos}: {text}"));
+
+    let queue = |call: fn(String) -> Call| {
+        let calls = calls.clone();
+        move |arg: &str| lock(&calls).push(call(arg.to_string()))
+    };
+    let queue_unit = |call: Call| {
+        let calls = calls.clone();
+        move || lock(&calls).push(call.clone())
+    };
+    engine
+        .register_fn("play", queue(Call::Play))
+        .register_fn("stop", queue(Call::Stop))
+        .register_fn("stop_all", queue_unit(Call::StopAll))
+        .register_fn("volume_up", queue_unit(Call::VolumeUp))
+        .register_fn("volume_down", queue_unit(Call::VolumeDown))
+        .register_fn("push", queue(Call::Push))
+        .register_fn("goto", queue(Call::Goto))
+        .register_fn("back", queue_unit(Call::Back))
+        .register_fn("home", queue_unit(Call::Home))
+        .register_fn("set_label", queue(Call::SetLabel))
+        .register_fn("rename", {
+            let calls = calls.clone();
+            move |sound: &str, label: &str| {
+                lock(&calls).push(Call::Rename {
+                    sound: sound.to_string(),
+                    label: label.to_string(),
+                });
+            }
+        });
+    engine
+}
+
+/// Only ever held for a single push, so even a poisoned queue is consistent.
+#[cfg(feature = "scripts")]
+fn lock(calls: &Mutex<Vec<Call>>) -> MutexGuard<'_, Vec<Call>> {
+    calls.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn resolve(deck: &NoiseDeck, call: Call) -> eyre::Result<Step> {
+    let behavior = match call {
+        Call::Play(sound) => ButtonBehavior::Play(track_named(deck, &sound)?),
+        Call::Stop(sound) => ButtonBehavior::Stop(track_named(deck, &sound)?),
+        Call::StopAll => ButtonBehavior::StopAll,
+        Call::VolumeUp => ButtonBehavior::VolumeUp,
+        Call::VolumeDown => ButtonBehavior::VolumeDown,
+        Call::Push(page) => ButtonBehavior::Push(page_named(deck, &page)?),
+        Call::Goto(page) => ButtonBehavior::Goto(page_named(deck, &page)?),
+        Call::Back => ButtonBehavior::Pop,
+        Call::Home => ButtonBehavior::PopN(usize::MAX),
+        Call::SetLabel(label) => return Ok(Step::SetLabel(label)),
+        Call::Rename { sound, label } => {
+            return Ok(Step::Rename(track_named(deck, &sound)?, label));
+        }
+    };
+    Ok(Step::Behavior(behavior))
+}
+
+/// Scripts name sounds by the end of their path, e.g. `rain.mp3` or `ambience/rain.mp3`, since
+/// where the audio files are kept is up to the command line.
+fn track_named(deck: &NoiseDeck, sound: &str) -> eyre::Result<Arc<PathBuf>> {
+    let mut matching = deck.tracks.keys().filter(|path| path.ends_with(sound));
+    match (matching.next(), matching.next()) {
+        (Some(path), None) => Ok(path.clone()),
+        (None, _) => eyre::bail!("No sound on any page matches '{sound}'"),
+        (Some(_), Some(_)) => eyre::bail!("Several sounds match '{sound}', name more of the path"),
+    }
+}
+
+fn page_named(deck: &NoiseDeck, page: &str) -> eyre::Result<Uuid> {
+    if let Ok(id) = Uuid::parse_str(page)
+        && deck.config.pages.contains_key(&id)
+    {
+        return Ok(id);
+    }
+    deck.config
+        .pages
+        .iter()
+        .find(|(_, p)| p.name == page)
+        .map(|(id, _)| *id)
+        .ok_or_else(|| eyre::eyre!("There is no page '{page}'"))
+}
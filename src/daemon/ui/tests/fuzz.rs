@@ -0,0 +1,186 @@
+//! Random sequences of taps and holds against the test harness, with the checks that have to
+//! pass after every one of them rather than particular outcomes: every page fills the deck
+//! exactly, shows no button twice, and pages no further than it has pages. These find the
+//! layout bugs that hand-written tests miss, such as offsets that run past the end of a page
+//! after paging back and forth.
+//!
+//! Time is paused, so that waiting for the deck to go quiet takes no real time.
+
+use super::harness::{TestHarness, create_test_config, sound_button, with_test_harness};
+use crate::config::{self, Config};
+use crate::daemon::audio::AudioEvent;
+use crate::daemon::ui::{ButtonRef, UiCommand, UiEvent};
+use kira::sound::PlaybackState;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use uuid::Uuid;
+
+/// Of the Stream Deck that the harness lays pages out for.
+const KEYS: usize = 15;
+
+/// Longer than the deck takes for any event, in paused time.
+const QUIET: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone)]
+enum Action {
+    Tap(usize),
+    Hold(usize),
+    /// The engine reports the track on the key as playing, which fills the dynamic keys.
+    Started(usize),
+    Stopped(usize),
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        4 => (0..KEYS).prop_map(Action::Tap),
+        2 => (0..KEYS).prop_map(Action::Hold),
+        1 => (0..KEYS).prop_map(Action::Started),
+        1 => (0..KEYS).prop_map(Action::Stopped),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 48,
+        failure_persistence: None,
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn test_random_navigation_keeps_pages_consistent(
+        page_sizes in vec(0..40usize, 1..4),
+        actions in vec(action(), 1..40),
+    ) {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()?;
+        let result = runtime.block_on(with_test_harness(async |harness| {
+            harness.reload_config(config_with_pages(&page_sizes)).await?;
+            settle(harness).await?;
+            for action in &actions {
+                act(harness, action).await?;
+                settle(harness)
+                    .await
+                    .map_err(|e| e.wrap_err(format!("after {action:?}")))?;
+            }
+            Ok(())
+        }));
+        if let Err(e) = result {
+            return Err(TestCaseError::fail(format!("{e:#}")));
+        }
+    }
+}
+
+/// The start page links to pages with this many sounds each. Every third sound asks for a key
+/// of its own, like imported ones do, and some of them ask for the same key.
+fn config_with_pages(page_sizes: &[usize]) -> Config {
+    let mut config = create_test_config();
+    let start_page = config.start_page;
+    for (p, &n) in page_sizes.iter().enumerate() {
+        let id = Uuid::from_u128(100 + p as u128);
+        let buttons = (0..n)
+            .map(|i| {
+                let mut button = sound_button(&format!("Sound {p}.{i}"), &format!("{p}/{i}.mp3"));
+                if i % 3 == 0 {
+                    button.position = Some(((i % 5) as u8, (i / 5 % 4) as u8));
+                }
+                button
+            })
+            .collect();
+        let page = config::Page {
+            name: format!("Page {p}"),
+            buttons,
+            bus: None,
+            defaults: Default::default(),
+        };
+        config.pages.insert(id, Arc::new(page));
+        let start = Arc::make_mut(config.pages.get_mut(&start_page).unwrap());
+        start.buttons.push(config::Button::new(
+            &format!("Open {p}"),
+            config::ButtonBehavior::PushPage(id),
+        ));
+    }
+    config
+}
+
+async fn act(harness: &mut TestHarness, action: &Action) -> eyre::Result<()> {
+    let (Action::Tap(key) | Action::Hold(key) | Action::Started(key) | Action::Stopped(key)) =
+        *action;
+    let Some(button) = harness.current_buttons[key].clone() else {
+        return Ok(());
+    };
+    match action {
+        Action::Tap(_) => harness.ui_event_tx.send(UiEvent::ButtonTap(button)).await?,
+        Action::Hold(_) => {
+            harness
+                .ui_event_tx
+                .send(UiEvent::ButtonHold(button))
+                .await?
+        }
+        Action::Started(_) | Action::Stopped(_) => {
+            let Some(track) = button.inner.track.clone() else {
+                return Ok(());
+            };
+            let playback = match action {
+                Action::Started(_) => PlaybackState::Playing,
+                _ => PlaybackState::Stopped,
+            };
+            track.update_mock_state(playback).await?;
+            harness
+                .audio_event_tx
+                .send(AudioEvent::TrackStateChanged(track))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Takes everything the deck sends until it goes quiet, checking every page it shows.
+async fn settle(harness: &mut TestHarness) -> eyre::Result<()> {
+    loop {
+        tokio::select! {
+            command = harness.ui_command_rx.recv() => match command {
+                Some(UiCommand::Flip(page)) => {
+                    check_page(&page).await?;
+                    harness.current_buttons = page;
+                }
+                Some(_) => (),
+                None => eyre::bail!("The deck stopped"),
+            },
+            command = harness.audio_command_rx.recv() => {
+                eyre::ensure!(command.is_some(), "The deck stopped");
+            }
+            _ = sleep(QUIET) => break,
+        }
+    }
+    eyre::ensure!(!harness.deck_handle.is_finished(), "The deck stopped");
+    Ok(())
+}
+
+async fn check_page(page: &[Option<ButtonRef>]) -> eyre::Result<()> {
+    eyre::ensure!(page.len() == KEYS, "{} keys instead of {KEYS}", page.len());
+    let buttons: Vec<_> = page.iter().flatten().collect();
+    for (i, button) in buttons.iter().enumerate() {
+        let label = button.read().await.label;
+        eyre::ensure!(!buttons[..i].contains(button), "'{label}' is shown twice");
+        // "Next\n2/3\n…" on pages of buttons, other pages say what they are
+        let Some((current, total)) = label
+            .strip_prefix("Next\n")
+            .and_then(|rest| rest.lines().next()?.split_once('/'))
+            .and_then(|(current, total)| {
+                Some((current.parse::<usize>().ok()?, total.parse::<usize>().ok()?))
+            })
+        else {
+            continue;
+        };
+        eyre::ensure!(
+            current <= usize::max(total, 1),
+            "Shows page {current} of {total}"
+        );
+    }
+    Ok(())
+}
@@ -1,50 +1,105 @@
-//! Testing harness for the `daemon::ui` test suite.
-//!
-//! Don't add tests here. Add tests in the `daemon::ui` module instead.
-
-use crate::{
-    config::{self, ButtonBehavior, Config, PlaySoundSettings, PlaybackMode},
-    daemon::{
-        audio::{AudioCommand, AudioEvent},
-        ui::{ButtonRef, NoiseDeck, UiCommand, UiEvent},
-    },
-};
-use assert_matches::assert_matches;
-use elgato_streamdeck::info::Kind;
-use std::{collections::HashMap, sync::Arc, time::Duration};
-use tokio::{
-    sync::mpsc::{Receiver, Sender},
-    time::timeout,
-};
+//! Testing harness for the `daemon::ui` test suite.
+//!
+//! Don't add tests here. Add tests in the `daemon::ui` module instead.
+
+use crate::{
+    config::{self, ButtonBehavior, Config, PlaySoundSettings, PlaybackMode},
+    daemon::{
+        audio::{AudioCommand, AudioEvent, InputStatus, Track},
+        ui::{
+            ButtonRef, ButtonStyle, MediaStatus, NoiseDeck, StripSegment, Transport, UiCommand,
+            UiEvent, UiSettings,
+        },
+    },
+    util::Switch,
+};
+use assert_matches::assert_matches;
+use elgato_streamdeck::info::Kind;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::{
+    sync::mpsc::{Receiver, Sender},
+    sync::watch,
+    time::timeout,
+};
 use uuid::Uuid;
 
 pub const NAV_BUTTON_LABEL: &str = "Go to Target";
 pub const BACK_BUTTON_LABEL: &str = "Back";
 pub const SOUND_BUTTON_LABEL: &str = "Play Sound";
+/// The page the test configuration starts on, with the button that leads to [`TARGET_PAGE`].
+pub const START_PAGE: Uuid = Uuid::from_u128(1);
+/// The page of the test configuration with the sound.
+pub const TARGET_PAGE: Uuid = Uuid::from_u128(2);
+/// The deck that the harness lays pages out for.
+pub const KIND: Kind = Kind::Mk2;
 
 use kira::sound::PlaybackState;
 
 pub struct MockTrackState {
     pub playback: PlaybackState,
+    pub volume_offset_db: f64,
+    pub pan_override: Option<f32>,
+    pub playback_rate_override: Option<f64>,
+    pub buffering: bool,
+    pub load_error: Option<Arc<String>>,
+    pub rem_duration: Option<Duration>,
+    pub duration: Option<Duration>,
+    pub instances: usize,
 }
 
 impl Default for MockTrackState {
     fn default() -> Self {
         MockTrackState {
             playback: PlaybackState::Stopped,
+            volume_offset_db: 0.0,
+            pan_override: None,
+            playback_rate_override: None,
+            buffering: false,
+            load_error: None,
+            rem_duration: None,
+            duration: None,
+            instances: 0,
         }
     }
 }
 
 impl crate::daemon::audio::TrackState for MockTrackState {
     fn rem_duration(&self) -> Option<std::time::Duration> {
-        None
+        self.rem_duration
+    }
+
+    fn duration(&self) -> Option<std::time::Duration> {
+        self.duration
     }
 
     fn playback_state(&self) -> PlaybackState {
         self.playback
     }
 
+    fn volume_offset_db(&self) -> f64 {
+        self.volume_offset_db
+    }
+
+    fn pan_override(&self) -> Option<f32> {
+        self.pan_override
+    }
+
+    fn playback_rate_override(&self) -> Option<f64> {
+        self.playback_rate_override
+    }
+
+    fn is_buffering(&self) -> bool {
+        self.buffering
+    }
+
+    fn load_error(&self) -> Option<Arc<String>> {
+        self.load_error.clone()
+    }
+
+    fn instances(&self) -> usize {
+        self.instances
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -53,52 +108,73 @@ impl crate::daemon::audio::TrackState for MockTrackState {
         self
     }
 }
-
-pub struct TestHarness {
-    pub ui_event_tx: Sender<UiEvent>,
-    pub ui_command_rx: Receiver<UiCommand>,
-    pub audio_command_rx: Receiver<AudioCommand>,
-    pub audio_event_tx: Sender<AudioEvent>,
-    pub deck_handle: tokio::task::JoinHandle<eyre::Result<()>>,
-    pub current_buttons: Vec<Option<ButtonRef>>,
-}
-
-impl TestHarness {
-    async fn new() -> eyre::Result<Self> {
-        let (mut deck, ui_event_tx, mut ui_command_rx, audio_event_tx, audio_command_rx) = {
-            let config = create_test_config();
-            NoiseDeck::new(Kind::Mk2, config)
-        };
-
-        let deck_handle = tokio::spawn(async move {
-            deck.init().await.unwrap();
-            deck.run().await
-        });
-
-        // Consume initial display and store current buttons
-        let initial_command = timeout(Duration::from_millis(100), ui_command_rx.recv())
-            .await
-            .expect("Should receive initial command")
-            .expect("Should receive command");
-
-        let current_buttons = match initial_command {
-            UiCommand::Flip(buttons) => buttons,
-            _ => panic!(
-                "Expected initial UiCommand::Flip, got {:?}",
-                initial_command
-            ),
-        };
-
-        Ok(TestHarness {
-            ui_event_tx,
-            ui_command_rx,
-            audio_command_rx,
-            audio_event_tx,
-            deck_handle,
-            current_buttons,
-        })
-    }
-
+
+pub struct TestHarness {
+    pub ui_event_tx: Sender<UiEvent>,
+    pub ui_command_rx: Receiver<UiCommand>,
+    pub audio_command_rx: Receiver<AudioCommand>,
+    pub audio_event_tx: Sender<AudioEvent>,
+    pub deck_handle: tokio::task::JoinHandle<eyre::Result<()>>,
+    pub current_buttons: Vec<Option<ButtonRef>>,
+    /// Tracks that the deck asked the audio engine to preload at startup.
+    pub preloaded: Vec<Arc<Track>>,
+    pub media_status: watch::Receiver<MediaStatus>,
+}
+
+impl TestHarness {
+    async fn new(settings: UiSettings) -> eyre::Result<Self> {
+        let (mut deck, ui_event_tx, mut ui_command_rx, audio_event_tx, mut audio_command_rx) = {
+            let config = Arc::new(create_test_config());
+            NoiseDeck::new(KIND, config, settings)
+        };
+
+        let media_status = deck.media_status();
+        let deck_handle = tokio::spawn(async move {
+            deck.init().await.unwrap();
+            deck.run().await
+        });
+
+        // Consume initial display and store current buttons
+        let initial_command = timeout(Duration::from_millis(100), ui_command_rx.recv())
+            .await
+            .expect("Should receive initial command")
+            .expect("Should receive command");
+
+        let current_buttons = match initial_command {
+            UiCommand::Flip(buttons) => buttons,
+            _ => panic!(
+                "Expected initial UiCommand::Flip, got {:?}",
+                initial_command
+            ),
+        };
+
+        assert_matches!(
+            timeout(Duration::from_millis(100), audio_command_rx.recv())
+                .await
+                .expect("Should ask for the global volume"),
+            Some(AudioCommand::GetGlobalVolume)
+        );
+
+        let preloaded = match timeout(Duration::from_millis(100), audio_command_rx.recv())
+            .await
+            .expect("Should receive initial preload")
+        {
+            Some(AudioCommand::Preload(tracks)) => tracks,
+            other => panic!("Expected initial AudioCommand::Preload, got {:?}", other),
+        };
+
+        Ok(TestHarness {
+            ui_event_tx,
+            ui_command_rx,
+            audio_command_rx,
+            audio_event_tx,
+            deck_handle,
+            current_buttons,
+            preloaded,
+            media_status,
+        })
+    }
+
     pub async fn tap_button(&mut self, label: &str) -> eyre::Result<()> {
         let button = self
             .find_button_by_label(label)
@@ -117,38 +193,131 @@ impl TestHarness {
 
         self.ui_event_tx.send(UiEvent::ButtonHold(button)).await?;
         Ok(())
-    }
-
-    pub async fn expect_navigation(&mut self) -> eyre::Result<()> {
-        let command = timeout(Duration::from_millis(100), self.ui_command_rx.recv())
-            .await
-            .expect("Should receive UI command within timeout")
-            .expect("Should receive UI command");
-
-        match command {
-            UiCommand::Flip(buttons) => {
-                self.current_buttons = buttons;
-            }
-            _ => {
-                return Err(eyre::eyre!(
-                    "Expected Flip command for navigation, got {:?}",
-                    command
-                ));
-            }
-        }
-        Ok(())
-    }
-
-    pub async fn expect_on_page_with_button(&self, label: &str) -> eyre::Result<()> {
-        if self.find_button_by_label(label).await.is_none() {
-            return Err(eyre::eyre!(
-                "Expected to be on page with '{}' button",
-                label
-            ));
-        }
-        Ok(())
-    }
-
+    }
+
+    pub async fn transport(&mut self, transport: Transport) -> eyre::Result<()> {
+        self.ui_event_tx.send(UiEvent::Transport(transport)).await?;
+        Ok(())
+    }
+
+    pub async fn reload_config(&mut self, config: Config) -> eyre::Result<()> {
+        self.ui_event_tx
+            .send(UiEvent::ConfigReloaded(Arc::new(config)))
+            .await?;
+        Ok(())
+    }
+
+    /// Reloads `config` and waits for what the deck does on every reload: it sets up the buses,
+    /// shows its page again and preloads the sounds.
+    pub async fn reload(&mut self, config: Config) -> eyre::Result<()> {
+        self.reload_config(config).await?;
+        assert_matches!(
+            self.expect_audio_command().await?,
+            AudioCommand::ConfigureBuses(_)
+        );
+        self.expect_navigation().await?;
+        assert_matches!(self.expect_audio_command().await?, AudioCommand::Preload(_));
+        Ok(())
+    }
+
+    /// Reloads the test configuration with `buttons` after those that `page` already has.
+    pub async fn reload_with_buttons(
+        &mut self,
+        page: Uuid,
+        buttons: impl IntoIterator<Item = config::Button>,
+    ) -> eyre::Result<()> {
+        let mut config = create_test_config();
+        page_mut(&mut config, page).buttons.extend(buttons);
+        self.reload(config).await
+    }
+
+    pub async fn expect_navigation(&mut self) -> eyre::Result<()> {
+        let command = timeout(Duration::from_millis(100), self.ui_command_rx.recv())
+            .await
+            .expect("Should receive UI command within timeout")
+            .expect("Should receive UI command");
+
+        match command {
+            UiCommand::Flip(buttons) => {
+                self.current_buttons = buttons;
+            }
+            _ => {
+                return Err(eyre::eyre!(
+                    "Expected Flip command for navigation, got {:?}",
+                    command
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for the next page like [`expect_navigation`](Self::expect_navigation) and hands it
+    /// to `check` as a grid, for tests about where buttons are rather than whether they are there.
+    pub async fn expect_layout(&mut self, check: impl FnOnce(&Layout)) -> eyre::Result<()> {
+        self.expect_navigation().await?;
+        check(&self.layout().await);
+        Ok(())
+    }
+
+    /// The current page as the deck shows it.
+    pub async fn layout(&self) -> Layout {
+        let (rows, cols) = KIND.key_layout();
+        let mut grid = vec![vec![None; cols as usize]; rows as usize];
+        for (key, button) in self.current_buttons.iter().enumerate() {
+            let Some(button) = button else { continue };
+            let (row, col) = (key / cols as usize, key % cols as usize);
+            if let Some(cell) = grid.get_mut(row).and_then(|r| r.get_mut(col)) {
+                *cell = Some(button.read().await.label.to_string());
+            }
+        }
+        Layout(grid)
+    }
+
+    pub async fn expect_strip(&mut self) -> eyre::Result<Vec<StripSegment>> {
+        let command = timeout(Duration::from_millis(100), self.ui_command_rx.recv())
+            .await
+            .expect("Should receive UI command within timeout")
+            .expect("Should receive UI command");
+
+        match command {
+            UiCommand::Strip(segments) => Ok(segments),
+            _ => Err(eyre::eyre!("Expected Strip command, got {:?}", command)),
+        }
+    }
+
+    /// Returns the labels of the second deck's keys, `None` for blank keys.
+    pub async fn expect_now_playing_deck(&mut self) -> eyre::Result<Vec<Option<String>>> {
+        let command = timeout(Duration::from_millis(100), self.ui_command_rx.recv())
+            .await
+            .expect("Should receive UI command within timeout")
+            .expect("Should receive UI command");
+
+        let UiCommand::FlipNowPlaying(buttons) = command else {
+            return Err(eyre::eyre!(
+                "Expected FlipNowPlaying command, got {:?}",
+                command
+            ));
+        };
+        let mut labels = Vec::with_capacity(buttons.len());
+        for button in buttons {
+            labels.push(match button {
+                Some(button) => Some(button.read().await.label.to_string()),
+                None => None,
+            });
+        }
+        Ok(labels)
+    }
+
+    pub async fn expect_on_page_with_button(&self, label: &str) -> eyre::Result<()> {
+        if self.find_button_by_label(label).await.is_none() {
+            return Err(eyre::eyre!(
+                "Expected to be on page with '{}' button",
+                label
+            ));
+        }
+        Ok(())
+    }
+
     pub async fn expect_audio_command(&mut self) -> eyre::Result<AudioCommand> {
         timeout(Duration::from_millis(100), self.audio_command_rx.recv())
             .await
@@ -162,24 +331,36 @@ impl TestHarness {
             AudioCommand::SetGlobalVolume(volume) => Ok(volume),
             _ => Err(eyre::eyre!("Expected SetGlobalVolume command, got {:?}", command)),
         }
-    }
-
-    pub async fn expect_refresh(&mut self) -> eyre::Result<()> {
-        let command = timeout(Duration::from_millis(100), self.ui_command_rx.recv())
-            .await
-            .expect("Should receive UI command within timeout")
-            .expect("Should receive UI command");
-
-        assert_matches!(command, UiCommand::Refresh);
-        Ok(())
-    }
-
-    pub async fn expect_no_audio_commands(&mut self) -> eyre::Result<()> {
-        let result = timeout(Duration::from_millis(50), self.audio_command_rx.recv()).await;
-        assert_matches!(result, Err(_)); // Timeout is expected - no commands
-        Ok(())
-    }
-
+    }
+
+    pub async fn expect_refresh(&mut self) -> eyre::Result<()> {
+        let command = timeout(Duration::from_millis(100), self.ui_command_rx.recv())
+            .await
+            .expect("Should receive UI command within timeout")
+            .expect("Should receive UI command");
+
+        assert_matches!(command, UiCommand::Refresh);
+        Ok(())
+    }
+
+    pub async fn expect_toast(&mut self) -> eyre::Result<String> {
+        let command = timeout(Duration::from_millis(100), self.ui_command_rx.recv())
+            .await
+            .expect("Should receive UI command within timeout")
+            .expect("Should receive UI command");
+
+        match command {
+            UiCommand::Toast(message, _) => Ok(message),
+            _ => Err(eyre::eyre!("Expected Toast command, got {:?}", command)),
+        }
+    }
+
+    pub async fn expect_no_audio_commands(&mut self) -> eyre::Result<()> {
+        let result = timeout(Duration::from_millis(50), self.audio_command_rx.recv()).await;
+        assert_matches!(result, Err(_)); // Timeout is expected - no commands
+        Ok(())
+    }
+
     pub async fn simulate_track_state_changed(&mut self, sound_path: &str) -> eyre::Result<()> {
         self.simulate_track_state_changed_with_playback(
             sound_path,
@@ -197,11 +378,11 @@ impl TestHarness {
             Arc::new(PathBuf::from(sound_path)),
             PlaySoundSettings {
                 volume: 0.8,
-                mode: PlaybackMode::PlayStop,
                 fade_in: Some(Duration::from_millis(100)),
                 fade_out: Some(Duration::from_millis(100)),
+                ..PlaySoundSettings::new(PlaybackMode::PlayStop)
             },
-            Box::new(MockTrackState { playback: PlaybackState::Stopped }),
+            Box::new(MockTrackState::default()),
         ));
 
         self.audio_event_tx
@@ -237,26 +418,180 @@ impl TestHarness {
 
         Ok(())
     }
-
+
+    pub async fn simulate_playback(
+        &mut self,
+        label: &str,
+        playback: PlaybackState,
+    ) -> eyre::Result<()> {
+        self.simulate_mock(label, |mock| mock.playback = playback)
+            .await
+    }
+
+    /// Pretends the engine stopped the track at this position, as it does for tracks that resume.
+    pub async fn simulate_stopped_at(
+        &mut self,
+        label: &str,
+        position: Duration,
+    ) -> eyre::Result<()> {
+        self.track_of(label)
+            .await?
+            .set_resume_position(Some(position));
+        self.simulate_mock(label, |mock| mock.playback = PlaybackState::Stopped)
+            .await
+    }
+
+    pub async fn resume_position(&self, label: &str) -> eyre::Result<Option<Duration>> {
+        Ok(self.track_of(label).await?.resume_position())
+    }
+
+    /// Pretends that several instances of an overlapping track are playing at once.
+    pub async fn simulate_instances(&mut self, label: &str, instances: usize) -> eyre::Result<()> {
+        self.simulate_mock(label, |mock| mock.instances = instances)
+            .await
+    }
+
+    pub async fn label_at(&self, key: usize) -> Option<String> {
+        let button = self.current_buttons.get(key)?.as_ref()?;
+        Some(button.read().await.label.to_string())
+    }
+
+    pub async fn simulate_recording_changed(&mut self, recording: Switch) -> eyre::Result<()> {
+        use crate::daemon::audio::AudioEvent;
+
+        self.audio_event_tx
+            .send(AudioEvent::RecordingChanged(recording))
+            .await?;
+        Ok(())
+    }
+
+    /// `label` names the button of the track the error is about, on the current page.
+    pub async fn simulate_audio_error(
+        &mut self,
+        label: Option<&str>,
+        message: &str,
+    ) -> eyre::Result<()> {
+        use crate::daemon::audio::AudioEvent;
+
+        let track = match label {
+            Some(label) => Some(self.track_of(label).await?),
+            None => None,
+        };
+        self.audio_event_tx
+            .send(AudioEvent::Error {
+                track,
+                message: message.to_string(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn simulate_global_volume_changed(&mut self, global_db: f64) -> eyre::Result<()> {
+        use crate::daemon::audio::AudioEvent;
+
+        self.audio_event_tx
+            .send(AudioEvent::GlobalVolumeChanged(global_db))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn simulate_input_changed(&mut self, status: InputStatus) -> eyre::Result<()> {
+        use crate::daemon::audio::AudioEvent;
+
+        self.audio_event_tx
+            .send(AudioEvent::InputChanged(status))
+            .await?;
+        Ok(())
+    }
+
+    /// Pretends the audio engine applied a volume offset to the sound button's track.
+    pub async fn simulate_track_volume_offset(
+        &mut self,
+        volume_offset_db: f64,
+    ) -> eyre::Result<()> {
+        self.simulate_mock(SOUND_BUTTON_LABEL, |mock| {
+            mock.volume_offset_db = volume_offset_db
+        })
+        .await
+    }
+
+    /// Pretends the sound button's track is waiting for stream data.
+    pub async fn simulate_track_buffering(&mut self, buffering: bool) -> eyre::Result<()> {
+        self.simulate_mock(SOUND_BUTTON_LABEL, |mock| mock.buffering = buffering)
+            .await
+    }
+
+    /// Pretends the sound button's track is playing with the given time left.
+    pub async fn simulate_track_remaining(&mut self, rem_duration: Duration) -> eyre::Result<()> {
+        self.simulate_mock(SOUND_BUTTON_LABEL, |mock| {
+            mock.rem_duration = Some(rem_duration)
+        })
+        .await
+    }
+
+    /// Like [`simulate_track_remaining`](Self::simulate_track_remaining), for a track of the
+    /// given length.
+    pub async fn simulate_track_progress(
+        &mut self,
+        rem_duration: Duration,
+        duration: Duration,
+    ) -> eyre::Result<()> {
+        self.simulate_mock(SOUND_BUTTON_LABEL, |mock| {
+            mock.rem_duration = Some(rem_duration);
+            mock.duration = Some(duration);
+        })
+        .await
+    }
+
+    /// Pretends preloading found that the button's file cannot be played.
+    pub async fn simulate_track_load_error(
+        &mut self,
+        label: &str,
+        load_error: Option<&str>,
+    ) -> eyre::Result<()> {
+        self.simulate_mock(label, |mock| {
+            mock.load_error = load_error.map(|e| Arc::new(e.to_string()))
+        })
+        .await
+    }
+
+    /// The track of the button with this label on the current page.
+    async fn track_of(&self, label: &str) -> eyre::Result<Arc<Track>> {
+        self.find_button_by_label(label)
+            .await
+            .and_then(|button| button.inner.track.clone())
+            .ok_or_else(|| eyre::eyre!("No track button '{}' on current page", label))
+    }
+
+    /// Changes the state of the button's track, and tells the deck like the audio engine would.
+    async fn simulate_mock(
+        &mut self,
+        label: &str,
+        update: impl FnOnce(&mut MockTrackState),
+    ) -> eyre::Result<()> {
+        let track = self.track_of(label).await?;
+        track.update_mock(update).await?;
+        self.audio_event_tx
+            .send(AudioEvent::TrackStateChanged(track))
+            .await?;
+        Ok(())
+    }
+
     async fn find_button_by_label(&self, label: &str) -> Option<ButtonRef> {
-        for opt_btn in &self.current_buttons {
-            if let Some(btn) = opt_btn {
-                let button_data = btn.read().await;
-                if button_data.label.as_str() == label {
-                    return Some(btn.clone());
-                }
+        for btn in self.current_buttons.iter().flatten() {
+            let button_data = btn.read().await;
+            if button_data.label.as_str() == label {
+                return Some(btn.clone());
             }
         }
         None
     }
 
     pub async fn find_button_by_label_prefix(&self, label_prefix: &str) -> Option<ButtonRef> {
-        for opt_btn in &self.current_buttons {
-            if let Some(btn) = opt_btn {
-                let button_data = btn.read().await;
-                if button_data.label.as_str().starts_with(label_prefix) {
-                    return Some(btn.clone());
-                }
+        for btn in self.current_buttons.iter().flatten() {
+            let button_data = btn.read().await;
+            if button_data.label.as_str().starts_with(label_prefix) {
+                return Some(btn.clone());
             }
         }
         None
@@ -280,57 +615,113 @@ impl TestHarness {
         let data = btn.read().await;
         Ok(data.notification.clone())
     }
-
-    async fn cleanup(self) {
-        drop(self.ui_event_tx);
-        let _ = timeout(Duration::from_millis(100), self.deck_handle).await;
-    }
-}
-
-/// Runs a test with automatic harness cleanup
-pub async fn with_test_harness<F>(test_fn: F) -> eyre::Result<()>
-where
-    F: AsyncFn(&mut TestHarness) -> eyre::Result<()>,
-{
-    let mut harness = TestHarness::new().await?;
-    let result = test_fn(&mut harness).await;
-    harness.cleanup().await;
-    result
-}
-
-fn create_test_config() -> Arc<Config> {
-    let start_page = Uuid::from_u128(1);
-    let target_page = Uuid::from_u128(2);
-
-    let mut pages = HashMap::new();
-
-    // Main page with a navigation button
-    let main_page = config::Page {
-        name: "Main".to_string(),
-        buttons: vec![config::Button {
-            label: Arc::new(NAV_BUTTON_LABEL.to_string()),
-            behavior: ButtonBehavior::PushPage(target_page),
-        }],
-    };
-    pages.insert(start_page, Arc::new(main_page));
-
-    // Target page with a sound button
-    let target_page_config = config::Page {
-        name: "Target".to_string(),
-        buttons: vec![config::Button {
-            label: Arc::new(SOUND_BUTTON_LABEL.to_string()),
-            behavior: ButtonBehavior::PlaySound(
-                Arc::new("test_sound.mp3".to_string()),
-                PlaySoundSettings {
-                    volume: 0.8,
-                    mode: PlaybackMode::PlayStop,
-                    fade_in: Some(Duration::from_millis(100)),
-                    fade_out: Some(Duration::from_millis(100)),
-                },
-            ),
-        }],
-    };
-    pages.insert(target_page, Arc::new(target_page_config));
-
-    Arc::new(Config { pages, start_page })
-}
+
+    pub async fn button_style(&self, label: &str) -> eyre::Result<ButtonStyle> {
+        let btn = self
+            .find_button_by_label(label)
+            .await
+            .ok_or_else(|| eyre::eyre!("Button '{}' not found on current page", label))?;
+        let data = btn.read().await;
+        Ok(data.style)
+    }
+
+    async fn cleanup(self) {
+        drop(self.ui_event_tx);
+        let _ = timeout(Duration::from_millis(100), self.deck_handle).await;
+    }
+}
+
+/// The labels of a page by row and column, `None` for blank keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout(pub Vec<Vec<Option<String>>>);
+
+impl Layout {
+    pub fn at(&self, row: usize, col: usize) -> Option<&str> {
+        self.0.get(row)?.get(col)?.as_deref()
+    }
+
+    /// Row and column of the first key whose label starts with `label_prefix`, row by row.
+    pub fn find(&self, label_prefix: &str) -> Option<(usize, usize)> {
+        self.0.iter().enumerate().find_map(|(row, labels)| {
+            let col = labels
+                .iter()
+                .position(|l| l.as_deref().is_some_and(|l| l.starts_with(label_prefix)))?;
+            Some((row, col))
+        })
+    }
+}
+
+/// Runs a test with automatic harness cleanup
+pub async fn with_test_harness<F>(test_fn: F) -> eyre::Result<()>
+where
+    F: AsyncFn(&mut TestHarness) -> eyre::Result<()>,
+{
+    with_test_harness_settings(UiSettings::default(), test_fn).await
+}
+
+pub async fn with_test_harness_settings<F>(settings: UiSettings, test_fn: F) -> eyre::Result<()>
+where
+    F: AsyncFn(&mut TestHarness) -> eyre::Result<()>,
+{
+    let mut harness = TestHarness::new(settings).await?;
+    let result = test_fn(&mut harness).await;
+    harness.cleanup().await;
+    result
+}
+
+pub fn create_test_config() -> Config {
+    let mut pages = HashMap::new();
+
+    // Main page with a navigation button
+    let main_page = config::Page {
+        name: "Main".to_string(),
+        bus: None,
+        buttons: vec![config::Button::new(
+            NAV_BUTTON_LABEL,
+            ButtonBehavior::PushPage(TARGET_PAGE),
+        )],
+        defaults: Default::default(),
+    };
+    pages.insert(START_PAGE, Arc::new(main_page));
+
+    // Target page with a sound button
+    let target_page_config = config::Page {
+        name: "Target".to_string(),
+        bus: None,
+        buttons: vec![sound_button(SOUND_BUTTON_LABEL, "test_sound.mp3")],
+        defaults: Default::default(),
+    };
+    pages.insert(TARGET_PAGE, Arc::new(target_page_config));
+
+    Config {
+        pages,
+        start_page: START_PAGE,
+        buses: vec![],
+        schedule: vec![],
+    }
+}
+
+/// For tests that change more of a page than [`TestHarness::reload_with_buttons`] does.
+pub fn page_mut(config: &mut Config, page: Uuid) -> &mut config::Page {
+    Arc::make_mut(
+        config
+            .pages
+            .get_mut(&page)
+            .expect("The test configuration should have the page"),
+    )
+}
+
+pub fn sound_button(label: &str, path: &str) -> config::Button {
+    config::Button::new(
+        label,
+        ButtonBehavior::PlaySound(
+            Arc::new(path.to_string()),
+            PlaySoundSettings {
+                volume: 0.8,
+                fade_in: Some(Duration::from_millis(100)),
+                fade_out: Some(Duration::from_millis(100)),
+                ..PlaySoundSettings::new(PlaybackMode::PlayStop)
+            },
+        ),
+    )
+}
@@ -0,0 +1,140 @@
+//! Notices sound files that are added to `--audio-path` while the daemon runs, e.g. sounds that
+//! are downloaded while preparing a session, so that they can be played without a restart.
+//!
+//! The directory is scanned every few seconds instead of being watched through the OS, which
+//! also works on network shares. A new file is only reported once its size stayed the same
+//! between two scans, so that a download in progress is not mistaken for a broken file.
+
+use crate::daemon::ui::UiEvent;
+use crate::util::canonical_path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, info, warn};
+
+const SCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What the audio engine is built to decode.
+const EXTENSIONS: [&str; 1] = ["mp3"];
+
+pub fn spawn(audio_path: PathBuf, event_tx: Sender<UiEvent>) {
+    tokio::spawn(async move {
+        if let Err(e) = watch(&audio_path, event_tx).await {
+            warn!(
+                "Stopped watching {} for new files: {e:#}",
+                audio_path.display()
+            );
+        }
+    });
+}
+
+async fn watch(audio_path: &Path, event_tx: Sender<UiEvent>) -> eyre::Result<()> {
+    // Files that are there from the start are either on a page or left out on purpose
+    let mut known = scan(audio_path).await?.into_keys().collect::<HashSet<_>>();
+    let mut growing = HashMap::new();
+    let mut interval = tokio::time::interval(SCAN_INTERVAL);
+    interval.tick().await;
+    info!("Watching {} for new files", audio_path.display());
+    loop {
+        interval.tick().await;
+        let files = match scan(audio_path).await {
+            Ok(files) => files,
+            Err(e) => {
+                debug!("Failed to scan {}: {e:#}", audio_path.display());
+                continue;
+            }
+        };
+        let added = settle(&mut known, &mut growing, files);
+        if !added.is_empty() {
+            // Spelled like the configured paths, see `rebase_path`
+            let added = added
+                .iter()
+                .map(|path| canonical_path(path))
+                .collect::<Vec<_>>();
+            info!("New files in {}: {added:?}", audio_path.display());
+            event_tx.send(UiEvent::FilesAdded(added)).await?;
+        }
+    }
+}
+
+/// The files that are new and done being written. Files that are still being written are kept
+/// in `growing` with their size.
+fn settle(
+    known: &mut HashSet<PathBuf>,
+    growing: &mut HashMap<PathBuf, u64>,
+    files: HashMap<PathBuf, u64>,
+) -> Vec<PathBuf> {
+    growing.retain(|path, _| files.contains_key(path));
+    known.retain(|path| files.contains_key(path));
+    let mut added = Vec::new();
+    for (path, size) in files {
+        if known.contains(&path) {
+            continue;
+        }
+        // Some downloaders create the file before they have anything to write into it
+        if size > 0 && growing.get(&path) == Some(&size) {
+            growing.remove(&path);
+            known.insert(path.clone());
+            added.push(path);
+        } else {
+            growing.insert(path, size);
+        }
+    }
+    added.sort();
+    added
+}
+
+/// Sound files below `dir` with their sizes. Symlinked directories are not followed, since they
+/// could lead back up the tree.
+async fn scan(dir: &Path) -> eyre::Result<HashMap<PathBuf, u64>> {
+    let mut files = HashMap::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if entry.file_type().await?.is_dir() {
+                dirs.push(path);
+            } else if is_sound(&path)
+                && let Ok(metadata) = tokio::fs::metadata(&path).await
+                && metadata.is_file()
+            {
+                files.insert(path, metadata.len());
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn is_sound(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EXTENSIONS.iter().any(|ext| e.eq_ignore_ascii_case(ext)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::settle;
+    use std::collections::{HashMap, HashSet};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_new_files_are_reported_once_they_stop_growing() {
+        let rain = PathBuf::from("sounds/rain.mp3");
+        let storm = PathBuf::from("sounds/storm.mp3");
+        let mut known = HashSet::from([rain.clone()]);
+        let mut growing = HashMap::new();
+        let scan =
+            |storm_size: u64| HashMap::from([(rain.clone(), 10), (storm.clone(), storm_size)]);
+
+        assert!(settle(&mut known, &mut growing, scan(0)).is_empty());
+        assert!(settle(&mut known, &mut growing, scan(0)).is_empty());
+        assert!(settle(&mut known, &mut growing, scan(512)).is_empty());
+        assert_eq!(
+            settle(&mut known, &mut growing, scan(512)),
+            vec![storm.clone()]
+        );
+        assert!(settle(&mut known, &mut growing, scan(512)).is_empty());
+    }
+}
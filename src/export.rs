@@ -0,0 +1,434 @@
+//! Writes a configuration back out as a Stream Deck profile, so that pages maintained here can
+//! be shared with people who use the Stream Deck software. The archive has the layout that
+//! [`crate::import`] reads, with one page profile per page.
+//!
+//! Only what the Stream Deck can do itself survives: sounds, folders, page switches and the
+//! placeholders of actions that were imported without being supported. Other buttons are left
+//! out with a warning, and their keys stay empty. So are the settings of sounds that the Stream
+//! Deck has no equivalent for, e.g. `pan`.
+
+use crate::config::{self, Config, PlaySoundSettings, PlaybackMode};
+use crate::import::elgato::{
+    Action, ActionBehavior, AudioActionType, AudioSettings, Controller, FadeType,
+    OpenChildSettings, PageManifest, Pos, ProfileManifest, ProfileManifestPages, State,
+};
+use base32::Alphabet;
+use clap::Args;
+use eyre::{Context, bail};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Seek, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+#[derive(Debug, Eq, PartialEq, Args, Clone)]
+pub struct ExportArgs {
+    /// The configuration to export, as JSON.
+    #[arg(required = true, env = "export_config")]
+    pub config: PathBuf,
+
+    /// Where to write the profile, usually a `.streamDeckProfile` file.
+    #[arg(long, required = true, env = "export_path")]
+    pub output: PathBuf,
+
+    #[arg(long, required = true, env = "profile_name")]
+    pub profile_name: String,
+
+    /// Put in front of the sound paths, which are relative to the audio directory, e.g. the
+    /// directory that holds the sounds on the machine that runs the Stream Deck software.
+    #[arg(long, env = "export_base_path")]
+    pub base_path: Option<String>,
+
+    /// Keys per row of the Stream Deck that the profile is for. Buttons without a position are
+    /// put on the keys that are left, row by row.
+    #[arg(long, default_value_t = 5, env = "export_columns")]
+    pub columns: u8,
+}
+
+#[instrument(skip(args))]
+pub(crate) async fn run(args: ExportArgs) -> eyre::Result<()> {
+    tokio::task::spawn_blocking(move || run_sync(&args)).await?
+}
+
+fn run_sync(args: &ExportArgs) -> eyre::Result<()> {
+    let config = config::read(&args.config)?;
+    let file = File::create(&args.output)
+        .with_context(|| format!("Failed to create profile {:?}", &args.output))?;
+    write_profile(args, &config, file)?;
+    info!(
+        "Exported {} pages to {}",
+        config.pages.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn write_profile<W: Write + Seek>(
+    args: &ExportArgs,
+    config: &Config,
+    writer: W,
+) -> eyre::Result<W> {
+    // Pages that a folder opens are its children, the others are pages of the profile itself
+    let folders = config
+        .pages
+        .values()
+        .flat_map(|page| &page.buttons)
+        .filter_map(|button| match button.behavior {
+            config::ButtonBehavior::PushPage(id) => Some(id),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    let mut top_pages = config
+        .pages
+        .keys()
+        .filter(|id| **id == config.start_page || !folders.contains(id))
+        .copied()
+        .collect::<Vec<_>>();
+    top_pages.sort_by_key(|id| (*id != config.start_page, *id));
+    let manifest = ProfileManifest {
+        name: args.profile_name.clone(),
+        pages: ProfileManifestPages {
+            current: config.start_page,
+            default: config.start_page,
+            pages: top_pages,
+        },
+    };
+
+    // Named after the start page, so that the Stream Deck software replaces the profile when it
+    // is exported again instead of adding a copy
+    let profile_dir = format!(
+        "{}.sdProfile",
+        config
+            .start_page
+            .hyphenated()
+            .encode_upper(&mut Uuid::encode_buffer())
+    );
+    let options = SimpleFileOptions::default();
+    let mut zip = ZipWriter::new(writer);
+    zip.start_file(format!("{profile_dir}/manifest.json"), options)?;
+    serde_json::to_writer(&mut zip, &manifest).context("Failed to write profile manifest")?;
+    let mut page_ids = config.pages.keys().copied().collect::<Vec<_>>();
+    page_ids.sort();
+    for id in page_ids {
+        let page = &config.pages[&id];
+        let manifest = page_manifest(args, page, folders.contains(&id))?;
+        zip.start_file(
+            format!("{profile_dir}/Profiles/{}/manifest.json", encode_uuid(id)),
+            options,
+        )?;
+        serde_json::to_writer(&mut zip, &manifest)
+            .with_context(|| format!("Failed to write manifest of page {}", page.name))?;
+    }
+    Ok(zip.finish()?)
+}
+
+fn page_manifest(
+    args: &ExportArgs,
+    page: &config::Page,
+    is_folder: bool,
+) -> eyre::Result<PageManifest> {
+    let columns = usize::from(args.columns.max(1));
+    let mut actions = HashMap::new();
+    // A folder cannot be left without its back key, which the import leaves out
+    if is_folder && !page.buttons.iter().any(|b| b.position == Some((0, 0))) {
+        actions.insert(
+            Pos::from_grid((0, 0)),
+            action(None, None, ActionBehavior::BackToParent),
+        );
+    }
+    if let Some(bus) = &page.bus {
+        warn!("Page {}: the Stream Deck has no bus like {bus}", page.name);
+    }
+    let mut taken = HashMap::new();
+    for button in &page.buttons {
+        if let Some(position) = button.position
+            && let Some(other) = taken.insert(position, &button.label)
+        {
+            bail!(
+                "Buttons '{other}' and '{}' on page {} are both at {position:?}",
+                button.label,
+                page.name
+            );
+        }
+    }
+    let mut next_free = 0usize;
+    for button in &page.buttons {
+        let Some(behavior) = action_behavior(args, &button.behavior) else {
+            warn!(
+                "Leaving out button '{}' on page {}, the Stream Deck has no {:?}",
+                button.label, page.name, button.behavior
+            );
+            continue;
+        };
+        if let config::ButtonBehavior::PlaySound(_, settings) = &button.behavior {
+            for setting in lost_settings(settings) {
+                warn!(
+                    "Button '{}' on page {}: the Stream Deck has no {setting}",
+                    button.label, page.name
+                );
+            }
+        }
+        let position = match button.position {
+            Some(position) => position,
+            None => loop {
+                let key = next_free;
+                next_free += 1;
+                let position = ((key % columns) as u8, (key / columns) as u8);
+                if !actions.contains_key(&Pos::from_grid(position))
+                    && !taken.contains_key(&position)
+                {
+                    taken.insert(position, &button.label);
+                    break position;
+                }
+            },
+        };
+        let title = Some(button.label.clone());
+        actions.insert(Pos::from_grid(position), action(button.id, title, behavior));
+    }
+    Ok(PageManifest {
+        controllers: vec![Controller {
+            ty: "Keypad".to_string(),
+            actions,
+        }],
+    })
+}
+
+/// Passes the button's id on as the action's, so that importing the profile names it the same.
+fn action(id: Option<Uuid>, title: Option<Arc<String>>, behavior: ActionBehavior) -> Action {
+    Action {
+        action_id: id,
+        state: 0,
+        states: vec![State {
+            show_title: title.is_some(),
+            title,
+        }],
+        behavior,
+    }
+}
+
+fn action_behavior(args: &ExportArgs, behavior: &config::ButtonBehavior) -> Option<ActionBehavior> {
+    match behavior {
+        config::ButtonBehavior::PlaySound(path, settings) => Some(ActionBehavior::PlayAudio {
+            settings: audio_settings(args, path, settings),
+        }),
+        config::ButtonBehavior::PushPage(id) => Some(ActionBehavior::OpenChild {
+            settings: OpenChildSettings { profile_uuid: *id },
+        }),
+        config::ButtonBehavior::GotoPage(id) => Some(ActionBehavior::SwitchProfile {
+            settings: OpenChildSettings { profile_uuid: *id },
+        }),
+        config::ButtonBehavior::Pop => Some(ActionBehavior::BackToParent),
+        config::ButtonBehavior::Placeholder(name) => ActionBehavior::of_unsupported(name),
+        _ => None,
+    }
+}
+
+/// The reverse of the import, which has a coarser volume, whole seconds of fading and the same
+/// length for fading in and out.
+fn audio_settings(args: &ExportArgs, path: &str, settings: &PlaySoundSettings) -> AudioSettings {
+    let fade_len = settings.fade_in.max(settings.fade_out).unwrap_or_default();
+    AudioSettings {
+        fade_len: fade_len.as_secs_f64().round() as u32,
+        // 50% is the default volume, see the import
+        volume: (settings.volume * 50.0).round().clamp(0.0, 100.0) as u8,
+        path: Arc::new(match &args.base_path {
+            // Profiles made on Windows spell paths with backslashes throughout
+            Some(base) if base.contains('\\') => {
+                format!(
+                    "{}\\{}",
+                    base.trim_end_matches('\\'),
+                    path.replace('/', "\\")
+                )
+            }
+            Some(base) => format!("{}/{path}", base.trim_end_matches('/')),
+            None => path.to_string(),
+        }),
+        action_type: match settings.mode {
+            PlaybackMode::PlayStop | PlaybackMode::Stinger => AudioActionType::PlayStop,
+            PlaybackMode::PlayOverlap => AudioActionType::PlayOverlap,
+            PlaybackMode::LoopStop => AudioActionType::LoopStop,
+        },
+        fade_type: match (settings.fade_in, settings.fade_out) {
+            (None, None) => FadeType::None,
+            (Some(_), None) => FadeType::In,
+            (None, Some(_)) => FadeType::Out,
+            (Some(_), Some(_)) => FadeType::InOut,
+        },
+    }
+}
+
+/// What a sound does here that it will not do on the Stream Deck.
+fn lost_settings(settings: &PlaySoundSettings) -> Vec<&'static str> {
+    let lost = [
+        (settings.gain_db != 0.0, "gain_db"),
+        (settings.pan != 0.0, "pan"),
+        (settings.playback_rate.is_some(), "playback_rate"),
+        (settings.bus.is_some(), "bus"),
+        (
+            settings.mode == PlaybackMode::Stinger,
+            "Stinger mode and plays it as PlayStop",
+        ),
+        (settings.cooldown.is_some(), "cooldown"),
+        (settings.max_instances.is_some(), "max_instances"),
+        (settings.resume.is_some(), "resume"),
+        (settings.fallback.is_some(), "fallback"),
+    ];
+    lost.into_iter()
+        .filter(|(lost, _)| *lost)
+        .map(|(_, setting)| setting)
+        .collect()
+}
+
+/// Names the directory of a page profile like the Stream Deck software does, the reverse of
+/// `import::decode_uuid`.
+fn encode_uuid(id: Uuid) -> String {
+    let mut encoded = base32::encode(Alphabet::Rfc4648Hex { padding: false }, id.as_bytes())
+        .replace('V', "W")
+        .replace('U', "V");
+    encoded.push('Z');
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExportArgs, page_manifest, write_profile};
+    use crate::config::{ButtonBehavior, Config, Page};
+    use crate::import::{ImportArgs, Progress};
+    use crate::util::Switch;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_exported_profiles_import_as_they_were() -> eyre::Result<()> {
+        let (start_page, folder) = (Uuid::from_u128(1), Uuid::from_u128(0xfeed));
+        let page = |value: serde_json::Value| -> eyre::Result<Arc<Page>> {
+            Ok(Arc::new(serde_json::from_value(value)?))
+        };
+        let config = Config {
+            pages: HashMap::from([
+                (
+                    start_page,
+                    page(json!({ "name": "Start", "buttons": [
+                        {
+                            "label": "Rain",
+                            "behavior": { "PlaySound": ["rain.mp3", { "mode": "LoopStop" }] },
+                            "position": [2, 1]
+                        },
+                        { "label": "Tavern", "behavior": { "PushPage": folder } },
+                        { "label": "Quiet", "behavior": "StopAll" }
+                    ]}))?,
+                ),
+                (
+                    folder,
+                    page(json!({ "name": "Tavern", "buttons": [
+                        { "label": "Mute", "behavior": { "Placeholder": "Hotkey" } }
+                    ]}))?,
+                ),
+            ]),
+            start_page,
+            buses: vec![],
+            schedule: vec![],
+        };
+        let args = ExportArgs {
+            config: PathBuf::from("scene.json"),
+            output: std::env::temp_dir().join(format!(
+                "noisedeck-export-{}.streamDeckProfile",
+                std::process::id()
+            )),
+            profile_name: "Scene".to_string(),
+            base_path: None,
+            columns: 5,
+        };
+        let profile = write_profile(&args, &config, Cursor::new(Vec::new()))?.into_inner();
+        std::fs::write(&args.output, profile)?;
+
+        let imported = crate::import::run_sync(
+            ImportArgs {
+                path: args.output.clone(),
+                base_paths: vec![],
+                profile_name: "Scene".to_string(),
+                drives: vec![],
+                send_keys: Switch::Off,
+                run_commands: Switch::Off,
+            },
+            Progress::Log,
+        );
+        std::fs::remove_file(&args.output)?;
+        let imported = imported?;
+
+        assert_eq!(imported.start_page, start_page);
+        let buttons = |id: &Uuid| {
+            imported.pages[id]
+                .buttons
+                .iter()
+                .map(|b| {
+                    let behavior = match &b.behavior {
+                        ButtonBehavior::PlaySound(path, settings) => {
+                            format!("PlaySound({path}, {:?})", settings.mode)
+                        }
+                        other => format!("{other:?}"),
+                    };
+                    (b.label.to_string(), behavior, b.position)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            buttons(&start_page),
+            [
+                (
+                    "Tavern".to_string(),
+                    format!("PushPage({folder})"),
+                    Some((0, 0))
+                ),
+                (
+                    "Rain".to_string(),
+                    "PlaySound(rain.mp3, LoopStop)".to_string(),
+                    Some((2, 1))
+                ),
+            ]
+        );
+        // The back key of the folder is left out again, and its key stays empty
+        assert_eq!(
+            buttons(&folder),
+            [(
+                "Mute".to_string(),
+                "Placeholder(\"Hotkey\")".to_string(),
+                Some((1, 0))
+            )]
+        );
+        assert_eq!(imported.pages[&folder].name, "Tavern");
+        Ok(())
+    }
+
+    #[test]
+    fn test_buttons_on_the_same_key_are_not_exported() -> eyre::Result<()> {
+        let page: Page = serde_json::from_value(json!({ "name": "Start", "buttons": [
+            { "label": "Rain", "behavior": "StopAll", "position": [1, 0] },
+            { "label": "Wind", "behavior": "StopAll", "position": [1, 0] }
+        ]}))?;
+        let args = ExportArgs {
+            config: PathBuf::from("scene.json"),
+            output: PathBuf::from("scene.streamDeckProfile"),
+            profile_name: "Scene".to_string(),
+            base_path: None,
+            columns: 5,
+        };
+
+        let error = page_manifest(&args, &page, false)
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+        assert_eq!(
+            error,
+            Err("Buttons 'Rain' and 'Wind' on page Start are both at (1, 0)".to_string())
+        );
+        Ok(())
+    }
+}
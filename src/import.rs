@@ -1,19 +1,23 @@
 use crate::config;
 use crate::config::{Config, PlaySoundSettings, PlaybackMode};
+use crate::import::base_path::{BasePath, Case};
 use crate::import::elgato::{
     Action, ActionBehavior, AudioActionType, PageManifest, ProfileManifest, ProfileManifestPages,
 };
+use crate::import::progress::PageProgress;
+use crate::util::Switch;
 use base32::Alphabet;
 use clap::Args;
 use eyre::{Context, ContextCompat, OptionExt, ensure};
 use regex::Regex;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{Read, Seek};
-use std::path::PathBuf;
+use std::io::{BufReader, Read, Seek};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, LazyLock, OnceLock};
 use std::time::Duration;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 use zip::ZipArchive;
 
@@ -22,29 +26,122 @@ pub struct ImportArgs {
     #[arg(required = true, env = "import_path")]
     pub path: PathBuf,
 
+    /// Prefix to remove from the sound paths in the profile, so that the rest is relative to the
+    /// audio directory; can be given several times. May contain `*` and `?` within a directory
+    /// name and `**` for any number of directories, e.g. `C:/Users/*/Music`. Paths of profiles
+    /// made on Windows match regardless of case.
     #[arg(long, required = true, env = "base_paths")]
     pub base_paths: Vec<PathBuf>,
 
     #[arg(long, required = true, env = "profile_name")]
     pub profile_name: String,
+
+    /// Where a drive of a profile made on Windows is mounted here, e.g. `D=/mnt/music`, for the
+    /// sound paths that no base path matches; can be given several times. Paths on other drives
+    /// keep the path below the drive, from the root of the file system.
+    #[arg(long = "drive", env = "drives", value_delimiter = ',', value_parser = parse_drive)]
+    pub drives: Vec<DriveMount>,
+
+    /// Whether Hotkey actions become buttons that send their keys rather than placeholders, set
+    /// from the `--allow-keystrokes` of the command that imports.
+    #[arg(skip = Switch::Off)]
+    pub send_keys: Switch,
+
+    /// Like [`Self::send_keys`] for Open actions and `--allow-commands`.
+    #[arg(skip = Switch::Off)]
+    pub run_commands: Switch,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriveMount {
+    letter: char,
+    mount: PathBuf,
+}
+
+fn parse_drive(s: &str) -> Result<DriveMount, String> {
+    let (letter, mount) = s
+        .split_once('=')
+        .ok_or_else(|| format!("'{s}' is not a drive and a mount point, e.g. D=/mnt/music"))?;
+    let mut chars = letter.trim_end_matches(':').chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) if letter.is_ascii_alphabetic() => Ok(DriveMount {
+            letter,
+            mount: PathBuf::from(mount),
+        }),
+        _ => Err(format!("'{letter}' is not a drive letter")),
+    }
+}
+
+#[derive(Debug, PartialEq, Args, Clone)]
+pub struct ImportCommandArgs {
+    #[command(flatten)]
+    pub import: ImportArgs,
+
+    /// Where to write the imported configuration as JSON, for the daemon's `--config`. Without
+    /// it, the profile is only checked for whether it can be imported.
+    #[arg(long, env = "import_output")]
+    pub output: Option<PathBuf>,
+
+    /// Measure the loudness of every sound in this directory, the daemon's `--audio-path`, and
+    /// write a gain that brings all of them to the same level. Decodes every file, so the import
+    /// takes longer, but the daemon does not have to.
+    #[arg(
+        long,
+        env = "analyze_loudness",
+        value_name = "AUDIO_PATH",
+        requires = "output"
+    )]
+    pub analyze_loudness: Option<PathBuf>,
+
+    /// Integrated loudness in LUFS that --analyze-loudness normalizes to
+    #[arg(long, env = "loudness_target", default_value_t = -18.0, allow_negative_numbers = true)]
+    pub loudness_target: f64,
+
+    /// Import Hotkey actions as buttons that send their keys instead of as placeholders. The
+    /// daemon only sends them when it is started with `--allow-keystrokes` as well.
+    #[arg(long, env = "allow_keystrokes")]
+    pub allow_keystrokes: bool,
+
+    /// Import Open actions as buttons that run the program instead of as placeholders. The
+    /// daemon only runs them when it is started with `--allow-commands` as well. Files that the
+    /// Stream Deck would open with their application do not run this way.
+    #[arg(long, env = "allow_commands")]
+    pub allow_commands: bool,
 }
 
 #[tracing::instrument(skip(args))]
-pub(crate) async fn run(args: ImportArgs) -> eyre::Result<()> {
-    _ = tokio::task::spawn_blocking(move || run_sync(args)).await?;
-    Ok(())
+pub(crate) async fn run(args: ImportCommandArgs, progress: Progress) -> eyre::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let import = ImportArgs {
+            send_keys: Switch::from(args.allow_keystrokes),
+            run_commands: Switch::from(args.allow_commands),
+            ..args.import
+        };
+        let mut config = run_sync(import, progress)?;
+        if let Some(audio_path) = &args.analyze_loudness {
+            loudness::normalize_loudness(&mut config, audio_path, args.loudness_target);
+        }
+        if let Some(output) = &args.output {
+            config::write(&config, output)?;
+            info!("Wrote {} pages to {}", config.pages.len(), output.display());
+        }
+        Ok(())
+    })
+    .await?
 }
 
-pub(crate) fn run_sync(args: ImportArgs) -> eyre::Result<Config> {
+pub(crate) fn run_sync(args: ImportArgs, progress: Progress) -> eyre::Result<Config> {
     info!("Running imports with args: {:#?}", args);
     let file = File::open(&args.path)
         .with_context(|| format!("Failed to import file {:?}", &args.path))?;
     let mut archive = ZipArchive::new(file)
         .with_context(|| format!("Failed to open zip archive {:?}", &args.path))?;
 
-    let mut manifest_paths = parse_manifest_paths(&mut archive)?;
-
-    let selected_profile = find_selected_profile(&args, &mut archive, &mut manifest_paths)?;
+    let SelectedProfile {
+        pages: selected_profile,
+        manifest_paths,
+        mut manifests,
+    } = read_selected_profile(&args, &mut archive)?;
     info!(
         "Selected profile: {:?} ({} manifests)",
         selected_profile,
@@ -54,31 +151,35 @@ pub(crate) fn run_sync(args: ImportArgs) -> eyre::Result<Config> {
     let profiles = decode_uuids(manifest_paths)?;
 
     // parse manifests
-    let mut profile_manifests = HashMap::new();
-    for page in profiles.values() {
-        let manifest_file = archive.by_name(&page.manifest_path).with_context(|| {
-            format!("Failed to read page manifest file {}", &page.manifest_path)
-        })?;
-        let mut manifest: PageManifest =
-            serde_json::from_reader(manifest_file).with_context(|| {
-                format!("Failed to parse page manifest file {}", &page.manifest_path)
+    let manifest_files = profiles
+        .values()
+        .map(|page| {
+            let json = manifests.remove(&page.manifest_path).with_context(|| {
+                format!("Page manifest file {} was not read", page.manifest_path)
             })?;
-        to_os_paths(&mut manifest);
-        profile_manifests.insert(page.profile_id, manifest);
-    }
+            Ok((page, json))
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+    let progress = PageProgress::new(progress, manifest_files.len());
+    let mut profile_manifests = parse_manifests(&manifest_files, &progress)?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
 
     // remove base paths
-    let mut file_path: PathBuf = PathBuf::new();
+    let base_paths = args
+        .base_paths
+        .iter()
+        .map(|base_path| Ok((base_path, BasePath::new(base_path)?)))
+        .collect::<eyre::Result<Vec<_>>>()?;
     for (id, manifest) in profile_manifests.iter_mut() {
         for ctrl in manifest.controllers.iter_mut() {
-            for (pos, action) in ctrl.actions.iter_mut() {
+            'actions: for (pos, action) in ctrl.actions.iter_mut() {
                 if let ActionBehavior::PlayAudio { settings } = &mut action.behavior {
-                    file_path.clear();
-                    file_path.push(&*settings.path);
-                    for base_path in &args.base_paths {
-                        if file_path.starts_with(base_path) {
-                            let new_path = file_path
-                                .strip_prefix(base_path)?
+                    let file_path = Path::new(&*settings.path);
+                    let case = Case::of_profile_path(&settings.path);
+                    for (base_path, pattern) in &base_paths {
+                        if let Some(stripped) = pattern.strip(file_path, case) {
+                            let new_path = stripped
                                 .to_str().with_context(|| format!("Stripping a path prefix '{}' from '{}' resulted on non-UTF-8 characters (manifest {}, {:?})",
                                 base_path.display(), settings.path, id, pos))?
                                 .to_string().into();
@@ -90,9 +191,16 @@ pub(crate) fn run_sync(args: ImportArgs) -> eyre::Result<Config> {
                                 pos
                             );
                             settings.path = new_path;
-                            break;
+                            continue 'actions;
                         }
                     }
+                    if let Some(mapped) = map_drive(&settings.path, &args.drives) {
+                        debug!(
+                            "Mapped '{}' to '{}' (manifest {}, {:?})",
+                            settings.path, mapped, id, pos
+                        );
+                        settings.path = mapped.into();
+                    }
                 }
             }
         }
@@ -105,14 +213,13 @@ pub(crate) fn run_sync(args: ImportArgs) -> eyre::Result<Config> {
             continue;
         };
         for (_, action) in keypad.actions.iter() {
-            if let ActionBehavior::OpenChild { settings } = &action.behavior {
-                if let Some(title) = action
+            if let ActionBehavior::OpenChild { settings } = &action.behavior
+                && let Some(title) = action
                     .states
                     .get(action.state)
                     .and_then(|x| x.title.as_ref())
-                {
-                    profile_names.insert(settings.profile_uuid, &title[..]);
-                }
+            {
+                profile_names.insert(settings.profile_uuid, &title[..]);
             }
         }
     }
@@ -126,12 +233,28 @@ pub(crate) fn run_sync(args: ImportArgs) -> eyre::Result<Config> {
         };
         let mut actions = keypad.actions.iter().collect::<Vec<_>>();
         actions.sort_by_key(|(pos, _)| *pos);
+        let mut button_ids = ButtonIds::new(*id);
         for (pos, action) in actions.iter() {
+            let button_id = Some(button_ids.of(action)?);
+            if let Some(name) = action.behavior.unsupported() {
+                let behavior = system_action(&args, &action.behavior).unwrap_or_else(|| {
+                    debug!("Importing {name} action as a placeholder: {}{:?}", id, pos);
+                    config::ButtonBehavior::Placeholder(name.to_string())
+                });
+                buttons.push(config::Button {
+                    id: button_id,
+                    label: label_of(action),
+                    behavior,
+                    position: Some(pos.grid()),
+                });
+                continue;
+            }
             match &action.behavior {
                 ActionBehavior::BackToParent => {}
                 ActionBehavior::PlayAudio { settings } => {
                     let fade_len = Duration::from_secs(settings.fade_len.into());
                     buttons.push(config::Button {
+                        id: button_id,
                         label: label_of(action),
                         behavior: config::ButtonBehavior::PlaySound(
                             settings.path.clone(),
@@ -139,21 +262,40 @@ pub(crate) fn run_sync(args: ImportArgs) -> eyre::Result<Config> {
                                 fade_in: settings.fade_type.when_in(fade_len),
                                 fade_out: settings.fade_type.when_out(fade_len),
                                 volume: settings.volume as f64 / 50.0, // 50% is the default volume,
-                                mode: match settings.action_type {
+                                ..PlaySoundSettings::new(match settings.action_type {
                                     AudioActionType::PlayStop => PlaybackMode::PlayStop,
                                     AudioActionType::PlayOverlap => PlaybackMode::PlayOverlap,
                                     AudioActionType::PlayRestart => PlaybackMode::PlayStop,
                                     AudioActionType::LoopStop => PlaybackMode::LoopStop,
-                                },
+                                })
                             },
                         ),
+                        position: Some(pos.grid()),
                     });
                 }
                 ActionBehavior::OpenChild { settings } => buttons.push(config::Button {
+                    id: button_id,
                     label: label_of(action),
                     behavior: config::ButtonBehavior::PushPage(settings.profile_uuid),
+                    position: Some(pos.grid()),
                 }),
-                ActionBehavior::Unknown => {
+                ActionBehavior::SwitchProfile { settings } => {
+                    // Other profiles are not imported, so only links within this one work
+                    if profile_manifests.contains_key(&settings.profile_uuid) {
+                        buttons.push(config::Button {
+                            id: button_id,
+                            label: label_of(action),
+                            behavior: config::ButtonBehavior::GotoPage(settings.profile_uuid),
+                            position: Some(pos.grid()),
+                        });
+                    } else {
+                        warn!(
+                            "Skipping switch to profile {} outside of the imported one ({}{:?})",
+                            settings.profile_uuid, id, pos
+                        );
+                    }
+                }
+                _ => {
                     debug!("Unknown action behavior: {}{:?}{:?}", id, pos, action);
                 }
             }
@@ -163,13 +305,25 @@ pub(crate) fn run_sync(args: ImportArgs) -> eyre::Result<Config> {
             Arc::new(config::Page {
                 name: profile_names.get(id).unwrap_or(&"Page?").to_string(),
                 buttons,
+                bus: None,
+                defaults: Default::default(),
             }),
         );
     }
 
+    info!(
+        "Mapped {} actions on {} pages",
+        config_pages
+            .values()
+            .map(|p| p.buttons.len())
+            .sum::<usize>(),
+        config_pages.len()
+    );
     let c = Config {
         pages: config_pages,
         start_page: selected_profile.current,
+        buses: vec![],
+        schedule: vec![],
     };
 
     Ok(c)
@@ -192,6 +346,131 @@ fn to_os_paths(_manifest: &mut PageManifest) {
     // no-op on Windows
 }
 
+/// A drive letter means nothing here, so `C:/...` (as converted by [`to_os_paths`]) is looked for
+/// where the drive is mounted, or else from the root.
+#[cfg(not(target_os = "windows"))]
+fn map_drive(path: &str, drives: &[DriveMount]) -> Option<String> {
+    let mut chars = path.chars();
+    let (Some(letter), Some(':')) = (chars.next(), chars.next()) else {
+        return None;
+    };
+    if !letter.is_ascii_alphabetic() {
+        return None;
+    }
+    let rest = chars.as_str().trim_start_matches('/');
+    let root = drives
+        .iter()
+        .find(|drive| drive.letter.eq_ignore_ascii_case(&letter))
+        .map_or(Path::new("/"), |drive| drive.mount.as_path());
+    Some(root.join(rest).to_string_lossy().into_owned())
+}
+
+#[cfg(target_os = "windows")]
+fn map_drive(_path: &str, _drives: &[DriveMount]) -> Option<String> {
+    // Drive letters are native on Windows
+    None
+}
+
+/// The archive can only be read one file at a time, but the pages can be parsed in parallel once
+/// they have been read.
+fn parse_manifests(
+    manifest_files: &[(&PageEntry, Vec<u8>)],
+    progress: &PageProgress,
+) -> eyre::Result<Vec<(Uuid, PageManifest)>> {
+    let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let chunk_len = manifest_files.len().div_ceil(threads).max(1);
+    std::thread::scope(|scope| {
+        let chunks = manifest_files
+            .chunks(chunk_len)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|(page, json)| {
+                            let mut manifest: PageManifest = serde_json::from_slice(json)
+                                .with_context(|| {
+                                    format!(
+                                        "Failed to parse page manifest file {}",
+                                        &page.manifest_path
+                                    )
+                                })?;
+                            to_os_paths(&mut manifest);
+                            progress.page_parsed();
+                            Ok((page.profile_id, manifest))
+                        })
+                        .collect::<eyre::Result<Vec<_>>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut manifests = Vec::with_capacity(manifest_files.len());
+        for chunk in chunks {
+            let parsed = chunk
+                .join()
+                .map_err(|_| eyre::eyre!("Parsing page manifests panicked"))??;
+            manifests.extend(parsed);
+        }
+        Ok(manifests)
+    })
+}
+
+/// Names the buttons of a page so that importing the profile again names them the same way, also
+/// after keys were moved around.
+struct ButtonIds {
+    page: Uuid,
+    /// How many actions that do the same thing were named so far, by their hash.
+    seen: HashMap<u64, u32>,
+}
+
+impl ButtonIds {
+    fn new(page: Uuid) -> Self {
+        ButtonIds {
+            page,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Actions from profiles that predate `ActionID` are named after the page and what they do.
+    /// Actions that do the same are told apart by the order of their keys, which only swaps the
+    /// names of buttons that are alike anyway.
+    fn of(&mut self, action: &Action) -> eyre::Result<Uuid> {
+        if let Some(id) = action.action_id {
+            return Ok(id);
+        }
+        let behavior = serde_json::to_vec(&action.behavior)?;
+        let count = self.seen.entry(fnv1a(&behavior)).or_default();
+        *count += 1;
+        let page = self.page.into_bytes();
+        let hash = fnv1a(&[&page[..], &behavior, &count.to_le_bytes()].concat());
+        let mut bytes = page;
+        bytes[8..].copy_from_slice(&hash.to_be_bytes());
+        Ok(uuid::Builder::from_custom_bytes(bytes).into_uuid())
+    }
+}
+
+/// Unlike the hashers of the standard library, it gives the same hash in every version.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Hotkeys and programs to open, where the import may make buttons that send keys or run
+/// programs, see [`ImportArgs::send_keys`].
+fn system_action(args: &ImportArgs, behavior: &ActionBehavior) -> Option<config::ButtonBehavior> {
+    match behavior {
+        ActionBehavior::Hotkey { settings } if args.send_keys == Switch::On => {
+            settings.chord().map(config::ButtonBehavior::SendKeys)
+        }
+        ActionBehavior::Open { settings } if args.run_commands == Switch::On => settings
+            .program()
+            .map(|program| config::ButtonBehavior::RunCommand {
+                program,
+                args: Vec::new(),
+            }),
+        _ => None,
+    }
+}
+
 fn label_of(action: &Action) -> Arc<String> {
     static EMPTY_STRING: LazyLock<Arc<String>> = LazyLock::new(|| Arc::new("".to_string()));
     action
@@ -269,82 +548,292 @@ fn decode_uuid(
     }
 }
 
-fn parse_manifest_paths<R>(
+/// The top-level manifest of a profile and the manifests of its pages.
+struct SelectedProfile {
+    pages: ProfileManifestPages,
+    /// (name, top-level profile, page profile) of every page manifest, see [`decode_uuids`].
+    manifest_paths: Vec<(String, String, Option<String>)>,
+    /// The page manifests as read from the archive, by name.
+    manifests: HashMap<String, Vec<u8>>,
+}
+
+/// Reads the manifests in a single pass in archive order, and nothing else. Profiles may embed
+/// large images and sounds, which are never decompressed, and pages of other profiles are
+/// dropped as soon as the selected profile is known.
+fn read_selected_profile<R: Read + Seek>(
+    args: &ImportArgs,
     archive: &mut ZipArchive<R>,
-) -> eyre::Result<Vec<(String, String, Option<String>)>>
-where
-    R: Read + Seek,
-{
+) -> eyre::Result<SelectedProfile> {
+    let stripped_arg_profile_name = args.profile_name.trim_matches('"');
+    let mut selected: Option<(String, ProfileManifest)> = None;
+    // Pages can come before the manifest of their profile, so they are kept by profile until then
+    let mut pages: HashMap<String, Vec<(String, String, Vec<u8>)>> = HashMap::new();
+    for index in 0..archive.len() {
+        let Some((name, top_profile, inner_profile)) =
+            archive.name_for_index(index).and_then(manifest_path)
+        else {
+            continue;
+        };
+        debug!(
+            "Found manifest in archive: {}/{:?}: {}",
+            top_profile, inner_profile, name
+        );
+        let is_other_profile = selected.as_ref().is_some_and(|(id, _)| *id != top_profile);
+        match inner_profile {
+            Some(_) if is_other_profile => {}
+            Some(inner_profile) => {
+                let mut manifest_file = archive
+                    .by_index(index)
+                    .with_context(|| format!("Failed to open manifest file {:?}", name))?;
+                let mut json = Vec::new();
+                manifest_file
+                    .read_to_end(&mut json)
+                    .with_context(|| format!("Failed to read page manifest file {name}"))?;
+                pages
+                    .entry(top_profile)
+                    .or_default()
+                    .push((name, inner_profile, json));
+            }
+            None if selected.is_some() => {}
+            None => {
+                let manifest_file = archive
+                    .by_index(index)
+                    .with_context(|| format!("Failed to open manifest file {:?}", name))?;
+                let manifest: ProfileManifest =
+                    serde_json::from_reader(BufReader::new(manifest_file))
+                        .with_context(|| format!("Failed to parse manifest file {name}"))?;
+                if manifest.name == args.profile_name || manifest.name == stripped_arg_profile_name
+                {
+                    info!(
+                        "Found profile manifest: {}/{}",
+                        top_profile, args.profile_name
+                    );
+                    pages.retain(|id, _| *id == top_profile);
+                    selected = Some((top_profile, manifest));
+                }
+            }
+        }
+    }
+    let (selected_id, selected) = selected.ok_or_eyre("Profile not found in archive")?;
+
+    let mut manifest_paths = Vec::new();
+    let mut manifests = HashMap::new();
+    for (name, inner_profile, json) in pages.remove(&selected_id).unwrap_or_default() {
+        manifest_paths.push((name.clone(), selected_id.clone(), Some(inner_profile)));
+        manifests.insert(name, json);
+    }
+    Ok(SelectedProfile {
+        pages: selected.pages,
+        manifest_paths,
+        manifests,
+    })
+}
+
+/// Splits the name of a manifest into the top-level profile and, for pages, the page profile.
+fn manifest_path(name: &str) -> Option<(String, String, Option<String>)> {
     static MANIFEST_PATTERN: OnceLock<Regex> = OnceLock::new();
     let manifest_pattern = MANIFEST_PATTERN.get_or_init(|| {
         Regex::new(r"^([A-Z0-9-]+).sdProfile/(?:Profiles/([A-Z0-9]+)/)?manifest\.json$")
             .expect("Regular expression to be valid")
     });
+    let captures = manifest_pattern.captures(name)?;
+    Some((
+        name.to_owned(),
+        captures.get(1)?.as_str().to_owned(),
+        captures.get(2).map(|m| m.as_str().to_owned()),
+    ))
+}
 
-    let manifest_paths = archive
-        .file_names()
-        .filter_map(|name| {
-            manifest_pattern.captures(name).map(|captures| {
-                let top_profile = captures
-                    .get(1)
-                    .expect("capture group 1 should always exist");
-                let inner_profile = captures.get(2);
-                (
-                    name.to_owned(),
-                    top_profile.as_str().to_owned(),
-                    inner_profile.map(|m| m.as_str().to_owned()),
-                )
-            })
-        })
-        .inspect(|(name, top_profile, inner_profile)| {
-            debug!(
-                "Found manifest in archive: {}/{:?}: {}",
-                top_profile, inner_profile, name
-            )
-        })
-        .collect::<Vec<_>>();
+mod base_path;
+pub(crate) mod elgato;
+mod progress;
+pub(crate) use progress::Progress;
+pub(crate) mod dedup;
+pub(crate) mod loudness;
 
-    Ok(manifest_paths)
-}
+#[cfg(test)]
+mod tests {
+    use super::{ImportArgs, Switch, read_selected_profile};
+    use serde_json::json;
+    use std::io::{Cursor, Write};
+    use std::path::PathBuf;
+    use zip::ZipArchive;
+    use zip::write::{SimpleFileOptions, ZipWriter};
 
-fn find_selected_profile(
-    args: &ImportArgs,
-    archive: &mut ZipArchive<File>,
-    manifest_paths: &mut Vec<(String, String, Option<String>)>,
-) -> eyre::Result<ProfileManifestPages> {
-    // search top-level manifests for the configured profile
-    let mut selected_profile = None;
-    let mut selected_profile_id = None;
-    let stripped_arg_profile_name = args.profile_name.trim_matches('"');
-    for (name, top_profile, inner_profile) in manifest_paths.iter() {
-        if inner_profile.is_some() {
-            continue;
-        }
-        let mut manifest_file = archive
-            .by_name(name)
-            .with_context(|| format!("Failed to open manifest file {:?}", name))?;
-        let mut manifest_buf = Vec::new();
-        manifest_file.read_to_end(&mut manifest_buf)?;
-        let manifest_buf = String::from_utf8(manifest_buf)?;
-        let manifest: ProfileManifest = serde_json::from_str(&manifest_buf)?;
-        if manifest.name == args.profile_name || manifest.name == stripped_arg_profile_name {
-            info!(
-                "Found profile manifest: {}/{}",
-                top_profile, args.profile_name
-            );
-            selected_profile = Some(manifest);
-            selected_profile_id = Some(top_profile.clone());
-            break;
+    #[test]
+    fn test_only_the_pages_of_the_selected_profile_are_read() -> eyre::Result<()> {
+        let profile = |name: &str| {
+            let uuid = uuid::Uuid::nil();
+            let pages = json!({ "Current": uuid, "Default": uuid, "Pages": [] });
+            json!({ "Name": name, "Pages": pages }).to_string()
+        };
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let entries = [
+            ("A.sdProfile/Profiles/P1/manifest.json", "{}".to_string()),
+            ("A.sdProfile/manifest.json", profile("Other")),
+            ("B.sdProfile/Profiles/P2/manifest.json", "{}".to_string()),
+            ("B.sdProfile/manifest.json", profile("Scene")),
+            ("B.sdProfile/Profiles/P3/manifest.json", "{}".to_string()),
+            (
+                "B.sdProfile/Profiles/P3/Images/ambience.png",
+                "not read".to_string(),
+            ),
+        ];
+        for (name, content) in entries {
+            zip.start_file(name, SimpleFileOptions::default())?;
+            zip.write_all(content.as_bytes())?;
         }
+        let mut archive = ZipArchive::new(zip.finish()?)?;
+        let args = ImportArgs {
+            path: PathBuf::from("profile.streamDeckProfilesBackup"),
+            base_paths: vec![],
+            profile_name: "Scene".to_string(),
+            drives: vec![],
+            send_keys: Switch::Off,
+            run_commands: Switch::Off,
+        };
+
+        let selected = read_selected_profile(&args, &mut archive)?;
+        let mut pages = selected.manifests.into_keys().collect::<Vec<_>>();
+        pages.sort();
+        assert_eq!(
+            pages,
+            [
+                "B.sdProfile/Profiles/P2/manifest.json",
+                "B.sdProfile/Profiles/P3/manifest.json"
+            ]
+        );
+        Ok(())
     }
-    let selected_profile = selected_profile.ok_or_eyre("Profile not found in archive")?;
-    let selected_profile_id = selected_profile_id.ok_or_eyre("Profile not found in archive")?;
 
-    // throw away all manifests that aren't children of the selected profile
-    manifest_paths.retain_mut(|(_, top_profile, inner_profile)| {
-        inner_profile.is_some() && &selected_profile_id == top_profile
-    });
-    Ok(selected_profile.pages)
-}
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_drive_letters_map_to_mount_points() -> Result<(), String> {
+        use super::{map_drive, parse_drive};
+
+        let drives = [parse_drive("D:=/mnt/music")?];
+        let mapped = |path: &str| map_drive(path, &drives);
+        assert_eq!(
+            mapped("d:/Sfx/rain.mp3").as_deref(),
+            Some("/mnt/music/Sfx/rain.mp3")
+        );
+        assert_eq!(
+            mapped("C:/Users/gm/rain.mp3").as_deref(),
+            Some("/Users/gm/rain.mp3")
+        );
+        assert_eq!(mapped("Sfx/rain.mp3"), None);
+        assert!(parse_drive("music=/mnt/music").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_button_ids_stay_the_same_when_keys_move() -> eyre::Result<()> {
+        use super::ButtonIds;
+        use crate::import::elgato::Action;
+        use uuid::Uuid;
+
+        let page = Uuid::from_u128(0x6f1e_22c4_5a7b_4d31_9c0e_7e3a_41b2_0d95);
+        let other_page = Uuid::from_u128(0x0b3d_9a44_21c6_4f8e_8d17_52f0_c6a9_e318);
+        let sound = |path: &str| -> serde_json::Result<Action> {
+            serde_json::from_value(json!({
+                "UUID": "com.elgato.streamdeck.soundboard.playaudio",
+                "State": 0,
+                "States": [{}],
+                "Settings": { "path": path, "volume": 50 }
+            }))
+        };
+        let ids = |page, actions: &[&Action]| {
+            let mut ids = ButtonIds::new(page);
+            actions
+                .iter()
+                .map(|a| ids.of(a))
+                .collect::<eyre::Result<Vec<_>>>()
+        };
+        let (rain, wind) = (sound("rain.mp3")?, sound("wind.mp3")?);
+
+        let first = ids(page, &[&rain, &wind])?;
+        let (rain_id, wind_id) = (first[0], first[1]);
+        assert_ne!(rain_id, wind_id);
+        assert_eq!(ids(page, &[&wind, &rain])?, vec![wind_id, rain_id]);
+        assert_ne!(ids(other_page, &[&rain])?, vec![rain_id]);
+        // The same sound twice still makes two buttons
+        let twice = ids(page, &[&rain, &rain])?;
+        assert_eq!(twice[0], rain_id);
+        assert_ne!(twice[1], rain_id);
+
+        let action_id = Uuid::from_u128(0x1d0c_7f52_93ab_4e60_b8f1_2a64_c09e_5d37);
+        let placed = Action {
+            action_id: Some(action_id),
+            ..sound("rain.mp3")?
+        };
+        assert_eq!(ids(page, &[&placed])?, vec![action_id]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hotkeys_and_programs_are_imported_where_allowed() -> eyre::Result<()> {
+        use super::system_action;
+        use crate::import::elgato::Action;
+
+        let hotkey: Action = serde_json::from_value(json!({
+            "UUID": "com.elgato.streamdeck.system.hotkey",
+            "State": 0,
+            "States": [{}],
+            "Settings": { "Coalesce": true, "Hotkeys": [
+                { "KeyCtrl": true, "KeyShift": true, "KeyOption": false, "VKeyCode": 77 },
+                { "KeyCtrl": false, "KeyShift": false, "KeyOption": false, "VKeyCode": -1 }
+            ] }
+        }))?;
+        let open: Action = serde_json::from_value(json!({
+            "UUID": "com.elgato.streamdeck.system.open",
+            "State": 0,
+            "States": [{}],
+            "Settings": { "openInBrowser": false, "path": "\"C:\\Program Files\\obs64.exe\"" }
+        }))?;
+        let args = |allowed| ImportArgs {
+            path: PathBuf::from("profile.streamDeckProfilesBackup"),
+            base_paths: vec![],
+            profile_name: "Scene".to_string(),
+            drives: vec![],
+            send_keys: allowed,
+            run_commands: allowed,
+        };
+        let imported = |allowed, action: &Action| {
+            format!("{:?}", system_action(&args(allowed), &action.behavior))
+        };
+
+        assert_eq!(
+            imported(Switch::On, &hotkey),
+            r#"Some(SendKeys("Ctrl+Shift+M"))"#
+        );
+        assert_eq!(
+            imported(Switch::On, &open),
+            r#"Some(RunCommand { program: "C:\\Program Files\\obs64.exe", args: [] })"#
+        );
+        assert_eq!(imported(Switch::Off, &hotkey), "None");
+        assert_eq!(imported(Switch::Off, &open), "None");
+        Ok(())
+    }
+
+    #[test]
+    fn test_written_configurations_keep_what_profiles_cannot_hold() -> eyre::Result<()> {
+        use crate::config;
 
-mod elgato;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("scene.json");
+        let start_page = "00000000-0000-0000-0000-000000000001";
+        let written: config::Config = serde_json::from_value(json!({
+            "pages": { start_page: { "name": "Tavern", "buttons": [] } },
+            "start_page": start_page,
+            "buses": [{ "name": "Ambience", "effects": [] }],
+            "schedule": [{ "from": "18:00", "to": "18:00", "start": { "Page": start_page } }]
+        }))?;
+        config::write(&written, &path)?;
+
+        let read = config::read(&path)?;
+        assert_eq!(read.buses, written.buses);
+        assert_eq!(read.schedule, written.schedule);
+        assert_eq!(read.pages.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! Base paths to remove from the sound paths of a profile, see [`super::ImportArgs::base_paths`].
+//!
+//! Profiles are usually made on another machine, whose paths rarely match a fixed prefix, e.g.
+//! `C:\Users\Someone\Music`. Patterns match whole path components, with `*` and `?` inside a
+//! component and `**` for any number of components.
+
+use regex::{Regex, RegexBuilder};
+use std::path::{Component, Path, PathBuf};
+
+/// Windows does not tell `Music` from `music`, so neither do profiles made there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Case {
+    Sensitive,
+    Insensitive,
+}
+
+impl Case {
+    /// Converted paths have forward slashes, so the drive letter is what is left to tell.
+    pub fn of_profile_path(path: &str) -> Case {
+        let mut chars = path.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(drive), Some(':'), Some('/' | '\\')) if drive.is_ascii_alphabetic() => {
+                Case::Insensitive
+            }
+            _ if path.contains('\\') => Case::Insensitive,
+            _ => Case::Sensitive,
+        }
+    }
+}
+
+enum Part {
+    /// `**`
+    AnyDepth,
+    Name {
+        sensitive: Regex,
+        insensitive: Regex,
+    },
+}
+
+pub(super) struct BasePath {
+    parts: Vec<Part>,
+}
+
+impl BasePath {
+    pub fn new(pattern: &Path) -> eyre::Result<BasePath> {
+        let parts = components(pattern)
+            .map(|name| {
+                if name == "**" {
+                    return Ok(Part::AnyDepth);
+                }
+                let regex = glob_regex(&name);
+                let build = |case_insensitive| {
+                    RegexBuilder::new(&regex)
+                        .case_insensitive(case_insensitive)
+                        .build()
+                };
+                Ok(Part::Name {
+                    sensitive: build(false)?,
+                    insensitive: build(true)?,
+                })
+            })
+            .collect::<Result<_, regex::Error>>()
+            .map_err(|e| eyre::eyre!("Invalid base path {}: {e}", pattern.display()))?;
+        Ok(BasePath { parts })
+    }
+
+    /// What is left of `path` once the pattern is removed from its start, or `None` if the path
+    /// does not start with the pattern. `**` takes as few components as it can.
+    pub fn strip(&self, path: &Path, case: Case) -> Option<PathBuf> {
+        let names = components(path).collect::<Vec<_>>();
+        let rest = match_parts(&self.parts, &names, case)?;
+        Some(names[names.len() - rest..].iter().collect())
+    }
+}
+
+/// Returns how many of `names` are left after `parts` matched.
+fn match_parts(parts: &[Part], names: &[String], case: Case) -> Option<usize> {
+    let Some((part, parts)) = parts.split_first() else {
+        return Some(names.len());
+    };
+    match part {
+        Part::AnyDepth => {
+            (0..=names.len()).find_map(|skip| match_parts(parts, &names[skip..], case))
+        }
+        Part::Name {
+            sensitive,
+            insensitive,
+        } => {
+            let (name, names) = names.split_first()?;
+            let regex = match case {
+                Case::Sensitive => sensitive,
+                Case::Insensitive => insensitive,
+            };
+            if regex.is_match(name) {
+                match_parts(parts, names, case)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Every root is spelled the same, so that `/` in a pattern matches `\` on Windows.
+fn components(path: &Path) -> impl Iterator<Item = String> {
+    path.components().filter_map(|c| match c {
+        Component::RootDir => Some("/".to_string()),
+        Component::CurDir => None,
+        c => Some(c.as_os_str().to_string_lossy().into_owned()),
+    })
+}
+
+fn glob_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BasePath, Case};
+    use std::path::{Path, PathBuf};
+
+    fn strip(pattern: &str, path: &str) -> eyre::Result<Option<PathBuf>> {
+        let base = BasePath::new(Path::new(pattern))?;
+        Ok(base.strip(Path::new(path), Case::of_profile_path(path)))
+    }
+
+    #[test]
+    fn test_base_paths_match_globs_and_ignore_case_of_windows_paths() -> eyre::Result<()> {
+        let rain = Some(PathBuf::from("Ambience/rain.mp3"));
+        assert_eq!(
+            strip("C:/Users/*/Music", "C:/Users/Gm/Music/Ambience/rain.mp3")?,
+            rain
+        );
+        assert_eq!(
+            strip("c:/users/gm/music", "C:/Users/Gm/Music/Ambience/rain.mp3")?,
+            rain
+        );
+        assert_eq!(
+            strip("/home/**/Sounds", "/home/gm/lib/Sounds/Ambience/rain.mp3")?,
+            rain
+        );
+        assert_eq!(
+            strip("/home/gm/sounds", "/home/gm/Sounds/Ambience/rain.mp3")?,
+            None
+        );
+        assert_eq!(
+            strip("/home/gm/Sou?", "/home/gm/Sounds/Ambience/rain.mp3")?,
+            None
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+//! Collapses copies of the same sound file into one path, e.g. when a profile was put together
+//! from packs that ship the same sounds. The deck shares a track between buttons by path, so
+//! buttons that play copies then show and control the same playback.
+//!
+//! Only files of the same size are read and hashed, which is rare enough among sound files that
+//! a large library loads about as fast as before. Hashes are kept for as long as a file's size
+//! and modification time stay the same, so that reloads do not read the files again. Files whose
+//! hashes match are compared byte by byte before they are treated as copies, since a collision
+//! would otherwise make a button play the wrong sound.
+
+use crate::config::{ButtonBehavior, Config, Page};
+use crate::util::is_stream_url;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{File, Metadata};
+use std::hash::{DefaultHasher, Hasher};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+use std::time::SystemTime;
+use tracing::{debug, info, instrument};
+
+/// Makes every button that refers to a copy of a file refer to the same path instead, the first
+/// of the copies' paths in sort order, so that the choice is the same after a reload.
+#[instrument(skip(config))]
+pub fn dedup_files(config: &mut Config) {
+    let mut paths = BTreeSet::new();
+    for page in config.pages.values() {
+        for button in &page.buttons {
+            collect_paths(&button.behavior, &mut paths);
+        }
+    }
+    let canonical = find_copies(paths);
+    if canonical.is_empty() {
+        return;
+    }
+    for page in config.pages.values_mut() {
+        let mut new_page: Page = (**page).clone();
+        for button in new_page.buttons.iter_mut() {
+            replace_paths(&mut button.behavior, &canonical);
+        }
+        *page = Arc::new(new_page);
+    }
+}
+
+fn collect_paths(behavior: &ButtonBehavior, paths: &mut BTreeSet<Arc<String>>) {
+    match behavior {
+        ButtonBehavior::PlaySound(path, _) if !is_stream_url(path) => {
+            paths.insert(path.clone());
+        }
+        ButtonBehavior::Sequence(steps) => {
+            for step in steps {
+                collect_paths(step, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Maps the path of every copy to the path that replaces it.
+fn find_copies(paths: BTreeSet<Arc<String>>) -> HashMap<Arc<String>, Arc<String>> {
+    let mut by_size: HashMap<u64, Vec<(Arc<String>, Metadata)>> = HashMap::new();
+    for path in paths {
+        match std::fs::metadata(&path[..]) {
+            Ok(metadata) => by_size
+                .entry(metadata.len())
+                .or_default()
+                .push((path, metadata)),
+            // Reported once the file is played or preloaded
+            Err(e) => debug!("Not checking {path} for copies: {e}"),
+        }
+    }
+    let mut canonical = HashMap::new();
+    for same_size in by_size.into_values().filter(|paths| paths.len() > 1) {
+        let mut by_hash: HashMap<u64, Vec<Arc<String>>> = HashMap::new();
+        // Paths are still in sort order, so the first of each set of copies is kept
+        for (path, metadata) in same_size {
+            let hash = match cached_hash(Path::new(&path[..]), &metadata) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    debug!("Not checking {path} for copies: {e}");
+                    continue;
+                }
+            };
+            let firsts = by_hash.entry(hash).or_default();
+            let mut copy_of = None;
+            for first in firsts.iter() {
+                match same_content(Path::new(&first[..]), Path::new(&path[..])) {
+                    Ok(true) => {
+                        copy_of = Some(first.clone());
+                        break;
+                    }
+                    Ok(false) => {}
+                    Err(e) => debug!("Not comparing {path} with {first}: {e}"),
+                }
+            }
+            match copy_of {
+                Some(first) => {
+                    info!("{path} is a copy of {first}, playing them as one track");
+                    canonical.insert(path, first);
+                }
+                None => firsts.push(path),
+            }
+        }
+    }
+    canonical
+}
+
+/// When a file was last seen, how large it was, and its hash.
+type HashCache = HashMap<PathBuf, (Option<SystemTime>, u64, u64)>;
+
+static HASHES: LazyLock<Mutex<HashCache>> = LazyLock::new(Default::default);
+
+fn cached_hash(path: &Path, metadata: &Metadata) -> std::io::Result<u64> {
+    let modified = metadata.modified().ok();
+    let len = metadata.len();
+    // Without a modification time, a file that changed could keep its size, so it is always read
+    if let Some(&(Some(m), l, hash)) = HASHES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(path)
+        && Some(m) == modified
+        && l == len
+    {
+        return Ok(hash);
+    }
+    let hash = hash_file(path)?;
+    HASHES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(path.to_path_buf(), (modified, len, hash));
+    Ok(hash)
+}
+
+fn hash_file(path: &Path) -> std::io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut hasher = DefaultHasher::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buf)? {
+            0 => return Ok(hasher.finish()),
+            n => hasher.write(&buf[..n]),
+        }
+    }
+}
+
+fn same_content(a: &Path, b: &Path) -> std::io::Result<bool> {
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    let (mut chunk_a, mut chunk_b) = (Vec::new(), Vec::new());
+    loop {
+        chunk_a.clear();
+        chunk_b.clear();
+        (&mut a).take(64 * 1024).read_to_end(&mut chunk_a)?;
+        (&mut b).take(64 * 1024).read_to_end(&mut chunk_b)?;
+        if chunk_a != chunk_b {
+            return Ok(false);
+        }
+        if chunk_a.is_empty() {
+            return Ok(true);
+        }
+    }
+}
+
+/// Stop buttons and sequence steps name their sounds by path too, and must keep matching the
+/// buttons that play them.
+fn replace_paths(behavior: &mut ButtonBehavior, canonical: &HashMap<Arc<String>, Arc<String>>) {
+    match behavior {
+        ButtonBehavior::PlaySound(path, _)
+        | ButtonBehavior::StopSound(path)
+        | ButtonBehavior::StopInstances(path, _)
+        | ButtonBehavior::Countdown {
+            chime: Some(path), ..
+        }
+        | ButtonBehavior::RollDice {
+            sound: Some(path), ..
+        } => {
+            if let Some(first) = canonical.get(path) {
+                *path = first.clone();
+            }
+        }
+        ButtonBehavior::Sequence(steps) => {
+            for step in steps {
+                replace_paths(step, canonical);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HASHES, cached_hash, dedup_files, find_copies};
+    use crate::config::{ButtonBehavior, Config, Page};
+    use serde_json::json;
+    use std::collections::{BTreeSet, HashMap};
+    use std::path::Path;
+    use std::sync::{Arc, PoisonError};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_copies_of_a_file_play_from_one_path() -> eyre::Result<()> {
+        let dir = std::env::temp_dir().join(format!("noisedeck-dedup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let file = |name: &str, content: &[u8]| -> eyre::Result<String> {
+            let path = dir.join(name);
+            std::fs::write(&path, content)?;
+            Ok(path.to_string_lossy().into_owned())
+        };
+        let rain = file("a-rain.mp3", b"rain")?;
+        let copy = file("b-rain.mp3", b"rain")?;
+        let wind = file("c-wind.mp3", b"wind")?;
+        let page = |buttons: serde_json::Value| -> eyre::Result<Arc<Page>> {
+            let page = serde_json::from_value(json!({ "name": "Weather", "buttons": buttons }))?;
+            Ok(Arc::new(page))
+        };
+        let play = |path: &str| json!({ "PlaySound": [path, { "mode": "LoopStop" }] });
+        let (start_page, other_page) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let mut config = Config {
+            pages: HashMap::from([
+                (
+                    start_page,
+                    page(json!([
+                        { "label": "Rain", "behavior": play(&copy) },
+                        { "label": "Wind", "behavior": play(&wind) },
+                        { "label": "Stop", "behavior": { "StopSound": copy } }
+                    ]))?,
+                ),
+                (
+                    other_page,
+                    page(json!([{ "label": "Rain", "behavior": play(&rain) }]))?,
+                ),
+            ]),
+            start_page,
+            buses: vec![],
+            schedule: vec![],
+        };
+
+        dedup_files(&mut config);
+        std::fs::remove_dir_all(&dir)?;
+
+        let paths = config.pages[&start_page]
+            .buttons
+            .iter()
+            .map(|b| match &b.behavior {
+                ButtonBehavior::PlaySound(path, _) | ButtonBehavior::StopSound(path) => {
+                    path.to_string()
+                }
+                _ => String::new(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(paths, [rain.clone(), wind, rain]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_files_with_the_same_hash_are_compared() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (rain, wind) = (dir.path().join("rain.mp3"), dir.path().join("wind.mp3"));
+        std::fs::write(&rain, b"rain")?;
+        std::fs::write(&wind, b"wind")?;
+        // Pretend that the hashes collide
+        let hash = cached_hash(&rain, &std::fs::metadata(&rain)?)?;
+        let metadata = std::fs::metadata(&wind)?;
+        HASHES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(
+                wind.clone(),
+                (metadata.modified().ok(), metadata.len(), hash),
+            );
+        let path = |path: &Path| Arc::new(path.to_string_lossy().into_owned());
+
+        let canonical = find_copies(BTreeSet::from([path(&rain), path(&wind)]));
+
+        assert_eq!(canonical, HashMap::new());
+        Ok(())
+    }
+}
@@ -1,18 +1,18 @@
-use serde::{Deserialize, Deserializer};
-use serde_repr::Deserialize_repr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_repr::{Deserialize_repr, Serialize_repr};
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
 use uuid::Uuid;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct ProfileManifest {
     pub name: String,
     pub pages: ProfileManifestPages,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct ProfileManifestPages {
     pub current: Uuid,
@@ -20,13 +20,13 @@ pub struct ProfileManifestPages {
     pub pages: Vec<Uuid>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct PageManifest {
     pub controllers: Vec<Controller>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct Controller {
     #[serde(rename = "Type")]
@@ -34,16 +34,19 @@ pub struct Controller {
     pub actions: HashMap<Pos, Action>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct Action {
+    /// Set by the Stream Deck software since version 6 and kept when the key is moved.
+    #[serde(rename = "ActionID", default, skip_serializing_if = "Option::is_none")]
+    pub action_id: Option<Uuid>,
     pub state: usize,
     pub states: Vec<State>,
     #[serde(flatten)]
     pub behavior: ActionBehavior,
 }
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug, Default)]
 #[serde(tag = "UUID")]
 pub enum ActionBehavior {
     #[serde(rename = "com.elgato.streamdeck.profile.backtoparent")]
@@ -61,19 +64,170 @@ pub enum ActionBehavior {
         settings: OpenChildSettings,
     },
 
+    /// "Switch Profile", whose settings name the target just like those of a folder
+    #[serde(rename = "com.elgato.streamdeck.profile.rotate")]
+    SwitchProfile {
+        #[serde(rename = "Settings")]
+        settings: OpenChildSettings,
+    },
+
+    // Known actions that the deck cannot carry out, imported as placeholders
+    #[serde(rename = "com.elgato.streamdeck.system.text")]
+    Text,
+    #[serde(rename = "com.elgato.streamdeck.system.hotkey")]
+    Hotkey {
+        #[serde(rename = "Settings", default)]
+        settings: HotkeySettings,
+    },
+    #[serde(rename = "com.elgato.streamdeck.system.website")]
+    Website,
+    #[serde(rename = "com.elgato.streamdeck.system.open")]
+    Open {
+        #[serde(rename = "Settings", default)]
+        settings: OpenSettings,
+    },
+    #[serde(rename = "com.elgato.streamdeck.system.multimedia")]
+    Multimedia,
+    #[serde(rename = "com.elgato.streamdeck.multiactions.routine")]
+    MultiAction,
+
     #[default]
     #[serde(other)]
     Unknown,
 }
 
-#[derive(Deserialize, Debug)]
+impl ActionBehavior {
+    /// The name of an action that is recognized but not supported, as shown on its placeholder.
+    pub fn unsupported(&self) -> Option<&'static str> {
+        match self {
+            ActionBehavior::Text => Some("Text"),
+            ActionBehavior::Hotkey { .. } => Some("Hotkey"),
+            ActionBehavior::Website => Some("Website"),
+            ActionBehavior::Open { .. } => Some("Open"),
+            ActionBehavior::Multimedia => Some("Multimedia"),
+            ActionBehavior::MultiAction => Some("Multi Action"),
+            _ => None,
+        }
+    }
+
+    /// The action that a placeholder named by [`Self::unsupported`] stands in for.
+    pub fn of_unsupported(name: &str) -> Option<ActionBehavior> {
+        [
+            ActionBehavior::Text,
+            ActionBehavior::Hotkey {
+                settings: HotkeySettings::default(),
+            },
+            ActionBehavior::Website,
+            ActionBehavior::Open {
+                settings: OpenSettings::default(),
+            },
+            ActionBehavior::Multimedia,
+            ActionBehavior::MultiAction,
+        ]
+        .into_iter()
+        .find(|behavior| behavior.unsupported() == Some(name))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct OpenChildSettings {
     #[serde(rename = "ProfileUUID")]
     pub profile_uuid: Uuid,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct HotkeySettings {
+    #[serde(default)]
+    pub hotkeys: Vec<Hotkey>,
+}
+
+impl HotkeySettings {
+    /// The first hotkey as a chord for `SendKeys`, e.g. `Ctrl+Shift+M`, unless its key has no
+    /// name there.
+    pub fn chord(&self) -> Option<String> {
+        let hotkey = self.hotkeys.iter().find(|h| h.v_key_code >= 0)?;
+        let modifiers = [
+            (hotkey.key_ctrl, "Ctrl"),
+            (hotkey.key_option, "Alt"),
+            (hotkey.key_shift, "Shift"),
+            (hotkey.key_cmd, "Meta"),
+        ];
+        let mut chord = modifiers
+            .into_iter()
+            .filter(|(pressed, _)| *pressed)
+            .map(|(_, name)| name.to_string())
+            .collect::<Vec<_>>();
+        chord.push(key_name(hotkey.v_key_code)?);
+        Some(chord.join("+"))
+    }
+}
+
+/// The list always has a few entries; unused ones have a `VKeyCode` of -1.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct Hotkey {
+    #[serde(default)]
+    pub key_ctrl: bool,
+    #[serde(default)]
+    pub key_option: bool,
+    #[serde(default)]
+    pub key_shift: bool,
+    #[serde(default)]
+    pub key_cmd: bool,
+    /// The key as a Windows virtual-key code.
+    #[serde(rename = "VKeyCode")]
+    pub v_key_code: i32,
+}
+
+/// Names the keys that `SendKeys` knows.
+fn key_name(code: i32) -> Option<String> {
+    let name = match code {
+        0x08 => "Backspace",
+        0x09 => "Tab",
+        0x0D => "Enter",
+        0x1B => "Esc",
+        0x20 => "Space",
+        0x21 => "PageUp",
+        0x22 => "PageDown",
+        0x23 => "End",
+        0x24 => "Home",
+        0x25 => "Left",
+        0x26 => "Up",
+        0x27 => "Right",
+        0x28 => "Down",
+        0x2E => "Delete",
+        0xAD => "Mute",
+        0xB3 => "PlayPause",
+        // Digits and letters are their own codes
+        0x30..=0x39 | 0x41..=0x5A => return char::from_u32(code as u32).map(String::from),
+        0x70..=0x87 => return Some(format!("F{}", code - 0x6F)),
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct OpenSettings {
+    /// A program, but also any file or URL that the operating system can open. It is quoted
+    /// when it has spaces in it.
+    #[serde(default)]
+    pub path: String,
+}
+
+impl OpenSettings {
+    pub fn program(&self) -> Option<String> {
+        let path = self.path.trim();
+        let path = path
+            .strip_prefix('"')
+            .and_then(|p| p.strip_suffix('"'))
+            .unwrap_or(path);
+        (!path.is_empty()).then(|| path.to_string())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct AudioSettings {
     #[serde(default)]
@@ -86,7 +240,7 @@ pub struct AudioSettings {
     pub fade_type: FadeType,
 }
 
-#[derive(Deserialize_repr, Debug, Default)]
+#[derive(Deserialize_repr, Serialize_repr, Debug, Default)]
 #[repr(u8)]
 pub enum AudioActionType {
     #[default]
@@ -96,7 +250,7 @@ pub enum AudioActionType {
     LoopStop = 3,
 }
 
-#[derive(Debug, Deserialize_repr, Default)]
+#[derive(Debug, Deserialize_repr, Serialize_repr, Default)]
 #[repr(u8)]
 pub enum FadeType {
     #[default]
@@ -121,11 +275,12 @@ impl FadeType {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct State {
     #[serde(default)]
     pub show_title: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<Arc<String>>,
 }
 
@@ -148,6 +303,16 @@ impl FromStr for Pos {
         Ok(Pos(x, y))
     }
 }
+impl Pos {
+    /// Column and row, see [`crate::config::Button::position`].
+    pub fn grid(&self) -> (u8, u8) {
+        (self.0, self.1)
+    }
+
+    pub fn from_grid((col, row): (u8, u8)) -> Pos {
+        Pos(col, row)
+    }
+}
 impl PartialOrd for Pos {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
@@ -168,3 +333,12 @@ impl<'de> Deserialize<'de> for Pos {
         s.parse().map_err(serde::de::Error::custom)
     }
 }
+
+impl Serialize for Pos {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&format!("{},{}", self.0, self.1))
+    }
+}
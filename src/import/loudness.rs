@@ -0,0 +1,306 @@
+//! Integrated loudness measurement loosely following EBU R128 / ITU-R BS.1770: K-weighted mean
+//! square over 400ms blocks with 75% overlap, gated at -70 LUFS and 10 LU below the ungated mean.
+//! All channels are weighted equally, which is exact for mono and stereo files.
+
+use crate::config::{ButtonBehavior, Config, Page};
+use crate::util::is_stream_url;
+use eyre::{Context, OptionExt};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::Arc;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CODEC_TYPE_NULL, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tracing::{debug, info, instrument, warn};
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// Quiet recordings would otherwise be boosted far enough to make the limiter pump.
+const MAX_GAIN_DB: f64 = 20.0;
+
+/// Sets the gain of every sound in the config so that it plays at `target_lufs`. The sounds are
+/// found below `audio_path`, like the daemon does.
+///
+/// Files that cannot be decoded or are silent keep their gain and only produce a warning, so
+/// that a single broken file does not spoil the import.
+#[instrument(skip(config))]
+pub fn normalize_loudness(config: &mut Config, audio_path: &Path, target_lufs: f64) {
+    let mut measured: HashMap<Arc<String>, Option<f64>> = HashMap::new();
+    for page in config.pages.values_mut() {
+        let mut new_page: Page = (**page).clone();
+        for b in new_page.buttons.iter_mut() {
+            let ButtonBehavior::PlaySound(path, settings) = &mut b.behavior else {
+                continue;
+            };
+            if is_stream_url(path) {
+                debug!("Not measuring the loudness of stream {path}");
+                continue;
+            }
+            let loudness = *measured.entry(path.clone()).or_insert_with(|| {
+                match measure_file(&audio_path.join(&path[..])) {
+                    Ok(Some(lufs)) => {
+                        info!("{path}: {lufs:.1} LUFS");
+                        Some(lufs)
+                    }
+                    Ok(None) => {
+                        warn!("{path} is silent, not normalizing it");
+                        None
+                    }
+                    Err(e) => {
+                        warn!("Unable to measure the loudness of {path}: {e:?}");
+                        None
+                    }
+                }
+            });
+            if let Some(lufs) = loudness {
+                settings.gain_db = (target_lufs - lufs).min(MAX_GAIN_DB);
+            }
+        }
+        *page = Arc::new(new_page);
+    }
+}
+
+/// Returns `None` for files without any audible block.
+fn measure_file(path: &Path) -> eyre::Result<Option<f64>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .context("Unsupported audio format")?;
+    let mut format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_eyre("No audio track found")?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .context("Unsupported codec")?;
+
+    let mut meter: Option<LoudnessMeter> = None;
+    let mut samples: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e).context("Failed to read audio packet"),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(SymphoniaError::DecodeError(e)) => {
+                // Players skip corrupt frames as well, so they don't count towards the loudness
+                debug!("Skipping undecodable packet in {}: {e}", path.display());
+                continue;
+            }
+            Err(e) => return Err(e).context("Failed to decode audio"),
+        };
+        let spec = *decoded.spec();
+        let needed = decoded.capacity() * spec.channels.count();
+        let buf = match &mut samples {
+            Some(buf) if buf.capacity() >= needed => buf,
+            _ => samples.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buf.copy_interleaved_ref(decoded);
+        meter
+            .get_or_insert_with(|| LoudnessMeter::new(spec.rate, spec.channels.count()))
+            .push_interleaved(buf.samples());
+    }
+    Ok(meter.and_then(|m| m.integrated_lufs()))
+}
+
+/// Accumulates K-weighted energy of interleaved samples into 100ms sub-blocks, four of which
+/// make up one 400ms gating block.
+struct LoudnessMeter {
+    channels: usize,
+    filters: Vec<KWeighting>,
+    samples_per_sub_block: usize,
+    sub_block_energy: f64,
+    sub_block_len: usize,
+    sub_blocks: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    fn new(sample_rate: u32, channels: usize) -> Self {
+        LoudnessMeter {
+            channels,
+            filters: (0..channels)
+                .map(|_| KWeighting::new(sample_rate as f64))
+                .collect(),
+            samples_per_sub_block: (sample_rate as usize / 10).max(1),
+            sub_block_energy: 0.0,
+            sub_block_len: 0,
+            sub_blocks: Vec::new(),
+        }
+    }
+
+    fn push_interleaved(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (sample, filter) in frame.iter().zip(self.filters.iter_mut()) {
+                let weighted = filter.process(*sample as f64);
+                self.sub_block_energy += weighted * weighted;
+            }
+            self.sub_block_len += 1;
+            if self.sub_block_len == self.samples_per_sub_block {
+                self.sub_blocks
+                    .push(self.sub_block_energy / self.samples_per_sub_block as f64);
+                self.sub_block_energy = 0.0;
+                self.sub_block_len = 0;
+            }
+        }
+    }
+
+    fn integrated_lufs(&self) -> Option<f64> {
+        let blocks = self
+            .sub_blocks
+            .windows(4)
+            .map(|w| w.iter().sum::<f64>() / 4.0)
+            .filter(|&z| loudness(z) > ABSOLUTE_GATE_LUFS)
+            .collect::<Vec<_>>();
+        if blocks.is_empty() {
+            return None;
+        }
+        let relative_gate = loudness(mean(&blocks)) + RELATIVE_GATE_LU;
+        let gated = blocks
+            .into_iter()
+            .filter(|&z| loudness(z) > relative_gate)
+            .collect::<Vec<_>>();
+        Some(loudness(mean(&gated)))
+    }
+}
+
+fn loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// The BS.1770 pre-filter (high shelf) followed by the RLB high-pass, with coefficients derived
+/// for the actual sample rate rather than the 48kHz values from the standard.
+struct KWeighting {
+    stages: [Biquad; 2],
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        let shelf = {
+            let f0 = 1681.974450955533;
+            let gain_db = 3.999843853973347;
+            let q = 0.7071752369554196;
+            let k = (PI * f0 / sample_rate).tan();
+            let vh = 10f64.powf(gain_db / 20.0);
+            let vb = vh.powf(0.4996667741545416);
+            let a0 = 1.0 + k / q + k * k;
+            Biquad::new(
+                [
+                    (vh + vb * k / q + k * k) / a0,
+                    2.0 * (k * k - vh) / a0,
+                    (vh - vb * k / q + k * k) / a0,
+                ],
+                [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            )
+        };
+        let high_pass = {
+            let f0 = 38.13547087602444;
+            let q = 0.5003270373238773;
+            let k = (PI * f0 / sample_rate).tan();
+            let a0 = 1.0 + k / q + k * k;
+            Biquad::new(
+                [1.0, -2.0, 1.0],
+                [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            )
+        };
+        KWeighting {
+            stages: [shelf, high_pass],
+        }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        self.stages
+            .iter_mut()
+            .fold(sample, |x, stage| stage.process(x))
+    }
+}
+
+/// Direct form II transposed, `a0` normalized to 1.
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Biquad { b, a, z: [0.0; 2] }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LoudnessMeter;
+    use std::f64::consts::PI;
+
+    fn stereo_sine(amplitude: f64, seconds: usize) -> Vec<f32> {
+        let rate = 48_000;
+        (0..rate * seconds)
+            .flat_map(|i| {
+                let s = (amplitude * (2.0 * PI * 1000.0 * i as f64 / rate as f64).sin()) as f32;
+                [s, s]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reference_sine_reads_its_level() {
+        // A 1kHz sine at -23 dBFS on both channels is -23 LUFS by definition of the K-weighting
+        let mut meter = LoudnessMeter::new(48_000, 2);
+        meter.push_interleaved(&stereo_sine(10f64.powf(-23.0 / 20.0), 5));
+        let lufs = meter.integrated_lufs().unwrap();
+        assert!((lufs - -23.0).abs() < 0.1, "measured {lufs} LUFS");
+    }
+
+    #[test]
+    fn test_silence_has_no_loudness() {
+        let mut meter = LoudnessMeter::new(48_000, 2);
+        meter.push_interleaved(&vec![0.0; 48_000 * 2 * 2]);
+        assert_eq!(meter.integrated_lufs(), None);
+    }
+
+    #[test]
+    fn test_quiet_passages_are_gated() {
+        let mut meter = LoudnessMeter::new(48_000, 2);
+        meter.push_interleaved(&stereo_sine(10f64.powf(-20.0 / 20.0), 3));
+        meter.push_interleaved(&stereo_sine(10f64.powf(-60.0 / 20.0), 30));
+        let lufs = meter.integrated_lufs().unwrap();
+        assert!((lufs - -20.0).abs() < 0.5, "measured {lufs} LUFS");
+    }
+}
@@ -0,0 +1,73 @@
+//! Progress of an import, since a profile with hundreds of pages takes a while to parse and would
+//! otherwise import in silence.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Progress {
+    /// Every tenth of the pages is logged, e.g. for the daemon, whose output goes to a journal.
+    Log,
+    /// A bar on stderr, for imports run by hand in a terminal.
+    Bar,
+}
+
+const BAR_WIDTH: usize = 30;
+
+/// Counts parsed pages from several threads.
+pub(super) struct PageProgress {
+    progress: Progress,
+    total: usize,
+    parsed: AtomicUsize,
+}
+
+impl PageProgress {
+    pub fn new(progress: Progress, total: usize) -> Self {
+        PageProgress {
+            progress,
+            total,
+            parsed: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn page_parsed(&self) {
+        let parsed = self.parsed.fetch_add(1, Ordering::Relaxed) + 1;
+        match self.progress {
+            Progress::Log => {
+                if parsed * 10 / self.total > (parsed - 1) * 10 / self.total {
+                    info!("Parsed {parsed} of {} pages", self.total);
+                }
+            }
+            Progress::Bar => {
+                eprint!("\r{}", bar(parsed, self.total));
+                if parsed == self.total {
+                    eprintln!();
+                }
+            }
+        }
+    }
+}
+
+fn bar(done: usize, total: usize) -> String {
+    let filled = done * BAR_WIDTH / total.max(1);
+    format!(
+        "[{}{}] {done}/{total} pages",
+        "#".repeat(filled),
+        ".".repeat(BAR_WIDTH - filled)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bar;
+
+    #[test]
+    fn test_bar_fills_up_with_parsed_pages() {
+        assert_eq!(bar(0, 3), format!("[{}] 0/3 pages", ".".repeat(30)));
+        assert_eq!(
+            bar(1, 3),
+            format!("[{}{}] 1/3 pages", "#".repeat(10), ".".repeat(20))
+        );
+        assert_eq!(bar(3, 3), format!("[{}] 3/3 pages", "#".repeat(30)));
+    }
+}
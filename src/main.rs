@@ -1,31 +1,68 @@
 #![allow(dead_code,mismatched_lifetime_syntaxes)]
 
-use crate::daemon::DaemonArgs;
-use crate::import::ImportArgs;
+use crate::daemon::{ConfigSource, DaemonArgs, DeckArgs, HistoryArgs};
+use crate::export::ExportArgs;
+use crate::import::ImportCommandArgs;
 use clap::{Parser, Subcommand};
 use dotenvy::dotenv;
+use std::io::IsTerminal;
 use tracing_subscriber::fmt::format::FmtSpan;
 
 #[derive(Debug, Parser)]
 #[command(version, about, author)]
 struct Cli {
+    /// How log lines are written. `json` includes the fields of the enclosing spans, for log
+    /// collectors such as journald or Loki.
+    #[arg(long, env = "log_format", value_enum, default_value_t = LogFormat::Text, global = true)]
+    log_format: LogFormat,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
-#[derive(Debug, Eq, PartialEq, Subcommand, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, PartialEq, Subcommand, Clone)]
 enum Commands {
-    Daemon(DaemonArgs),
-    Import(ImportArgs),
+    // Boxed because the daemon has many more options than the other commands
+    Daemon(Box<DaemonArgs>),
+    /// Shows the deck of a daemon on another machine, see the daemon's `--remote-deck`.
+    Deck(DeckArgs),
+    /// Lists what was pressed and played on the deck, see the daemon's `--history-file`.
+    History(HistoryArgs),
+    /// Imports a Stream Deck profile, e.g. to write it as a configuration for the daemon's
+    /// `--config`.
+    Import(ImportCommandArgs),
+    /// Reads the configuration or imports the profile like the daemon would and lists every
+    /// problem with it.
+    Validate(ConfigSource),
+    /// Writes a configuration as a Stream Deck profile, e.g. to share it with someone who uses
+    /// the Stream Deck software.
+    Export(ExportArgs),
+    /// Edits a configuration and checks it before saving, e.g. from a script.
+    Config(config::EditArgs),
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let no_env_var_file = dotenv();
-    tracing_subscriber::FmtSubscriber::builder()
+    // Parsed before logging is set up, because the arguments choose the log format
+    let cli = Cli::parse();
+    let subscriber = tracing_subscriber::FmtSubscriber::builder()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
-        .init();
+        .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE);
+    match cli.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init(),
+    }
     stable_eyre::install()?;
     if let Err(e) = no_env_var_file {
         if e.not_found() {
@@ -35,15 +72,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    let cli = Cli::parse();
     tracing::debug!("Parsed command line arguments {:?}", &cli);
 
     match cli.command {
         Some(Commands::Daemon(args)) => {
-            daemon::run(args).await?;
+            daemon::run(*args).await?;
+        }
+        Some(Commands::Deck(args)) => {
+            daemon::run_remote(args).await?;
+        }
+        Some(Commands::History(args)) => {
+            daemon::history(args).await?;
         }
         Some(Commands::Import(args)) => {
-            import::run(args).await?;
+            import::run(args, interactive_progress()).await?;
+        }
+        Some(Commands::Validate(source)) => {
+            let issues = daemon::validate(source, interactive_progress()).await?;
+            for issue in &issues {
+                println!("{issue}");
+            }
+            config::ensure_no_errors(&issues)?;
+        }
+        Some(Commands::Export(args)) => {
+            export::run(args).await?;
+        }
+        Some(Commands::Config(args)) => {
+            config::edit(args).await?;
         }
         None => {
             return Ok(());
@@ -53,54 +108,378 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// A progress bar would only clutter logs that are written to a file.
+fn interactive_progress() -> import::Progress {
+    if std::io::stderr().is_terminal() {
+        import::Progress::Bar
+    } else {
+        import::Progress::Log
+    }
+}
+
 mod daemon;
+mod export;
 mod import;
 mod util;
 
 mod config {
+    use eyre::Context;
     use serde::{Deserialize, Serialize};
     use std::collections::HashMap;
+    use std::path::Path;
     use std::sync::Arc;
     use std::time::Duration;
     use uuid::Uuid;
 
+    mod defaults;
+    pub mod dice;
+    mod edit;
+    pub mod schedule;
+    mod template;
+    mod validate;
+    pub use dice::Dice;
+    pub use edit::{EditArgs, run as edit};
+    pub use schedule::{Schedule, ScheduledStart};
+    pub use validate::{Severity, ValidationIssue, ensure_no_errors, validate};
+
+    /// Reads a configuration in its JSON form, e.g. as `import --output` writes it.
+    pub fn read(path: &Path) -> eyre::Result<Config> {
+        let json = std::fs::read(path)
+            .with_context(|| format!("Failed to read configuration {path:?}"))?;
+        serde_json::from_slice(&json)
+            .with_context(|| format!("Failed to parse configuration {path:?}"))
+    }
+
+    /// Fills in the source of every script button. Scripts live next to the configuration or
+    /// profile in `dir` rather than with the audio files, because they are edited along with it.
+    pub fn read_scripts(config: &mut Config, dir: &Path) -> eyre::Result<()> {
+        for page in config.pages.values_mut() {
+            let mut new_page: Page = (**page).clone();
+            for b in new_page.buttons.iter_mut() {
+                read_script(&mut b.behavior, dir)?;
+            }
+            *page = Arc::new(new_page);
+        }
+        Ok(())
+    }
+
+    fn read_script(behavior: &mut ButtonBehavior, dir: &Path) -> eyre::Result<()> {
+        match behavior {
+            ButtonBehavior::Script { path, source } => {
+                let full_path = dir.join(&**path);
+                let script = std::fs::read_to_string(&full_path)
+                    .with_context(|| format!("Failed to read script {}", full_path.display()))?;
+                *source = Arc::new(script);
+                Ok(())
+            }
+            ButtonBehavior::Sequence(steps) => {
+                steps.iter_mut().try_for_each(|step| read_script(step, dir))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Goes through a temporary file, so that a crash while writing cannot truncate the
+    /// configuration.
+    pub fn write(config: &impl Serialize, path: &Path) -> eyre::Result<()> {
+        let json =
+            serde_json::to_vec_pretty(config).context("Failed to serialize configuration")?;
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, json)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))
+    }
+
+    /// Pages may be made from `templates`, see [`template`].
+    ///
+    /// The daemon reads it from the file given with `--config`. Importing a Stream Deck profile
+    /// instead only yields pages: no buses, schedule, templates or page defaults, and only the
+    /// buttons that the Stream Deck has itself. `import --output` writes such a file to start
+    /// from.
     #[derive(Debug, Serialize, Deserialize)]
+    #[serde(try_from = "template::ConfigSource")]
     pub struct Config {
         pub pages: HashMap<Uuid, Arc<Page>>,
         pub start_page: Uuid,
+        #[serde(default)]
+        pub buses: Vec<Bus>,
+        /// Start pages for parts of the week, see [`schedule`].
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub schedule: Vec<Schedule>,
     }
 
-    #[derive(Debug, Serialize, Deserialize, Clone)]
+    /// Pages may also set `defaults` for the playback settings of their sounds, see
+    /// [`defaults::PageDefaults`].
+    #[derive(Debug, Deserialize, Clone)]
+    #[serde(try_from = "defaults::PageSource")]
     pub struct Page {
         pub name: String,
         pub buttons: Vec<Button>,
+        /// Bus for the sounds on this page that don't name one themselves.
+        #[serde(default)]
+        pub bus: Option<String>,
+        /// Already handed down to the buttons; only kept to write the page out as it was.
+        pub defaults: defaults::PageDefaults,
+    }
+
+    /// A shared mixer track that sounds can be routed through to apply effects to all of them.
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct Bus {
+        pub name: String,
+        /// Applied in order.
+        pub effects: Vec<Effect>,
+        /// Output device (as named by the OS) that the bus plays on instead of the main output,
+        /// e.g. a virtual cable, so that a voice chat or a streaming program only hears the
+        /// music. The global volume still applies, but the recording leaves the bus out.
+        #[serde(default)]
+        pub output: Option<String>,
+    }
+
+    /// `mix` is the share of the processed signal, from 0.0 (dry) to 1.0 (wet).
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub enum Effect {
+        /// `feedback` (room size) and `damping` range from 0.0 to 1.0.
+        Reverb {
+            feedback: f64,
+            damping: f64,
+            mix: f32,
+        },
+        /// `resonance` ranges from 0.0 to 1.0.
+        Filter {
+            mode: FilterMode,
+            cutoff_hz: f64,
+            resonance: f64,
+            mix: f32,
+        },
+        Delay {
+            delay: Duration,
+            feedback_db: f64,
+            mix: f32,
+        },
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+    pub enum FilterMode {
+        LowPass,
+        BandPass,
+        HighPass,
+        Notch,
     }
 
     #[derive(Debug, Serialize, Deserialize, Clone)]
     pub struct Button {
+        /// Names the button for other programs and for what is kept about it outside of the
+        /// configuration, where neither its label nor its place are reliable. Imports give every
+        /// button one; it has to be unique across all pages.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub id: Option<Uuid>,
+        /// May have `{filename}`, `{page}` and `{duration}` in it, which the deck fills in with
+        /// the sound's file name without extension, the page's name and the sound's length.
+        /// Shortcodes such as `:fire:` or `:loop:` are drawn as emoji.
         pub label: Arc<String>,
         pub behavior: ButtonBehavior,
+        /// Column and row of the key, counted from the top left, on the Stream Deck the page was
+        /// made for. The bottom row of the deck holds its controls, so rows past the others go
+        /// to the next page. Buttons without a position take the keys that are left.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub position: Option<(u8, u8)>,
     }
 
-    #[derive(Debug, Serialize, Deserialize, Clone)]
+    impl Button {
+        /// Takes whichever key is left and has no id, like a button added to a page by hand.
+        pub fn new(label: &str, behavior: ButtonBehavior) -> Self {
+            Button {
+                id: None,
+                label: Arc::new(label.to_string()),
+                behavior,
+                position: None,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
     pub struct PlaySoundSettings {
+        /// Linear amplitude factor: 1.0 plays the file at its original level, 0.0 is silent.
+        #[serde(default = "PlaySoundSettings::default_volume")]
         pub volume: f64,
+        /// Loudness normalization in dB, applied on top of `volume`.
+        #[serde(default)]
+        pub gain_db: f64,
+        /// Stereo position from -1.0 (left) over 0.0 (center) to 1.0 (right).
+        #[serde(default)]
+        pub pan: f32,
+        /// Speed factor that also shifts the pitch, e.g. 0.5 plays at half speed an octave lower.
+        pub playback_rate: Option<f64>,
+        /// Name of the bus to play through, see [`Config::buses`].
+        #[serde(default)]
+        pub bus: Option<String>,
         pub mode: PlaybackMode,
         pub fade_in: Option<Duration>,
         pub fade_out: Option<Duration>,
+        /// Free-form names for groups of sounds, e.g. `combat`, which [`ButtonBehavior::StopTag`]
+        /// and [`ButtonBehavior::PlayTag`] act on.
+        #[serde(default)]
+        pub tags: Vec<String>,
+        /// How long after starting the sound its button ignores taps, so that an accidental
+        /// double tap neither starts it twice nor stops it right away.
+        #[serde(default)]
+        pub cooldown: Option<Duration>,
+        /// How many instances of a `PlayOverlap` sound may play at once; what happens to one
+        /// more is up to the daemon's `--over-limit`.
+        #[serde(default)]
+        pub max_instances: Option<usize>,
+        /// Starts the sound where it was last stopped instead of from the beginning, rewound by
+        /// this much so that the listeners find their way back in, e.g. for hour-long ambiences.
+        /// The position is kept in the daemon's state file.
+        #[serde(default)]
+        pub resume: Option<Duration>,
+        /// Another file to play when this one cannot be opened, e.g. a local copy of a sound that
+        /// lives on a network share that is not always reachable.
+        #[serde(default)]
+        pub fallback: Option<Arc<String>>,
     }
 
+    impl PlaySoundSettings {
+        /// Plays the file as it is, like a hand-written button that only gives the mode.
+        pub fn new(mode: PlaybackMode) -> Self {
+            PlaySoundSettings {
+                volume: Self::default_volume(),
+                gain_db: 0.0,
+                pan: 0.0,
+                playback_rate: None,
+                bus: None,
+                mode,
+                fade_in: None,
+                fade_out: None,
+                tags: Vec::new(),
+                cooldown: None,
+                max_instances: None,
+                resume: None,
+                fallback: None,
+            }
+        }
+
+        fn default_volume() -> f64 {
+            1.0
+        }
+    }
+
+    /// Stream Deck profiles only have sounds, folders and page switches; the other behaviors
+    /// come from a configuration file, see [`Config`].
     #[derive(Debug, Serialize, Deserialize, Clone)]
     pub enum ButtonBehavior {
         PushPage(Uuid),
+        /// Starts a new navigation stack at the page, so Back does not lead to the linking page.
+        GotoPage(Uuid),
+        /// Same as the Back key.
+        Pop,
+        /// Back to the page at the bottom of the navigation stack.
+        PopToRoot,
+        /// Back by this many pages at once, stopping at the bottom of the navigation stack.
+        PopN(usize),
         PlaySound(Arc<String>, PlaySoundSettings),
+        /// Stops the sound wherever it was started; does nothing if it is not playing.
+        StopSound(Arc<String>),
+        /// Stops some of the instances of a `PlayOverlap` sound that are playing at once, e.g.
+        /// the oldest of several crowd murmurs; `StopSound` always stops all of them.
+        StopInstances(Arc<String>, Instances),
+        StopAll,
+        /// Freezes every playing sound where it is, e.g. when the party goes off-script.
+        PauseAll,
+        /// Picks the paused sounds up where `PauseAll` left them.
+        ResumeAll,
+        /// Shows how many of the sounds can be played and, for those that cannot, which pages
+        /// they are on. Only files that the daemon checked at startup are known to be broken,
+        /// see its `--preload`.
+        LibraryStatus,
+        /// Opens the volume page without a track, which has room for the controls of the audio
+        /// input, see the daemon's `--input-device`.
+        Mixer,
+        /// Stops every playing sound with the tag, wherever it was started, e.g. all combat
+        /// sounds when initiative ends.
+        StopTag(String),
+        /// Starts one of the sounds with the tag at random, among those that are not playing.
+        PlayTag(String),
+        /// Runs the behaviors one after the other on a single tap, e.g. to stop one scene, start
+        /// the next and go to its page. A `PlaySound` step starts the sound as configured on its
+        /// own button and leaves it playing, so the sound must also be on some page.
+        Sequence(Vec<ButtonBehavior>),
+        /// Starts a program, e.g. to switch OBS scenes or smart lights along with the sounds.
+        /// Only runs when the daemon was started with `--allow-commands`.
+        RunCommand {
+            program: String,
+            args: Vec<String>,
+        },
+        /// Presses a key chord such as `Ctrl+Shift+M` in the focused application, e.g. to mute
+        /// the microphone. Only sent when the daemon was started with `--allow-keystrokes`.
+        SendKeys(String),
+        /// Runs a Rhai script that lives next to the imported profile, e.g.
+        /// `stop_all(); play("rain.mp3"); set_label("Raining");`. Scripts can call `play`,
+        /// `stop`, `stop_all`, `volume_up`, `volume_down`, `push`, `goto`, `back`, `home`,
+        /// `set_label` and `rename(sound, label)`, which keeps the sound's new label in the state
+        /// file; sounds are named by the end of their path and pages by name or id.
+        Script {
+            path: Arc<String>,
+            /// Read by the daemon along with the rest of the configuration, and so reloaded
+            /// with it.
+            #[serde(skip)]
+            source: Arc<String>,
+        },
+        /// An action that is not built into the deck, looked up by `kind` among the registered
+        /// behaviors. `params` are handed to it as they are.
+        Custom {
+            kind: String,
+            #[serde(default)]
+            params: serde_json::Value,
+        },
+        /// Stands in for an action of an imported profile that the deck cannot carry out, e.g.
+        /// typing a text, named by the action's kind. It keeps the page laid out like on the
+        /// Stream Deck; tapping it only says that the action is not supported.
+        Placeholder(String),
+        /// Switches the deck to another profile of the imported archive, or to another
+        /// configuration next to the daemon's `--config`, e.g. the next campaign at a table that
+        /// plays several. Playing sounds are stopped or kept according to the daemon's
+        /// `--campaign-switch`.
+        SwitchProfile(String),
+        /// Counts up from the first tap and shows the time on the button, e.g. for the length of
+        /// a fight or a break. Taps stop and continue it, holding it resets it. A reload of the
+        /// configuration resets it as well.
+        Stopwatch,
+        /// Counts down on the button, e.g. a five minute break, and plays `chime` once it is
+        /// over. The chime plays as it is, even if a button elsewhere has other settings for it.
+        /// Taps stop and continue it, holding it resets it; a tap after it ran out starts it over.
+        Countdown {
+            duration: Duration,
+            #[serde(default)]
+            chime: Option<Arc<String>>,
+        },
+        /// Rolls `spec` like `"2d6+3"` and shows the result on the button for a few seconds,
+        /// e.g. for a table without dice at hand. `sound` plays along with each roll, as it is
+        /// like a chime.
+        RollDice {
+            spec: Dice,
+            #[serde(default)]
+            sound: Option<Arc<String>>,
+        },
     }
 
-    #[derive(Debug, Serialize, Deserialize, Clone)]
+    /// Which of the instances of an overlapping sound to stop.
+    #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+    pub enum Instances {
+        Newest,
+        Oldest,
+        All,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
     pub enum PlaybackMode {
         PlayStop,
         PlayOverlap,
         LoopStop,
+        /// Pauses whatever else is playing, plays once and then resumes the rest where it was,
+        /// e.g. for a dramatic reveal.
+        Stinger,
     }
 
     impl PlaybackMode {
@@ -1,3 +1,6 @@
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
 pub struct PadIter<I>
 where
     I: Iterator,
@@ -150,3 +153,98 @@ where
         )
     }
 }
+
+/// Explicit on/off value for command line flags whose feature is enabled by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Switch {
+    On,
+    Off,
+}
+
+impl From<bool> for Switch {
+    fn from(on: bool) -> Self {
+        if on { Switch::On } else { Switch::Off }
+    }
+}
+
+/// Parses a (possibly fractional) number of seconds, for use as a clap value parser.
+pub fn parse_duration_secs(s: &str) -> Result<Duration, String> {
+    let secs: f64 = s
+        .trim()
+        .parse()
+        .map_err(|e| format!("'{s}' is not a number of seconds: {e}"))?;
+    Duration::try_from_secs_f64(secs).map_err(|e| format!("'{s}' is not a valid duration: {e}"))
+}
+
+/// Like [`parse_duration_secs`], but for periods of timers, which cannot be zero.
+pub fn parse_interval_secs(s: &str) -> Result<Duration, String> {
+    let duration = parse_duration_secs(s)?;
+    if duration.is_zero() {
+        return Err(format!(
+            "'{s}' is too short, the interval must be above zero"
+        ));
+    }
+    Ok(duration)
+}
+
+/// Sound paths with these prefixes are streamed over the network instead of read from disk.
+pub fn is_stream_url(path: &str) -> bool {
+    ["http://", "https://"].iter().any(|scheme| {
+        path.get(..scheme.len())
+            .is_some_and(|p| p.eq_ignore_ascii_case(scheme))
+    })
+}
+
+/// One spelling of the path to a file, so that paths that lead to the same file through
+/// symlinks, `..` or differently cased names on case-insensitive file systems compare equal.
+/// Files that do not exist (yet) only lose `.` and `..`.
+pub fn canonical_path(path: &Path) -> PathBuf {
+    if let Ok(canonical) = std::fs::canonicalize(path) {
+        return canonical;
+    }
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// From 0 to below `n`, each equally likely, from the operating system's random numbers. A
+/// plain remainder would favor the low numbers when `n` does not divide the range of the random
+/// numbers, so those at its end are drawn again.
+pub fn random_below(n: u64) -> Result<u64, getrandom::Error> {
+    let fair = u64::MAX - u64::MAX % n;
+    loop {
+        let random = getrandom::u64()?;
+        if random < fair {
+            return Ok(random % n);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::canonical_path;
+    use std::path::Path;
+
+    #[test]
+    fn test_canonical_path_of_a_missing_file_drops_dots() {
+        let path = Path::new("/nowhere/sounds/./ambience/../rain.mp3");
+        assert_eq!(canonical_path(path), Path::new("/nowhere/sounds/rain.mp3"));
+        assert_eq!(
+            canonical_path(Path::new("../rain.mp3")),
+            Path::new("../rain.mp3")
+        );
+    }
+}